@@ -0,0 +1,72 @@
+// font-kit/benches/advances.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures how long looking up the advances of a 100-glyph string takes, one glyph at a time
+//! with `advance` and all at once with `advances`.
+//!
+//! Run with `cargo bench --bench advances`.
+
+extern crate font_kit;
+
+use font_kit::font::{FaceIndex, Font};
+use std::time::{Duration, Instant};
+
+static FONT_PATH: &'static str = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+
+static TEXT: &'static str = "The quick brown fox jumps over the lazy dog. \
+                             Sphinx of black quartz, judge my vow! Pack my box with five dozen.";
+
+const ITERATIONS: u32 = 1000;
+
+fn main() {
+    let font = Font::from_path(FONT_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_ids: Vec<u32> = TEXT
+        .chars()
+        .cycle()
+        .take(100)
+        .map(|character| font.glyph_for_char(character).unwrap())
+        .collect();
+
+    let one_at_a_time = time(|| {
+        for &glyph_id in &glyph_ids {
+            font.advance(glyph_id).unwrap();
+        }
+    });
+    let all_at_once = time(|| {
+        font.advances(&glyph_ids).unwrap();
+    });
+
+    println!("looking up the advances of {} glyphs:", glyph_ids.len());
+    println!("  one at a time: {:>8.1} µs", microseconds(one_at_a_time));
+    println!("  all at once:   {:>8.1} µs", microseconds(all_at_once));
+    println!(
+        "  speedup:       {:>8.2}x",
+        microseconds(one_at_a_time) / microseconds(all_at_once)
+    );
+}
+
+// Returns the shortest time that the closure took over several iterations.
+fn time<F>(mut f: F) -> Duration
+where
+    F: FnMut(),
+{
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn microseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1_000_000.0 + duration.subsec_nanos() as f64 / 1000.0
+}
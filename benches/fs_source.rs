@@ -0,0 +1,92 @@
+// font-kit/benches/fs_source.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures how long `FsSource` takes to index a directory of several hundred fonts, reading the
+//! files one at a time and, with the `rayon` feature, in parallel.
+//!
+//! Run with `cargo bench --bench fs_source`, adding `--features rayon` to compare with reading the
+//! files on Rayon's thread pool.
+
+extern crate font_kit;
+
+use font_kit::source::Source;
+use font_kit::sources::fs::{FsSource, FsSourceOptions};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+static FONT_PATHS: [&'static str; 4] = [
+    "resources/tests/eb-garamond/EBGaramond12-Regular.otf",
+    "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+    "resources/tests/eb-garamond/EBGaramond12.otc",
+    "resources/tests/inconsolata/Inconsolata-Regular.ttf",
+];
+
+const FONT_FILE_COUNT: usize = 600;
+const ITERATIONS: u32 = 5;
+
+fn main() {
+    let directory = env::temp_dir().join(format!("font-kit-bench-fs-source-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for index in 0..FONT_FILE_COUNT {
+        let font_path = Path::new(FONT_PATHS[index % FONT_PATHS.len()]);
+        let extension = font_path.extension().unwrap().to_str().unwrap();
+        fs::copy(
+            font_path,
+            directory.join(format!("{:04}.{}", index, extension)),
+        )
+        .unwrap();
+    }
+
+    println!("indexing {} font files:", FONT_FILE_COUNT);
+    #[cfg(not(feature = "rayon"))]
+    {
+        let serial_time = time_indexing(&directory, FsSourceOptions::default());
+        println!("  one at a time: {:>8.1} ms", milliseconds(serial_time));
+    }
+    #[cfg(feature = "rayon")]
+    {
+        let serial_time = time_indexing(
+            &directory,
+            FsSourceOptions {
+                parallel: false,
+                ..FsSourceOptions::default()
+            },
+        );
+        let parallel_time = time_indexing(&directory, FsSourceOptions::default());
+        println!("  one at a time: {:>8.1} ms", milliseconds(serial_time));
+        println!("  in parallel:   {:>8.1} ms", milliseconds(parallel_time));
+        println!(
+            "  speedup:       {:>8.2}x",
+            milliseconds(serial_time) / milliseconds(parallel_time)
+        );
+    }
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+// Returns the shortest time that indexing the directory took over several iterations.
+fn time_indexing(directory: &Path, options: FsSourceOptions) -> Duration {
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            let source = FsSource::with_options(vec![PathBuf::from(directory)], options);
+            let elapsed = start.elapsed();
+            assert!(!source.all_fonts().unwrap().is_empty());
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
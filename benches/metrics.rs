@@ -0,0 +1,82 @@
+// font-kit/benches/metrics.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures how long scaling the advances of a 100-glyph string takes when `metrics` is called
+//! for every glyph, as layout loops often do, compared to calling it once before the loop.
+//!
+//! Run with `cargo bench --bench metrics`.
+
+extern crate font_kit;
+
+use font_kit::font::{FaceIndex, Font};
+use std::time::{Duration, Instant};
+
+static FONT_PATH: &'static str = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+
+static TEXT: &'static str = "The quick brown fox jumps over the lazy dog. \
+                             Sphinx of black quartz, judge my vow! Pack my box with five dozen.";
+
+const POINT_SIZE: f32 = 16.0;
+
+const ITERATIONS: u32 = 1000;
+
+fn main() {
+    let font = Font::from_path(FONT_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_ids: Vec<u32> = TEXT
+        .chars()
+        .cycle()
+        .take(100)
+        .map(|character| font.glyph_for_char(character).unwrap())
+        .collect();
+
+    let mut width = 0.0;
+    let per_glyph = time(|| {
+        width = 0.0;
+        for &glyph_id in &glyph_ids {
+            let scale = POINT_SIZE / font.metrics().units_per_em as f32;
+            width += font.advance(glyph_id).unwrap().x * scale;
+        }
+    });
+    let once = time(|| {
+        width = 0.0;
+        let scale = POINT_SIZE / font.metrics().units_per_em as f32;
+        for &glyph_id in &glyph_ids {
+            width += font.advance(glyph_id).unwrap().x * scale;
+        }
+    });
+    assert!(width > 0.0);
+
+    println!("scaling the advances of {} glyphs:", glyph_ids.len());
+    println!("  metrics per glyph: {:>8.1} µs", microseconds(per_glyph));
+    println!("  metrics once:      {:>8.1} µs", microseconds(once));
+    println!(
+        "  overhead:          {:>8.1} µs",
+        microseconds(per_glyph) - microseconds(once)
+    );
+}
+
+// Returns the shortest time that the closure took over several iterations.
+fn time<F>(mut f: F) -> Duration
+where
+    F: FnMut(),
+{
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn microseconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1_000_000.0 + duration.subsec_nanos() as f64 / 1000.0
+}
@@ -8,6 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use font_kit::font::FaceIndex;
 use font_kit::handle::Handle;
 use std::mem;
 use std::slice;
@@ -32,7 +33,7 @@ pub unsafe extern "C" fn FKHandleCreateWithMemory(bytes: FKDataRef, font_index:
                                                   -> FKHandleRef {
     let bytes = Arc::from_raw(bytes);
     mem::forget(bytes.clone());
-    Box::into_raw(Box::new(Handle::from_memory(bytes, font_index)))
+    Box::into_raw(Box::new(Handle::from_memory(bytes, FaceIndex(font_index))))
 }
 
 #[no_mangle]
@@ -30,7 +30,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     } = handle
     {
         println!("Path: {}", path.display());
-        println!("Index: {}", font_index);
+        println!("Index: {}", font_index.0);
     }
 
     let font = handle.load()?;
@@ -16,7 +16,7 @@ extern crate font_kit;
 use clap::{App, Arg, ArgGroup, ArgMatches};
 use colored::Colorize;
 use euclid::Point2D;
-use font_kit::canvas::{Canvas, Format, RasterizationOptions};
+use font_kit::canvas::{AlphaMode, Canvas, Format, RasterizationOptions};
 use font_kit::hinting::HintingOptions;
 use font_kit::loader::FontTransform;
 use font_kit::source::SystemSource;
@@ -59,6 +59,7 @@ fn get_args() -> ArgMatches<'static> {
         .possible_value("none")
         .possible_value("vertical")
         .possible_value("full")
+        .possible_value("autohint")
         .value_names(&["TYPE"]);
     let transform_arg = Arg::with_name("transform")
         .help("Transform to apply to glyph when rendering")
@@ -107,6 +108,7 @@ fn main() {
     let hinting_options = match matches.value_of("hinting") {
         Some(value) if value == "vertical" => HintingOptions::Vertical(size),
         Some(value) if value == "full" => HintingOptions::Full(size),
+        Some(value) if value == "autohint" => HintingOptions::AutoHint(size),
         _ => HintingOptions::None,
     };
 
@@ -128,7 +130,7 @@ fn main() {
         )
         .unwrap();
 
-    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), canvas_format);
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), canvas_format).unwrap();
 
     let origin = Point2D::new(
         -raster_rect.origin.x,
@@ -144,6 +146,7 @@ fn main() {
         &origin,
         hinting_options,
         rasterization_options,
+        AlphaMode::Premultiplied,
     )
     .unwrap();
 
@@ -0,0 +1,252 @@
+// font-kit/src/bitmap.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading of the monochrome and grayscale glyph bitmaps embedded in the OpenType `EBLC` and
+//! `EBDT` tables.
+
+use euclid::default::{Point2D, Size2D};
+
+use crate::canvas::{Canvas, Format};
+use crate::sfnt::{read_u16, read_u32, read_u8};
+use crate::utils;
+
+pub(crate) const EBLC_TABLE_TAG: u32 = 0x4542_4c43;
+pub(crate) const EBDT_TABLE_TAG: u32 = 0x4542_4454;
+
+// The size of a `BitmapSize` record in the `EBLC` table.
+const BITMAP_SIZE_LENGTH: usize = 48;
+
+// Where a glyph's image is in the `EBDT` table, and how to read it.
+struct GlyphImage {
+    offset: usize,
+    image_format: u16,
+    bit_depth: u8,
+    // The metrics shared by all glyphs of the index subtable, for image formats that don't store
+    // metrics of their own.
+    metrics: Option<GlyphMetrics>,
+}
+
+// The size of a glyph image and its position relative to the glyph origin, in pixels, with y
+// pointing up.
+#[derive(Clone, Copy)]
+struct GlyphMetrics {
+    height: u8,
+    width: u8,
+    bearing_x: i8,
+    bearing_y: i8,
+}
+
+/// Draws the bitmap of a glyph into a new A8 canvas, using the strike whose size is closest to
+/// `pixel_size` among those that have a bitmap for the glyph. A tie goes to the larger strike.
+///
+/// Returns the canvas along with the offset from the glyph origin to its top left corner. Returns
+/// `None` if no strike has a bitmap for the glyph in one of the image formats 1, 2, 5, 6, or 7.
+pub(crate) fn glyph_bitmap(
+    eblc: &[u8],
+    ebdt: &[u8],
+    glyph_id: u32,
+    pixel_size: f32,
+) -> Option<(Canvas, Point2D<i32>)> {
+    let size_count = read_u32(eblc, 4)? as usize;
+    let mut best_strike: Option<(f32, u8, GlyphImage)> = None;
+    for size_index in 0..size_count {
+        let bitmap_size_offset = 8 + size_index * BITMAP_SIZE_LENGTH;
+        let ppem = read_u8(eblc, bitmap_size_offset + 45)?;
+        let image = match find_glyph_image(eblc, bitmap_size_offset, glyph_id) {
+            Some(image) => image,
+            None => continue,
+        };
+        let distance = (ppem as f32 - pixel_size).abs();
+        let is_better = match best_strike {
+            None => true,
+            Some((best_distance, best_ppem, _)) => {
+                distance < best_distance || (distance == best_distance && ppem > best_ppem)
+            }
+        };
+        if is_better {
+            best_strike = Some((distance, ppem, image));
+        }
+    }
+    let (_, _, image) = best_strike?;
+    draw_glyph_image(ebdt, &image)
+}
+
+// Looks up a glyph in the index subtables of the strike whose `BitmapSize` record is at the given
+// offset.
+fn find_glyph_image(eblc: &[u8], bitmap_size_offset: usize, glyph_id: u32) -> Option<GlyphImage> {
+    let subtable_array_offset = read_u32(eblc, bitmap_size_offset)? as usize;
+    let subtable_count = read_u32(eblc, bitmap_size_offset + 8)? as usize;
+    let bit_depth = read_u8(eblc, bitmap_size_offset + 46)?;
+    if ![1, 2, 4, 8].contains(&bit_depth) {
+        return None;
+    }
+
+    for subtable_index in 0..subtable_count {
+        let array_entry_offset = subtable_array_offset.checked_add(subtable_index * 8)?;
+        let first_glyph_id = read_u16(eblc, array_entry_offset)? as u32;
+        let last_glyph_id = read_u16(eblc, array_entry_offset + 2)? as u32;
+        if glyph_id < first_glyph_id || glyph_id > last_glyph_id {
+            continue;
+        }
+
+        let subtable_offset =
+            subtable_array_offset.checked_add(read_u32(eblc, array_entry_offset + 4)? as usize)?;
+        let index_format = read_u16(eblc, subtable_offset)?;
+        let image_format = read_u16(eblc, subtable_offset + 2)?;
+        let image_data_offset = read_u32(eblc, subtable_offset + 4)? as usize;
+        let glyph_index = (glyph_id - first_glyph_id) as usize;
+        let (offset, metrics) = match index_format {
+            // Variable-size images with 32-bit offsets.
+            1 => {
+                let offsets_offset = subtable_offset + 8 + glyph_index * 4;
+                let start = read_u32(eblc, offsets_offset)? as usize;
+                let end = read_u32(eblc, offsets_offset + 4)? as usize;
+                if end <= start {
+                    return None;
+                }
+                (start, None)
+            }
+            // Images of the same size and metrics for every glyph in the range.
+            2 => {
+                let image_size = read_u32(eblc, subtable_offset + 8)? as usize;
+                let metrics = read_big_metrics(eblc, subtable_offset + 12)?;
+                (glyph_index.checked_mul(image_size)?, Some(metrics))
+            }
+            // Variable-size images with 16-bit offsets.
+            3 => {
+                let offsets_offset = subtable_offset + 8 + glyph_index * 2;
+                let start = read_u16(eblc, offsets_offset)? as usize;
+                let end = read_u16(eblc, offsets_offset + 2)? as usize;
+                if end <= start {
+                    return None;
+                }
+                (start, None)
+            }
+            // Variable-size images of a sparse set of glyphs, sorted by glyph ID.
+            4 => {
+                let pairs_offset = subtable_offset + 12;
+                // Only look at as many pairs as fit in the table, whatever the count says.
+                let glyph_count = (read_u32(eblc, subtable_offset + 8)? as usize)
+                    .min(eblc.len().saturating_sub(pairs_offset) / 4);
+                let pair_index = (0..glyph_count).find(|&pair_index| {
+                    read_u16(eblc, pairs_offset + pair_index * 4) == Some(glyph_id as u16)
+                })?;
+                let start = read_u16(eblc, pairs_offset + pair_index * 4 + 2)? as usize;
+                let end = read_u16(eblc, pairs_offset + pair_index * 4 + 6)? as usize;
+                if end <= start {
+                    return None;
+                }
+                (start, None)
+            }
+            // Images of the same size and metrics for a sparse set of glyphs, sorted by glyph ID.
+            5 => {
+                let image_size = read_u32(eblc, subtable_offset + 8)? as usize;
+                let metrics = read_big_metrics(eblc, subtable_offset + 12)?;
+                let glyph_ids_offset = subtable_offset + 24;
+                let glyph_count = (read_u32(eblc, subtable_offset + 20)? as usize)
+                    .min(eblc.len().saturating_sub(glyph_ids_offset) / 2);
+                let glyph_index = (0..glyph_count).find(|&glyph_index| {
+                    read_u16(eblc, glyph_ids_offset + glyph_index * 2) == Some(glyph_id as u16)
+                })?;
+                (glyph_index.checked_mul(image_size)?, Some(metrics))
+            }
+            _ => return None,
+        };
+
+        let is_supported = match image_format {
+            1 | 2 | 6 | 7 => true,
+            5 => metrics.is_some(),
+            _ => false,
+        };
+        if !is_supported {
+            return None;
+        }
+        return Some(GlyphImage {
+            offset: image_data_offset.checked_add(offset)?,
+            image_format,
+            bit_depth,
+            metrics,
+        });
+    }
+    None
+}
+
+fn draw_glyph_image(ebdt: &[u8], image: &GlyphImage) -> Option<(Canvas, Point2D<i32>)> {
+    // Formats 1 and 6 pad each row to a whole byte; formats 2, 5, and 7 don't.
+    let (metrics, data_offset, is_byte_aligned) = match image.image_format {
+        1 => (
+            read_small_metrics(ebdt, image.offset)?,
+            image.offset.checked_add(5)?,
+            true,
+        ),
+        2 => (
+            read_small_metrics(ebdt, image.offset)?,
+            image.offset.checked_add(5)?,
+            false,
+        ),
+        5 => (image.metrics?, image.offset, false),
+        6 => (
+            read_big_metrics(ebdt, image.offset)?,
+            image.offset.checked_add(8)?,
+            true,
+        ),
+        7 => (
+            read_big_metrics(ebdt, image.offset)?,
+            image.offset.checked_add(8)?,
+            false,
+        ),
+        _ => return None,
+    };
+
+    let (width, height) = (metrics.width as usize, metrics.height as usize);
+    let bit_depth = image.bit_depth as usize;
+    let mut row_bits = width * bit_depth;
+    if is_byte_aligned {
+        row_bits = utils::div_round_up(row_bits, 8) * 8;
+    }
+    let data_length = utils::div_round_up(row_bits * height, 8);
+    let data = ebdt.get(data_offset..data_offset.checked_add(data_length)?)?;
+
+    // Pixels are packed most significant bit first, and never straddle a byte.
+    let mut canvas = Canvas::new(&Size2D::new(width, height).to_u32(), Format::A8).ok()?;
+    let max_value = (1 << bit_depth) - 1;
+    for y in 0..height {
+        for x in 0..width {
+            let bit_index = y * row_bits + x * bit_depth;
+            let shift = 8 - bit_depth - bit_index % 8;
+            let value = (data[bit_index / 8] as usize >> shift) & max_value;
+            canvas.pixels[y * canvas.stride + x] = (value * 255 / max_value) as u8;
+        }
+    }
+    let origin = Point2D::new(metrics.bearing_x as i32, -(metrics.bearing_y as i32));
+    Some((canvas, origin))
+}
+
+// Reads a `SmallGlyphMetrics` record.
+fn read_small_metrics(table: &[u8], offset: usize) -> Option<GlyphMetrics> {
+    let record = table.get(offset..offset.checked_add(5)?)?;
+    Some(GlyphMetrics {
+        height: record[0],
+        width: record[1],
+        bearing_x: record[2] as i8,
+        bearing_y: record[3] as i8,
+    })
+}
+
+// Reads the horizontal metrics of a `BigGlyphMetrics` record.
+fn read_big_metrics(table: &[u8], offset: usize) -> Option<GlyphMetrics> {
+    let record = table.get(offset..offset.checked_add(8)?)?;
+    Some(GlyphMetrics {
+        height: record[0],
+        width: record[1],
+        bearing_x: record[2] as i8,
+        bearing_y: record[3] as i8,
+    })
+}
@@ -12,10 +12,20 @@
 
 use euclid::default::{Point2D, Rect, Size2D};
 use euclid::point2;
+#[cfg(feature = "image")]
+use image::{DynamicImage, ImageBuffer};
 use lazy_static::lazy_static;
 use std::cmp;
 use std::fmt;
-
+#[cfg(feature = "png")]
+use std::fs::File;
+#[cfg(feature = "png")]
+use std::io::{self, BufWriter, Write};
+#[cfg(feature = "png")]
+use std::path::Path;
+
+use crate::error::GlyphLoadingError;
+use crate::hinting::HintingOptions;
 use crate::utils;
 
 lazy_static! {
@@ -52,25 +62,242 @@ impl Canvas {
     /// Stride is automatically calculated from width.
     ///
     /// The canvas is initialized with transparent black (all values 0).
+    ///
+    /// Returns `GlyphLoadingError::OutOfMemory` if the pixels can't be allocated.
     #[inline]
-    pub fn new(size: &Size2D<u32>, format: Format) -> Canvas {
-        Canvas::with_stride(
-            size,
-            size.width as usize * format.bytes_per_pixel() as usize,
-            format,
-        )
+    pub fn new(size: &Size2D<u32>, format: Format) -> Result<Canvas, GlyphLoadingError> {
+        let stride = (size.width as usize)
+            .checked_mul(format.bytes_per_pixel() as usize)
+            .ok_or(GlyphLoadingError::OutOfMemory)?;
+        Canvas::with_stride(size, stride, format)
     }
 
     /// Creates a new blank canvas with the given pixel size, stride (number of bytes between
     /// successive rows), and format.
     ///
     /// The canvas is initialized with transparent black (all values 0).
-    pub fn with_stride(size: &Size2D<u32>, stride: usize, format: Format) -> Canvas {
-        Canvas {
-            pixels: vec![0; stride * size.height as usize],
+    ///
+    /// Returns `GlyphLoadingError::OutOfMemory` if the pixels can't be allocated.
+    pub fn with_stride(
+        size: &Size2D<u32>,
+        stride: usize,
+        format: Format,
+    ) -> Result<Canvas, GlyphLoadingError> {
+        let pixel_count = stride
+            .checked_mul(size.height as usize)
+            .ok_or(GlyphLoadingError::OutOfMemory)?;
+        let mut pixels = vec![];
+        pixels
+            .try_reserve_exact(pixel_count)
+            .map_err(|_| GlyphLoadingError::OutOfMemory)?;
+        pixels.resize(pixel_count, 0);
+        Ok(Canvas {
+            pixels,
             size: *size,
             stride,
             format,
+        })
+    }
+
+    /// Interleaves four A8 canvases into the red, green, blue, and alpha channels of a new
+    /// `Rgba32` canvas of the same size.
+    ///
+    /// This allows four glyphs to share each texel of a glyph atlas. Returns `None` if any of the
+    /// canvases is not in A8 format or if their sizes differ.
+    pub fn pack_channels(r: &Canvas, g: &Canvas, b: &Canvas, a: &Canvas) -> Option<Canvas> {
+        let channels = [r, g, b, a];
+        if channels
+            .iter()
+            .any(|channel| channel.format != Format::A8 || channel.size != r.size)
+        {
+            return None;
+        }
+
+        let mut canvas = Canvas::new(&r.size, Format::Rgba32).ok()?;
+        let width = r.size.width as usize;
+        for y in 0..(r.size.height as usize) {
+            let dest_row_start = y * canvas.stride;
+            let dest_row = &mut canvas.pixels[dest_row_start..(dest_row_start + width * 4)];
+            for (channel_index, channel) in channels.iter().enumerate() {
+                let src_row_start = y * channel.stride;
+                let src_row = &channel.pixels[src_row_start..(src_row_start + width)];
+                for (dest_pixel, &src_value) in dest_row.chunks_mut(4).zip(src_row.iter()) {
+                    dest_pixel[channel_index] = src_value;
+                }
+            }
+        }
+        Some(canvas)
+    }
+
+    /// Converts the pixels of an `Rgba32` canvas from premultiplied to straight alpha, by dividing
+    /// each color channel by the alpha of its pixel.
+    ///
+    /// Fully transparent pixels become transparent black. Canvases in other formats have no alpha
+    /// channel and are left alone.
+    pub fn unpremultiply(&mut self) {
+        self.unpremultiply_within(&Rect::new(point2(0, 0), self.size.to_i32()));
+    }
+
+    // Like `unpremultiply`, but only for the pixels in the given rectangle, which loaders use for
+    // the pixels that they drew a glyph to.
+    pub(crate) fn unpremultiply_within(&mut self, rect: &Rect<i32>) {
+        if self.format != Format::Rgba32 {
+            return;
+        }
+        let rect = match rect.intersection(&Rect::new(point2(0, 0), self.size.to_i32())) {
+            Some(rect) => rect.to_usize(),
+            None => return,
+        };
+        for y in rect.min_y()..rect.max_y() {
+            let row_start = y * self.stride + rect.origin.x * 4;
+            let row = &mut self.pixels[row_start..(row_start + rect.size.width * 4)];
+            for pixel in row.chunks_mut(4) {
+                let alpha = pixel[3] as u32;
+                for channel in &mut pixel[0..3] {
+                    *channel = match alpha {
+                        0 => 0,
+                        _ => cmp::min((*channel as u32 * 255 + alpha / 2) / alpha, 255) as u8,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Copies the pixels of this canvas into an image from the `image` crate, leaving out the
+    /// padding at the end of each row.
+    ///
+    /// `A8` canvases become grayscale images, `Rgb24` canvases RGB images, and `Rgba32` canvases
+    /// RGBA images. The alpha of `Rgba32` canvases is copied as is, so it stays premultiplied
+    /// unless the canvas was rasterized with straight alpha or `unpremultiply` was called.
+    ///
+    /// Requires the `image` feature.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> DynamicImage {
+        let (width, height) = (self.size.width, self.size.height);
+        let pixels = self.packed_pixels();
+
+        // The buffer is exactly the right size for the image, so creating it can't fail.
+        match self.format {
+            Format::A8 => {
+                DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, pixels).unwrap())
+            }
+            Format::Rgb24 => {
+                DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, pixels).unwrap())
+            }
+            Format::Rgba32 => {
+                DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, pixels).unwrap())
+            }
+        }
+    }
+
+    /// Encodes this canvas as a PNG image and writes it to the file at `path`, replacing the file
+    /// if it exists.
+    ///
+    /// See `to_png_bytes` for how each format is encoded, and for the canvases that can't be.
+    /// Those leave the file alone. Requires the `png` feature.
+    #[cfg(feature = "png")]
+    pub fn save_to_png(&self, path: &Path) -> io::Result<()> {
+        self.check_png_size()?;
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Encodes this canvas as a PNG image.
+    ///
+    /// `A8` canvases become 8-bit grayscale images, `Rgb24` canvases RGB images, and `Rgba32`
+    /// canvases RGBA images. PNG images have straight alpha, so the pixels of `Rgba32` canvases are
+    /// unpremultiplied first; canvases that were rasterized with straight alpha should be encoded
+    /// through `to_image` instead.
+    ///
+    /// PNG images must be at least one pixel wide and tall, so canvases with a width or height of
+    /// zero, such as those of empty glyphs, return an `InvalidInput` error.
+    ///
+    /// Requires the `png` feature.
+    #[cfg(feature = "png")]
+    pub fn to_png_bytes(&self) -> io::Result<Vec<u8>> {
+        self.check_png_size()?;
+        let mut bytes = vec![];
+        self.write_png(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "png")]
+    fn check_png_size(&self) -> io::Result<()> {
+        if self.size.width == 0 || self.size.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "an empty canvas can't be encoded as a PNG image",
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "png")]
+    fn write_png<W>(&self, writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let color_type = match self.format {
+            Format::A8 => png::ColorType::Grayscale,
+            Format::Rgb24 => png::ColorType::RGB,
+            Format::Rgba32 => png::ColorType::RGBA,
+        };
+        let mut packed_canvas = Canvas {
+            pixels: self.packed_pixels(),
+            size: self.size,
+            stride: self.size.width as usize * self.format.bytes_per_pixel() as usize,
+            format: self.format,
+        };
+        packed_canvas.unpremultiply();
+
+        let mut encoder = png::Encoder::new(writer, self.size.width, self.size.height);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut png_writer = encoder.write_header()?;
+        png_writer.write_image_data(&packed_canvas.pixels)?;
+        Ok(())
+    }
+
+    // Copies the pixels of this canvas, leaving out the padding at the end of each row.
+    #[cfg(any(feature = "image", feature = "png"))]
+    fn packed_pixels(&self) -> Vec<u8> {
+        let row_length = self.size.width as usize * self.format.bytes_per_pixel() as usize;
+        let mut pixels = Vec::with_capacity(row_length * self.size.height as usize);
+        for y in 0..(self.size.height as usize) {
+            let row_start = y * self.stride;
+            pixels.extend_from_slice(&self.pixels[row_start..(row_start + row_length)]);
+        }
+        pixels
+    }
+
+    /// Composites this canvas onto `dst`, with its top left corner at (`x`, `y`) in `dst`.
+    ///
+    /// Pixels are treated as premultiplied colors: `Rgba32` pixels as they are, `Rgb24` pixels as
+    /// opaque, and `A8` pixels as white with the value as alpha, so an `A8` glyph composited onto
+    /// a background draws white text. The parts of this canvas that fall outside `dst` are
+    /// clipped.
+    pub fn composite_onto(&self, dst: &mut Canvas, x: i32, y: i32, mode: CompositeMode) {
+        let src_rect = Rect::new(point2(x, y), self.size.to_i32());
+        let dst_rect = match src_rect.intersection(&Rect::new(point2(0, 0), dst.size.to_i32())) {
+            Some(dst_rect) => dst_rect,
+            None => return,
+        };
+
+        let src_bytes_per_pixel = self.format.bytes_per_pixel() as usize;
+        let dst_bytes_per_pixel = dst.format.bytes_per_pixel() as usize;
+        for dst_y in dst_rect.min_y()..dst_rect.max_y() {
+            let src_row_start = (dst_y - y) as usize * self.stride;
+            let dst_row_start = dst_y as usize * dst.stride;
+            for dst_x in dst_rect.min_x()..dst_rect.max_x() {
+                let src_offset = src_row_start + (dst_x - x) as usize * src_bytes_per_pixel;
+                let dst_offset = dst_row_start + dst_x as usize * dst_bytes_per_pixel;
+                let src_pixel = read_premultiplied_pixel(self.format, &self.pixels[src_offset..]);
+                let dst_pixel = &mut dst.pixels[dst_offset..(dst_offset + dst_bytes_per_pixel)];
+                let pixel =
+                    mode.composite(src_pixel, read_premultiplied_pixel(dst.format, dst_pixel));
+                write_premultiplied_pixel(dst.format, pixel, dst_pixel);
+            }
         }
     }
 
@@ -110,12 +337,47 @@ impl Canvas {
             }
             (Format::Rgb24, Format::Rgba32) => self
                 .blit_from_with::<BlitRgba32ToRgb24>(&dst_rect, src_bytes, src_stride, src_format),
-            (Format::Rgba32, Format::Rgb24)
-            | (Format::Rgba32, Format::A8)
-            | (Format::A8, Format::Rgba32) => unimplemented!(),
+            (Format::Rgba32, Format::A8) => {
+                self.blit_from_with::<BlitA8ToRgba32>(&dst_rect, src_bytes, src_stride, src_format)
+            }
+            (Format::Rgba32, Format::Rgb24) | (Format::A8, Format::Rgba32) => unimplemented!(),
         }
     }
 
+    // Copies the smallest rectangle of `src` that holds all of its nonzero pixels to the same
+    // place in this canvas, leaving the pixels around it alone.
+    pub(crate) fn blit_covered_from(&mut self, src: &Canvas) {
+        let bytes_per_pixel = src.format.bytes_per_pixel() as usize;
+        let mut bounds: Option<Rect<i32>> = None;
+        for y in 0..(src.size.height as usize) {
+            let row_start = y * src.stride;
+            let row =
+                &src.pixels[row_start..(row_start + src.size.width as usize * bytes_per_pixel)];
+            for (x, pixel) in row.chunks(bytes_per_pixel).enumerate() {
+                if pixel.iter().all(|&value| value == 0) {
+                    continue;
+                }
+                let pixel_rect = Rect::new(point2(x as i32, y as i32), Size2D::new(1, 1));
+                bounds = Some(match bounds {
+                    Some(bounds) => bounds.union(&pixel_rect),
+                    None => pixel_rect,
+                });
+            }
+        }
+        let bounds = match bounds {
+            Some(bounds) => bounds.to_usize(),
+            None => return,
+        };
+        let offset = bounds.origin.y * src.stride + bounds.origin.x * bytes_per_pixel;
+        self.blit_from(
+            bounds.origin.to_i32(),
+            &src.pixels[offset..],
+            &bounds.size.to_u32(),
+            src.stride,
+            src.format,
+        );
+    }
+
     #[allow(dead_code)]
     pub(crate) fn blit_from_bitmap_1bpp(
         &mut self,
@@ -161,6 +423,91 @@ impl Canvas {
         }
     }
 
+    // Builds an Rgb24 canvas of the given size from subpixel coverage values laid out in the
+    // given order: three values per pixel along each row for horizontal orders, and three rows
+    // per row of pixels for vertical ones.
+    pub(crate) fn from_subpixel_coverage(
+        size: &Size2D<u32>,
+        coverage: &[u8],
+        coverage_stride: usize,
+        subpixel_order: SubpixelOrder,
+    ) -> Result<Canvas, GlyphLoadingError> {
+        let mut canvas = Canvas::new(size, Format::Rgb24)?;
+        let (width, height) = (size.width as usize, size.height as usize);
+        for y in 0..height {
+            let dest_row_start = y * canvas.stride;
+            let dest_row = &mut canvas.pixels[dest_row_start..(dest_row_start + width * 3)];
+            for (x, dest_pixel) in dest_row.chunks_mut(3).enumerate() {
+                for subpixel in 0..3 {
+                    let offset = match subpixel_order {
+                        SubpixelOrder::Rgb | SubpixelOrder::Bgr => {
+                            y * coverage_stride + x * 3 + subpixel
+                        }
+                        SubpixelOrder::Vrgb | SubpixelOrder::Vbgr => {
+                            (y * 3 + subpixel) * coverage_stride + x
+                        }
+                    };
+                    let channel = match subpixel_order {
+                        SubpixelOrder::Rgb | SubpixelOrder::Vrgb => subpixel,
+                        SubpixelOrder::Bgr | SubpixelOrder::Vbgr => 2 - subpixel,
+                    };
+                    dest_pixel[channel] = coverage[offset];
+                }
+            }
+        }
+        Ok(canvas)
+    }
+
+    // Draws `src` over this premultiplied RGBA canvas at `dest_origin`. An A8 source is used as the
+    // coverage of `color`, which is unpremultiplied; an RGBA source is drawn as is.
+    pub(crate) fn composite_from(
+        &mut self,
+        src: &Canvas,
+        dest_origin: Point2D<i32>,
+        color: Option<[u8; 4]>,
+    ) {
+        for src_y in 0..src.size.height as i32 {
+            let dest_y = dest_origin.y + src_y;
+            if dest_y < 0 || dest_y >= self.size.height as i32 {
+                continue;
+            }
+            for src_x in 0..src.size.width as i32 {
+                let dest_x = dest_origin.x + src_x;
+                if dest_x < 0 || dest_x >= self.size.width as i32 {
+                    continue;
+                }
+
+                let src_offset = src_y as usize * src.stride;
+                let src_pixel = match color {
+                    Some(color) => {
+                        let alpha =
+                            color[3] as u32 * src.pixels[src_offset + src_x as usize] as u32 / 255;
+                        [
+                            (color[0] as u32 * alpha / 255) as u8,
+                            (color[1] as u32 * alpha / 255) as u8,
+                            (color[2] as u32 * alpha / 255) as u8,
+                            alpha as u8,
+                        ]
+                    }
+                    None => {
+                        let offset = src_offset + src_x as usize * 4;
+                        let mut pixel = [0; 4];
+                        pixel.copy_from_slice(&src.pixels[offset..(offset + 4)]);
+                        pixel
+                    }
+                };
+
+                let dest_offset = dest_y as usize * self.stride + dest_x as usize * 4;
+                let dest_pixel = &mut self.pixels[dest_offset..(dest_offset + 4)];
+                let inverse_alpha = 255 - src_pixel[3] as u32;
+                for (dest_channel, &src_channel) in dest_pixel.iter_mut().zip(src_pixel.iter()) {
+                    *dest_channel =
+                        (src_channel as u32 + *dest_channel as u32 * inverse_alpha / 255) as u8;
+                }
+            }
+        }
+    }
+
     fn blit_from_with<B: Blit>(
         &mut self,
         rect: &Rect<usize>,
@@ -199,7 +546,8 @@ impl fmt::Debug for Canvas {
 /// The image format for the canvas.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Format {
-    /// Premultiplied R8G8B8A8, little-endian.
+    /// R8G8B8A8, little-endian. Alpha is premultiplied, except in glyphs rasterized with straight
+    /// alpha; see `AlphaMode`.
     Rgba32,
     /// R8G8B8, little-endian.
     Rgb24,
@@ -252,6 +600,132 @@ pub enum RasterizationOptions {
     SubpixelAa,
 }
 
+/// The order of the color subpixels of an LCD screen, for subpixel antialiasing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubpixelOrder {
+    /// Red, green, and blue stripes from left to right. This is the most common layout, and the
+    /// default.
+    Rgb,
+    /// Blue, green, and red stripes from left to right.
+    Bgr,
+    /// Red, green, and blue stripes from top to bottom.
+    Vrgb,
+    /// Blue, green, and red stripes from top to bottom.
+    Vbgr,
+}
+
+impl SubpixelOrder {
+    /// Returns true if the subpixels are stacked vertically.
+    #[inline]
+    pub fn is_vertical(self) -> bool {
+        match self {
+            SubpixelOrder::Rgb | SubpixelOrder::Bgr => false,
+            SubpixelOrder::Vrgb | SubpixelOrder::Vbgr => true,
+        }
+    }
+}
+
+impl Default for SubpixelOrder {
+    #[inline]
+    fn default() -> SubpixelOrder {
+        SubpixelOrder::Rgb
+    }
+}
+
+/// How a glyph is rasterized with subpixel antialiasing; see
+/// `Loader::rasterize_glyph_with_subpixel_order`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SubpixelAaOptions {
+    /// The grid fitting to perform.
+    pub hinting_options: HintingOptions,
+    /// The order of the color subpixels of the screen.
+    pub subpixel_order: SubpixelOrder,
+}
+
+/// How the color channels of RGBA pixels relate to their alpha.
+///
+/// `Loader::rasterize_glyph` and `Loader::rasterize_color_glyph` take one of these. It only matters
+/// for `Rgba32` canvases, since the other formats have no alpha channel; `Canvas::unpremultiply`
+/// converts pixels that are already premultiplied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// The color channels are already multiplied by alpha, as compositors usually expect. This is
+    /// the default.
+    Premultiplied,
+    /// The color channels are independent of alpha, as most image formats, including PNG, expect.
+    Straight,
+}
+
+impl Default for AlphaMode {
+    #[inline]
+    fn default() -> AlphaMode {
+        AlphaMode::Premultiplied
+    }
+}
+
+/// How `Canvas::composite_onto` combines the pixels of a canvas with the pixels beneath them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompositeMode {
+    /// Draws the canvas over the destination, which shows through where the canvas is
+    /// transparent. This is Porter-Duff "source over".
+    Over,
+    /// Adds the canvas to the destination, saturating at full intensity.
+    Add,
+    /// Multiplies the colors of the canvas and the destination, which darkens. Where either is
+    /// transparent, the other is left as it is.
+    Multiply,
+}
+
+impl CompositeMode {
+    // Combines two premultiplied RGBA pixels.
+    fn composite(self, src: [u8; 4], dst: [u8; 4]) -> [u8; 4] {
+        let (src_alpha, dst_alpha) = (src[3] as u32, dst[3] as u32);
+        let mut pixel = [0; 4];
+        for channel in 0..4 {
+            let (src_value, dst_value) = (src[channel] as u32, dst[channel] as u32);
+            let value = match self {
+                CompositeMode::Over => src_value + mul_div_255(dst_value, 255 - src_alpha),
+                CompositeMode::Add => src_value + dst_value,
+                CompositeMode::Multiply if channel == 3 => {
+                    src_value + dst_value - mul_div_255(src_value, dst_value)
+                }
+                CompositeMode::Multiply => {
+                    mul_div_255(src_value, dst_value)
+                        + mul_div_255(src_value, 255 - dst_alpha)
+                        + mul_div_255(dst_value, 255 - src_alpha)
+                }
+            };
+            pixel[channel] = cmp::min(value, 255) as u8;
+        }
+        pixel
+    }
+}
+
+// Reads the pixel at the start of `pixels` as a premultiplied RGBA color. `Rgb24` pixels are
+// opaque, and `A8` pixels are white with the value as alpha.
+fn read_premultiplied_pixel(format: Format, pixels: &[u8]) -> [u8; 4] {
+    match format {
+        Format::Rgba32 => [pixels[0], pixels[1], pixels[2], pixels[3]],
+        Format::Rgb24 => [pixels[0], pixels[1], pixels[2], 255],
+        Format::A8 => [pixels[0]; 4],
+    }
+}
+
+// The inverse of `read_premultiplied_pixel`. `Rgb24` pixels drop the alpha, and `A8` pixels keep
+// only the alpha.
+fn write_premultiplied_pixel(format: Format, pixel: [u8; 4], pixels: &mut [u8]) {
+    match format {
+        Format::Rgba32 => pixels[0..4].copy_from_slice(&pixel),
+        Format::Rgb24 => pixels[0..3].copy_from_slice(&pixel[0..3]),
+        Format::A8 => pixels[0] = pixel[3],
+    }
+}
+
+// Multiplies two 8-bit values as though they were fractions of 255, rounding to nearest.
+fn mul_div_255(a: u32, b: u32) -> u32 {
+    (a * b + 127) / 255
+}
+
 trait Blit {
     fn blit(dest: &mut [u8], src: &[u8]);
 }
@@ -290,6 +764,18 @@ impl Blit for BlitA8ToRgb24 {
     }
 }
 
+// Coverage becomes premultiplied white, as when Core Text draws a glyph into an RGBA canvas.
+struct BlitA8ToRgba32;
+
+impl Blit for BlitA8ToRgba32 {
+    #[inline]
+    fn blit(dest: &mut [u8], src: &[u8]) {
+        for (dest, src) in dest.chunks_mut(4).zip(src.iter()) {
+            dest.copy_from_slice(&[*src; 4])
+        }
+    }
+}
+
 struct BlitRgba32ToRgb24;
 
 impl Blit for BlitRgba32ToRgb24 {
@@ -0,0 +1,210 @@
+// font-kit/src/cmap.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading of the character-to-glyph mapping in the OpenType `cmap` table.
+
+use std::char;
+use std::mem;
+
+use crate::loader::Loader;
+use crate::sfnt::{read_u16, read_u32};
+
+pub(crate) const CMAP_TABLE_TAG: u32 = 0x636d_6170;
+
+const PLATFORM_ID_UNICODE: u16 = 0;
+const PLATFORM_ID_WINDOWS: u16 = 3;
+const ENCODING_ID_WINDOWS_SYMBOL: u16 = 0;
+const ENCODING_ID_WINDOWS_UNICODE_BMP: u16 = 1;
+const ENCODING_ID_WINDOWS_UNICODE_FULL: u16 = 10;
+
+// The most entries that a map holds: one per code point. Tables with overlapping groups or
+// segments could otherwise make us allocate far more than this.
+const MAX_ENTRY_COUNT: usize = char::MAX as usize + 1;
+
+/// The characters that map to each glyph in a font, the inverse of its `cmap` table.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ReverseCharMap {
+    // Sorted, deduplicated pairs of glyph ID and character.
+    entries: Vec<(u32, char)>,
+}
+
+impl ReverseCharMap {
+    /// Inverts the Unicode subtable of the given font's `cmap` table.
+    ///
+    /// A format 12 subtable is preferred over a format 4 one, since the latter only covers the
+    /// Basic Multilingual Plane. A missing or malformed table results in an empty map.
+    pub(crate) fn new<F>(font: &F) -> ReverseCharMap
+    where
+        F: Loader,
+    {
+        match font.load_font_table(CMAP_TABLE_TAG) {
+            Some(cmap) => ReverseCharMap::from_cmap_table(&cmap, font.glyph_count()),
+            None => ReverseCharMap::default(),
+        }
+    }
+
+    /// Inverts the Unicode subtable of the given `cmap` table, leaving out mappings to glyphs
+    /// that a font with `glyph_count` glyphs doesn't have.
+    pub(crate) fn from_cmap_table(cmap: &[u8], glyph_count: u32) -> ReverseCharMap {
+        let mut entries = vec![];
+        if let Some(subtable_offset) = find_unicode_subtable(cmap) {
+            add_subtable_entries(cmap, subtable_offset, glyph_count, &mut entries);
+        }
+        entries.sort();
+        entries.dedup();
+        ReverseCharMap { entries }
+    }
+
+    /// Returns the number of bytes of heap memory that the map uses.
+    pub(crate) fn heap_size(&self) -> usize {
+        self.entries.capacity() * mem::size_of::<(u32, char)>()
+    }
+
+    /// Returns all characters that map to the glyph with the given ID, in code point order.
+    pub(crate) fn chars_for_glyph(&self, glyph_id: u32) -> Vec<char> {
+        let start = match self.entries.binary_search(&(glyph_id, '\0')) {
+            Ok(index) | Err(index) => index,
+        };
+        self.entries[start..]
+            .iter()
+            .take_while(|&&(entry_glyph_id, _)| entry_glyph_id == glyph_id)
+            .map(|&(_, character)| character)
+            .collect()
+    }
+}
+
+// Returns the offset of the best Unicode subtable that's in a format we can read, preferring the
+// full repertoire subtables in format 12.
+fn find_unicode_subtable(cmap: &[u8]) -> Option<usize> {
+    let subtable_count = read_u16(cmap, 2)? as usize;
+    let mut best_subtable: Option<(u8, usize)> = None;
+    for subtable_index in 0..subtable_count {
+        let record_offset = 4 + subtable_index * 8;
+        let platform_id = read_u16(cmap, record_offset)?;
+        let encoding_id = read_u16(cmap, record_offset + 2)?;
+        let subtable_offset = read_u32(cmap, record_offset + 4)? as usize;
+        let format = read_u16(cmap, subtable_offset)?;
+        let rank = match (platform_id, encoding_id, format) {
+            (PLATFORM_ID_UNICODE, _, 12)
+            | (PLATFORM_ID_WINDOWS, ENCODING_ID_WINDOWS_UNICODE_FULL, 12) => 3,
+            (PLATFORM_ID_UNICODE, _, 4)
+            | (PLATFORM_ID_WINDOWS, ENCODING_ID_WINDOWS_UNICODE_BMP, 4) => 2,
+            (PLATFORM_ID_WINDOWS, ENCODING_ID_WINDOWS_SYMBOL, 4) => 1,
+            _ => continue,
+        };
+        match best_subtable {
+            Some((best_rank, _)) if best_rank >= rank => {}
+            _ => best_subtable = Some((rank, subtable_offset)),
+        }
+    }
+    best_subtable.map(|(_, subtable_offset)| subtable_offset)
+}
+
+// Adds the entries of a subtable, stopping at the first out-of-bounds read or once there are
+// `MAX_ENTRY_COUNT` entries.
+fn add_subtable_entries(
+    cmap: &[u8],
+    subtable_offset: usize,
+    glyph_count: u32,
+    entries: &mut Vec<(u32, char)>,
+) -> Option<()> {
+    match read_u16(cmap, subtable_offset)? {
+        4 => add_format_4_entries(cmap, subtable_offset, glyph_count, entries),
+        12 => add_format_12_entries(cmap, subtable_offset, glyph_count, entries),
+        _ => None,
+    }
+}
+
+// Reads a segment mapping to delta values subtable.
+fn add_format_4_entries(
+    cmap: &[u8],
+    subtable_offset: usize,
+    glyph_count: u32,
+    entries: &mut Vec<(u32, char)>,
+) -> Option<()> {
+    let segment_count = read_u16(cmap, subtable_offset + 6)? as usize / 2;
+    let end_codes_offset = subtable_offset + 14;
+    let start_codes_offset = end_codes_offset + segment_count * 2 + 2;
+    let id_deltas_offset = start_codes_offset + segment_count * 2;
+    let id_range_offsets_offset = id_deltas_offset + segment_count * 2;
+    for segment_index in 0..segment_count {
+        let end_code = read_u16(cmap, end_codes_offset + segment_index * 2)?;
+        let start_code = read_u16(cmap, start_codes_offset + segment_index * 2)?;
+        let id_delta = read_u16(cmap, id_deltas_offset + segment_index * 2)?;
+        let id_range_offset_offset = id_range_offsets_offset + segment_index * 2;
+        let id_range_offset = read_u16(cmap, id_range_offset_offset)? as usize;
+        if start_code > end_code {
+            continue;
+        }
+        for code in start_code..=end_code {
+            // The final segment maps 0xffff to the missing glyph, and surrogates aren't
+            // characters.
+            let character = match char::from_u32(code as u32) {
+                Some(character) if code != 0xffff => character,
+                _ => continue,
+            };
+            let glyph_id = if id_range_offset == 0 {
+                code.wrapping_add(id_delta)
+            } else {
+                let glyph_id_offset =
+                    id_range_offset_offset + id_range_offset + (code - start_code) as usize * 2;
+                match read_u16(cmap, glyph_id_offset) {
+                    Some(0) | None => continue,
+                    Some(glyph_id) => glyph_id.wrapping_add(id_delta),
+                }
+            };
+            if glyph_id != 0 && (glyph_id as u32) < glyph_count {
+                if entries.len() == MAX_ENTRY_COUNT {
+                    return None;
+                }
+                entries.push((glyph_id as u32, character));
+            }
+        }
+    }
+    Some(())
+}
+
+// Reads a segmented coverage subtable.
+fn add_format_12_entries(
+    cmap: &[u8],
+    subtable_offset: usize,
+    glyph_count: u32,
+    entries: &mut Vec<(u32, char)>,
+) -> Option<()> {
+    let group_count = read_u32(cmap, subtable_offset + 12)? as usize;
+    for group_index in 0..group_count {
+        let group_offset = subtable_offset + 16 + group_index * 12;
+        let start_char_code = read_u32(cmap, group_offset)?;
+        let end_char_code = read_u32(cmap, group_offset + 4)?;
+        let start_glyph_id = read_u32(cmap, group_offset + 8)?;
+        // Skip groups that are backwards, that run past the last code point, or that map to
+        // glyphs the font doesn't have.
+        if start_char_code > end_char_code || end_char_code > char::MAX as u32 {
+            continue;
+        }
+        let char_code_count = end_char_code - start_char_code;
+        match start_glyph_id.checked_add(char_code_count) {
+            Some(end_glyph_id) if end_glyph_id < glyph_count => {}
+            _ => continue,
+        }
+        if entries.len() + char_code_count as usize >= MAX_ENTRY_COUNT {
+            return None;
+        }
+        for char_code in start_char_code..=end_char_code {
+            let glyph_id = start_glyph_id + (char_code - start_char_code);
+            if let Some(character) = char::from_u32(char_code) {
+                if glyph_id != 0 {
+                    entries.push((glyph_id, character));
+                }
+            }
+        }
+    }
+    Some(())
+}
@@ -0,0 +1,290 @@
+// font-kit/src/color.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Access to the color glyph data in OpenType fonts.
+
+use std::cmp::Ordering;
+use std::mem;
+
+use crate::loader::Loader;
+use crate::sfnt::{read_u16, read_u32};
+
+pub(crate) const COLR_TABLE_TAG: u32 = 0x434f_4c52;
+pub(crate) const CPAL_TABLE_TAG: u32 = 0x4350_414c;
+const SBIX_TABLE_TAG: u32 = 0x7362_6978;
+const CBLC_TABLE_TAG: u32 = 0x4342_4c43;
+const SVG_TABLE_TAG: u32 = 0x5356_4720;
+
+/// One layer of a color glyph defined in a `COLR` version 0 table.
+///
+/// A color glyph is drawn by filling the outline of each of its layers, bottom to top, with the
+/// color at `palette_index` in the chosen `CPAL` palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ColorLayer {
+    /// The glyph whose outline this layer uses.
+    pub glyph_id: u32,
+    /// The index of the layer's color in the palette.
+    ///
+    /// `ColorLayer::FOREGROUND_PALETTE_INDEX` means that the layer should be drawn in the
+    /// foreground (text) color instead.
+    pub palette_index: u16,
+}
+
+impl ColorLayer {
+    /// The palette index that stands for the foreground color.
+    pub const FOREGROUND_PALETTE_INDEX: u16 = 0xffff;
+}
+
+/// The set of glyphs in a font that have a color representation in any of the `COLR`, `sbix`,
+/// `CBDT`/`CBLC`, or `SVG ` tables.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ColorGlyphCoverage {
+    // Sorted, non-overlapping, inclusive ranges of glyph IDs.
+    ranges: Vec<(u32, u32)>,
+}
+
+impl ColorGlyphCoverage {
+    /// Parses the color tables of the given font.
+    ///
+    /// Malformed tables are skipped rather than reported, since the only consequence is that the
+    /// affected glyphs are treated as monochrome.
+    pub(crate) fn new<F>(font: &F) -> ColorGlyphCoverage
+    where
+        F: Loader,
+    {
+        let mut ranges = vec![];
+        if let Some(colr) = font.load_font_table(COLR_TABLE_TAG) {
+            add_colr_ranges(&colr, &mut ranges);
+        }
+        if let Some(sbix) = font.load_font_table(SBIX_TABLE_TAG) {
+            add_sbix_ranges(&sbix, font.glyph_count(), &mut ranges);
+        }
+        if let Some(cblc) = font.load_font_table(CBLC_TABLE_TAG) {
+            add_cblc_ranges(&cblc, &mut ranges);
+        }
+        if let Some(svg) = font.load_font_table(SVG_TABLE_TAG) {
+            add_svg_ranges(&svg, &mut ranges);
+        }
+
+        ranges.sort();
+        let mut merged_ranges: Vec<(u32, u32)> = vec![];
+        for (first, last) in ranges {
+            match merged_ranges.last_mut() {
+                Some(&mut (_, ref mut merged_last)) if first <= *merged_last + 1 => {
+                    *merged_last = (*merged_last).max(last)
+                }
+                _ => merged_ranges.push((first, last)),
+            }
+        }
+        ColorGlyphCoverage {
+            ranges: merged_ranges,
+        }
+    }
+
+    /// Returns the number of bytes of heap memory that the coverage uses.
+    pub(crate) fn heap_size(&self) -> usize {
+        self.ranges.capacity() * mem::size_of::<(u32, u32)>()
+    }
+
+    /// Returns true if and only if the glyph with the given ID has a color representation.
+    pub(crate) fn contains(&self, glyph_id: u32) -> bool {
+        self.ranges
+            .binary_search_by(|&(first, last)| {
+                if last < glyph_id {
+                    Ordering::Less
+                } else if first > glyph_id {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+}
+
+/// Looks up the layers of a glyph in a `COLR` table.
+///
+/// Only the version 0 base glyph records are consulted; glyphs that are only defined by version 1
+/// paint graphs have no layers.
+pub(crate) fn colr_layers(colr: &[u8], glyph_id: u32) -> Option<Vec<ColorLayer>> {
+    let base_glyph_count = read_u16(colr, 2)? as usize;
+    let base_glyph_records_offset = read_u32(colr, 4)? as usize;
+    let layer_records_offset = read_u32(colr, 8)? as usize;
+    let layer_count = read_u16(colr, 12)? as usize;
+
+    // Base glyph records are sorted by glyph ID.
+    let (mut low, mut high) = (0, base_glyph_count);
+    while low < high {
+        let mid = (low + high) / 2;
+        let record_offset = base_glyph_records_offset + mid * 6;
+        let base_glyph_id = read_u16(colr, record_offset)? as u32;
+        if base_glyph_id < glyph_id {
+            low = mid + 1;
+        } else if base_glyph_id > glyph_id {
+            high = mid;
+        } else {
+            let first_layer_index = read_u16(colr, record_offset + 2)? as usize;
+            let num_layers = read_u16(colr, record_offset + 4)? as usize;
+            if first_layer_index + num_layers > layer_count {
+                return None;
+            }
+            return (first_layer_index..(first_layer_index + num_layers))
+                .map(|layer_index| {
+                    let layer_offset = layer_records_offset + layer_index * 4;
+                    Some(ColorLayer {
+                        glyph_id: read_u16(colr, layer_offset)? as u32,
+                        palette_index: read_u16(colr, layer_offset + 2)?,
+                    })
+                })
+                .collect();
+        }
+    }
+    None
+}
+
+/// Reads the colors of a palette in a `CPAL` table, converting them to RGBA.
+pub(crate) fn cpal_palette(cpal: &[u8], palette_index: u16) -> Option<Vec<[u8; 4]>> {
+    let entry_count = read_u16(cpal, 2)? as usize;
+    let palette_count = read_u16(cpal, 4)?;
+    let color_records_offset = read_u32(cpal, 8)? as usize;
+    if palette_index >= palette_count {
+        return None;
+    }
+    let first_color_index = read_u16(cpal, 12 + palette_index as usize * 2)? as usize;
+
+    let start = color_records_offset + first_color_index * 4;
+    let colors = cpal.get(start..(start + entry_count * 4))?;
+    // Color records are stored in BGRA order.
+    Some(
+        colors
+            .chunks(4)
+            .map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+            .collect(),
+    )
+}
+
+// Adds the glyphs with base glyph records (version 0) or paint records (version 1).
+fn add_colr_ranges(colr: &[u8], ranges: &mut Vec<(u32, u32)>) {
+    let (version, base_glyph_count, base_glyph_records_offset) =
+        match (read_u16(colr, 0), read_u16(colr, 2), read_u32(colr, 4)) {
+            (Some(version), Some(count), Some(offset)) => (version, count as usize, offset),
+            _ => return,
+        };
+    add_glyph_id_list(
+        colr,
+        base_glyph_records_offset as usize,
+        base_glyph_count,
+        6,
+        ranges,
+    );
+
+    if version >= 1 {
+        let base_glyph_list_offset = match read_u32(colr, 14) {
+            Some(offset) if offset != 0 => offset as usize,
+            _ => return,
+        };
+        if let Some(paint_record_count) = read_u32(colr, base_glyph_list_offset) {
+            add_glyph_id_list(
+                colr,
+                base_glyph_list_offset + 4,
+                paint_record_count as usize,
+                6,
+                ranges,
+            );
+        }
+    }
+}
+
+// Adds the glyphs that have data in at least one strike.
+fn add_sbix_ranges(sbix: &[u8], glyph_count: u32, ranges: &mut Vec<(u32, u32)>) {
+    let strike_count = match read_u32(sbix, 4) {
+        Some(strike_count) => strike_count as usize,
+        None => return,
+    };
+    for strike_index in 0..strike_count {
+        let strike_offset = match read_u32(sbix, 8 + strike_index * 4) {
+            Some(strike_offset) => strike_offset as usize,
+            None => return,
+        };
+        let glyph_data_offsets = strike_offset + 4;
+        for glyph_id in 0..glyph_count {
+            let offset = glyph_data_offsets + glyph_id as usize * 4;
+            match (read_u32(sbix, offset), read_u32(sbix, offset + 4)) {
+                (Some(start), Some(end)) if end > start => ranges.push((glyph_id, glyph_id)),
+                (Some(_), Some(_)) => {}
+                _ => break,
+            }
+        }
+    }
+}
+
+// Adds the glyph ranges of the index subtables of every bitmap size.
+fn add_cblc_ranges(cblc: &[u8], ranges: &mut Vec<(u32, u32)>) {
+    let size_count = match read_u32(cblc, 4) {
+        Some(size_count) => size_count as usize,
+        None => return,
+    };
+    for size_index in 0..size_count {
+        let bitmap_size_offset = 8 + size_index * 48;
+        let (subtable_array_offset, subtable_count) = match (
+            read_u32(cblc, bitmap_size_offset),
+            read_u32(cblc, bitmap_size_offset + 8),
+        ) {
+            (Some(offset), Some(count)) => (offset as usize, count as usize),
+            _ => return,
+        };
+        for subtable_index in 0..subtable_count {
+            let offset = subtable_array_offset + subtable_index * 8;
+            match (read_u16(cblc, offset), read_u16(cblc, offset + 2)) {
+                (Some(first), Some(last)) if first <= last => {
+                    ranges.push((first as u32, last as u32))
+                }
+                (Some(_), Some(_)) => {}
+                _ => break,
+            }
+        }
+    }
+}
+
+// Adds the glyph ranges of every SVG document record.
+fn add_svg_ranges(svg: &[u8], ranges: &mut Vec<(u32, u32)>) {
+    let document_list_offset = match read_u32(svg, 2) {
+        Some(offset) => offset as usize,
+        None => return,
+    };
+    let record_count = match read_u16(svg, document_list_offset) {
+        Some(record_count) => record_count as usize,
+        None => return,
+    };
+    for record_index in 0..record_count {
+        let offset = document_list_offset + 2 + record_index * 12;
+        match (read_u16(svg, offset), read_u16(svg, offset + 2)) {
+            (Some(first), Some(last)) if first <= last => ranges.push((first as u32, last as u32)),
+            (Some(_), Some(_)) => {}
+            _ => break,
+        }
+    }
+}
+
+// Adds the glyph IDs at the start of each of `count` records of `record_size` bytes.
+fn add_glyph_id_list(
+    table: &[u8],
+    offset: usize,
+    count: usize,
+    record_size: usize,
+    ranges: &mut Vec<(u32, u32)>,
+) {
+    for record_index in 0..count {
+        match read_u16(table, offset + record_index * record_size) {
+            Some(glyph_id) => ranges.push((glyph_id as u32, glyph_id as u32)),
+            None => break,
+        }
+    }
+}
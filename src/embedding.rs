@@ -0,0 +1,44 @@
+// font-kit/src/embedding.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Describes how a font's license permits it to be embedded in documents.
+
+const FS_TYPE_USAGE_PERMISSIONS_MASK: u16 = 0x000e;
+const FS_TYPE_RESTRICTED_LICENSE: u16 = 0x0002;
+const FS_TYPE_BITMAP_EMBEDDING_ONLY: u16 = 0x0200;
+
+/// Describes how a font's license permits it to be embedded in documents.
+///
+/// This is derived from the `fsType` field of the OpenType `OS/2` table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EmbeddingMode {
+    /// The font's outlines may be embedded.
+    FullOutline,
+    /// Only bitmaps may be embedded, so glyphs must be rasterized before embedding.
+    BitmapOnly,
+    /// The font must not be embedded at all.
+    Forbidden,
+}
+
+impl EmbeddingMode {
+    /// Interprets the `fsType` flags of the OpenType `OS/2` table.
+    ///
+    /// Fonts predating OpenType 1.3 may set several of the usage permission bits at once; as the
+    /// specification requires, the least restrictive one wins.
+    pub fn from_fs_type(fs_type: u16) -> EmbeddingMode {
+        if fs_type & FS_TYPE_USAGE_PERMISSIONS_MASK == FS_TYPE_RESTRICTED_LICENSE {
+            EmbeddingMode::Forbidden
+        } else if fs_type & FS_TYPE_BITMAP_EMBEDDING_ONLY != 0 {
+            EmbeddingMode::BitmapOnly
+        } else {
+            EmbeddingMode::FullOutline
+        }
+    }
+}
@@ -13,6 +13,7 @@
 use std::convert::From;
 use std::error::Error;
 use std::io;
+use std::sync::Arc;
 
 macro_rules! impl_display {
     ($enum:ident, {$($variant:pat => $fmt_string:expr),+$(,)* }) => {
@@ -47,16 +48,37 @@ pub enum FontLoadingError {
     NoFilesystem,
     /// A disk or similar I/O error occurred while attempting to load the font.
     Io(io::Error),
+    /// The checksums stored in the font data don't match the data, as reported by
+    /// `validate::validate_checksums`.
+    ///
+    /// This holds the tags of the tables whose checksums are wrong. The `head` table is listed if
+    /// the checksum of the whole font is wrong.
+    ChecksumMismatch(Vec<u32>),
 }
 
-impl Error for FontLoadingError {}
+impl Error for FontLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FontLoadingError::Io(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl_display! { FontLoadingError, {
-        UnknownFormat => "unknown format",
+        UnknownFormat => "unknown font format",
         NoSuchFontInCollection => "no such font in the collection",
-        Parse => "parse error",
+        Parse => "failed to parse font data",
         NoFilesystem => "no filesystem present",
         Io(e) => format!("I/O error: {}", e),
+        ChecksumMismatch(table_tags) => format!(
+            "checksum mismatch in tables: {}",
+            table_tags
+                .iter()
+                .map(|&table_tag| String::from_utf8_lossy(&table_tag.to_be_bytes()).into_owned())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
     }
 }
 
@@ -73,13 +95,16 @@ pub enum GlyphLoadingError {
     NoSuchGlyph,
     /// A platform function returned an error.
     PlatformError,
+    /// There wasn't enough memory for the glyph's bitmap, or its size overflowed.
+    OutOfMemory,
 }
 
 impl Error for GlyphLoadingError {}
 
 impl_display! { GlyphLoadingError, {
         NoSuchGlyph => "no such glyph",
-        PlatformError => "platform error",
+        PlatformError => "a platform function failed",
+        OutOfMemory => "out of memory for the glyph bitmap",
     }
 }
 
@@ -91,18 +116,114 @@ impl From<winapi::um::winnt::HRESULT> for GlyphLoadingError {
 }
 
 /// Reasons why a source might fail to look up a font or fonts.
-#[derive(Clone, Copy, PartialEq, Debug)]
+///
+/// Two I/O errors compare equal if they have the same kind and message.
+#[derive(Clone, Debug)]
 pub enum SelectionError {
     /// No font matching the given query was found.
     NotFound,
-    /// The source was inaccessible because of an I/O or similar error.
+    /// The source was inaccessible, such as because its fonts couldn't be read.
     CannotAccessSource,
+    /// A disk or similar I/O error occurred while accessing the source.
+    ///
+    /// The error is shared so that selection results, which caching sources keep, can be cloned.
+    Io(Arc<io::Error>),
 }
 
-impl Error for SelectionError {}
+impl Error for SelectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SelectionError::Io(ref error) => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for SelectionError {
+    fn eq(&self, other: &SelectionError) -> bool {
+        match (self, other) {
+            (SelectionError::NotFound, SelectionError::NotFound)
+            | (SelectionError::CannotAccessSource, SelectionError::CannotAccessSource) => true,
+            (SelectionError::Io(error), SelectionError::Io(other_error)) => {
+                error.kind() == other_error.kind() && error.to_string() == other_error.to_string()
+            }
+            _ => false,
+        }
+    }
+}
 
 impl_display! { SelectionError, {
         NotFound => "no font found",
         CannotAccessSource => "failed to access source",
+        Io(e) => format!("I/O error: {}", e),
+    }
+}
+
+impl From<io::Error> for SelectionError {
+    fn from(error: io::Error) -> SelectionError {
+        SelectionError::Io(Arc::new(error))
+    }
+}
+
+/// Reasons why a string might fail to parse as a font property.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PropertyParseError {
+    /// The string was neither a keyword for the property nor a number.
+    UnknownKeyword,
+    /// The string was a number outside the range of values of the property.
+    OutOfRange,
+}
+
+impl Error for PropertyParseError {}
+
+impl_display! { PropertyParseError, {
+        UnknownKeyword => "unknown keyword",
+        OutOfRange => "value out of range",
+    }
+}
+
+/// Reasons why a string might fail to render.
+#[derive(Debug)]
+pub enum RenderingError {
+    /// None of the requested font families could be found.
+    Selection(SelectionError),
+    /// None of the selected fonts could be loaded.
+    FontLoading(FontLoadingError),
+    /// A canvas to draw the text or one of its glyphs on couldn't be allocated.
+    GlyphLoading(GlyphLoadingError),
+}
+
+impl Error for RenderingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RenderingError::Selection(ref error) => Some(error),
+            RenderingError::FontLoading(ref error) => Some(error),
+            RenderingError::GlyphLoading(ref error) => Some(error),
+        }
+    }
+}
+
+impl_display! { RenderingError, {
+        Selection(e) => format!("font selection failed: {}", e),
+        FontLoading(e) => format!("font loading failed: {}", e),
+        GlyphLoading(e) => format!("glyph loading failed: {}", e),
+    }
+}
+
+impl From<SelectionError> for RenderingError {
+    fn from(error: SelectionError) -> RenderingError {
+        RenderingError::Selection(error)
+    }
+}
+
+impl From<FontLoadingError> for RenderingError {
+    fn from(error: FontLoadingError) -> RenderingError {
+        RenderingError::FontLoading(error)
+    }
+}
+
+impl From<GlyphLoadingError> for RenderingError {
+    fn from(error: GlyphLoadingError) -> RenderingError {
+        RenderingError::GlyphLoading(error)
     }
 }
@@ -22,6 +22,7 @@ pub struct Family<F = Font>
 where
     F: Loader,
 {
+    name: Option<String>,
     fonts: Vec<F>,
 }
 
@@ -37,12 +38,23 @@ where
         for font_handle in font_handles {
             fonts.push(F::from_handle(font_handle)?)
         }
-        Ok(Family { fonts })
+        Ok(Family { name: None, fonts })
     }
 
+    /// Loads all the fonts in the given family.
+    ///
+    /// The family keeps the name of the handle, if it has one.
     #[inline]
-    pub(crate) fn from_handle(family_handle: &FamilyHandle) -> Result<Family<F>, FontLoadingError> {
-        Family::from_font_handles(family_handle.fonts.iter())
+    pub fn from_handle(family_handle: &FamilyHandle) -> Result<Family<F>, FontLoadingError> {
+        let mut family = Family::from_font_handles(family_handle.fonts.iter())?;
+        family.name = family_handle.name.clone();
+        Ok(family)
+    }
+
+    /// Returns the name of the family, if the source that found it knows the name.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
     }
 
     /// Returns the individual fonts in this family.
@@ -51,6 +63,12 @@ where
         &self.fonts
     }
 
+    /// Returns the number of fonts in this family.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
     /// Returns true if and only if this family is empty.
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -13,8 +13,9 @@
 use crate::handle::Handle;
 
 /// Encapsulates the information needed to locate and open the fonts in a family.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FamilyHandle {
+    pub(crate) name: Option<String>,
     pub(crate) fonts: Vec<Handle>,
 }
 
@@ -22,7 +23,10 @@ impl FamilyHandle {
     /// Creates an empty set of family handles.
     #[inline]
     pub fn new() -> FamilyHandle {
-        FamilyHandle { fonts: vec![] }
+        FamilyHandle {
+            name: None,
+            fonts: vec![],
+        }
     }
 
     /// Creates a set of font family handles.
@@ -32,6 +36,7 @@ impl FamilyHandle {
         I: Iterator<Item = Handle>,
     {
         FamilyHandle {
+            name: None,
             fonts: fonts.collect::<Vec<Handle>>(),
         }
     }
@@ -42,6 +47,24 @@ impl FamilyHandle {
         self.fonts.push(font)
     }
 
+    /// Returns the name of the family, if the source that found it knows the name.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Sets the name of the family.
+    #[inline]
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name)
+    }
+
+    /// Returns the number of fonts in this set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
     /// Returns true if and only if this set has no fonts in it.
     #[inline]
     pub fn is_empty(&self) -> bool {
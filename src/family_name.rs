@@ -10,13 +10,17 @@
 
 //! A possible value for the `font-family` CSS property.
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// A possible value for the `font-family` CSS property.
 ///
 /// These descriptions are taken from CSS Fonts Level 3 § 3.1:
 /// https://drafts.csswg.org/css-fonts-3/#font-family-prop.
 ///
 /// TODO(pcwalton): `system-ui`, `emoji`, `math`, `fangsong`
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FamilyName {
     /// A specific font family, specified by name: e.g. "Arial", "times".
     Title(String),
@@ -12,4 +12,33 @@
 //!
 //! The Font type in this crate represents the default loader.
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub use crate::loaders::default::Font;
+
+/// The index of a font within a TrueType/OpenType collection (`.ttc`/`.otc`/etc.).
+///
+/// Files that contain a single font only have one valid index, `FaceIndex::FIRST`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FaceIndex(pub u32);
+
+impl FaceIndex {
+    /// The first font in a collection, and the only font in a single-font file.
+    pub const FIRST: FaceIndex = FaceIndex(0);
+}
+
+impl From<u32> for FaceIndex {
+    #[inline]
+    fn from(index: u32) -> FaceIndex {
+        FaceIndex(index)
+    }
+}
+
+impl From<FaceIndex> for u32 {
+    #[inline]
+    fn from(index: FaceIndex) -> u32 {
+        index.0
+    }
+}
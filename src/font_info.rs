@@ -0,0 +1,60 @@
+// font-kit/src/font_info.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The names and properties of a font, read without loading it.
+
+use std::io::Cursor;
+
+use crate::error::FontLoadingError;
+use crate::font::FaceIndex;
+use crate::properties::Properties;
+use crate::sfnt::{self, FontTables};
+
+/// The names and properties of a font, as read by `Loader::peek_info`.
+///
+/// This is the information that a font index usually needs, and it is much cheaper to get than
+/// from a loaded font.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontInfo {
+    /// The PostScript name of the font, if it has one.
+    pub postscript_name: Option<String>,
+    /// The name of the font family, preferring the typographic family name.
+    pub family_name: String,
+    /// The human-readable name of the font, falling back to the family name.
+    pub full_name: String,
+    /// The values of various font properties, corresponding to those defined in CSS.
+    pub properties: Properties,
+}
+
+impl FontInfo {
+    /// Reads the names and properties of the font with the given index in a TrueType/OpenType
+    /// file or collection.
+    ///
+    /// Only the table directory and the `name`, `OS/2`, and `head` tables are parsed, along with
+    /// the `post` table of oblique fonts. The font must have a `name` table that names its family.
+    pub(crate) fn from_bytes(
+        font_data: &[u8],
+        font_index: FaceIndex,
+    ) -> Result<FontInfo, FontLoadingError> {
+        let mut font_tables = FontTables::new(Cursor::new(font_data), font_index)?;
+        let name_table = font_tables
+            .load_table(sfnt::NAME_TABLE_TAG)?
+            .ok_or(FontLoadingError::Parse)?;
+        let properties = font_tables.properties()?;
+
+        let family_name = sfnt::family_name(&name_table).ok_or(FontLoadingError::Parse)?;
+        Ok(FontInfo {
+            postscript_name: sfnt::postscript_name(&name_table),
+            full_name: sfnt::full_name(&name_table).unwrap_or_else(|| family_name.clone()),
+            family_name,
+            properties,
+        })
+    }
+}
@@ -14,11 +14,18 @@
 //!
 //! To open the font referenced by a handle, use a loader.
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Cursor;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::error::FontLoadingError;
-use crate::font::Font;
+use crate::font::{FaceIndex, Font};
+use crate::post;
+use crate::properties::Properties;
+use crate::sfnt::{self, FontTables, ReadSeek};
 
 /// Encapsulates the information needed to locate and open a font.
 ///
@@ -26,6 +33,7 @@ use crate::font::Font;
 ///
 /// To open the font referenced by a handle, use a loader.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Handle {
     /// A font on disk referenced by a path.
     Path {
@@ -33,17 +41,18 @@ pub enum Handle {
         path: PathBuf,
         /// The index of the font, if the path refers to a collection.
         ///
-        /// If the path refers to a single font, this value will be 0.
-        font_index: u32,
+        /// If the path refers to a single font, this value will be `FaceIndex::FIRST`.
+        font_index: FaceIndex,
     },
     /// A font in memory.
     Memory {
         /// The raw TrueType/OpenType/etc. data that makes up this font.
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
         bytes: Arc<Vec<u8>>,
         /// The index of the font, if the memory consists of a collection.
         ///
-        /// If the memory consists of a single font, this value will be 0.
-        font_index: u32,
+        /// If the memory consists of a single font, this value will be `FaceIndex::FIRST`.
+        font_index: FaceIndex,
     },
 }
 
@@ -51,18 +60,20 @@ impl Handle {
     /// Creates a new handle from a path.
     ///
     /// `font_index` specifies the index of the font to choose if the path points to a font
-    /// collection. If the path points to a single font file, pass 0.
+    /// collection. If the path points to a single font file, pass
+    /// `FaceIndex::FIRST`.
     #[inline]
-    pub fn from_path(path: PathBuf, font_index: u32) -> Handle {
+    pub fn from_path(path: PathBuf, font_index: FaceIndex) -> Handle {
         Handle::Path { path, font_index }
     }
 
     /// Creates a new handle from raw TTF/OTF/etc. data in memory.
     ///
     /// `font_index` specifies the index of the font to choose if the memory represents a font
-    /// collection. If the memory represents a single font file, pass 0.
+    /// collection. If the memory represents a single font file, pass
+    /// `FaceIndex::FIRST`.
     #[inline]
-    pub fn from_memory(bytes: Arc<Vec<u8>>, font_index: u32) -> Handle {
+    pub fn from_memory(bytes: Arc<Vec<u8>>, font_index: FaceIndex) -> Handle {
         Handle::Memory { bytes, font_index }
     }
 
@@ -71,4 +82,131 @@ impl Handle {
     pub fn load(&self) -> Result<Font, FontLoadingError> {
         Font::from_handle(self)
     }
+
+    /// Returns the values of various font properties, corresponding to those defined in CSS,
+    /// without loading the font.
+    ///
+    /// Only the table directory and the `OS/2` and `head` tables are read, along with the `post`
+    /// table of oblique fonts, which is much cheaper than creating a `Font`. The result matches
+    /// what the FreeType loader reports; other loaders may apply their own heuristics and
+    /// occasionally disagree.
+    pub fn properties(&self) -> Result<Properties, FontLoadingError> {
+        self.font_tables()?.properties()
+    }
+
+    /// Returns the name of the font family without loading the font.
+    ///
+    /// Only the table directory and the `name` table are read. The typographic family name is
+    /// preferred, as most loaders do.
+    pub fn family_name(&self) -> Result<String, FontLoadingError> {
+        let name_table = self.name_table()?;
+        sfnt::family_name(&name_table).ok_or(FontLoadingError::Parse)
+    }
+
+    /// Returns the PostScript name of the font without loading the font.
+    ///
+    /// Only the table directory and the `name` table are read.
+    pub fn postscript_name(&self) -> Result<String, FontLoadingError> {
+        let name_table = self.name_table()?;
+        sfnt::postscript_name(&name_table).ok_or(FontLoadingError::Parse)
+    }
+
+    /// Returns true if and only if the font is monospace (fixed-width), without loading the font.
+    ///
+    /// Only the table directory and the `post` table are read, as the default implementation of
+    /// `Loader::is_monospace` does. Fonts without a `post` table are reported as proportional.
+    pub fn is_monospace(&self) -> Result<bool, FontLoadingError> {
+        match self.font_tables()?.load_table(post::POST_TABLE_TAG)? {
+            Some(post_table) => Ok(post::is_fixed_pitch(&post_table).unwrap_or(false)),
+            None => Ok(false),
+        }
+    }
+
+    // Reads the tags of the tables in the font from its table directory.
+    pub(crate) fn table_tags(&self) -> Result<Vec<u32>, FontLoadingError> {
+        Ok(self.font_tables()?.table_tags())
+    }
+
+    // Reads the `name` table of the font, which must have one.
+    pub(crate) fn name_table(&self) -> Result<Vec<u8>, FontLoadingError> {
+        self.font_tables()?
+            .load_table(sfnt::NAME_TABLE_TAG)?
+            .ok_or(FontLoadingError::Parse)
+    }
+
+    fn font_tables(&self) -> Result<FontTables<Box<dyn ReadSeek + '_>>, FontLoadingError> {
+        match *self {
+            Handle::Path {
+                ref path,
+                font_index,
+            } => FontTables::new(Box::new(File::open(path)?), font_index),
+            Handle::Memory {
+                ref bytes,
+                font_index,
+            } => FontTables::new(Box::new(Cursor::new(&bytes[..])), font_index),
+        }
+    }
+}
+
+/// Serializes the font data of `Handle::Memory` as a byte string rather than as a sequence of
+/// individual integers, so that compact formats such as bincode don't bloat it.
+#[cfg(feature = "serde")]
+mod serde_bytes {
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt::{self, Formatter};
+    use std::sync::Arc;
+
+    pub fn serialize<S>(bytes: &Arc<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Arc<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_byte_buf(BytesVisitor)
+            .map(Arc::new)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+            formatter.write_str("font data as a byte string")
+        }
+
+        fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Vec<u8>, E>
+        where
+            E: de::Error,
+        {
+            Ok(bytes.to_vec())
+        }
+
+        fn visit_byte_buf<E>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E>
+        where
+            E: de::Error,
+        {
+            Ok(bytes)
+        }
+
+        // Self-describing formats without a native byte string type, such as JSON, hand us a
+        // sequence of integers instead.
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
 }
@@ -40,6 +40,13 @@ pub enum HintingOptions {
     /// This corresponds to what GDI in non-ClearType modes and FreeType in its normal hinting mode
     /// do.
     Full(f32),
+
+    /// Hinting is performed in both horizontal and vertical directions by FreeType's autohinter,
+    /// which ignores any hints in the font. The specified point size is used for grid fitting.
+    ///
+    /// This helps fonts that have no hints of their own. Loaders other than FreeType don't have
+    /// the autohinter and treat this as `None`.
+    AutoHint(f32),
 }
 
 impl HintingOptions {
@@ -50,7 +57,8 @@ impl HintingOptions {
             HintingOptions::None => None,
             HintingOptions::Vertical(size)
             | HintingOptions::VerticalSubpixel(size)
-            | HintingOptions::Full(size) => Some(size),
+            | HintingOptions::Full(size)
+            | HintingOptions::AutoHint(size) => Some(size),
         }
     }
 }
@@ -0,0 +1,65 @@
+// font-kit/src/identity.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The identity of a loaded font, which the loaders use to implement `PartialEq` and `Hash`.
+
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use twox_hash::XxHash64;
+
+use crate::font::FaceIndex;
+
+/// What makes two loaded fonts the same font.
+///
+/// Fonts whose data is held in memory are identified by the data itself. Fonts whose data is left
+/// in a file are identified by the file instead, so that comparing them doesn't read the whole
+/// file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum FontIdentity {
+    /// A font whose data is in memory.
+    Data {
+        postscript_name: Option<String>,
+        /// The 64-bit xxHash of the font data.
+        data_hash: u64,
+    },
+    /// A font whose data is in a file.
+    File {
+        postscript_name: Option<String>,
+        /// The canonical path of the file.
+        path: PathBuf,
+        font_index: FaceIndex,
+    },
+}
+
+impl FontIdentity {
+    /// Returns the 64-bit xxHash of this identity.
+    pub(crate) fn to_u64(&self) -> u64 {
+        let mut hasher = XxHash64::with_seed(0);
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Returns the 64-bit xxHash of the given font data, computing it only the first time.
+pub(crate) fn font_data_hash(cache: &Mutex<Option<u64>>, font_data: &[u8]) -> u64 {
+    let mut cache = cache.lock().unwrap();
+    *cache.get_or_insert_with(|| {
+        let mut hasher = XxHash64::with_seed(0);
+        hasher.write(font_data);
+        hasher.finish()
+    })
+}
+
+/// Resolves symbolic links and relative components in a path, so that fonts loaded through
+/// different paths to the same file compare equal. Paths that can't be resolved are kept as is.
+pub(crate) fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
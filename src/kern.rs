@@ -0,0 +1,110 @@
+// font-kit/src/kern.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading of kerning pairs from the legacy TrueType `kern` table.
+
+use crate::sfnt::{read_u16, read_u32};
+
+pub(crate) const KERN_TABLE_TAG: u32 = 0x6b65_726e;
+
+// The coverage flags of the subtables of the OpenType version of the table.
+const OPENTYPE_COVERAGE_HORIZONTAL: u16 = 0x0001;
+const OPENTYPE_COVERAGE_MINIMUM: u16 = 0x0002;
+const OPENTYPE_COVERAGE_CROSS_STREAM: u16 = 0x0004;
+const OPENTYPE_COVERAGE_OVERRIDE: u16 = 0x0008;
+
+// The coverage flags of the subtables of the Apple version of the table.
+const APPLE_COVERAGE_VERTICAL: u16 = 0x8000;
+const APPLE_COVERAGE_CROSS_STREAM: u16 = 0x4000;
+const APPLE_COVERAGE_VARIATION: u16 = 0x2000;
+
+/// Returns the horizontal kerning value that a `kern` table gives the pair of glyphs, or `None`
+/// if none of its subtables lists the pair.
+///
+/// Both the OpenType version of the table and the Apple version with a 32-bit header are
+/// understood, but only ordered list subtables (format 0) are read. Values from several
+/// subtables are added together unless a subtable overrides the ones before it. Subtables for
+/// vertical text, cross-stream kerning, minimum values, and font variations are skipped.
+pub(crate) fn kerning_for_pair(
+    kern_table: &[u8],
+    left_glyph: u32,
+    right_glyph: u32,
+) -> Option<i32> {
+    // Glyph IDs in the table are 16 bits wide.
+    if left_glyph > 0xffff || right_glyph > 0xffff {
+        return None;
+    }
+    let key = (left_glyph << 16) | right_glyph;
+
+    // Each subtable is described by its format, whether it applies to horizontal kerning, whether
+    // it overrides the subtables before it, and the offset of its format-specific data.
+    let (subtable_count, mut subtable_offset, apple) = match read_u16(kern_table, 0)? {
+        0 => (read_u16(kern_table, 2)? as u32, 4, false),
+        1 => (read_u32(kern_table, 4)?, 8, true),
+        _ => return None,
+    };
+    let mut total_kerning = None;
+    for _ in 0..subtable_count {
+        let (length, format, applies, overrides) = if apple {
+            let length = read_u32(kern_table, subtable_offset)? as usize;
+            let coverage = read_u16(kern_table, subtable_offset + 4)?;
+            let flags =
+                APPLE_COVERAGE_VERTICAL | APPLE_COVERAGE_CROSS_STREAM | APPLE_COVERAGE_VARIATION;
+            (length, coverage & 0xff, coverage & flags == 0, false)
+        } else {
+            let length = read_u16(kern_table, subtable_offset + 2)? as usize;
+            let coverage = read_u16(kern_table, subtable_offset + 4)?;
+            let flags = OPENTYPE_COVERAGE_MINIMUM | OPENTYPE_COVERAGE_CROSS_STREAM;
+            let applies = coverage & OPENTYPE_COVERAGE_HORIZONTAL != 0 && coverage & flags == 0;
+            (
+                length,
+                coverage >> 8,
+                applies,
+                coverage & OPENTYPE_COVERAGE_OVERRIDE != 0,
+            )
+        };
+        let data_offset = subtable_offset + if apple { 8 } else { 6 };
+        if applies && format == 0 {
+            if let Some(kerning) = format_0_kerning(kern_table, data_offset, key) {
+                total_kerning = match total_kerning {
+                    Some(total_kerning) if !overrides => Some(total_kerning + kerning as i32),
+                    _ => Some(kerning as i32),
+                };
+            }
+        }
+        if length == 0 {
+            break;
+        }
+        subtable_offset += length;
+    }
+    total_kerning
+}
+
+// Looks up a pair, given as the left glyph in the high 16 bits and the right glyph in the low 16
+// bits, in the sorted list of pairs of a format 0 subtable.
+fn format_0_kerning(kern_table: &[u8], data_offset: usize, key: u32) -> Option<i16> {
+    let pair_count = read_u16(kern_table, data_offset)? as usize;
+    let pairs_offset = data_offset + 8;
+    let (mut low, mut high) = (0, pair_count);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let pair_offset = pairs_offset + middle * 6;
+        let pair_key = read_u32(kern_table, pair_offset)?;
+        if pair_key == key {
+            return read_u16(kern_table, pair_offset + 4).map(|kerning| kerning as i16);
+        }
+        if pair_key < key {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    None
+}
@@ -0,0 +1,536 @@
+// font-kit/src/layout.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading of the script, language, and feature information and the ligatures in the OpenType
+//! `GSUB` and `GPOS` tables.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::loader::Loader;
+use crate::sfnt::{read_u16, read_u32};
+
+pub(crate) const GSUB_TABLE_TAG: u32 = 0x4753_5542;
+pub(crate) const GPOS_TABLE_TAG: u32 = 0x4750_4f53;
+
+const GSUB_LIGATURE_LOOKUP_TYPE: u16 = 4;
+const GSUB_EXTENSION_LOOKUP_TYPE: u16 = 7;
+const GPOS_PAIR_LOOKUP_TYPE: u16 = 2;
+const GPOS_EXTENSION_LOOKUP_TYPE: u16 = 9;
+
+// The bit of a ValueFormat that says whether a ValueRecord has an XAdvance field, and the bits of
+// the fields before it.
+const VALUE_FORMAT_X_ADVANCE: u16 = 0x0004;
+const VALUE_FORMAT_PLACEMENT: u16 = 0x0003;
+
+/// Loads the `GSUB` table of the given font, falling back to its `GPOS` table if it has none.
+pub(crate) fn load_layout_table<F>(font: &F) -> Option<Box<[u8]>>
+where
+    F: Loader,
+{
+    font.load_font_table(GSUB_TABLE_TAG)
+        .or_else(|| font.load_font_table(GPOS_TABLE_TAG))
+}
+
+/// Returns the tags of the scripts in the ScriptList of a `GSUB` or `GPOS` table.
+pub(crate) fn script_tags(layout_table: &[u8]) -> Vec<[u8; 4]> {
+    let script_list_offset = match read_u16(layout_table, 4) {
+        Some(offset) => offset as usize,
+        None => return vec![],
+    };
+    tagged_records(layout_table, script_list_offset)
+        .into_iter()
+        .map(|(tag, _)| tag)
+        .collect()
+}
+
+/// Returns the tags of the language systems defined for the given script in a `GSUB` or `GPOS`
+/// table, not including the default language system.
+pub(crate) fn language_tags(layout_table: &[u8], script: [u8; 4]) -> Vec<[u8; 4]> {
+    let script_list_offset = match read_u16(layout_table, 4) {
+        Some(offset) => offset as usize,
+        None => return vec![],
+    };
+    let script_offset = match tagged_records(layout_table, script_list_offset)
+        .into_iter()
+        .find(|&(tag, _)| tag == script)
+    {
+        Some((_, offset)) => script_list_offset + offset as usize,
+        None => return vec![],
+    };
+    // A Script table starts with the offset of its default language system.
+    tagged_records(layout_table, script_offset + 2)
+        .into_iter()
+        .map(|(tag, _)| tag)
+        .collect()
+}
+
+/// Returns the tags of the features in the FeatureList of a `GSUB` or `GPOS` table.
+///
+/// A tag may appear more than once, since a font can define several features with the same tag
+/// for different scripts and languages.
+pub(crate) fn feature_tags(layout_table: &[u8]) -> Vec<[u8; 4]> {
+    let feature_list_offset = match read_u16(layout_table, 6) {
+        Some(offset) => offset as usize,
+        None => return vec![],
+    };
+    tagged_records(layout_table, feature_list_offset)
+        .into_iter()
+        .map(|(tag, _)| tag)
+        .collect()
+}
+
+/// Returns the tags of the features that a `GSUB` or `GPOS` table enables for the given script
+/// and language system, including the required feature if there is one.
+///
+/// If the table doesn't list the script, the `DFLT` script is used instead, as the OpenType
+/// specification recommends. Likewise, the script's default language system is used if
+/// `language` is `None` or isn't listed for the script.
+pub(crate) fn script_feature_tags(
+    layout_table: &[u8],
+    script: [u8; 4],
+    language: Option<[u8; 4]>,
+) -> Vec<[u8; 4]> {
+    let (script_list_offset, feature_list_offset) =
+        match (read_u16(layout_table, 4), read_u16(layout_table, 6)) {
+            (Some(script_list_offset), Some(feature_list_offset)) => {
+                (script_list_offset as usize, feature_list_offset as usize)
+            }
+            _ => return vec![],
+        };
+    let script_records = tagged_records(layout_table, script_list_offset);
+    let script_offset = match script_records
+        .iter()
+        .find(|&&(tag, _)| tag == script)
+        .or_else(|| script_records.iter().find(|&&(tag, _)| tag == *b"DFLT"))
+    {
+        Some(&(_, offset)) => script_list_offset + offset as usize,
+        None => return vec![],
+    };
+
+    let language_offset = language.and_then(|language| {
+        tagged_records(layout_table, script_offset + 2)
+            .into_iter()
+            .find(|&(tag, _)| tag == language)
+            .map(|(_, offset)| offset)
+    });
+    let language_offset = match language_offset.or_else(|| read_u16(layout_table, script_offset)) {
+        Some(offset) if offset != 0 => script_offset + offset as usize,
+        _ => return vec![],
+    };
+
+    // A LangSys table consists of a reserved offset, the index of the required feature (0xffff if
+    // there is none), and a counted list of feature indices.
+    let mut feature_indices = vec![];
+    match read_u16(layout_table, language_offset + 2) {
+        Some(0xffff) | None => {}
+        Some(required_feature_index) => feature_indices.push(required_feature_index),
+    }
+    let feature_index_count = read_u16(layout_table, language_offset + 4).unwrap_or(0) as usize;
+    for index in 0..feature_index_count {
+        match read_u16(layout_table, language_offset + 6 + index * 2) {
+            Some(feature_index) => feature_indices.push(feature_index),
+            None => break,
+        }
+    }
+
+    let feature_records = tagged_records(layout_table, feature_list_offset);
+    feature_indices
+        .into_iter()
+        .filter_map(|feature_index| feature_records.get(feature_index as usize))
+        .map(|&(tag, _)| tag)
+        .collect()
+}
+
+/// Returns the ligature glyph that the given feature of a `GSUB` table substitutes for exactly the
+/// given sequence of glyphs, if any.
+///
+/// Only ligature substitution lookups (type 4, format 1) are consulted, including those wrapped in
+/// extension lookups. The lookups are tried in the order in which the feature lists them.
+pub(crate) fn ligature_for(gsub_table: &[u8], feature: [u8; 4], glyphs: &[u32]) -> Option<u32> {
+    if glyphs.len() < 2 {
+        return None;
+    }
+    find_ligature(gsub_table, feature, glyphs, true).map(|(ligature, _)| ligature)
+}
+
+/// Returns the first ligature that the given feature of a `GSUB` table forms from the glyphs at
+/// the start of the given sequence, along with the number of glyphs that it replaces.
+///
+/// The lookups are tried in the same order as in `ligature_for`, and the ligatures within a
+/// lookup in the order in which the font lists them, which by convention puts longer ligatures
+/// first.
+pub(crate) fn ligature_at_start(
+    gsub_table: &[u8],
+    feature: [u8; 4],
+    glyphs: &[u32],
+) -> Option<(u32, usize)> {
+    find_ligature(gsub_table, feature, glyphs, false)
+}
+
+// Looks up a ligature in the lookups of a feature. If `whole` is true, the ligature must consume
+// all of the glyphs; otherwise, it may consume any number of them from the start.
+fn find_ligature(
+    gsub_table: &[u8],
+    feature: [u8; 4],
+    glyphs: &[u32],
+    whole: bool,
+) -> Option<(u32, usize)> {
+    // Glyph IDs in layout tables are 16 bits wide, so no ligature can reach past a wider one.
+    let glyph_count = glyphs
+        .iter()
+        .position(|&glyph| glyph > 0xffff)
+        .unwrap_or(glyphs.len());
+    if glyph_count == 0 || (whole && glyph_count < glyphs.len()) {
+        return None;
+    }
+    let glyphs = &glyphs[..glyph_count];
+
+    for lookup_offset in feature_lookups(gsub_table, feature) {
+        if let Some(ligature) = ligature_in_lookup(gsub_table, lookup_offset, glyphs, whole) {
+            return Some(ligature);
+        }
+    }
+    None
+}
+
+// Looks up a sequence of glyphs in the subtables of a ligature substitution lookup.
+fn ligature_in_lookup(
+    gsub_table: &[u8],
+    lookup_offset: usize,
+    glyphs: &[u32],
+    whole: bool,
+) -> Option<(u32, usize)> {
+    for (subtable_type, subtable_offset) in
+        lookup_subtables(gsub_table, lookup_offset, GSUB_EXTENSION_LOOKUP_TYPE)
+    {
+        if subtable_type != GSUB_LIGATURE_LOOKUP_TYPE {
+            continue;
+        }
+        if let Some(ligature) = ligature_in_subtable(gsub_table, subtable_offset, glyphs, whole) {
+            return Some(ligature);
+        }
+    }
+    None
+}
+
+// Looks up a sequence of glyphs in a ligature substitution subtable.
+fn ligature_in_subtable(
+    gsub_table: &[u8],
+    subtable_offset: usize,
+    glyphs: &[u32],
+    whole: bool,
+) -> Option<(u32, usize)> {
+    if read_u16(gsub_table, subtable_offset)? != 1 {
+        return None;
+    }
+    let coverage_offset = subtable_offset + read_u16(gsub_table, subtable_offset + 2)? as usize;
+    let ligature_set_index = coverage_index(gsub_table, coverage_offset, glyphs[0])?;
+    let ligature_set_count = read_u16(gsub_table, subtable_offset + 4)? as usize;
+    if ligature_set_index >= ligature_set_count {
+        return None;
+    }
+    let ligature_set_offset = subtable_offset
+        + read_u16(gsub_table, subtable_offset + 6 + ligature_set_index * 2)? as usize;
+
+    // Each Ligature table holds the ligature glyph, the number of components, and the components
+    // after the first.
+    let ligature_count = read_u16(gsub_table, ligature_set_offset)? as usize;
+    for ligature_index in 0..ligature_count {
+        let ligature_offset = ligature_set_offset
+            + read_u16(gsub_table, ligature_set_offset + 2 + ligature_index * 2)? as usize;
+        let component_count = read_u16(gsub_table, ligature_offset + 2)? as usize;
+        let fits = if whole {
+            component_count == glyphs.len()
+        } else {
+            (1..=glyphs.len()).contains(&component_count)
+        };
+        if !fits {
+            continue;
+        }
+        let components_match =
+            glyphs[1..component_count]
+                .iter()
+                .enumerate()
+                .all(|(index, &glyph)| {
+                    read_u16(gsub_table, ligature_offset + 4 + index * 2) == Some(glyph as u16)
+                });
+        if components_match {
+            return read_u16(gsub_table, ligature_offset)
+                .map(|ligature| (ligature as u32, component_count));
+        }
+    }
+    None
+}
+
+/// Returns the horizontal advance adjustment that the given feature of a `GPOS` table applies
+/// to the first glyph of a pair, or `None` if none of its lookups adjusts the pair.
+///
+/// Only pair adjustment lookups (type 2, formats 1 and 2) are consulted, including those wrapped
+/// in extension lookups. The adjustments of all the feature's lookups are added together, and
+/// within a lookup, the first subtable that covers the pair applies.
+pub(crate) fn pair_adjustment(
+    gpos_table: &[u8],
+    feature: [u8; 4],
+    left_glyph: u32,
+    right_glyph: u32,
+) -> Option<i32> {
+    // Glyph IDs in layout tables are 16 bits wide.
+    if left_glyph > 0xffff || right_glyph > 0xffff {
+        return None;
+    }
+    let mut total_adjustment = None;
+    for lookup_offset in feature_lookups(gpos_table, feature) {
+        for (subtable_type, subtable_offset) in
+            lookup_subtables(gpos_table, lookup_offset, GPOS_EXTENSION_LOOKUP_TYPE)
+        {
+            if subtable_type != GPOS_PAIR_LOOKUP_TYPE {
+                continue;
+            }
+            let adjustment = pair_adjustment_in_subtable(
+                gpos_table,
+                subtable_offset,
+                left_glyph as u16,
+                right_glyph as u16,
+            );
+            if let Some(adjustment) = adjustment {
+                total_adjustment = Some(total_adjustment.unwrap_or(0) + adjustment as i32);
+                break;
+            }
+        }
+    }
+    total_adjustment
+}
+
+// Looks up a pair of glyphs in a pair adjustment subtable, returning the XAdvance of the first
+// glyph's ValueRecord, which is zero if the record has none.
+fn pair_adjustment_in_subtable(
+    gpos_table: &[u8],
+    subtable_offset: usize,
+    left_glyph: u16,
+    right_glyph: u16,
+) -> Option<i16> {
+    let format = read_u16(gpos_table, subtable_offset)?;
+    let coverage_offset = subtable_offset + read_u16(gpos_table, subtable_offset + 2)? as usize;
+    let coverage_index = coverage_index(gpos_table, coverage_offset, left_glyph as u32)?;
+    let value_format_1 = read_u16(gpos_table, subtable_offset + 4)?;
+    let value_format_2 = read_u16(gpos_table, subtable_offset + 6)?;
+    let value_record_1_size = value_record_size(value_format_1);
+    let value_record_2_size = value_record_size(value_format_2);
+
+    let value_record_offset = match format {
+        // Pairs of individual glyphs, with a PairSet table of second glyphs for each first glyph.
+        1 => {
+            let pair_set_count = read_u16(gpos_table, subtable_offset + 8)? as usize;
+            if coverage_index >= pair_set_count {
+                return None;
+            }
+            let pair_set_offset = subtable_offset
+                + read_u16(gpos_table, subtable_offset + 10 + coverage_index * 2)? as usize;
+            let pair_value_count = read_u16(gpos_table, pair_set_offset)? as usize;
+            let pair_value_record_size = 2 + value_record_1_size + value_record_2_size;
+            let pair_value_index = (0..pair_value_count).find(|&index| {
+                let pair_value_offset = pair_set_offset + 2 + index * pair_value_record_size;
+                read_u16(gpos_table, pair_value_offset) == Some(right_glyph)
+            })?;
+            pair_set_offset + 2 + pair_value_index * pair_value_record_size + 2
+        }
+        // Pairs of glyph classes, with a record for every combination of classes.
+        2 => {
+            let class_def_1_offset =
+                subtable_offset + read_u16(gpos_table, subtable_offset + 8)? as usize;
+            let class_def_2_offset =
+                subtable_offset + read_u16(gpos_table, subtable_offset + 10)? as usize;
+            let class_1_count = read_u16(gpos_table, subtable_offset + 12)? as usize;
+            let class_2_count = read_u16(gpos_table, subtable_offset + 14)? as usize;
+            let class_1 = glyph_class(gpos_table, class_def_1_offset, left_glyph)? as usize;
+            let class_2 = glyph_class(gpos_table, class_def_2_offset, right_glyph)? as usize;
+            if class_1 >= class_1_count || class_2 >= class_2_count {
+                return None;
+            }
+            let class_2_record_size = value_record_1_size + value_record_2_size;
+            subtable_offset + 16 + (class_1 * class_2_count + class_2) * class_2_record_size
+        }
+        _ => return None,
+    };
+
+    if value_format_1 & VALUE_FORMAT_X_ADVANCE == 0 {
+        return Some(0);
+    }
+    let x_advance_offset =
+        value_record_offset + value_record_size(value_format_1 & VALUE_FORMAT_PLACEMENT);
+    read_u16(gpos_table, x_advance_offset).map(|x_advance| x_advance as i16)
+}
+
+// Returns the size of a ValueRecord in bytes: two for each field that its ValueFormat includes.
+fn value_record_size(value_format: u16) -> usize {
+    value_format.count_ones() as usize * 2
+}
+
+// Returns the class of a glyph in a ClassDef table. Glyphs that the table doesn't mention are in
+// class 0.
+fn glyph_class(table: &[u8], class_def_offset: usize, glyph: u16) -> Option<u16> {
+    match read_u16(table, class_def_offset)? {
+        // Classes of a consecutive run of glyphs.
+        1 => {
+            let start_glyph = read_u16(table, class_def_offset + 2)?;
+            let glyph_count = read_u16(table, class_def_offset + 4)?;
+            if glyph < start_glyph || glyph - start_glyph >= glyph_count {
+                return Some(0);
+            }
+            read_u16(
+                table,
+                class_def_offset + 6 + (glyph - start_glyph) as usize * 2,
+            )
+        }
+        // Sorted ranges of glyphs, each with its class.
+        2 => {
+            let range_count = read_u16(table, class_def_offset + 2)? as usize;
+            for range_index in 0..range_count {
+                let range_offset = class_def_offset + 4 + range_index * 6;
+                let start_glyph = read_u16(table, range_offset)?;
+                let end_glyph = read_u16(table, range_offset + 2)?;
+                if glyph >= start_glyph && glyph <= end_glyph {
+                    return read_u16(table, range_offset + 4);
+                }
+            }
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+// Returns the offsets of the lookups that the features with the given tag list, in order. Fonts
+// usually define a feature once for each script, so lookups listed by several of them are only
+// returned once. Lists that point outside the table are cut short.
+fn feature_lookups(layout_table: &[u8], feature: [u8; 4]) -> Vec<usize> {
+    let mut lookup_offsets = vec![];
+    let (feature_list_offset, lookup_list_offset) =
+        match (read_u16(layout_table, 6), read_u16(layout_table, 8)) {
+            (Some(feature_list_offset), Some(lookup_list_offset)) => {
+                (feature_list_offset as usize, lookup_list_offset as usize)
+            }
+            _ => return lookup_offsets,
+        };
+    for (tag, feature_offset) in tagged_records(layout_table, feature_list_offset) {
+        if tag != feature {
+            continue;
+        }
+        // A Feature table consists of an offset to its parameters and a counted list of lookup
+        // indices.
+        let feature_offset = feature_list_offset + feature_offset as usize;
+        let lookup_index_count = match read_u16(layout_table, feature_offset + 2) {
+            Some(lookup_index_count) => lookup_index_count as usize,
+            None => continue,
+        };
+        for index in 0..lookup_index_count {
+            let lookup_index = match read_u16(layout_table, feature_offset + 4 + index * 2) {
+                Some(lookup_index) => lookup_index as usize,
+                None => break,
+            };
+            let lookup_offset =
+                match read_u16(layout_table, lookup_list_offset + 2 + lookup_index * 2) {
+                    Some(lookup_offset) => lookup_list_offset + lookup_offset as usize,
+                    None => break,
+                };
+            if !lookup_offsets.contains(&lookup_offset) {
+                lookup_offsets.push(lookup_offset);
+            }
+        }
+    }
+    lookup_offsets
+}
+
+// Returns the type and offset of each subtable of a lookup, looking through extension subtables,
+// which hold the real lookup type and a 32-bit offset to the real subtable.
+fn lookup_subtables(
+    layout_table: &[u8],
+    lookup_offset: usize,
+    extension_lookup_type: u16,
+) -> Vec<(u16, usize)> {
+    let mut subtables = vec![];
+    let (lookup_type, subtable_count) = match (
+        read_u16(layout_table, lookup_offset),
+        read_u16(layout_table, lookup_offset + 4),
+    ) {
+        (Some(lookup_type), Some(subtable_count)) => (lookup_type, subtable_count as usize),
+        _ => return subtables,
+    };
+    for subtable_index in 0..subtable_count {
+        let mut subtable_offset =
+            match read_u16(layout_table, lookup_offset + 6 + subtable_index * 2) {
+                Some(subtable_offset) => lookup_offset + subtable_offset as usize,
+                None => break,
+            };
+        let mut subtable_type = lookup_type;
+        if subtable_type == extension_lookup_type {
+            match (
+                read_u16(layout_table, subtable_offset + 2),
+                read_u32(layout_table, subtable_offset + 4),
+            ) {
+                (Some(extension_type), Some(extension_offset)) => {
+                    subtable_type = extension_type;
+                    subtable_offset += extension_offset as usize;
+                }
+                _ => continue,
+            }
+        }
+        subtables.push((subtable_type, subtable_offset));
+    }
+    subtables
+}
+
+// Returns the index of a glyph in a Coverage table, if the table covers it.
+fn coverage_index(table: &[u8], coverage_offset: usize, glyph: u32) -> Option<usize> {
+    match read_u16(table, coverage_offset)? {
+        // A sorted list of glyphs.
+        1 => {
+            let glyph_count = read_u16(table, coverage_offset + 2)? as usize;
+            (0..glyph_count).find(|&index| {
+                read_u16(table, coverage_offset + 4 + index * 2) == Some(glyph as u16)
+            })
+        }
+        // Sorted ranges of glyphs, each with the coverage index of its first glyph.
+        2 => {
+            let range_count = read_u16(table, coverage_offset + 2)? as usize;
+            for range_index in 0..range_count {
+                let range_offset = coverage_offset + 4 + range_index * 6;
+                let start_glyph = read_u16(table, range_offset)? as u32;
+                let end_glyph = read_u16(table, range_offset + 2)? as u32;
+                if glyph >= start_glyph && glyph <= end_glyph {
+                    let start_coverage_index = read_u16(table, range_offset + 4)? as u32;
+                    return Some((start_coverage_index + glyph - start_glyph) as usize);
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+// Reads a list of records, each of which consists of a tag and a 16-bit offset, preceded by a
+// 16-bit count. Truncated lists are cut short.
+fn tagged_records(table: &[u8], offset: usize) -> Vec<([u8; 4], u16)> {
+    let record_count = match read_u16(table, offset) {
+        Some(record_count) => record_count as usize,
+        None => return vec![],
+    };
+    let mut records = Vec::with_capacity(record_count);
+    for record_index in 0..record_count {
+        let record_offset = offset + 2 + record_index * 6;
+        let record = match table.get(record_offset..(record_offset + 6)) {
+            Some(record) => record,
+            None => break,
+        };
+        let mut tag = [0; 4];
+        tag.copy_from_slice(&record[0..4]);
+        records.push((tag, BigEndian::read_u16(&record[4..6])));
+    }
+    records
+}
@@ -18,7 +18,7 @@
 //!     # extern crate font_kit;
 //!     #
 //!     use euclid::{Point2D, Size2D};
-//!     use font_kit::canvas::{Canvas, Format, RasterizationOptions};
+//!     use font_kit::canvas::{AlphaMode, Canvas, Format, RasterizationOptions};
 //!     use font_kit::family_name::FamilyName;
 //!     use font_kit::hinting::HintingOptions;
 //!     use font_kit::properties::Properties;
@@ -31,14 +31,15 @@
 //!                                   .load()
 //!                                   .unwrap();
 //!     let glyph_id = font.glyph_for_char('A').unwrap();
-//!     let mut canvas = Canvas::new(&Size2D::new(32, 32), Format::A8);
+//!     let mut canvas = Canvas::new(&Size2D::new(32, 32), Format::A8).unwrap();
 //!     font.rasterize_glyph(&mut canvas,
 //!                          glyph_id,
 //!                          32.0,
 //!                          &FontTransform::identity(),
 //!                          &Point2D::new(0.0, 32.0),
 //!                          HintingOptions::None,
-//!                          RasterizationOptions::GrayscaleAa)
+//!                          RasterizationOptions::GrayscaleAa,
+//!                          AlphaMode::Premultiplied)
 //!         .unwrap();
 //!
 //! ## Backends
@@ -82,6 +83,9 @@
 //! `source-fontconfig-default` Cargo features respectively. Beware that
 //! `source-fontconfig-default` is rarely what you want on those two platforms!
 //!
+//! The `testing` Cargo feature adds the `testing` module, whose mock fonts and sources let code
+//! that uses `font-kit` be tested without depending on the fonts installed on the system.
+//!
 //! ## Features
 //!
 //! `font-kit` is capable of doing the following:
@@ -126,23 +130,42 @@
 extern crate log;
 
 pub mod canvas;
+pub mod color;
+pub mod embedding;
 pub mod error;
 pub mod family;
 pub mod family_handle;
 pub mod family_name;
 pub mod file_type;
 pub mod font;
+pub mod font_info;
 pub mod handle;
 pub mod hinting;
 pub mod loader;
 pub mod loaders;
 pub mod metrics;
+pub mod panose;
 pub mod properties;
+pub mod render;
+pub mod sized_font;
 pub mod source;
 pub mod sources;
+pub mod synthetic;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod unicode_ranges;
+pub mod validate;
 
 #[cfg(test)]
 pub mod test;
 
+mod bitmap;
+mod cmap;
+mod identity;
+mod kern;
+mod layout;
 mod matching;
+mod post;
+mod rasterizer;
+mod sfnt;
 mod utils;
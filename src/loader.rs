@@ -11,18 +11,36 @@
 //! Provides a common interface to the platform-specific API that loads, parses, and rasterizes
 //! fonts.
 
-use euclid::default::{Point2D, Rect, Transform2D, Vector2D};
+use byteorder::{BigEndian, ByteOrder};
+use euclid::default::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use euclid::point2;
 use log::warn;
 use lyon_path::builder::PathBuilder;
+use lyon_path::{Path as GlyphPath, PathEvent};
+use std::cmp;
+use std::mem;
+use std::ops::Deref;
 use std::sync::Arc;
 
-use crate::canvas::{Canvas, RasterizationOptions};
+use crate::bitmap;
+use crate::canvas::{AlphaMode, Canvas, Format, RasterizationOptions, SubpixelAaOptions};
+use crate::cmap::ReverseCharMap;
+use crate::color::{self, ColorGlyphCoverage, ColorLayer};
+use crate::embedding::EmbeddingMode;
 use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
+use crate::font::FaceIndex;
+use crate::font_info::FontInfo;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
-use crate::metrics::Metrics;
+use crate::kern;
+use crate::layout;
+use crate::metrics::{LineMetrics, Metrics};
+use crate::post;
 use crate::properties::Properties;
+use crate::sfnt::OS2_TABLE_TAG;
+use crate::synthetic::SyntheticFont;
+use crate::unicode_ranges::UnicodeRanges;
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
@@ -59,6 +77,16 @@ impl FontTransform {
     }
 }
 
+const HHEA_TABLE_TAG: u32 = 0x6868_6561;
+const VHEA_TABLE_TAG: u32 = 0x7668_6561;
+const VMTX_TABLE_TAG: u32 = 0x766d_7478;
+
+// The color that `rasterize_color_glyph` draws foreground layers in unless told otherwise.
+const DEFAULT_FOREGROUND_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+// The OpenType tables that can contain color glyph data: `COLR`, `sbix`, `CBDT`, and `SVG `.
+const COLOR_TABLE_TAGS: [u32; 4] = [0x434f_4c52, 0x7362_6978, 0x4342_4454, 0x5356_4720];
+
 /// Provides a common interface to the platform-specific API that loads, parses, and rasterizes
 /// fonts.
 pub trait Loader: Clone + Sized {
@@ -68,22 +96,26 @@ pub trait Loader: Clone + Sized {
     /// Loads a font from raw font data (the contents of a `.ttf`/`.otf`/etc. file).
     ///
     /// If the data represents a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index
-    /// of the font to load from it. If the data represents a single font, pass 0 for `font_index`.
-    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError>;
+    /// of the font to load from it. If the data represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: FaceIndex)
+        -> Result<Self, FontLoadingError>;
 
     /// Loads a font from a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
     #[cfg(not(target_arch = "wasm32"))]
-    fn from_file(file: &mut File, font_index: u32) -> Result<Self, FontLoadingError>;
+    fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Self, FontLoadingError>;
 
     /// Loads a font from the path to a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
     #[cfg(not(target_arch = "wasm32"))]
-    fn from_path<P>(path: P, font_index: u32) -> Result<Self, FontLoadingError>
+    fn from_path<P>(path: P, font_index: FaceIndex) -> Result<Self, FontLoadingError>
     where
         P: AsRef<Path>,
     {
@@ -128,7 +160,22 @@ pub trait Loader: Clone + Sized {
         <Self as Loader>::analyze_file(&mut File::open(path)?)
     }
 
+    /// Reads the names and properties of a font from raw font data without loading it.
+    ///
+    /// Only the `name`, `OS/2`, and `head` tables are parsed, which is much cheaper than creating
+    /// a font. This only supports TrueType/OpenType fonts and collections, and the results may
+    /// occasionally differ from those of a loaded font, since loaders apply their own heuristics.
+    #[inline]
+    fn peek_info(font_data: &[u8], font_index: FaceIndex) -> Result<FontInfo, FontLoadingError> {
+        FontInfo::from_bytes(font_data, font_index)
+    }
+
     /// Returns the wrapped native font handle.
+    ///
+    /// The returned handle holds its own reference to the native font, and it can be passed back
+    /// to `from_native_font`. Ownership and thread-safety rules are platform-specific; see the
+    /// documentation of each loader for details. In particular, FreeType faces are not
+    /// thread-safe.
     fn native_font(&self) -> Self::NativeFont;
 
     /// Returns the PostScript name of the font. This should be globally unique.
@@ -141,16 +188,358 @@ pub trait Loader: Clone + Sized {
     fn family_name(&self) -> String;
 
     /// Returns true if and only if the font is monospace (fixed-width).
-    fn is_monospace(&self) -> bool;
+    ///
+    /// The default implementation reads the `isFixedPitch` flag of the `post` table, and returns
+    /// false if the font has no such table.
+    fn is_monospace(&self) -> bool {
+        self.load_font_table(post::POST_TABLE_TAG)
+            .and_then(|post_table| post::is_fixed_pitch(&post_table))
+            .unwrap_or(false)
+    }
+
+    /// Returns true if and only if the font contains color glyphs (e.g. emoji).
+    ///
+    /// The default implementation checks for the presence of any of the `COLR`, `sbix`, `CBDT`, or
+    /// `SVG ` tables.
+    fn is_color_font(&self) -> bool {
+        COLOR_TABLE_TAGS
+            .iter()
+            .any(|&table_tag| self.has_table(table_tag))
+    }
+
+    /// Returns true if and only if the glyph with the given ID has a color representation in any
+    /// of the `COLR`, `sbix`, `CBDT`, or `SVG ` tables.
+    ///
+    /// This allows a run of text that mixes color and monochrome glyphs to be split up for
+    /// rasterization. The default implementation parses the color tables on every call; the
+    /// built-in loaders cache the result of the first parse.
+    fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        ColorGlyphCoverage::new(self).contains(glyph_id)
+    }
+
+    /// Returns the layers that make up the given glyph in the font's `COLR` (version 0) table, from
+    /// bottom to top.
+    ///
+    /// Returns `None` if the font has no `COLR` table or the glyph isn't a color glyph. Use
+    /// `color_palette` to find the colors that the layers' palette indices refer to.
+    fn color_glyph_layers(&self, glyph_id: u32) -> Option<Vec<ColorLayer>> {
+        let colr_table = self.load_font_table(color::COLR_TABLE_TAG)?;
+        color::colr_layers(&colr_table, glyph_id)
+    }
+
+    /// Returns the colors of the palette with the given index in the font's `CPAL` table, as
+    /// unpremultiplied RGBA.
+    ///
+    /// Returns `None` if the font has no `CPAL` table or no palette with that index. Palette 0 is
+    /// the default.
+    fn color_palette(&self, palette_index: u16) -> Option<Vec<[u8; 4]>> {
+        let cpal_table = self.load_font_table(color::CPAL_TABLE_TAG)?;
+        color::cpal_palette(&cpal_table, palette_index)
+    }
+
+    /// Rasterizes a color glyph to a new RGBA canvas that tightly fits it, by filling the outline
+    /// of each of its `COLR` layers with its color from the given palette.
+    ///
+    /// If the font has no palette with index `palette_index`, the default palette is used.
+    /// `palette_overrides` replaces individual palette entries, given as palette indices and
+    /// unpremultiplied RGBA colors; overriding `ColorLayer::FOREGROUND_PALETTE_INDEX` sets the
+    /// foreground color, which is otherwise opaque black. Glyphs without `COLR` layers, as well
+    /// as layers whose palette entry doesn't exist, are drawn in the foreground color.
+    ///
+    /// The pixels of the canvas have premultiplied alpha unless `alpha_mode` asks for straight
+    /// alpha.
+    ///
+    /// Returns the canvas along with the offset, in whole pixels, from the glyph origin to the top
+    /// left corner of the canvas.
+    fn rasterize_color_glyph(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        palette_index: u16,
+        palette_overrides: &[(u16, [u8; 4])],
+        alpha_mode: AlphaMode,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        let layers = self.color_glyph_layers(glyph_id).unwrap_or_else(|| {
+            vec![ColorLayer {
+                glyph_id,
+                palette_index: ColorLayer::FOREGROUND_PALETTE_INDEX,
+            }]
+        });
+        let palette = self
+            .color_palette(palette_index)
+            .or_else(|| self.color_palette(0))
+            .unwrap_or_default();
+        let color_at = |palette_index: u16| {
+            palette_overrides
+                .iter()
+                .rev()
+                .find(|&&(override_index, _)| override_index == palette_index)
+                .map(|&(_, color)| color)
+                .or_else(|| match palette_index {
+                    ColorLayer::FOREGROUND_PALETTE_INDEX => None,
+                    _ => palette.get(palette_index as usize).cloned(),
+                })
+        };
+        let foreground_color =
+            color_at(ColorLayer::FOREGROUND_PALETTE_INDEX).unwrap_or(DEFAULT_FOREGROUND_COLOR);
+
+        let mut layer_bounds = Vec::with_capacity(layers.len());
+        let mut bounds: Option<Rect<i32>> = None;
+        for layer in &layers {
+            let raster_bounds = self.raster_bounds(
+                layer.glyph_id,
+                point_size,
+                transform,
+                &Point2D::zero(),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )?;
+            if !raster_bounds.is_empty() {
+                bounds = Some(match bounds {
+                    Some(bounds) => bounds.union(&raster_bounds),
+                    None => raster_bounds,
+                });
+            }
+            layer_bounds.push(raster_bounds);
+        }
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => {
+                return Ok((
+                    Canvas::new(&Size2D::zero(), Format::Rgba32)?,
+                    Point2D::zero(),
+                ))
+            }
+        };
+
+        let mut canvas = Canvas::new(&bounds.size.to_u32(), Format::Rgba32)?;
+        for (layer, raster_bounds) in layers.iter().zip(layer_bounds) {
+            if raster_bounds.is_empty() {
+                continue;
+            }
+            let mut layer_canvas = Canvas::new(&raster_bounds.size.to_u32(), Format::A8)?;
+            self.rasterize_glyph(
+                &mut layer_canvas,
+                layer.glyph_id,
+                point_size,
+                transform,
+                &(-raster_bounds.origin.to_f32()),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+                AlphaMode::Premultiplied,
+            )?;
+            let color = color_at(layer.palette_index).unwrap_or(foreground_color);
+            canvas.composite_from(
+                &layer_canvas,
+                raster_bounds.origin - bounds.origin.to_vector(),
+                Some(color),
+            );
+        }
+        if alpha_mode == AlphaMode::Straight {
+            canvas.unpremultiply();
+        }
+        Ok((canvas, bounds.origin))
+    }
+
+    /// Draws the bitmap that the font's `EBLC` and `EBDT` tables embed for the given glyph into a
+    /// new A8 canvas.
+    ///
+    /// Fonts carry these monochrome or grayscale bitmaps in strikes of fixed sizes, typically to
+    /// look sharper than their outlines at small sizes. The strike whose size in pixels per em is
+    /// closest to `point_size` is used, and the bitmap is not scaled, so it has the size of the
+    /// strike. Image formats 1, 2, 5, 6, and 7 are supported. Returns `None` if no strike has a
+    /// bitmap for the glyph in a supported format.
+    ///
+    /// Returns the canvas along with the offset, in whole pixels, from the glyph origin to the top
+    /// left corner of the canvas.
+    fn glyph_bitmap(&self, glyph_id: u32, point_size: f32) -> Option<(Canvas, Point2D<i32>)> {
+        let eblc_table = self.load_font_table(bitmap::EBLC_TABLE_TAG)?;
+        let ebdt_table = self.load_font_table(bitmap::EBDT_TABLE_TAG)?;
+        bitmap::glyph_bitmap(&eblc_table, &ebdt_table, glyph_id, point_size)
+    }
+
+    /// Returns the tags of the OpenType scripts that the font supports, such as `*b"latn"` or
+    /// `*b"arab"`.
+    ///
+    /// These are read from the `GSUB` table, or from the `GPOS` table if the font has no `GSUB`
+    /// table. Returns an empty list if the font has neither.
+    fn opentype_script_tags(&self) -> Vec<[u8; 4]> {
+        match layout::load_layout_table(self) {
+            Some(layout_table) => layout::script_tags(&layout_table),
+            None => vec![],
+        }
+    }
+
+    /// Returns the tags of the OpenType language systems that the font defines for the given
+    /// script, such as `*b"TRK "`.
+    ///
+    /// The default language system, which every script has, is not included. The tags are read
+    /// from the same table as `opentype_script_tags`. Returns an empty list if the font doesn't
+    /// support the script.
+    fn opentype_language_tags(&self, script: [u8; 4]) -> Vec<[u8; 4]> {
+        match layout::load_layout_table(self) {
+            Some(layout_table) => layout::language_tags(&layout_table, script),
+            None => vec![],
+        }
+    }
+
+    /// Returns the tags of the OpenType features that the font defines in its `GSUB` and `GPOS`
+    /// tables, such as `*b"liga"` or `*b"kern"`.
+    ///
+    /// The tags are sorted and free of duplicates. Returns an empty list if the font has neither
+    /// table.
+    fn opentype_feature_tags(&self) -> Vec<[u8; 4]> {
+        let mut feature_tags = vec![];
+        for &table_tag in &[layout::GSUB_TABLE_TAG, layout::GPOS_TABLE_TAG] {
+            if let Some(layout_table) = self.load_font_table(table_tag) {
+                feature_tags.extend(layout::feature_tags(&layout_table));
+            }
+        }
+        feature_tags.sort();
+        feature_tags.dedup();
+        feature_tags
+    }
+
+    /// Returns true if and only if the font defines the OpenType feature with the given tag in its
+    /// `GSUB` or `GPOS` table.
+    fn has_opentype_feature(&self, feature: [u8; 4]) -> bool {
+        self.opentype_feature_tags().contains(&feature)
+    }
+
+    /// Returns true if and only if the font's `GSUB` or `GPOS` table enables the OpenType feature
+    /// with the given tag for the given script and language system.
+    ///
+    /// Pass `None` as the language to check the script's default language system. Scripts and
+    /// language systems that the font doesn't list fall back to `DFLT` and the default language
+    /// system respectively, as a shaper would. Unlike `has_opentype_feature`, this walks the
+    /// script and language tables on every call, so callers checking many features should cache
+    /// the results.
+    fn supports_opentype_feature(
+        &self,
+        feature: [u8; 4],
+        script: [u8; 4],
+        language: Option<[u8; 4]>,
+    ) -> bool {
+        [layout::GSUB_TABLE_TAG, layout::GPOS_TABLE_TAG]
+            .iter()
+            .filter_map(|&table_tag| self.load_font_table(table_tag))
+            .any(|layout_table| {
+                layout::script_feature_tags(&layout_table, script, language).contains(&feature)
+            })
+    }
+
+    /// Returns the glyph that the font's `liga` (standard ligatures) feature substitutes for
+    /// exactly the given sequence of glyphs, such as the "ffi" ligature for the glyphs of "f",
+    /// "f", and "i".
+    ///
+    /// This reads the ligature substitution lookups of the `GSUB` table directly, without shaping,
+    /// so it ignores script, language, and context. Returns `None` if the sequence has fewer than
+    /// two glyphs or no `liga` lookup forms a ligature from it.
+    fn ligature_for(&self, glyphs: &[u32]) -> Option<u32> {
+        let gsub_table = self.load_font_table(layout::GSUB_TABLE_TAG)?;
+        layout::ligature_for(&gsub_table, *b"liga", glyphs)
+    }
+
+    /// Returns the first ligature that the font's `liga` feature forms from the glyphs at the
+    /// start of the given sequence, along with the number of glyphs that it replaces.
+    ///
+    /// Like `ligature_for`, this reads the `GSUB` table directly and is no substitute for a
+    /// shaping engine, but it is enough for basic Latin ligatures like "fi" and "fl". To apply
+    /// ligatures to a run of text, call this at each position and skip past the glyphs that a
+    /// ligature replaces.
+    fn lookup_ligature(&self, glyph_ids: &[u32]) -> Option<(u32, usize)> {
+        let gsub_table = self.load_font_table(layout::GSUB_TABLE_TAG)?;
+        layout::ligature_at_start(&gsub_table, *b"liga", glyph_ids)
+    }
 
     /// Returns the values of various font properties, corresponding to those defined in CSS.
     fn properties(&self) -> Properties;
 
+    /// Returns the raw embedding permission flags (`fsType`) from the OpenType `OS/2` table, if
+    /// the font has one.
+    fn embedding_restrictions(&self) -> Option<u16> {
+        let os2_table = self.load_font_table(OS2_TABLE_TAG)?;
+        if os2_table.len() < 10 {
+            return None;
+        }
+        Some(BigEndian::read_u16(&os2_table[8..10]))
+    }
+
+    /// Returns how the font's license permits it to be embedded in documents.
+    ///
+    /// Fonts that do not specify any restrictions may be embedded freely.
+    fn embedding_mode(&self) -> EmbeddingMode {
+        match self.embedding_restrictions() {
+            Some(fs_type) => EmbeddingMode::from_fs_type(fs_type),
+            None => EmbeddingMode::FullOutline,
+        }
+    }
+
+    /// Returns the 10-byte PANOSE classification from the OpenType `OS/2` table, if the font has
+    /// one.
+    ///
+    /// Wrap the result in `Panose` to decode the individual digits.
+    fn panose(&self) -> Option<[u8; 10]> {
+        let os2_table = self.load_font_table(OS2_TABLE_TAG)?;
+        if os2_table.len() < 42 {
+            return None;
+        }
+        let mut panose = [0; 10];
+        panose.copy_from_slice(&os2_table[32..42]);
+        Some(panose)
+    }
+
+    /// Returns the Unicode blocks that the font claims to cover, from the `ulUnicodeRange` fields
+    /// of the OpenType `OS/2` table.
+    ///
+    /// This is a much cheaper check than looking up glyphs, but only as accurate as the font's
+    /// metadata. Fonts without an `OS/2` table claim no blocks.
+    fn unicode_ranges(&self) -> UnicodeRanges {
+        let os2_table = match self.load_font_table(OS2_TABLE_TAG) {
+            Some(os2_table) if os2_table.len() >= 58 => os2_table,
+            _ => return UnicodeRanges::default(),
+        };
+        let mut unicode_ranges = [0; 4];
+        BigEndian::read_u32_into(&os2_table[42..58], &mut unicode_ranges);
+        UnicodeRanges(unicode_ranges)
+    }
+
     /// Returns the number of glyphs in the font.
     ///
     /// Glyph IDs range from 0 inclusive to this value exclusive.
     fn glyph_count(&self) -> u32;
 
+    /// Returns true if and only if the font has a glyph with the given ID.
+    ///
+    /// This is a cheap way to validate glyph IDs that may have come from another font before
+    /// passing them to methods such as `outline` or `rasterize_glyph`. Glyph 0 (`.notdef`) exists
+    /// in every font that has any glyphs.
+    fn glyph_exists(&self, glyph_id: u32) -> bool {
+        glyph_id < self.glyph_count()
+    }
+
+    /// Returns the ID of the glyph to draw in place of characters that the font has no glyph for.
+    ///
+    /// By convention this is glyph 0, `.notdef`, which is what the default implementation returns.
+    /// Unlike `glyph_for_char`, this never fails.
+    fn default_glyph(&self) -> u32 {
+        0
+    }
+
+    /// Returns the number of full horizontal metrics records (`numberOfHMetrics`) in the `hmtx`
+    /// table, as given by the `hhea` table.
+    ///
+    /// Glyphs with IDs greater than or equal to this value share the advance width of the last
+    /// full record. Returns `None` if the font has no `hhea` table.
+    fn number_of_h_metrics(&self) -> Option<u16> {
+        let hhea_table = self.load_font_table(HHEA_TABLE_TAG)?;
+        if hhea_table.len() < 36 {
+            return None;
+        }
+        Some(BigEndian::read_u16(&hhea_table[34..36]))
+    }
+
     /// Returns the usual glyph ID for a Unicode character.
     ///
     /// Be careful with this function; typographically correct character-to-glyph mapping must be
@@ -158,6 +547,27 @@ pub trait Loader: Clone + Sized {
     /// use cases like "what does character X look like on its own".
     fn glyph_for_char(&self, character: char) -> Option<u32>;
 
+    /// Returns all Unicode characters that the font's `cmap` table maps to the given glyph, in
+    /// code point order.
+    ///
+    /// Several characters can share a glyph, and glyphs reachable only through shaping (e.g.
+    /// ligatures) have none. This is useful for recovering text from glyph IDs, as in PDF text
+    /// extraction. The default implementation inverts the `cmap` table on every call; the built-in
+    /// loaders cache the reverse mapping.
+    fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        ReverseCharMap::new(self).chars_for_glyph(glyph_id)
+    }
+
+    /// Returns the usual glyph IDs for each Unicode character in a string, in order.
+    ///
+    /// This is equivalent to calling `glyph_for_char` on every character, but some loaders can
+    /// look up all the glyphs at once. The same caveats regarding shaping apply.
+    fn glyphs_for_string(&self, text: &str) -> Vec<Option<u32>> {
+        text.chars()
+            .map(|character| self.glyph_for_char(character))
+            .collect()
+    }
+
     /// Returns the glyph ID for the specified glyph name.
     #[inline]
     fn glyph_by_name(&self, _name: &str) -> Option<u32> {
@@ -165,6 +575,15 @@ pub trait Loader: Clone + Sized {
         None
     }
 
+    /// Returns the PostScript name of the glyph with the given ID, such as `"A"` or `"uni20BD"`.
+    ///
+    /// This is the reverse of `glyph_by_name`. Names are read from the `post` table, so this
+    /// returns `None` for fonts whose `post` table is version 3.0, which carries no glyph names.
+    fn glyph_name(&self, glyph_id: u32) -> Option<String> {
+        let post_table = self.load_font_table(post::POST_TABLE_TAG)?;
+        post::glyph_name(&post_table, glyph_id)
+    }
+
     /// Sends the vector path for a glyph to a path builder.
     ///
     /// If `hinting_mode` is not None, this function performs grid-fitting as requested before
@@ -184,10 +603,197 @@ pub trait Loader: Clone + Sized {
     /// space is at the bottom left.
     fn typographic_bounds(&self, glyph_id: u32) -> Result<Rect<f32>, GlyphLoadingError>;
 
+    /// Returns the boundaries of several glyphs, in order, in font units.
+    ///
+    /// This is equivalent to calling `typographic_bounds` on every glyph, except that glyphs that
+    /// aren't in the font get zero bounds instead of failing the whole batch. Some loaders look
+    /// up all the bounds at once.
+    fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        glyph_ids
+            .iter()
+            .map(|&glyph_id| match self.typographic_bounds(glyph_id) {
+                Err(GlyphLoadingError::NoSuchGlyph) => Ok(Rect::zero()),
+                result => result,
+            })
+            .collect()
+    }
+
+    /// Returns the boundaries of a glyph in font units, computed from its outline. The origin of
+    /// the coordinate space is at the bottom left.
+    ///
+    /// The result is the smallest rectangle that contains every point and control point of the
+    /// outline, so it may be slightly larger than the area that the curves actually cover. Unlike
+    /// `typographic_bounds`, this reflects hinting when `hinting_options` asks for it. Glyphs with
+    /// no outline, such as the space, have empty bounds at the origin.
+    fn outline_bounding_box(
+        &self,
+        glyph_id: u32,
+        hinting_options: HintingOptions,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        let mut path_builder = GlyphPath::builder();
+        self.outline(glyph_id, hinting_options, &mut path_builder)?;
+        let path = path_builder.build();
+
+        let mut points = vec![];
+        for event in path.iter() {
+            match event {
+                PathEvent::MoveTo(to) => points.push(to),
+                PathEvent::Line(segment) | PathEvent::Close(segment) => {
+                    points.extend_from_slice(&[segment.from, segment.to])
+                }
+                PathEvent::Quadratic(segment) => {
+                    points.extend_from_slice(&[segment.from, segment.ctrl, segment.to])
+                }
+                PathEvent::Cubic(segment) => points.extend_from_slice(&[
+                    segment.from,
+                    segment.ctrl1,
+                    segment.ctrl2,
+                    segment.to,
+                ]),
+            }
+        }
+        Ok(Rect::from_points(&points))
+    }
+
     /// Returns the distance from the origin of the glyph with the given ID to the next, in font
     /// units.
     fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError>;
 
+    /// Returns the advances of several glyphs, in order, in font units.
+    ///
+    /// This is equivalent to calling `advance` on every glyph, but some loaders can look up all
+    /// the advances at once, which is much faster for long runs of text.
+    fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        glyph_ids
+            .iter()
+            .map(|&glyph_id| self.advance(glyph_id))
+            .collect()
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in pixels
+    /// at the given point size.
+    ///
+    /// This is `advance` scaled by `Metrics::scale_factor`.
+    fn advance_pixels(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        Ok(self.advance(glyph_id)? * self.metrics().scale_factor(point_size))
+    }
+
+    /// Returns the ascent, descent, line gap, and line height of the font, in pixels at the given
+    /// point size.
+    ///
+    /// This is `Metrics::line_metrics` of the font's metrics.
+    fn line_metrics(&self, point_size: f32) -> LineMetrics {
+        self.metrics().line_metrics(point_size)
+    }
+
+    /// Returns the bounds of a run of glyphs laid out left to right, in pixels at the given point
+    /// size. The origin of the coordinate space is at the start of the baseline, at the bottom
+    /// left.
+    ///
+    /// Each glyph is placed at the sum of the advances of the glyphs before it, with no kerning.
+    /// The result covers the typographic bounds of every glyph as well as the total advance of the
+    /// run, so trailing spaces are included.
+    fn run_bounds(&self, glyphs: &[u32], point_size: f32) -> Result<Rect<f32>, GlyphLoadingError> {
+        let mut points = vec![Point2D::zero()];
+        let mut pen_x = 0.0;
+        for &glyph_id in glyphs {
+            let bounds = self
+                .typographic_bounds(glyph_id)?
+                .translate(Vector2D::new(pen_x, 0.0));
+            points.push(bounds.min());
+            points.push(bounds.max());
+            pen_x += self.advance(glyph_id)?.x;
+        }
+        points.push(point2(pen_x, 0.0));
+
+        let scale_factor = self.metrics().scale_factor(point_size);
+        Ok(Rect::from_points(&points).scale(scale_factor, scale_factor))
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next in vertical
+    /// writing modes, in font units.
+    ///
+    /// The advance height is returned as a positive `y` component. The default implementation
+    /// reads it from the `vmtx` table; fonts without vertical metrics get the sum of the ascent
+    /// and the magnitude of the descent.
+    fn vertical_advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        if glyph_id >= self.glyph_count() {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+
+        if let (Some(vhea_table), Some(vmtx_table)) = (
+            self.load_font_table(VHEA_TABLE_TAG),
+            self.load_font_table(VMTX_TABLE_TAG),
+        ) {
+            if let Some(advance_height) =
+                lookup_vertical_advance(&vhea_table, &vmtx_table, glyph_id)
+            {
+                return Ok(Vector2D::new(0.0, advance_height as f32));
+            }
+        }
+
+        let metrics = self.metrics();
+        Ok(Vector2D::new(0.0, metrics.ascent + metrics.descent.abs()))
+    }
+
+    /// Returns the kerning that the font's `GPOS` table applies between the given pair of
+    /// glyphs, in font units.
+    ///
+    /// The adjustment comes from the pair adjustment lookups of the `kern` feature and is
+    /// returned as an `x` component to add to the advance of the left glyph. Pairs that no lookup
+    /// adjusts, and fonts without a `GPOS` table, get zero.
+    fn gpos_kerning_for_pair(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        let glyph_count = self.glyph_count();
+        if left_glyph >= glyph_count || right_glyph >= glyph_count {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        let kerning = self
+            .load_font_table(layout::GPOS_TABLE_TAG)
+            .and_then(|gpos_table| {
+                layout::pair_adjustment(&gpos_table, *b"kern", left_glyph, right_glyph)
+            });
+        Ok(Vector2D::new(kerning.unwrap_or(0) as f32, 0.0))
+    }
+
+    /// Returns the kerning between the given pair of glyphs, in font units, from whichever
+    /// kerning data the font has.
+    ///
+    /// A `GPOS` table with a `kern` feature is preferred, as in `gpos_kerning_for_pair`. Fonts
+    /// without one fall back to the legacy `kern` table. Pairs that neither adjusts get zero.
+    fn kerning_for_pair_any(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        let has_gpos_kerning = match self.load_font_table(layout::GPOS_TABLE_TAG) {
+            Some(gpos_table) => layout::feature_tags(&gpos_table).contains(b"kern"),
+            None => false,
+        };
+        if has_gpos_kerning {
+            return self.gpos_kerning_for_pair(left_glyph, right_glyph);
+        }
+
+        let glyph_count = self.glyph_count();
+        if left_glyph >= glyph_count || right_glyph >= glyph_count {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        let kerning = self
+            .load_font_table(kern::KERN_TABLE_TAG)
+            .and_then(|kern_table| kern::kerning_for_pair(&kern_table, left_glyph, right_glyph));
+        Ok(Vector2D::new(kerning.unwrap_or(0) as f32, 0.0))
+    }
+
     /// Returns the amount that the given glyph should be displaced from the origin.
     fn origin(&self, glyph_id: u32) -> Result<Point2D<f32>, GlyphLoadingError>;
 
@@ -200,7 +806,7 @@ pub trait Loader: Clone + Sized {
     fn handle(&self) -> Option<Handle> {
         // FIXME(pcwalton): This doesn't handle font collections!
         self.copy_font_data()
-            .map(|font_data| Handle::from_memory(font_data, 0))
+            .map(|font_data| Handle::from_memory(font_data, FaceIndex::FIRST))
     }
 
     /// Attempts to return the raw font data (contents of the font file).
@@ -209,6 +815,26 @@ pub trait Loader: Clone + Sized {
     /// collection.
     fn copy_font_data(&self) -> Option<Arc<Vec<u8>>>;
 
+    /// Attempts to return the raw font data (contents of the font file) without copying it.
+    ///
+    /// Fonts mapped from a file borrow the mapping, and fonts loaded from memory share their
+    /// data. As with `copy_font_data`, the data of a font in a collection is that of the entire
+    /// collection. The default implementation wraps the data that `copy_font_data` returns.
+    fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        self.copy_font_data().map(FontBytesRef::Owned)
+    }
+
+    /// Returns a rough estimate of the number of bytes of memory that this font uses, for the
+    /// purpose of sizing font caches.
+    ///
+    /// The estimate is explicitly approximate. It includes font data held in memory, counted in
+    /// full even if it is shared with other fonts, and guesses at the platform's own structures.
+    /// The default implementation counts the data returned by `font_bytes`, which may be
+    /// expensive; the built-in loaders override it with a cheaper estimate.
+    fn approximate_memory_usage(&self) -> usize {
+        mem::size_of_val(self) + self.font_bytes().map_or(0, |font_bytes| font_bytes.len())
+    }
+
     /// Returns true if and only if the font loader can perform hinting in the requested way.
     ///
     /// Some APIs support only rasterizing glyphs with hinting, not retriving hinted outlines. If
@@ -235,7 +861,7 @@ pub trait Loader: Clone + Sized {
     ) -> Result<Rect<i32>, GlyphLoadingError> {
         let typographic_bounds = self.typographic_bounds(glyph_id)?;
         let mut typographic_raster_bounds =
-            typographic_bounds * point_size / self.metrics().units_per_em as f32;
+            typographic_bounds * self.metrics().scale_factor(point_size);
         typographic_raster_bounds.origin.y =
             -typographic_raster_bounds.origin.y - typographic_raster_bounds.size.height;
         let transform: Transform2D<f32> = Transform2D::column_major(
@@ -246,10 +872,12 @@ pub trait Loader: Clone + Sized {
             transform.scale_y,
             origin.y,
         );
-        Ok(transform
+        // Absurd point sizes can produce bounds that don't fit in an `i32`.
+        transform
             .transform_rect(&typographic_raster_bounds)
             .round_out()
-            .to_i32())
+            .try_cast()
+            .ok_or(GlyphLoadingError::OutOfMemory)
     }
 
     /// Rasterizes a glyph to a canvas with the given size and origin.
@@ -258,10 +886,13 @@ pub trait Loader: Clone + Sized {
     /// options. For example, if bilevel (black and white) rendering is requested to an RGBA
     /// surface, this function will automatically convert the 1-bit raster image to the 32-bit
     /// format of the canvas. Note that this may result in a performance penalty, depending on the
-    /// loader.
+    /// loader. The glyph is drawn in white, and the pixels that it covers on RGBA canvases get
+    /// premultiplied or straight alpha as `alpha_mode` asks.
     ///
     /// If `hinting_options` is not None, the requested grid fitting is performed.
-    /// `origin` is not transformed by `transform`.
+    /// `origin` is not transformed by `transform`. It need not lie on a pixel boundary: the
+    /// fractional part of the origin shifts the glyph's coverage, which allows for subpixel
+    /// positioning.
     fn rasterize_glyph(
         &self,
         canvas: &mut Canvas,
@@ -271,8 +902,150 @@ pub trait Loader: Clone + Sized {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
     ) -> Result<(), GlyphLoadingError>;
 
+    /// Rasterizes a glyph at a quantized horizontal subpixel offset to a new canvas that tightly
+    /// fits it.
+    ///
+    /// The glyph origin is placed at `subpixel_x / 2^subpixel_bits` pixels to the right of an
+    /// integral pixel position. `subpixel_bits` should be at most 8, and `subpixel_x` less than
+    /// `2^subpixel_bits`; larger values are clamped to those limits. Rasterizing each glyph once
+    /// per bucket allows callers to cache subpixel-positioned glyphs.
+    ///
+    /// Returns the canvas along with the offset, in whole pixels, from the integral pen position
+    /// to the top left corner of the canvas.
+    fn rasterize_glyph_subpixel(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        subpixel_x: u8,
+        subpixel_bits: u8,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        // Out-of-range values would shift the glyph by a whole pixel or more, so they go to the
+        // last bucket instead.
+        let subpixel_bits = subpixel_bits.min(8);
+        let subpixel_x = (subpixel_x as u32).min((1 << subpixel_bits) - 1);
+        let subpixel_origin = point2(subpixel_x as f32 / (1 << subpixel_bits) as f32, 0.0);
+        let raster_bounds = self.raster_bounds(
+            glyph_id,
+            point_size,
+            &FontTransform::identity(),
+            &subpixel_origin,
+            HintingOptions::None,
+            rasterization_options,
+        )?;
+
+        let format = match rasterization_options {
+            RasterizationOptions::Bilevel | RasterizationOptions::GrayscaleAa => Format::A8,
+            RasterizationOptions::SubpixelAa => Format::Rgb24,
+        };
+        let mut canvas = Canvas::new(&raster_bounds.size.to_u32(), format)?;
+        let origin = subpixel_origin - raster_bounds.origin.to_f32().to_vector();
+        self.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            point_size,
+            &FontTransform::identity(),
+            &origin,
+            HintingOptions::None,
+            rasterization_options,
+            AlphaMode::Premultiplied,
+        )?;
+        Ok((canvas, raster_bounds.origin))
+    }
+
+    /// Rasterizes a glyph to a canvas with subpixel antialiasing for an LCD screen whose color
+    /// subpixels are in the order that `options` gives, grid-fitting it as `options` asks.
+    ///
+    /// `rasterize_glyph` with `RasterizationOptions::SubpixelAa` assumes `SubpixelOrder::Rgb`.
+    /// For the vertical orders, each pixel row is covered by three rows of subpixels, so the glyph
+    /// may extend up to a pixel beyond the bounds that `raster_bounds` returns.
+    ///
+    /// The default implementation swaps the red and blue channels of an RGB rasterization for the
+    /// BGR orders, and rasterizes the glyph in grayscale at triple height for the vertical orders.
+    /// Pixels that the glyph doesn't cover are left alone.
+    fn rasterize_glyph_with_subpixel_order(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        options: SubpixelAaOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        let glyph_canvas = if options.subpixel_order.is_vertical() {
+            let coverage_height = canvas
+                .size
+                .height
+                .checked_mul(3)
+                .ok_or(GlyphLoadingError::OutOfMemory)?;
+            let coverage_size = Size2D::new(canvas.size.width, coverage_height);
+            let mut coverage = Canvas::new(&coverage_size, Format::A8)?;
+            let coverage_transform = FontTransform::new(
+                transform.scale_x,
+                transform.skew_x,
+                transform.skew_y * 3.0,
+                transform.scale_y * 3.0,
+            );
+            self.rasterize_glyph(
+                &mut coverage,
+                glyph_id,
+                point_size,
+                &coverage_transform,
+                &point2(origin.x, origin.y * 3.0),
+                options.hinting_options,
+                RasterizationOptions::GrayscaleAa,
+                AlphaMode::Premultiplied,
+            )?;
+            Canvas::from_subpixel_coverage(
+                &canvas.size,
+                &coverage.pixels,
+                coverage.stride,
+                options.subpixel_order,
+            )?
+        } else {
+            let mut rgb_canvas = Canvas::new(&canvas.size, Format::Rgb24)?;
+            self.rasterize_glyph(
+                &mut rgb_canvas,
+                glyph_id,
+                point_size,
+                transform,
+                origin,
+                options.hinting_options,
+                RasterizationOptions::SubpixelAa,
+                AlphaMode::Premultiplied,
+            )?;
+            Canvas::from_subpixel_coverage(
+                &canvas.size,
+                &rgb_canvas.pixels,
+                rgb_canvas.stride,
+                options.subpixel_order,
+            )?
+        };
+        canvas.blit_covered_from(&glyph_canvas);
+        Ok(())
+    }
+
+    /// Returns a simulated bold version of this font, for families that have no bold face.
+    ///
+    /// The glyph outlines are stroked outward so that they become `extra_weight` font units wider
+    /// and taller, and advances grow by the same amount. `synthetic::DEFAULT_EXTRA_WEIGHT` suits
+    /// most text. See `SyntheticFont::bold` for details.
+    fn synthetic_bold(&self, extra_weight: f32) -> Result<SyntheticFont<Self>, FontLoadingError> {
+        SyntheticFont::bold(self.clone(), extra_weight)
+    }
+
+    /// Returns a simulated italic version of this font, for families that have no italic face.
+    ///
+    /// The glyph outlines are slanted to the right by `slant_angle` degrees from the vertical:
+    /// each point moves right by its height times the tangent of the angle.
+    /// `synthetic::DEFAULT_SLANT_ANGLE` suits most text.
+    fn synthetic_italic(&self, slant_angle: f32) -> SyntheticFont<Self> {
+        SyntheticFont::italic(self.clone(), slant_angle)
+    }
+
     /// Get font fallback results for the given text and locale.
     ///
     /// The `locale` argument is a language tag such as `"en-US"` or `"zh-Hans-CN"`.
@@ -280,6 +1053,44 @@ pub trait Loader: Clone + Sized {
 
     /// Returns the OpenType font table with the given tag, if the table exists.
     fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>>;
+
+    /// Returns the tags of all the OpenType tables in the font, in no particular order.
+    ///
+    /// Returns an empty list if the font isn't a TrueType/OpenType font.
+    ///
+    /// The default implementation reads the table directory from the font that `handle` returns.
+    /// Loaders that can list the tables without going through the font data override this.
+    fn table_tags(&self) -> Vec<u32> {
+        match self.handle() {
+            Some(handle) => handle.table_tags().unwrap_or_default(),
+            None => vec![],
+        }
+    }
+
+    /// Returns true if and only if the font has an OpenType table with the given tag.
+    ///
+    /// The default implementation loads the table with `load_font_table`. Loaders that can check
+    /// for a table without copying its contents override this.
+    fn has_table(&self, table_tag: u32) -> bool {
+        self.load_font_table(table_tag).is_some()
+    }
+}
+
+// Looks up the advance height of a glyph in the `vmtx` table. Glyphs past the last long metric
+// share its advance height.
+fn lookup_vertical_advance(vhea_table: &[u8], vmtx_table: &[u8], glyph_id: u32) -> Option<u16> {
+    if vhea_table.len() < 36 {
+        return None;
+    }
+    let long_metric_count = BigEndian::read_u16(&vhea_table[34..36]) as usize;
+    if long_metric_count == 0 {
+        return None;
+    }
+    let offset = cmp::min(glyph_id as usize, long_metric_count - 1) * 4;
+    if vmtx_table.len() < offset + 2 {
+        return None;
+    }
+    Some(BigEndian::read_u16(&vmtx_table[offset..(offset + 2)]))
 }
 
 /// The result of a fallback query.
@@ -300,3 +1111,22 @@ pub struct FallbackFont<Font> {
     pub scale: f32,
     // TODO: add font simulation data
 }
+
+/// The raw data of a font, as returned by `Loader::font_bytes`.
+#[derive(Clone, Debug)]
+pub enum FontBytesRef<'a> {
+    /// Data borrowed from the font, such as a memory-mapped file.
+    Slice(&'a [u8]),
+    /// Data in memory, shared with the font.
+    Owned(Arc<Vec<u8>>),
+}
+
+impl<'a> Deref for FontBytesRef<'a> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match *self {
+            FontBytesRef::Slice(data) => data,
+            FontBytesRef::Owned(ref data) => data,
+        }
+    }
+}
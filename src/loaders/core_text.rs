@@ -11,6 +11,8 @@
 //! A loader that uses Apple's Core Text API to load and rasterize fonts.
 
 use byteorder::{BigEndian, ReadBytesExt};
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::{CFIndex, TCFType};
 use core_graphics::base::{kCGImageAlphaPremultipliedLast, CGFloat};
 use core_graphics::color_space::CGColorSpace;
 use core_graphics::context::{CGContext, CGTextDrawingMode};
@@ -20,59 +22,92 @@ use core_graphics::geometry::{CGAffineTransform, CGRect, CGSize};
 use core_graphics::geometry::{CGPoint, CG_AFFINE_TRANSFORM_IDENTITY, CG_ZERO_POINT, CG_ZERO_SIZE};
 use core_graphics::path::CGPathElementType;
 use core_text;
-use core_text::font::CTFont;
-use core_text::font_descriptor::kCTFontDefaultOrientation;
+use core_text::font::{CTFont, CTFontGetBoundingRectsForGlyphs, CTFontRef};
+use core_text::font_descriptor::{kCTFontDefaultOrientation, kCTFontVerticalOrientation};
 use core_text::font_descriptor::{SymbolicTraitAccessors, TraitAccessors};
 use euclid::default::{Point2D, Rect, Size2D, Vector2D};
 use log::warn;
 use lyon_path::builder::PathBuilder;
+use memmap2::Mmap;
 use std::f32;
 use std::fmt::{self, Debug, Formatter};
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{Seek, SeekFrom};
+use std::mem;
 use std::ops::Deref;
-use std::path::Path;
-use std::sync::Arc;
-
-use crate::canvas::{Canvas, Format, RasterizationOptions};
+use std::os::raw::c_void;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use tokio::io::{self as async_io, AsyncRead};
+
+use crate::canvas::{AlphaMode, Canvas, Format, RasterizationOptions, SubpixelAaOptions};
+use crate::cmap::ReverseCharMap;
+use crate::color::{ColorGlyphCoverage, ColorLayer};
+use crate::embedding::EmbeddingMode;
 use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
+use crate::font::FaceIndex;
+use crate::font_info::FontInfo;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
-use crate::loader::{FallbackResult, FontTransform, Loader};
-use crate::metrics::Metrics;
+use crate::identity::{self, FontIdentity};
+use crate::loader::{FallbackResult, FontBytesRef, FontTransform, Loader};
+use crate::metrics::{LineMetrics, Metrics};
 use crate::properties::{Properties, Stretch, Style, Weight};
 use crate::sources;
+use crate::synthetic::SyntheticFont;
+use crate::unicode_ranges::UnicodeRanges;
 use crate::utils;
 
 const TTC_TAG: [u8; 4] = [b't', b't', b'c', b'f'];
 
+// A rough estimate of the memory that Core Text and Core Graphics allocate for a font, including
+// the tables that they parse up front.
+const CORE_TEXT_FONT_MEMORY_USAGE: usize = 32 * 1024;
+
 #[allow(non_upper_case_globals)]
 const kCGImageAlphaOnly: u32 = 7;
+#[allow(non_upper_case_globals)]
+const kCTFontColorGlyphsTrait: u32 = 1 << 13;
+#[allow(non_upper_case_globals)]
+const kCTFontTableOptionNoOptions: u32 = 0;
+
+#[link(name = "CoreText", kind = "framework")]
+extern "C" {
+    fn CTFontCopyAvailableTables(font: CTFontRef, options: u32) -> CFArrayRef;
+}
 
 /// Core Text's representation of a font.
 pub type NativeFont = CTFont;
 
 /// A loader that uses Apple's Core Text API to load and rasterize fonts.
-#[derive(Clone)]
+///
+/// Unlike the other loaders, this one is `Send` and `Sync`, so fonts can be shared between threads.
 pub struct Font {
     core_text_font: CTFont,
     font_data: FontData,
+    color_glyph_coverage: Mutex<Option<Arc<ColorGlyphCoverage>>>,
+    font_data_hash: Mutex<Option<u64>>,
+    metrics: Mutex<Option<Metrics>>,
+    reverse_char_map: Mutex<Option<Arc<ReverseCharMap>>>,
 }
 
 impl Font {
     /// Loads a font from raw font data (the contents of a `.ttf`/`.otf`/etc. file).
     ///
     /// If the data represents a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index
-    /// of the font to load from it. If the data represents a single font, pass 0 for `font_index`.
+    /// of the font to load from it. If the data represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
     pub fn from_bytes(
         mut font_data: Arc<Vec<u8>>,
-        font_index: u32,
+        font_index: FaceIndex,
     ) -> Result<Font, FontLoadingError> {
         // Sadly, there's no API to load OpenType collections on macOS, I don't believe…
         if font_is_collection(&**font_data) {
             let mut new_font_data = (*font_data).clone();
-            unpack_otc_font(&mut new_font_data, font_index)?;
+            unpack_otc_font(&mut new_font_data, font_index.0)?;
             font_data = Arc::new(new_font_data);
         }
 
@@ -83,14 +118,19 @@ impl Font {
         Ok(Font {
             core_text_font,
             font_data: FontData::Memory(font_data),
+            color_glyph_coverage: Mutex::new(None),
+            font_data_hash: Mutex::new(None),
+            metrics: Mutex::new(None),
+            reverse_char_map: Mutex::new(None),
         })
     }
 
     /// Loads a font from a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
-    pub fn from_file(file: &mut File, font_index: u32) -> Result<Font, FontLoadingError> {
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    pub fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Font, FontLoadingError> {
         file.seek(SeekFrom::Start(0))?;
         let font_data = Arc::new(utils::slurp_file(file).map_err(FontLoadingError::Io)?);
         Font::from_bytes(font_data, font_index)
@@ -99,10 +139,60 @@ impl Font {
     /// Loads a font from the path to a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
-    #[inline]
-    pub fn from_path<P: AsRef<Path>>(path: P, font_index: u32) -> Result<Font, FontLoadingError> {
-        <Font as Loader>::from_path(path, font_index)
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    ///
+    /// The file is memory-mapped instead of being read into memory, so it must not be modified
+    /// while the font or any clone of it is alive. Collections are still read into memory, since
+    /// the font has to be unpacked from them.
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError> {
+        let mut file = File::open(path.as_ref())?;
+        // Some files, such as empty ones, can't be mapped. Leave reporting any errors with those
+        // to the buffered path.
+        let mmap = match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => Arc::new(mmap),
+            Err(_) => return Font::from_file(&mut file, font_index),
+        };
+        if font_is_collection(&**mmap) {
+            return Font::from_bytes(Arc::new(mmap.to_vec()), font_index);
+        }
+
+        let data_provider = CGDataProvider::from_buffer(mmap.clone());
+        let core_graphics_font =
+            CGFont::from_data_provider(data_provider).map_err(|_| FontLoadingError::Parse)?;
+        let core_text_font = core_text::font::new_from_CGFont(&core_graphics_font, 16.0);
+        Ok(Font {
+            core_text_font,
+            font_data: FontData::File(mmap, identity::canonical_path(path.as_ref())),
+            color_glyph_coverage: Mutex::new(None),
+            font_data_hash: Mutex::new(None),
+            metrics: Mutex::new(None),
+            reverse_char_map: Mutex::new(None),
+        })
+    }
+
+    /// Loads a font by reading the raw font data (the contents of a `.ttf`/`.otf`/etc. file) from
+    /// an asynchronous reader.
+    ///
+    /// The whole stream is read into memory before the font is loaded, so the executor isn't
+    /// blocked on the I/O. If the data represents a collection (`.ttc`/`.otc`/etc.),
+    /// `font_index` specifies the index of the font to load from it. If the data represents a
+    /// single font, pass `FaceIndex::FIRST` for `font_index`.
+    #[cfg(feature = "async")]
+    pub async fn from_async_read<R>(
+        mut reader: R,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut font_data = vec![];
+        // Importing `AsyncReadExt` would make its methods clash with those of `ReadBytesExt`.
+        async_io::AsyncReadExt::read_to_end(&mut reader, &mut font_data).await?;
+        Font::from_bytes(Arc::new(font_data), font_index)
     }
 
     /// Creates a font from a native API handle.
@@ -115,10 +205,13 @@ impl Font {
         match core_text_font.url() {
             None => warn!("No URL found for Core Text font!"),
             Some(url) => match url.to_path() {
-                Some(path) => match File::open(path) {
-                    Ok(ref mut file) => match utils::slurp_file(file) {
-                        Ok(data) => font_data = FontData::Memory(Arc::new(data)),
-                        Err(_) => warn!("Couldn't read file data for Core Text font!"),
+                Some(path) => match File::open(&path) {
+                    Ok(ref file) => match Mmap::map(file) {
+                        Ok(mmap) => {
+                            let path = identity::canonical_path(&path);
+                            font_data = FontData::File(Arc::new(mmap), path)
+                        }
+                        Err(_) => warn!("Couldn't map file data for Core Text font!"),
                     },
                     Err(_) => warn!("Could not open file for Core Text font!"),
                 },
@@ -129,6 +222,10 @@ impl Font {
         Font {
             core_text_font,
             font_data,
+            color_glyph_coverage: Mutex::new(None),
+            font_data_hash: Mutex::new(None),
+            metrics: Mutex::new(None),
+            reverse_char_map: Mutex::new(None),
         }
     }
 
@@ -181,7 +278,19 @@ impl Font {
         <Self as Loader>::analyze_path(path)
     }
 
+    /// Reads the names and properties of a font from raw font data without loading it.
+    #[inline]
+    pub fn peek_info(
+        font_data: &[u8],
+        font_index: FaceIndex,
+    ) -> Result<FontInfo, FontLoadingError> {
+        <Self as Loader>::peek_info(font_data, font_index)
+    }
+
     /// Returns the wrapped native font handle.
+    ///
+    /// The returned `CTFont` is retained, so it remains valid after this font is dropped. Core
+    /// Text fonts are immutable and may be used from any thread.
     #[inline]
     pub fn native_font(&self) -> NativeFont {
         self.core_text_font.clone()
@@ -214,9 +323,128 @@ impl Font {
     }
 
     /// Returns true if and only if the font is monospace (fixed-width).
+    ///
+    /// This reads the `isFixedPitch` flag of the `post` table, since Core Text's monospace trait
+    /// isn't reliable for fonts that aren't installed on the system.
     #[inline]
     pub fn is_monospace(&self) -> bool {
-        self.core_text_font.symbolic_traits().is_monospace()
+        <Self as Loader>::is_monospace(self)
+    }
+
+    /// Returns true if and only if the font contains color glyphs (e.g. emoji).
+    #[inline]
+    pub fn is_color_font(&self) -> bool {
+        (self.core_text_font.symbolic_traits() & kCTFontColorGlyphsTrait) != 0
+    }
+
+    /// Returns true if and only if the glyph with the given ID has a color representation in any
+    /// of the `COLR`, `sbix`, `CBDT`, or `SVG ` tables.
+    ///
+    /// The color tables are parsed on the first call, and the result is cached.
+    pub fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        let mut color_glyph_coverage = self.color_glyph_coverage.lock().unwrap();
+        if color_glyph_coverage.is_none() {
+            *color_glyph_coverage = Some(Arc::new(ColorGlyphCoverage::new(self)));
+        }
+        color_glyph_coverage.as_ref().unwrap().contains(glyph_id)
+    }
+
+    /// Returns the layers that make up the given glyph in the font's `COLR` (version 0) table, from
+    /// bottom to top.
+    #[inline]
+    pub fn color_glyph_layers(&self, glyph_id: u32) -> Option<Vec<ColorLayer>> {
+        <Self as Loader>::color_glyph_layers(self, glyph_id)
+    }
+
+    /// Returns the colors of the palette with the given index in the font's `CPAL` table, as
+    /// unpremultiplied RGBA.
+    #[inline]
+    pub fn color_palette(&self, palette_index: u16) -> Option<Vec<[u8; 4]>> {
+        <Self as Loader>::color_palette(self, palette_index)
+    }
+
+    /// Rasterizes a color glyph to a new RGBA canvas that tightly fits it, using the colors of the
+    /// given palette with the given entries replaced, with premultiplied or straight alpha.
+    #[inline]
+    pub fn rasterize_color_glyph(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        palette_index: u16,
+        palette_overrides: &[(u16, [u8; 4])],
+        alpha_mode: AlphaMode,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        <Self as Loader>::rasterize_color_glyph(
+            self,
+            glyph_id,
+            point_size,
+            transform,
+            palette_index,
+            palette_overrides,
+            alpha_mode,
+        )
+    }
+
+    /// Draws the bitmap that the font embeds for the given glyph, in the strike closest to the
+    /// given size, into a new A8 canvas.
+    #[inline]
+    pub fn glyph_bitmap(&self, glyph_id: u32, point_size: f32) -> Option<(Canvas, Point2D<i32>)> {
+        <Self as Loader>::glyph_bitmap(self, glyph_id, point_size)
+    }
+
+    /// Returns the tags of the OpenType scripts that the font supports, such as `*b"latn"` or
+    /// `*b"arab"`.
+    #[inline]
+    pub fn opentype_script_tags(&self) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_script_tags(self)
+    }
+
+    /// Returns the tags of the OpenType language systems that the font defines for the given
+    /// script, such as `*b"TRK "`.
+    #[inline]
+    pub fn opentype_language_tags(&self, script: [u8; 4]) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_language_tags(self, script)
+    }
+
+    /// Returns the tags of the OpenType features that the font defines in its `GSUB` and `GPOS`
+    /// tables, such as `*b"liga"` or `*b"kern"`.
+    #[inline]
+    pub fn opentype_feature_tags(&self) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_feature_tags(self)
+    }
+
+    /// Returns true if and only if the font defines the OpenType feature with the given tag in its
+    /// `GSUB` or `GPOS` table.
+    #[inline]
+    pub fn has_opentype_feature(&self, feature: [u8; 4]) -> bool {
+        <Self as Loader>::has_opentype_feature(self, feature)
+    }
+
+    /// Returns true if and only if the font's `GSUB` or `GPOS` table enables the OpenType feature
+    /// with the given tag for the given script and language system.
+    #[inline]
+    pub fn supports_opentype_feature(
+        &self,
+        feature: [u8; 4],
+        script: [u8; 4],
+        language: Option<[u8; 4]>,
+    ) -> bool {
+        <Self as Loader>::supports_opentype_feature(self, feature, script, language)
+    }
+
+    /// Returns the glyph that the font's `liga` (standard ligatures) feature substitutes for
+    /// exactly the given sequence of glyphs, if any.
+    #[inline]
+    pub fn ligature_for(&self, glyphs: &[u32]) -> Option<u32> {
+        <Self as Loader>::ligature_for(self, glyphs)
+    }
+
+    /// Returns the first ligature that the font's `liga` feature forms from the glyphs at the
+    /// start of the given sequence, along with the number of glyphs that it replaces.
+    #[inline]
+    pub fn lookup_ligature(&self, glyph_ids: &[u32]) -> Option<(u32, usize)> {
+        <Self as Loader>::lookup_ligature(self, glyph_ids)
     }
 
     /// Returns the values of various font properties, corresponding to those defined in CSS.
@@ -227,7 +455,8 @@ impl Font {
         let style = if symbolic_traits.is_italic() {
             Style::Italic
         } else if all_traits.normalized_slant() > 0.0 {
-            Style::Oblique
+            // Core Text normalizes the slant so that 1.0 is 30 degrees clockwise.
+            Style::Oblique(all_traits.normalized_slant() as f32 * 30.0)
         } else {
             Style::Normal
         };
@@ -242,6 +471,33 @@ impl Font {
         }
     }
 
+    /// Returns the raw embedding permission flags (`fsType`) from the OpenType `OS/2` table, if
+    /// the font has one.
+    #[inline]
+    pub fn embedding_restrictions(&self) -> Option<u16> {
+        <Self as Loader>::embedding_restrictions(self)
+    }
+
+    /// Returns how the font's license permits it to be embedded in documents.
+    #[inline]
+    pub fn embedding_mode(&self) -> EmbeddingMode {
+        <Self as Loader>::embedding_mode(self)
+    }
+
+    /// Returns the 10-byte PANOSE classification from the OpenType `OS/2` table, if the font has
+    /// one.
+    #[inline]
+    pub fn panose(&self) -> Option<[u8; 10]> {
+        <Self as Loader>::panose(self)
+    }
+
+    /// Returns the Unicode blocks that the font claims to cover, from the `ulUnicodeRange` fields
+    /// of the OpenType `OS/2` table.
+    #[inline]
+    pub fn unicode_ranges(&self) -> UnicodeRanges {
+        <Self as Loader>::unicode_ranges(self)
+    }
+
     /// Returns the number of glyphs in the font.
     ///
     /// Glyph IDs range from 0 inclusive to this value exclusive.
@@ -249,6 +505,31 @@ impl Font {
         self.core_text_font.glyph_count() as u32
     }
 
+    /// Returns true if and only if the font has a glyph with the given ID.
+    #[inline]
+    pub fn glyph_exists(&self, glyph_id: u32) -> bool {
+        <Self as Loader>::glyph_exists(self, glyph_id)
+    }
+
+    /// Returns the ID of the glyph to draw in place of characters that the font has no glyph for.
+    ///
+    /// This is the glyph named `.notdef`, or glyph 0 if the font has no glyph by that name.
+    pub fn default_glyph(&self) -> u32 {
+        let glyph_id = self.core_text_font.get_glyph_with_name(".notdef") as u32;
+        if glyph_id < self.glyph_count() {
+            glyph_id
+        } else {
+            0
+        }
+    }
+
+    /// Returns the number of full horizontal metrics records (`numberOfHMetrics`) in the `hmtx`
+    /// table, as given by the `hhea` table.
+    #[inline]
+    pub fn number_of_h_metrics(&self) -> Option<u16> {
+        <Self as Loader>::number_of_h_metrics(self)
+    }
+
     /// Returns the usual glyph ID for a Unicode character.
     ///
     /// Be careful with this function; typographically correct character-to-glyph mapping must be
@@ -270,6 +551,45 @@ impl Font {
         }
     }
 
+    /// Returns the usual glyph IDs for each Unicode character in a string, in order.
+    pub fn glyphs_for_string(&self, text: &str) -> Vec<Option<u32>> {
+        let characters: Vec<u16> = text.encode_utf16().collect();
+        let mut glyphs: Vec<CGGlyph> = vec![0; characters.len()];
+        unsafe {
+            self.core_text_font.get_glyphs_for_characters(
+                characters.as_ptr(),
+                glyphs.as_mut_ptr(),
+                characters.len() as CFIndex,
+            );
+        }
+
+        // Core Text places the glyph for a surrogate pair at the index of its leading surrogate.
+        let mut glyph_index = 0;
+        text.chars()
+            .map(|character| {
+                let id = glyphs[glyph_index] as u32;
+                glyph_index += character.len_utf16();
+                if id != 0 {
+                    Some(id)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns all Unicode characters that the font's `cmap` table maps to the given glyph, in
+    /// code point order.
+    ///
+    /// The reverse mapping is built on the first call, and the result is cached.
+    pub fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        let mut reverse_char_map = self.reverse_char_map.lock().unwrap();
+        if reverse_char_map.is_none() {
+            *reverse_char_map = Some(Arc::new(ReverseCharMap::new(self)));
+        }
+        reverse_char_map.as_ref().unwrap().chars_for_glyph(glyph_id)
+    }
+
     /// Returns the glyph ID for the specified glyph name.
     #[inline]
     pub fn glyph_by_name(&self, name: &str) -> Option<u32> {
@@ -278,6 +598,14 @@ impl Font {
         Some(u32::from(code))
     }
 
+    /// Returns the PostScript name of the glyph with the given ID.
+    ///
+    /// Names are read from the `post` table; returns `None` if it carries no glyph names.
+    #[inline]
+    pub fn glyph_name(&self, glyph_id: u32) -> Option<String> {
+        <Self as Loader>::glyph_name(self, glyph_id)
+    }
+
     /// Sends the vector path for a glyph to a path builder.
     ///
     /// If `hinting_mode` is not None, this function performs grid-fitting as requested before
@@ -336,8 +664,49 @@ impl Font {
         let rect = self
             .core_text_font
             .get_bounding_rects_for_glyphs(kCTFontDefaultOrientation, &[glyph_id as u16]);
+        Ok(self.cg_rect_to_font_units(&rect))
+    }
+
+    /// Returns the boundaries of several glyphs, in order, in font units. Glyphs that aren't in
+    /// the font get zero bounds.
+    ///
+    /// Core Text looks up all the bounds in one call.
+    pub fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        let glyph_count = self.glyph_count();
+        let glyphs: Vec<CGGlyph> = glyph_ids
+            .iter()
+            .map(|&glyph_id| glyph_id as CGGlyph)
+            .collect();
+        let mut rects = vec![CGRect::new(&CG_ZERO_POINT, &CG_ZERO_SIZE); glyphs.len()];
+        unsafe {
+            CTFontGetBoundingRectsForGlyphs(
+                self.core_text_font.as_concrete_TypeRef(),
+                kCTFontDefaultOrientation,
+                glyphs.as_ptr(),
+                rects.as_mut_ptr(),
+                glyphs.len() as CFIndex,
+            );
+        }
+        Ok(glyph_ids
+            .iter()
+            .zip(rects.iter())
+            .map(|(&glyph_id, rect)| {
+                if glyph_id < glyph_count {
+                    self.cg_rect_to_font_units(rect)
+                } else {
+                    Rect::zero()
+                }
+            })
+            .collect())
+    }
+
+    // Converts a rectangle in points at the size of `core_text_font` to font units.
+    fn cg_rect_to_font_units(&self, rect: &CGRect) -> Rect<f32> {
         let units_per_point = self.units_per_point();
-        Ok(Rect::new(
+        Rect::new(
             Point2D::new(
                 (rect.origin.x * units_per_point) as f32,
                 (rect.origin.y * units_per_point) as f32,
@@ -346,7 +715,18 @@ impl Font {
                 (rect.size.width * units_per_point) as f32,
                 (rect.size.height * units_per_point) as f32,
             ),
-        ))
+        )
+    }
+
+    /// Returns the boundaries of a glyph in font units, computed from the points and control
+    /// points of its outline. The origin of the coordinate space is at the bottom left.
+    #[inline]
+    pub fn outline_bounding_box(
+        &self,
+        glyph_id: u32,
+        hinting_options: HintingOptions,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        <Self as Loader>::outline_bounding_box(self, glyph_id, hinting_options)
     }
 
     /// Returns the distance from the origin of the glyph with the given ID to the next, in font
@@ -368,6 +748,108 @@ impl Font {
         }
     }
 
+    /// Returns the advances of several glyphs, in order, in font units.
+    ///
+    /// Core Text looks up all the advances in one call.
+    pub fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        let glyph_ids: Vec<u16> = glyph_ids.iter().map(|&glyph_id| glyph_id as u16).collect();
+        let mut advances = vec![CG_ZERO_SIZE; glyph_ids.len()];
+        unsafe {
+            self.core_text_font.get_advances_for_glyphs(
+                kCTFontDefaultOrientation,
+                glyph_ids.as_ptr(),
+                advances.as_mut_ptr(),
+                glyph_ids.len() as CFIndex,
+            );
+        }
+        let units_per_point = self.units_per_point();
+        Ok(advances
+            .iter()
+            .map(|advance| {
+                Vector2D::new(
+                    (advance.width * units_per_point) as f32,
+                    (advance.height * units_per_point) as f32,
+                )
+            })
+            .collect())
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in pixels
+    /// at the given point size.
+    #[inline]
+    pub fn advance_pixels(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::advance_pixels(self, glyph_id, point_size)
+    }
+
+    /// Returns the ascent, descent, line gap, and line height of the font, in pixels at the given
+    /// point size.
+    #[inline]
+    pub fn line_metrics(&self, point_size: f32) -> LineMetrics {
+        <Self as Loader>::line_metrics(self, point_size)
+    }
+
+    /// Returns the bounds of a run of glyphs laid out left to right, in pixels at the given point
+    /// size. The origin of the coordinate space is at the start of the baseline, at the bottom
+    /// left.
+    #[inline]
+    pub fn run_bounds(
+        &self,
+        glyphs: &[u32],
+        point_size: f32,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        <Self as Loader>::run_bounds(self, glyphs, point_size)
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next in vertical
+    /// writing modes, in font units.
+    ///
+    /// The advance height is returned as a positive `y` component.
+    pub fn vertical_advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        if glyph_id >= self.glyph_count() {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        unsafe {
+            let (glyph_id, mut advance) = (glyph_id as u16, CG_ZERO_SIZE);
+            self.core_text_font.get_advances_for_glyphs(
+                kCTFontVerticalOrientation,
+                &glyph_id,
+                &mut advance,
+                1,
+            );
+            // Core Text reports vertical advances in the `width` component.
+            Ok(Vector2D::new(
+                0.0,
+                (advance.width.abs() * self.units_per_point()) as f32,
+            ))
+        }
+    }
+
+    /// Returns the kerning that the font's `GPOS` table applies between the given pair of
+    /// glyphs, in font units.
+    #[inline]
+    pub fn gpos_kerning_for_pair(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::gpos_kerning_for_pair(self, left_glyph, right_glyph)
+    }
+
+    /// Returns the kerning between the given pair of glyphs, in font units, preferring the
+    /// `GPOS` table and falling back to the legacy `kern` table.
+    #[inline]
+    pub fn kerning_for_pair_any(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::kerning_for_pair_any(self, left_glyph, right_glyph)
+    }
+
     /// Returns the amount that the given glyph should be displaced from the origin.
     pub fn origin(&self, glyph_id: u32) -> Result<Point2D<f32>, GlyphLoadingError> {
         unsafe {
@@ -387,7 +869,14 @@ impl Font {
     }
 
     /// Retrieves various metrics that apply to the entire font.
+    ///
+    /// The metrics are computed on the first call and cached, since they never change.
     pub fn metrics(&self) -> Metrics {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics.get_or_insert_with(|| self.compute_metrics())
+    }
+
+    fn compute_metrics(&self) -> Metrics {
         let units_per_em = self.core_text_font.units_per_em();
         let units_per_point = (units_per_em as f64) / self.core_text_font.pt_size();
         Metrics {
@@ -405,20 +894,106 @@ impl Font {
 
     /// Returns a handle to this font, if possible.
     ///
-    /// This is useful if you want to open the font with a different loader.
-    #[inline]
+    /// This is useful if you want to open the font with a different loader. If the font was
+    /// loaded from a path, the handle refers to that path instead of to a copy of the file.
     pub fn handle(&self) -> Option<Handle> {
-        <Self as Loader>::handle(self)
+        match self.font_data {
+            FontData::Unavailable => None,
+            // Collections are unpacked when they're loaded, so the data is always a single font.
+            FontData::Memory(ref memory) => {
+                Some(Handle::from_memory((*memory).clone(), FaceIndex::FIRST))
+            }
+            // `from_path` reads collections into memory, but a native font can come from a
+            // collection file.
+            // FIXME: Find the index of native fonts that come from collections.
+            FontData::File(_, ref path) => Some(Handle::from_path(path.clone(), FaceIndex::FIRST)),
+        }
     }
 
     /// Attempts to return the raw font data (contents of the font file).
     ///
     /// If this font is a member of a collection, this function returns the data for the entire
-    /// collection.
+    /// collection. If the font was loaded from a path, the file is copied into memory.
     pub fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
         match self.font_data {
             FontData::Unavailable => None,
             FontData::Memory(ref memory) => Some((*memory).clone()),
+            FontData::File(ref mmap, _) => Some(Arc::new(mmap.to_vec())),
+        }
+    }
+
+    /// Attempts to return the raw font data (contents of the font file) without copying it.
+    ///
+    /// If the font was loaded from a path, this borrows the mapped file.
+    pub fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        match self.font_data {
+            FontData::Unavailable => None,
+            FontData::Memory(ref memory) => Some(FontBytesRef::Owned((*memory).clone())),
+            FontData::File(ref mmap, _) => Some(FontBytesRef::Slice(&mmap[..])),
+        }
+    }
+
+    /// Returns a rough estimate of the number of bytes of memory that this font uses.
+    ///
+    /// Font data loaded into memory is counted in full, even if it is shared with other fonts.
+    /// Font data mapped from a file is counted at the size of the file, since Core Text reads all
+    /// of it. Fonts whose data Core Text manages itself only count the platform's structures.
+    pub fn approximate_memory_usage(&self) -> usize {
+        let font_data_size = match self.font_data {
+            FontData::Unavailable => 0,
+            FontData::Memory(ref memory) => memory.capacity(),
+            FontData::File(ref mmap, _) => mmap.len(),
+        };
+        let color_glyph_coverage_size = self
+            .color_glyph_coverage
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |color_glyph_coverage| color_glyph_coverage.heap_size());
+        let reverse_char_map_size = self
+            .reverse_char_map
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |reverse_char_map| reverse_char_map.heap_size());
+        mem::size_of::<Font>()
+            + CORE_TEXT_FONT_MEMORY_USAGE
+            + font_data_size
+            + color_glyph_coverage_size
+            + reverse_char_map_size
+    }
+
+    /// Returns a number that identifies this font: fonts that compare equal have the same identity,
+    /// and different fonts almost certainly don't.
+    ///
+    /// This is an identity of the font's contents, not of this object. It combines the PostScript
+    /// name with a hash of the font data or, for fonts whose data is left in a file, with the
+    /// canonical path of the file and the index of the font within it. It stays the same for the
+    /// lifetime of the process, but may differ between versions of this crate.
+    #[inline]
+    pub fn identity(&self) -> u64 {
+        self.font_identity().to_u64()
+    }
+
+    fn font_identity(&self) -> FontIdentity {
+        let postscript_name = self.postscript_name();
+        match self.font_data {
+            FontData::Memory(ref font_data) => FontIdentity::Data {
+                postscript_name,
+                data_hash: identity::font_data_hash(&self.font_data_hash, font_data),
+            },
+            // Core Text doesn't say which font of a collection it loaded, but the PostScript name
+            // tells them apart. Fonts without a file are only told apart by PostScript name.
+            FontData::File(_, ref path) => FontIdentity::File {
+                postscript_name,
+                path: path.clone(),
+                font_index: FaceIndex::FIRST,
+            },
+            FontData::Unavailable => FontIdentity::File {
+                postscript_name,
+                path: PathBuf::new(),
+                font_index: FaceIndex::FIRST,
+            },
         }
     }
 
@@ -451,7 +1026,9 @@ impl Font {
     /// options. For example, if bilevel (black and white) rendering is requested to an RGBA
     /// surface, this function will automatically convert the 1-bit raster image to the 32-bit
     /// format of the canvas. Note that this may result in a performance penalty, depending on the
-    /// loader.
+    /// loader. The pixels that the glyph covers on RGBA canvases get premultiplied or straight
+    /// alpha as `alpha_mode` asks. Core Graphics only draws with premultiplied alpha, so straight
+    /// alpha is converted afterward.
     ///
     /// If `hinting_options` is not None, the requested grid fitting is performed.
     ///
@@ -466,6 +1043,7 @@ impl Font {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
     ) -> Result<(), GlyphLoadingError> {
         let (cg_color_space, cg_image_format) =
             match format_to_cg_color_space_and_image_format(canvas.format) {
@@ -475,7 +1053,7 @@ impl Font {
                     //
                     // FIXME(pcwalton): Could improve this by only allocating a canvas with a tight
                     // bounding rect and blitting only that part.
-                    let mut temp_canvas = Canvas::new(&canvas.size, Format::Rgba32);
+                    let mut temp_canvas = Canvas::new(&canvas.size, Format::Rgba32)?;
                     self.rasterize_glyph(
                         &mut temp_canvas,
                         glyph_id,
@@ -484,6 +1062,7 @@ impl Font {
                         origin,
                         hinting_options,
                         rasterization_options,
+                        AlphaMode::Premultiplied,
                     )?;
                     canvas.blit_from_canvas(&temp_canvas);
                     return Ok(());
@@ -491,6 +1070,17 @@ impl Font {
                 Some(cg_color_space_and_format) => cg_color_space_and_format,
             };
 
+        // `create_bitmap_context` panics if Core Graphics can't create the context, so rule out
+        // the bitmaps that it rejects up front.
+        if canvas.size.width == 0 || canvas.size.height == 0 {
+            return Ok(());
+        }
+        if canvas.stride < canvas.size.width as usize * canvas.format.bytes_per_pixel() as usize
+            || canvas.pixels.len() < canvas.stride * canvas.size.height as usize
+        {
+            return Err(GlyphLoadingError::PlatformError);
+        }
+
         let core_graphics_context = CGContext::create_bitmap_context(
             Some(canvas.pixels.as_mut_ptr() as *mut _),
             canvas.size.width as usize,
@@ -532,6 +1122,13 @@ impl Font {
             Format::A8 => core_graphics_context.set_gray_fill_color(1.0, 1.0),
         }
 
+        // Core Graphics snaps glyphs to whole pixels unless told otherwise. Position them exactly
+        // so that the fractional part of the origin shifts the coverage.
+        core_graphics_context.set_allows_font_subpixel_positioning(true);
+        core_graphics_context.set_should_subpixel_position_fonts(true);
+        core_graphics_context.set_allows_font_subpixel_quantization(false);
+        core_graphics_context.set_should_subpixel_quantize_fonts(false);
+
         //CoreGraphics origin is in the bottom left. This makes behavior consistent.
         core_graphics_context.translate(0., canvas.size.height as CGFloat);
         core_graphics_context.set_font(&self.core_text_font.copy_to_CGFont());
@@ -545,12 +1142,70 @@ impl Font {
             tx: origin.x as CGFloat,
             ty: -origin.y as CGFloat,
         });
-        let origin = CGPoint::new(0. as CGFloat, 0. as CGFloat);
-        core_graphics_context.show_glyphs_at_positions(&[glyph_id as CGGlyph], &[origin]);
-
+        let cg_origin = CGPoint::new(0. as CGFloat, 0. as CGFloat);
+        core_graphics_context.show_glyphs_at_positions(&[glyph_id as CGGlyph], &[cg_origin]);
+        drop(core_graphics_context);
+
+        if alpha_mode == AlphaMode::Straight {
+            let raster_bounds = self.raster_bounds(
+                glyph_id,
+                point_size,
+                transform,
+                origin,
+                hinting_options,
+                rasterization_options,
+            )?;
+            canvas.unpremultiply_within(&raster_bounds);
+        }
         Ok(())
     }
 
+    /// Rasterizes a glyph at a quantized horizontal subpixel offset to a new canvas that tightly
+    /// fits it.
+    ///
+    /// The glyph origin is placed at `subpixel_x / 2^subpixel_bits` pixels to the right of an
+    /// integral pixel position. `subpixel_bits` should be at most 8, and `subpixel_x` less than
+    /// `2^subpixel_bits`; larger values are clamped to those limits. Rasterizing each glyph once
+    /// per bucket allows callers to cache subpixel-positioned glyphs.
+    ///
+    /// Returns the canvas along with the offset, in whole pixels, from the integral pen position
+    /// to the top left corner of the canvas.
+    #[inline]
+    pub fn rasterize_glyph_subpixel(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        subpixel_x: u8,
+        subpixel_bits: u8,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        <Self as Loader>::rasterize_glyph_subpixel(
+            self,
+            glyph_id,
+            point_size,
+            subpixel_x,
+            subpixel_bits,
+            rasterization_options,
+        )
+    }
+
+    /// Rasterizes a glyph to a canvas with subpixel antialiasing for an LCD screen whose color
+    /// subpixels are in the given order.
+    #[inline]
+    pub fn rasterize_glyph_with_subpixel_order(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        options: SubpixelAaOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        <Self as Loader>::rasterize_glyph_with_subpixel_order(
+            self, canvas, glyph_id, point_size, transform, origin, options,
+        )
+    }
+
     /// Returns true if and only if the font loader can perform hinting in the requested way.
     ///
     /// Some APIs support only rasterizing glyphs with hinting, not retriving hinted outlines. If
@@ -563,10 +1218,28 @@ impl Font {
             HintingOptions::None => true,
             HintingOptions::Vertical(..)
             | HintingOptions::VerticalSubpixel(..)
-            | HintingOptions::Full(..) => false,
+            | HintingOptions::Full(..)
+            | HintingOptions::AutoHint(..) => false,
         }
     }
 
+    /// Returns a simulated bold version of this font, whose glyph outlines are stroked outward so
+    /// that they become `extra_weight` font units wider and taller.
+    #[inline]
+    pub fn synthetic_bold(
+        &self,
+        extra_weight: f32,
+    ) -> Result<SyntheticFont<Font>, FontLoadingError> {
+        <Self as Loader>::synthetic_bold(self, extra_weight)
+    }
+
+    /// Returns a simulated italic version of this font, whose glyph outlines are slanted to the
+    /// right by `slant_angle` degrees from the vertical.
+    #[inline]
+    pub fn synthetic_italic(&self, slant_angle: f32) -> SyntheticFont<Font> {
+        <Self as Loader>::synthetic_italic(self, slant_angle)
+    }
+
     /// Get font fallback results for the given text and locale.
     ///
     /// Note: this is currently just a stub implementation, a proper implementation
@@ -595,21 +1268,59 @@ impl Font {
             .get_font_table(table_tag)
             .map(|data| data.bytes().into())
     }
+
+    /// Returns the tags of all the OpenType tables in the font, in no particular order.
+    ///
+    /// Returns an empty list if the font isn't a TrueType/OpenType font.
+    pub fn table_tags(&self) -> Vec<u32> {
+        unsafe {
+            let table_tags = CTFontCopyAvailableTables(
+                self.core_text_font.as_concrete_TypeRef(),
+                kCTFontTableOptionNoOptions,
+            );
+            if table_tags.is_null() {
+                return vec![];
+            }
+            // The array holds the tags themselves rather than objects.
+            CFArray::<*const c_void>::wrap_under_create_rule(table_tags)
+                .get_all_values()
+                .into_iter()
+                .map(|table_tag| table_tag as usize as u32)
+                .collect()
+        }
+    }
+
+    /// Returns true if and only if the font has an OpenType table with the given tag.
+    #[inline]
+    pub fn has_table(&self, table_tag: u32) -> bool {
+        <Self as Loader>::has_table(self, table_tag)
+    }
 }
 
 impl Loader for Font {
     type NativeFont = NativeFont;
 
     #[inline]
-    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError> {
+    fn from_bytes(
+        font_data: Arc<Vec<u8>>,
+        font_index: FaceIndex,
+    ) -> Result<Self, FontLoadingError> {
         Font::from_bytes(font_data, font_index)
     }
 
     #[inline]
-    fn from_file(file: &mut File, font_index: u32) -> Result<Font, FontLoadingError> {
+    fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Font, FontLoadingError> {
         Font::from_file(file, font_index)
     }
 
+    #[inline]
+    fn from_path<P>(path: P, font_index: FaceIndex) -> Result<Font, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        Font::from_path(path, font_index)
+    }
+
     #[inline]
     unsafe fn from_native_font(native_font: Self::NativeFont) -> Self {
         Font::from_native_font(native_font)
@@ -646,8 +1357,13 @@ impl Loader for Font {
     }
 
     #[inline]
-    fn is_monospace(&self) -> bool {
-        self.is_monospace()
+    fn is_color_font(&self) -> bool {
+        self.is_color_font()
+    }
+
+    #[inline]
+    fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        self.glyph_is_color(glyph_id)
     }
 
     #[inline]
@@ -660,6 +1376,16 @@ impl Loader for Font {
         self.glyph_for_char(character)
     }
 
+    #[inline]
+    fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        self.glyph_to_char(glyph_id)
+    }
+
+    #[inline]
+    fn glyphs_for_string(&self, text: &str) -> Vec<Option<u32>> {
+        self.glyphs_for_string(text)
+    }
+
     #[inline]
     fn glyph_by_name(&self, name: &str) -> Option<u32> {
         self.glyph_by_name(name)
@@ -670,6 +1396,11 @@ impl Loader for Font {
         self.glyph_count()
     }
 
+    #[inline]
+    fn default_glyph(&self) -> u32 {
+        self.default_glyph()
+    }
+
     #[inline]
     fn outline<B>(
         &self,
@@ -688,11 +1419,29 @@ impl Loader for Font {
         self.typographic_bounds(glyph_id)
     }
 
+    #[inline]
+    fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        self.typographic_bounds_for_glyphs(glyph_ids)
+    }
+
     #[inline]
     fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
         self.advance(glyph_id)
     }
 
+    #[inline]
+    fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        self.advances(glyph_ids)
+    }
+
+    #[inline]
+    fn vertical_advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        self.vertical_advance(glyph_id)
+    }
+
     #[inline]
     fn origin(&self, glyph_id: u32) -> Result<Point2D<f32>, GlyphLoadingError> {
         self.origin(glyph_id)
@@ -703,11 +1452,26 @@ impl Loader for Font {
         self.metrics()
     }
 
+    #[inline]
+    fn handle(&self) -> Option<Handle> {
+        self.handle()
+    }
+
     #[inline]
     fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
         self.copy_font_data()
     }
 
+    #[inline]
+    fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        self.font_bytes()
+    }
+
+    #[inline]
+    fn approximate_memory_usage(&self) -> usize {
+        self.approximate_memory_usage()
+    }
+
     #[inline]
     fn supports_hinting_options(
         &self,
@@ -727,6 +1491,7 @@ impl Loader for Font {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
     ) -> Result<(), GlyphLoadingError> {
         self.rasterize_glyph(
             canvas,
@@ -736,6 +1501,7 @@ impl Loader for Font {
             origin,
             hinting_options,
             rasterization_options,
+            alpha_mode,
         )
     }
 
@@ -748,8 +1514,52 @@ impl Loader for Font {
     fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
         self.load_font_table(table_tag)
     }
+
+    #[inline]
+    fn table_tags(&self) -> Vec<u32> {
+        self.table_tags()
+    }
+}
+
+impl Clone for Font {
+    #[inline]
+    fn clone(&self) -> Font {
+        Font {
+            core_text_font: self.core_text_font.clone(),
+            font_data: self.font_data.clone(),
+            color_glyph_coverage: Mutex::new((*self.color_glyph_coverage.lock().unwrap()).clone()),
+            font_data_hash: Mutex::new(*self.font_data_hash.lock().unwrap()),
+            metrics: Mutex::new(*self.metrics.lock().unwrap()),
+            reverse_char_map: Mutex::new((*self.reverse_char_map.lock().unwrap()).clone()),
+        }
+    }
 }
 
+// Two fonts are equal if they have the same PostScript name and either the same font data or, for
+// fonts mapped from files, the same file.
+impl PartialEq for Font {
+    fn eq(&self, other: &Font) -> bool {
+        self.font_identity() == other.font_identity()
+    }
+}
+
+impl Eq for Font {}
+
+impl Hash for Font {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.font_identity().hash(state)
+    }
+}
+
+// Core Text font objects are immutable and documented to be usable from multiple threads at
+// once. The font data is never mutated after loading, and the color glyph cache is behind a
+// mutex.
+unsafe impl Send for Font {}
+unsafe impl Sync for Font {}
+
 impl Debug for Font {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
         self.full_name().fmt(fmt)
@@ -760,6 +1570,8 @@ impl Debug for Font {
 enum FontData {
     Unavailable,
     Memory(Arc<Vec<u8>>),
+    // A mapped file, along with its canonical path.
+    File(Arc<Mmap>, PathBuf),
 }
 
 impl Deref for FontData {
@@ -768,6 +1580,7 @@ impl Deref for FontData {
         match *self {
             FontData::Unavailable => panic!("Font data unavailable!"),
             FontData::Memory(ref data) => &***data,
+            FontData::File(ref mmap, _) => &***mmap,
         }
     }
 }
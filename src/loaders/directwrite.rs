@@ -30,29 +30,52 @@ use std::borrow::Cow;
 use std::ffi::OsString;
 use std::fmt::{self, Debug, Formatter};
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Read, Seek, SeekFrom};
+use std::mem;
 use std::os::windows::ffi::OsStringExt;
 use std::os::windows::io::AsRawHandle;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use tokio::io::{self as async_io, AsyncRead};
 use winapi::shared::minwindef::{FALSE, MAX_PATH};
+use winapi::um::dwrite::{
+    DWRITE_GLYPH_METRICS, DWRITE_MEASURING_MODE, DWRITE_MEASURING_MODE_GDI_CLASSIC,
+    DWRITE_RENDERING_MODE, DWRITE_RENDERING_MODE_GDI_CLASSIC,
+    DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC,
+};
 use winapi::um::dwrite::{
     DWRITE_NUMBER_SUBSTITUTION_METHOD_NONE, DWRITE_READING_DIRECTION,
     DWRITE_READING_DIRECTION_LEFT_TO_RIGHT,
 };
 use winapi::um::fileapi;
 
-use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::canvas::{AlphaMode, Canvas, Format, RasterizationOptions, SubpixelAaOptions};
+use crate::cmap::ReverseCharMap;
+use crate::color::{ColorGlyphCoverage, ColorLayer};
+use crate::embedding::EmbeddingMode;
 use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
+use crate::font::FaceIndex;
+use crate::font_info::FontInfo;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
-use crate::loader::{FallbackFont, FallbackResult, FontTransform, Loader};
-use crate::metrics::Metrics;
+use crate::identity::{self, FontIdentity};
+use crate::loader::{FallbackFont, FallbackResult, FontBytesRef, FontTransform, Loader};
+use crate::metrics::{LineMetrics, Metrics};
+use crate::post;
 use crate::properties::{Properties, Stretch, Style, Weight};
+use crate::sfnt::FontTables;
+use crate::synthetic::SyntheticFont;
+use crate::unicode_ranges::UnicodeRanges;
 
 const ERROR_BOUND: f32 = 0.0001;
 
+// A rough estimate of the memory that DirectWrite allocates for a font and its font face,
+// including the tables that it parses up front.
+const DIRECTWRITE_FONT_MEMORY_USAGE: usize = 16 * 1024;
+
 /// DirectWrite's representation of a font.
 pub struct NativeFont {
     /// The native DirectWrite font object.
@@ -66,6 +89,10 @@ pub struct Font {
     dwrite_font: DWriteFont,
     dwrite_font_face: DWriteFontFace,
     cached_data: Mutex<Option<Arc<Vec<u8>>>>,
+    color_glyph_coverage: Mutex<Option<Arc<ColorGlyphCoverage>>>,
+    font_data_hash: Mutex<Option<u64>>,
+    metrics: Mutex<Option<Metrics>>,
+    reverse_char_map: Mutex<Option<Arc<ReverseCharMap>>>,
 }
 
 struct MyTextAnalysisSource {
@@ -104,6 +131,10 @@ impl Font {
                     dwrite_font,
                     dwrite_font_face,
                     cached_data: Mutex::new(font_data),
+                    color_glyph_coverage: Mutex::new(None),
+                    font_data_hash: Mutex::new(None),
+                    metrics: Mutex::new(None),
+                    reverse_char_map: Mutex::new(None),
                 });
             }
         }
@@ -113,18 +144,23 @@ impl Font {
     /// Loads a font from raw font data (the contents of a `.ttf`/`.otf`/etc. file).
     ///
     /// If the data represents a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index
-    /// of the font to load from it. If the data represents a single font, pass 0 for `font_index`.
-    pub fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Font, FontLoadingError> {
+    /// of the font to load from it. If the data represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    pub fn from_bytes(
+        font_data: Arc<Vec<u8>>,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError> {
         let font_file =
             DWriteFontFile::new_from_data(font_data.clone()).ok_or(FontLoadingError::Parse)?;
-        Font::from_dwrite_font_file(font_file, font_index, Some(font_data))
+        Font::from_dwrite_font_file(font_file, font_index.0, Some(font_data))
     }
 
     /// Loads a font from a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
-    pub fn from_file(file: &mut File, font_index: u32) -> Result<Font, FontLoadingError> {
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    pub fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Font, FontLoadingError> {
         unsafe {
             let mut path = vec![0; MAX_PATH + 1];
             let path_len = fileapi::GetFinalPathNameByHandleW(
@@ -144,11 +180,46 @@ impl Font {
     /// Loads a font from the path to a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
     #[inline]
-    pub fn from_path<P: AsRef<Path>>(path: P, font_index: u32) -> Result<Font, FontLoadingError> {
-        let font_file = DWriteFontFile::new_from_path(path).ok_or(FontLoadingError::Parse)?;
-        Font::from_dwrite_font_file(font_file, font_index, None)
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError> {
+        let path = path.as_ref();
+        let font_file = match DWriteFontFile::new_from_path(path) {
+            Some(font_file) => font_file,
+            None => {
+                // Report why the file couldn't be opened, if that's what went wrong.
+                return Err(match std::fs::metadata(path) {
+                    Err(io_error) => FontLoadingError::Io(io_error),
+                    Ok(_) => FontLoadingError::Parse,
+                });
+            }
+        };
+        Font::from_dwrite_font_file(font_file, font_index.0, None)
+    }
+
+    /// Loads a font by reading the raw font data (the contents of a `.ttf`/`.otf`/etc. file) from
+    /// an asynchronous reader.
+    ///
+    /// The whole stream is read into memory before the font is loaded, so the executor isn't
+    /// blocked on the I/O. If the data represents a collection (`.ttc`/`.otc`/etc.),
+    /// `font_index` specifies the index of the font to load from it. If the data represents a
+    /// single font, pass `FaceIndex::FIRST` for `font_index`.
+    #[cfg(feature = "async")]
+    pub async fn from_async_read<R>(
+        mut reader: R,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut font_data = vec![];
+        // Importing `AsyncReadExt` would make its methods clash with those of `Read`.
+        async_io::AsyncReadExt::read_to_end(&mut reader, &mut font_data).await?;
+        Font::from_bytes(Arc::new(font_data), font_index)
     }
 
     /// Creates a font from a native API handle.
@@ -158,6 +229,10 @@ impl Font {
             dwrite_font: native_font.dwrite_font,
             dwrite_font_face: native_font.dwrite_font_face,
             cached_data: Mutex::new(None),
+            color_glyph_coverage: Mutex::new(None),
+            font_data_hash: Mutex::new(None),
+            metrics: Mutex::new(None),
+            reverse_char_map: Mutex::new(None),
         }
     }
 
@@ -189,6 +264,9 @@ impl Font {
     }
 
     /// Returns the wrapped native font handle.
+    ///
+    /// Both DirectWrite objects are `AddRef`'d, so they remain valid after this font is dropped.
+    /// DirectWrite font objects are immutable and may be used from any thread.
     pub fn native_font(&self) -> NativeFont {
         NativeFont {
             dwrite_font: self.dwrite_font.clone(),
@@ -202,6 +280,15 @@ impl Font {
         <Self as Loader>::analyze_path(path)
     }
 
+    /// Reads the names and properties of a font from raw font data without loading it.
+    #[inline]
+    pub fn peek_info(
+        font_data: &[u8],
+        font_index: FaceIndex,
+    ) -> Result<FontInfo, FontLoadingError> {
+        <Self as Loader>::peek_info(font_data, font_index)
+    }
+
     /// Returns the PostScript name of the font. This should be globally unique.
     #[inline]
     pub fn postscript_name(&self) -> Option<String> {
@@ -230,14 +317,152 @@ impl Font {
         self.dwrite_font.is_monospace().unwrap_or(false)
     }
 
+    /// Returns true if and only if the font contains color glyphs (e.g. emoji).
+    #[inline]
+    pub fn is_color_font(&self) -> bool {
+        <Self as Loader>::is_color_font(self)
+    }
+
+    /// Returns true if and only if the glyph with the given ID has a color representation in any
+    /// of the `COLR`, `sbix`, `CBDT`, or `SVG ` tables.
+    ///
+    /// The color tables are parsed on the first call, and the result is cached.
+    pub fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        let mut color_glyph_coverage = self.color_glyph_coverage.lock().unwrap();
+        if color_glyph_coverage.is_none() {
+            *color_glyph_coverage = Some(Arc::new(ColorGlyphCoverage::new(self)));
+        }
+        color_glyph_coverage.as_ref().unwrap().contains(glyph_id)
+    }
+
+    /// Returns the layers that make up the given glyph in the font's `COLR` (version 0) table, from
+    /// bottom to top.
+    #[inline]
+    pub fn color_glyph_layers(&self, glyph_id: u32) -> Option<Vec<ColorLayer>> {
+        <Self as Loader>::color_glyph_layers(self, glyph_id)
+    }
+
+    /// Returns the colors of the palette with the given index in the font's `CPAL` table, as
+    /// unpremultiplied RGBA.
+    #[inline]
+    pub fn color_palette(&self, palette_index: u16) -> Option<Vec<[u8; 4]>> {
+        <Self as Loader>::color_palette(self, palette_index)
+    }
+
+    /// Rasterizes a color glyph to a new RGBA canvas that tightly fits it, using the colors of the
+    /// given palette with the given entries replaced, with premultiplied or straight alpha.
+    #[inline]
+    pub fn rasterize_color_glyph(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        palette_index: u16,
+        palette_overrides: &[(u16, [u8; 4])],
+        alpha_mode: AlphaMode,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        <Self as Loader>::rasterize_color_glyph(
+            self,
+            glyph_id,
+            point_size,
+            transform,
+            palette_index,
+            palette_overrides,
+            alpha_mode,
+        )
+    }
+
+    /// Draws the bitmap that the font embeds for the given glyph, in the strike closest to the
+    /// given size, into a new A8 canvas.
+    #[inline]
+    pub fn glyph_bitmap(&self, glyph_id: u32, point_size: f32) -> Option<(Canvas, Point2D<i32>)> {
+        <Self as Loader>::glyph_bitmap(self, glyph_id, point_size)
+    }
+
+    /// Returns the tags of the OpenType scripts that the font supports, such as `*b"latn"` or
+    /// `*b"arab"`.
+    #[inline]
+    pub fn opentype_script_tags(&self) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_script_tags(self)
+    }
+
+    /// Returns the tags of the OpenType language systems that the font defines for the given
+    /// script, such as `*b"TRK "`.
+    #[inline]
+    pub fn opentype_language_tags(&self, script: [u8; 4]) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_language_tags(self, script)
+    }
+
+    /// Returns the tags of the OpenType features that the font defines in its `GSUB` and `GPOS`
+    /// tables, such as `*b"liga"` or `*b"kern"`.
+    #[inline]
+    pub fn opentype_feature_tags(&self) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_feature_tags(self)
+    }
+
+    /// Returns true if and only if the font defines the OpenType feature with the given tag in its
+    /// `GSUB` or `GPOS` table.
+    #[inline]
+    pub fn has_opentype_feature(&self, feature: [u8; 4]) -> bool {
+        <Self as Loader>::has_opentype_feature(self, feature)
+    }
+
+    /// Returns true if and only if the font's `GSUB` or `GPOS` table enables the OpenType feature
+    /// with the given tag for the given script and language system.
+    #[inline]
+    pub fn supports_opentype_feature(
+        &self,
+        feature: [u8; 4],
+        script: [u8; 4],
+        language: Option<[u8; 4]>,
+    ) -> bool {
+        <Self as Loader>::supports_opentype_feature(self, feature, script, language)
+    }
+
+    /// Returns the glyph that the font's `liga` (standard ligatures) feature substitutes for
+    /// exactly the given sequence of glyphs, if any.
+    #[inline]
+    pub fn ligature_for(&self, glyphs: &[u32]) -> Option<u32> {
+        <Self as Loader>::ligature_for(self, glyphs)
+    }
+
+    /// Returns the first ligature that the font's `liga` feature forms from the glyphs at the
+    /// start of the given sequence, along with the number of glyphs that it replaces.
+    #[inline]
+    pub fn lookup_ligature(&self, glyph_ids: &[u32]) -> Option<(u32, usize)> {
+        <Self as Loader>::lookup_ligature(self, glyph_ids)
+    }
+
     /// Returns the values of various font properties, corresponding to those defined in CSS.
     pub fn properties(&self) -> Properties {
-        let dwrite_font = &self.dwrite_font;
-        Properties {
-            style: style_for_dwrite_style(dwrite_font.style()),
-            stretch: Stretch(Stretch::MAPPING[(dwrite_font.stretch() as usize) - 1]),
-            weight: Weight(dwrite_font.weight().to_u32() as f32),
-        }
+        properties_for_dwrite_font(&self.dwrite_font)
+    }
+
+    /// Returns the raw embedding permission flags (`fsType`) from the OpenType `OS/2` table, if
+    /// the font has one.
+    #[inline]
+    pub fn embedding_restrictions(&self) -> Option<u16> {
+        <Self as Loader>::embedding_restrictions(self)
+    }
+
+    /// Returns how the font's license permits it to be embedded in documents.
+    #[inline]
+    pub fn embedding_mode(&self) -> EmbeddingMode {
+        <Self as Loader>::embedding_mode(self)
+    }
+
+    /// Returns the 10-byte PANOSE classification from the OpenType `OS/2` table, if the font has
+    /// one.
+    #[inline]
+    pub fn panose(&self) -> Option<[u8; 10]> {
+        <Self as Loader>::panose(self)
+    }
+
+    /// Returns the Unicode blocks that the font claims to cover, from the `ulUnicodeRange` fields
+    /// of the OpenType `OS/2` table.
+    #[inline]
+    pub fn unicode_ranges(&self) -> UnicodeRanges {
+        <Self as Loader>::unicode_ranges(self)
     }
 
     /// Returns the usual glyph ID for a Unicode character.
@@ -254,6 +479,36 @@ impl Font {
             .map(|g| g as u32)
     }
 
+    /// Returns the usual glyph IDs for each Unicode character in a string, in order.
+    pub fn glyphs_for_string(&self, text: &str) -> Vec<Option<u32>> {
+        let chars: Vec<u32> = text.chars().map(|character| character as u32).collect();
+        self.dwrite_font_face
+            .get_glyph_indices(&chars)
+            .into_iter()
+            .map(|g| Some(g as u32))
+            .collect()
+    }
+
+    /// Returns all Unicode characters that the font's `cmap` table maps to the given glyph, in
+    /// code point order.
+    ///
+    /// The reverse mapping is built on the first call, and the result is cached.
+    pub fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        let mut reverse_char_map = self.reverse_char_map.lock().unwrap();
+        if reverse_char_map.is_none() {
+            *reverse_char_map = Some(Arc::new(ReverseCharMap::new(self)));
+        }
+        reverse_char_map.as_ref().unwrap().chars_for_glyph(glyph_id)
+    }
+
+    /// Returns the PostScript name of the glyph with the given ID.
+    ///
+    /// Names are read from the `post` table; returns `None` if it carries no glyph names.
+    #[inline]
+    pub fn glyph_name(&self, glyph_id: u32) -> Option<String> {
+        <Self as Loader>::glyph_name(self, glyph_id)
+    }
+
     /// Returns the number of glyphs in the font.
     ///
     /// Glyph IDs range from 0 inclusive to this value exclusive.
@@ -262,6 +517,25 @@ impl Font {
         self.dwrite_font_face.get_glyph_count() as u32
     }
 
+    /// Returns true if and only if the font has a glyph with the given ID.
+    #[inline]
+    pub fn glyph_exists(&self, glyph_id: u32) -> bool {
+        <Self as Loader>::glyph_exists(self, glyph_id)
+    }
+
+    /// Returns the ID of the glyph to draw in place of characters that the font has no glyph for.
+    #[inline]
+    pub fn default_glyph(&self) -> u32 {
+        <Self as Loader>::default_glyph(self)
+    }
+
+    /// Returns the number of full horizontal metrics records (`numberOfHMetrics`) in the `hmtx`
+    /// table, as given by the `hhea` table.
+    #[inline]
+    pub fn number_of_h_metrics(&self) -> Option<u16> {
+        <Self as Loader>::number_of_h_metrics(self)
+    }
+
     /// Sends the vector path for a glyph to a path builder.
     ///
     /// If `hinting_mode` is not None, this function performs grid-fitting as requested before
@@ -296,24 +570,44 @@ impl Font {
         let metrics = self
             .dwrite_font_face
             .get_design_glyph_metrics(&[glyph_id as u16], false);
+        Ok(design_glyph_bounds(&metrics[0]))
+    }
 
-        let metrics = &metrics[0];
-        let advance_width = metrics.advanceWidth as i32;
-        let advance_height = metrics.advanceHeight as i32;
-        let left_side_bearing = metrics.leftSideBearing as i32;
-        let right_side_bearing = metrics.rightSideBearing as i32;
-        let top_side_bearing = metrics.topSideBearing as i32;
-        let bottom_side_bearing = metrics.bottomSideBearing as i32;
-        let vertical_origin_y = metrics.verticalOriginY as i32;
-
-        let y_offset = vertical_origin_y + bottom_side_bearing - advance_height;
-        let width = advance_width - (left_side_bearing + right_side_bearing);
-        let height = advance_height - (top_side_bearing + bottom_side_bearing);
+    /// Returns the boundaries of several glyphs, in order, in font units. Glyphs that aren't in
+    /// the font get zero bounds.
+    ///
+    /// DirectWrite looks up the metrics of all the glyphs in one call.
+    pub fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        let glyph_count = self.glyph_count();
+        let glyphs: Vec<u16> = glyph_ids.iter().map(|&glyph_id| glyph_id as u16).collect();
+        let metrics = self
+            .dwrite_font_face
+            .get_design_glyph_metrics(&glyphs, false);
+        Ok(glyph_ids
+            .iter()
+            .zip(metrics.iter())
+            .map(|(&glyph_id, metrics)| {
+                if glyph_id < glyph_count {
+                    design_glyph_bounds(metrics)
+                } else {
+                    Rect::zero()
+                }
+            })
+            .collect())
+    }
 
-        Ok(Rect::new(
-            Point2D::new(left_side_bearing as f32, y_offset as f32),
-            Size2D::new(width as f32, height as f32),
-        ))
+    /// Returns the boundaries of a glyph in font units, computed from the points and control
+    /// points of its outline. The origin of the coordinate space is at the bottom left.
+    #[inline]
+    pub fn outline_bounding_box(
+        &self,
+        glyph_id: u32,
+        hinting_options: HintingOptions,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        <Self as Loader>::outline_bounding_box(self, glyph_id, hinting_options)
     }
 
     /// Returns the distance from the origin of the glyph with the given ID to the next, in font
@@ -326,6 +620,79 @@ impl Font {
         Ok(Vector2D::new(metrics.advanceWidth as f32, 0.0))
     }
 
+    /// Returns the advances of several glyphs, in order, in font units.
+    ///
+    /// DirectWrite looks up the metrics of all the glyphs in one call.
+    pub fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        let glyph_ids: Vec<u16> = glyph_ids.iter().map(|&glyph_id| glyph_id as u16).collect();
+        let metrics = self
+            .dwrite_font_face
+            .get_design_glyph_metrics(&glyph_ids, false);
+        Ok(metrics
+            .iter()
+            .map(|metrics| Vector2D::new(metrics.advanceWidth as f32, 0.0))
+            .collect())
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in pixels
+    /// at the given point size.
+    #[inline]
+    pub fn advance_pixels(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::advance_pixels(self, glyph_id, point_size)
+    }
+
+    /// Returns the ascent, descent, line gap, and line height of the font, in pixels at the given
+    /// point size.
+    #[inline]
+    pub fn line_metrics(&self, point_size: f32) -> LineMetrics {
+        <Self as Loader>::line_metrics(self, point_size)
+    }
+
+    /// Returns the bounds of a run of glyphs laid out left to right, in pixels at the given point
+    /// size. The origin of the coordinate space is at the start of the baseline, at the bottom
+    /// left.
+    #[inline]
+    pub fn run_bounds(
+        &self,
+        glyphs: &[u32],
+        point_size: f32,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        <Self as Loader>::run_bounds(self, glyphs, point_size)
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next in vertical
+    /// writing modes, in font units.
+    #[inline]
+    pub fn vertical_advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::vertical_advance(self, glyph_id)
+    }
+
+    /// Returns the kerning that the font's `GPOS` table applies between the given pair of
+    /// glyphs, in font units.
+    #[inline]
+    pub fn gpos_kerning_for_pair(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::gpos_kerning_for_pair(self, left_glyph, right_glyph)
+    }
+
+    /// Returns the kerning between the given pair of glyphs, in font units, preferring the
+    /// `GPOS` table and falling back to the legacy `kern` table.
+    #[inline]
+    pub fn kerning_for_pair_any(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::kerning_for_pair_any(self, left_glyph, right_glyph)
+    }
+
     /// Returns the amount that the given glyph should be displaced from the origin.
     pub fn origin(&self, glyph: u32) -> Result<Point2D<f32>, GlyphLoadingError> {
         let metrics = self
@@ -338,7 +705,14 @@ impl Font {
     }
 
     /// Retrieves various metrics that apply to the entire font.
+    ///
+    /// The metrics are computed on the first call and cached, since they never change.
     pub fn metrics(&self) -> Metrics {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics.get_or_insert_with(|| self.compute_metrics())
+    }
+
+    fn compute_metrics(&self) -> Metrics {
         let dwrite_font = &self.dwrite_font;
         let dwrite_metrics = dwrite_font.metrics();
         Metrics {
@@ -377,6 +751,80 @@ impl Font {
         (*font_data).clone()
     }
 
+    /// Attempts to return the raw font data (contents of the font file) without copying it.
+    ///
+    /// DirectWrite only hands out copies of font files, so this shares the copy that
+    /// `copy_font_data` makes and keeps.
+    #[inline]
+    pub fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        <Self as Loader>::font_bytes(self)
+    }
+
+    /// Returns a rough estimate of the number of bytes of memory that this font uses.
+    ///
+    /// The font file is only counted once `copy_font_data` has read it into memory; until then,
+    /// DirectWrite maps it on demand, and the OS can evict those pages whenever it needs to.
+    pub fn approximate_memory_usage(&self) -> usize {
+        let font_data_size = self
+            .cached_data
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |font_data| font_data.capacity());
+        let color_glyph_coverage_size = self
+            .color_glyph_coverage
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |color_glyph_coverage| color_glyph_coverage.heap_size());
+        let reverse_char_map_size = self
+            .reverse_char_map
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |reverse_char_map| reverse_char_map.heap_size());
+        mem::size_of::<Font>()
+            + DIRECTWRITE_FONT_MEMORY_USAGE
+            + font_data_size
+            + color_glyph_coverage_size
+            + reverse_char_map_size
+    }
+
+    /// Returns a number that identifies this font: fonts that compare equal have the same identity,
+    /// and different fonts almost certainly don't.
+    ///
+    /// This is an identity of the font's contents, not of this object. It combines the PostScript
+    /// name with a hash of the font data or, for fonts whose data is left in a file, with the
+    /// canonical path of the file and the index of the font within it. It stays the same for the
+    /// lifetime of the process, but may differ between versions of this crate.
+    #[inline]
+    pub fn identity(&self) -> u64 {
+        self.font_identity().to_u64()
+    }
+
+    fn font_identity(&self) -> FontIdentity {
+        let postscript_name = self.postscript_name();
+        let path = self
+            .dwrite_font_face
+            .get_files()
+            .get(0)
+            .and_then(|file| file.get_font_file_path());
+        match path {
+            Some(path) => FontIdentity::File {
+                postscript_name,
+                path: identity::canonical_path(&path),
+                font_index: FaceIndex(self.dwrite_font_face.get_index()),
+            },
+            // Fonts loaded from memory have no path.
+            None => FontIdentity::Data {
+                postscript_name,
+                data_hash: self.copy_font_data().map_or(0, |font_data| {
+                    identity::font_data_hash(&self.font_data_hash, &font_data)
+                }),
+            },
+        }
+    }
+
     /// Returns the pixel boundaries that the glyph will take up when rendered using this loader's
     /// rasterizer at the given size and origin.
     #[inline]
@@ -421,9 +869,14 @@ impl Font {
     /// options. For example, if bilevel (black and white) rendering is requested to an RGBA
     /// surface, this function will automatically convert the 1-bit raster image to the 32-bit
     /// format of the canvas. Note that this may result in a performance penalty, depending on the
-    /// loader.
+    /// loader. The pixels that the glyph covers on RGBA canvases get premultiplied or straight
+    /// alpha as `alpha_mode` asks.
     ///
     /// If `hinting_options` is not None, the requested grid fitting is performed.
+    ///
+    /// The glyph is rasterized by DirectWrite's `IDWriteGlyphRunAnalysis`, so it looks the same as
+    /// text that Windows draws. Subpixel antialiasing produces ClearType coverage with a separate
+    /// value for each color channel, and grayscale antialiasing averages those values.
     pub fn rasterize_glyph(
         &self,
         canvas: &mut Canvas,
@@ -433,10 +886,8 @@ impl Font {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
     ) -> Result<(), GlyphLoadingError> {
-        // TODO(pcwalton): This is woefully incomplete. See WebRender's code for a more complete
-        // implementation.
-
         let dwrite_analysis = self.build_glyph_analysis(
             glyph_id,
             point_size,
@@ -464,29 +915,86 @@ impl Font {
             return Ok(());
         }
 
-        let texture_format = if texture_type == DWRITE_TEXTURE_ALIASED_1x1 {
-            Format::A8
-        } else {
-            Format::Rgb24
+        // ClearType textures hold a coverage value for each of the red, green, and blue subpixels.
+        let mut texture_bytes =
+            dwrite_analysis.create_alpha_texture(texture_type, texture_bounds)?;
+        let texture_format = match rasterization_options {
+            RasterizationOptions::Bilevel => Format::A8,
+            RasterizationOptions::GrayscaleAa => {
+                texture_bytes = texture_bytes
+                    .chunks(3)
+                    .map(|rgb| ((rgb[0] as u32 + rgb[1] as u32 + rgb[2] as u32) / 3) as u8)
+                    .collect();
+                Format::A8
+            }
+            RasterizationOptions::SubpixelAa => Format::Rgb24,
         };
         let texture_bits_per_pixel = texture_format.bits_per_pixel();
         let texture_bytes_per_pixel = texture_bits_per_pixel as usize / 8;
         let texture_size = Size2D::new(texture_width, texture_height).to_u32();
         let texture_stride = texture_width as usize * texture_bytes_per_pixel;
 
-        let mut texture_bytes =
-            dwrite_analysis.create_alpha_texture(texture_type, texture_bounds)?;
+        let texture_origin = point2(texture_bounds.left, texture_bounds.top);
         canvas.blit_from(
-            point2(texture_bounds.left, texture_bounds.top),
+            texture_origin,
             &mut texture_bytes,
             &texture_size,
             texture_stride,
             texture_format,
         );
+        if alpha_mode == AlphaMode::Straight {
+            canvas.unpremultiply_within(&Rect::new(texture_origin, texture_size.to_i32()));
+        }
 
         Ok(())
     }
 
+    /// Rasterizes a glyph at a quantized horizontal subpixel offset to a new canvas that tightly
+    /// fits it.
+    ///
+    /// The glyph origin is placed at `subpixel_x / 2^subpixel_bits` pixels to the right of an
+    /// integral pixel position. `subpixel_bits` should be at most 8, and `subpixel_x` less than
+    /// `2^subpixel_bits`; larger values are clamped to those limits. Rasterizing each glyph once
+    /// per bucket allows callers to cache subpixel-positioned glyphs.
+    ///
+    /// Returns the canvas along with the offset, in whole pixels, from the integral pen position
+    /// to the top left corner of the canvas.
+    #[inline]
+    pub fn rasterize_glyph_subpixel(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        subpixel_x: u8,
+        subpixel_bits: u8,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        <Self as Loader>::rasterize_glyph_subpixel(
+            self,
+            glyph_id,
+            point_size,
+            subpixel_x,
+            subpixel_bits,
+            rasterization_options,
+        )
+    }
+
+    /// Rasterizes a glyph to a canvas with subpixel antialiasing for an LCD screen whose color
+    /// subpixels are in the given order.
+    #[inline]
+    pub fn rasterize_glyph_with_subpixel_order(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        options: SubpixelAaOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        <Self as Loader>::rasterize_glyph_with_subpixel_order(
+            self, canvas, glyph_id, point_size, transform, origin, options,
+        )
+    }
+
     /// Returns true if and only if the font loader can perform hinting in the requested way.
     ///
     /// Some APIs support only rasterizing glyphs with hinting, not retriving hinted outlines. If
@@ -501,10 +1009,12 @@ impl Font {
         match (hinting_options, for_rasterization) {
             (HintingOptions::None, _)
             | (HintingOptions::Vertical(_), true)
-            | (HintingOptions::VerticalSubpixel(_), true) => true,
+            | (HintingOptions::VerticalSubpixel(_), true)
+            | (HintingOptions::Full(_), true) => true,
             (HintingOptions::Vertical(_), false)
             | (HintingOptions::VerticalSubpixel(_), false)
-            | (HintingOptions::Full(_), _) => false,
+            | (HintingOptions::Full(_), false)
+            | (HintingOptions::AutoHint(_), _) => false,
         }
     }
 
@@ -514,9 +1024,27 @@ impl Font {
         point_size: f32,
         transform: &FontTransform,
         origin: &Point2D<f32>,
-        _hinting_options: HintingOptions,
+        hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
     ) -> Result<DWriteGlyphRunAnalysis, GlyphLoadingError> {
+        // DirectWrite has no autohinter, so autohinting means no grid fitting at all, as the
+        // `AutoHint` docs promise.
+        let hinting_options = match hinting_options {
+            HintingOptions::AutoHint(_) => HintingOptions::None,
+            hinting_options => hinting_options,
+        };
+
+        // DirectWrite grid-fits glyphs at the size of the run, so lay the glyph out at the grid
+        // fitting size and scale it to the requested size.
+        let (em_size, scale) = match hinting_options.grid_fitting_size() {
+            Some(grid_fitting_size) if grid_fitting_size > 0.0 => {
+                (grid_fitting_size, point_size / grid_fitting_size)
+            }
+            _ => (point_size, 1.0),
+        };
+        let (rendering_mode, measuring_mode) =
+            rendering_and_measuring_modes(hinting_options, rasterization_options);
+
         unsafe {
             let glyph_id = glyph_id as u16;
             let advance = 0.0;
@@ -526,7 +1054,7 @@ impl Font {
             };
             let glyph_run = DWRITE_GLYPH_RUN {
                 fontFace: self.dwrite_font_face.as_ptr(),
-                fontEmSize: point_size,
+                fontEmSize: em_size,
                 glyphCount: 1,
                 glyphIndices: &glyph_id,
                 glyphAdvances: &advance,
@@ -535,32 +1063,42 @@ impl Font {
                 bidiLevel: 0,
             };
 
-            let rendering_mode = match rasterization_options {
-                RasterizationOptions::Bilevel => DWRITE_RENDERING_MODE_ALIASED,
-                RasterizationOptions::GrayscaleAa | RasterizationOptions::SubpixelAa => {
-                    DWRITE_RENDERING_MODE_NATURAL
-                }
-            };
-
             Ok(DWriteGlyphRunAnalysis::create(
                 &glyph_run,
                 1.0,
                 Some(dwrote::DWRITE_MATRIX {
-                    m11: transform.scale_x,
-                    m12: transform.skew_y,
-                    m21: transform.skew_x,
-                    m22: transform.scale_y,
+                    m11: transform.scale_x * scale,
+                    m12: transform.skew_y * scale,
+                    m21: transform.skew_x * scale,
+                    m22: transform.scale_y * scale,
                     dx: origin.x,
                     dy: origin.y,
                 }),
                 rendering_mode,
-                DWRITE_MEASURING_MODE_NATURAL,
+                measuring_mode,
                 0.0,
                 0.0,
             )?)
         }
     }
 
+    /// Returns a simulated bold version of this font, whose glyph outlines are stroked outward so
+    /// that they become `extra_weight` font units wider and taller.
+    #[inline]
+    pub fn synthetic_bold(
+        &self,
+        extra_weight: f32,
+    ) -> Result<SyntheticFont<Font>, FontLoadingError> {
+        <Self as Loader>::synthetic_bold(self, extra_weight)
+    }
+
+    /// Returns a simulated italic version of this font, whose glyph outlines are slanted to the
+    /// right by `slant_angle` degrees from the vertical.
+    #[inline]
+    pub fn synthetic_italic(&self, slant_angle: f32) -> SyntheticFont<Font> {
+        <Self as Loader>::synthetic_italic(self, slant_angle)
+    }
+
     /// Get font fallback results for the given text and locale.
     ///
     /// The `locale` argument is a language tag such as `"en-US"` or `"zh-Hans-CN"`.
@@ -607,6 +1145,10 @@ impl Font {
                 dwrite_font,
                 dwrite_font_face,
                 cached_data: Mutex::new(None),
+                color_glyph_coverage: Mutex::new(None),
+                font_data_hash: Mutex::new(None),
+                metrics: Mutex::new(None),
+                reverse_char_map: Mutex::new(None),
             };
             let fallback_font = FallbackFont {
                 font,
@@ -629,6 +1171,29 @@ impl Font {
             .get_font_table(table_tag)
             .map(|v| v.into())
     }
+
+    /// Returns the tags of all the OpenType tables in the font, in the order of its table
+    /// directory.
+    ///
+    /// DirectWrite doesn't list the tables of a font, so this reads the table directory from the
+    /// copy of the font file that `copy_font_data` makes and keeps.
+    pub fn table_tags(&self) -> Vec<u32> {
+        let font_data = match self.copy_font_data() {
+            Some(font_data) => font_data,
+            None => return vec![],
+        };
+        let font_index = FaceIndex(self.dwrite_font_face.get_index());
+        match FontTables::new(io::Cursor::new(&font_data[..]), font_index) {
+            Ok(font_tables) => font_tables.table_tags(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Returns true if and only if the font has an OpenType table with the given tag.
+    #[inline]
+    pub fn has_table(&self, table_tag: u32) -> bool {
+        <Self as Loader>::has_table(self, table_tag)
+    }
 }
 
 // There might well be a more efficient impl that doesn't fully decode the text,
@@ -655,10 +1220,33 @@ impl Clone for Font {
             dwrite_font: self.dwrite_font.clone(),
             dwrite_font_face: self.dwrite_font_face.clone(),
             cached_data: Mutex::new((*self.cached_data.lock().unwrap()).clone()),
+            color_glyph_coverage: Mutex::new((*self.color_glyph_coverage.lock().unwrap()).clone()),
+            font_data_hash: Mutex::new(*self.font_data_hash.lock().unwrap()),
+            metrics: Mutex::new(*self.metrics.lock().unwrap()),
+            reverse_char_map: Mutex::new((*self.reverse_char_map.lock().unwrap()).clone()),
         }
     }
 }
 
+// Two fonts are equal if they have the same PostScript name and either the same font data or, for
+// fonts backed by files, the same file and index within it.
+impl PartialEq for Font {
+    fn eq(&self, other: &Font) -> bool {
+        self.font_identity() == other.font_identity()
+    }
+}
+
+impl Eq for Font {}
+
+impl Hash for Font {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.font_identity().hash(state)
+    }
+}
+
 impl Debug for Font {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
         self.family_name().fmt(fmt)
@@ -669,12 +1257,15 @@ impl Loader for Font {
     type NativeFont = NativeFont;
 
     #[inline]
-    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError> {
+    fn from_bytes(
+        font_data: Arc<Vec<u8>>,
+        font_index: FaceIndex,
+    ) -> Result<Self, FontLoadingError> {
         Font::from_bytes(font_data, font_index)
     }
 
     #[inline]
-    fn from_file(file: &mut File, font_index: u32) -> Result<Font, FontLoadingError> {
+    fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Font, FontLoadingError> {
         Font::from_file(file, font_index)
     }
 
@@ -718,6 +1309,11 @@ impl Loader for Font {
         self.is_monospace()
     }
 
+    #[inline]
+    fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        self.glyph_is_color(glyph_id)
+    }
+
     #[inline]
     fn properties(&self) -> Properties {
         self.properties()
@@ -728,6 +1324,16 @@ impl Loader for Font {
         self.glyph_for_char(character)
     }
 
+    #[inline]
+    fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        self.glyph_to_char(glyph_id)
+    }
+
+    #[inline]
+    fn glyphs_for_string(&self, text: &str) -> Vec<Option<u32>> {
+        self.glyphs_for_string(text)
+    }
+
     #[inline]
     fn glyph_count(&self) -> u32 {
         self.glyph_count()
@@ -751,11 +1357,24 @@ impl Loader for Font {
         self.typographic_bounds(glyph_id)
     }
 
+    #[inline]
+    fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        self.typographic_bounds_for_glyphs(glyph_ids)
+    }
+
     #[inline]
     fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
         self.advance(glyph_id)
     }
 
+    #[inline]
+    fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        self.advances(glyph_ids)
+    }
+
     #[inline]
     fn origin(&self, origin: u32) -> Result<Point2D<f32>, GlyphLoadingError> {
         self.origin(origin)
@@ -766,6 +1385,11 @@ impl Loader for Font {
         self.metrics()
     }
 
+    #[inline]
+    fn approximate_memory_usage(&self) -> usize {
+        self.approximate_memory_usage()
+    }
+
     #[inline]
     fn supports_hinting_options(
         &self,
@@ -790,6 +1414,7 @@ impl Loader for Font {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
     ) -> Result<(), GlyphLoadingError> {
         self.rasterize_glyph(
             canvas,
@@ -799,6 +1424,7 @@ impl Loader for Font {
             origin,
             hinting_options,
             rasterization_options,
+            alpha_mode,
         )
     }
 
@@ -811,6 +1437,11 @@ impl Loader for Font {
     fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
         self.load_font_table(table_tag)
     }
+
+    #[inline]
+    fn table_tags(&self) -> Vec<u32> {
+        self.table_tags()
+    }
 }
 
 enum Event {
@@ -922,10 +1553,70 @@ impl OutlineBuilder for OutlineBuffer {
     }
 }
 
-fn style_for_dwrite_style(style: DWriteFontStyle) -> Style {
-    match style {
+// Converts the weight, stretch, and style that DirectWrite reports for a font to CSS properties.
+pub(crate) fn properties_for_dwrite_font(dwrite_font: &DWriteFont) -> Properties {
+    Properties {
+        style: style_for_dwrite_font(dwrite_font),
+        stretch: Stretch::from_uswidth_class(dwrite_font.stretch() as u16),
+        weight: Weight(dwrite_font.weight().to_u32() as f32),
+    }
+}
+
+// DirectWrite doesn't report the angle of oblique fonts, so it's read from the `post` table.
+fn style_for_dwrite_font(dwrite_font: &DWriteFont) -> Style {
+    match dwrite_font.style() {
         DWriteFontStyle::Normal => Style::Normal,
-        DWriteFontStyle::Oblique => Style::Oblique,
+        DWriteFontStyle::Oblique => {
+            let angle = dwrite_font
+                .create_font_face()
+                .get_font_table(post::POST_TABLE_TAG)
+                .and_then(|post_table| post::oblique_angle(&post_table));
+            Style::Oblique(angle.unwrap_or(Style::OBLIQUE_DEFAULT_ANGLE))
+        }
         DWriteFontStyle::Italic => Style::Italic,
     }
 }
+
+// Chooses how DirectWrite should rasterize and position glyphs. Full hinting uses the
+// GDI-compatible modes, which grid-fit in both directions. Vertically hinted ClearType uses the
+// natural mode, which antialiases horizontally only; everything else is antialiased in both
+// directions.
+fn rendering_and_measuring_modes(
+    hinting_options: HintingOptions,
+    rasterization_options: RasterizationOptions,
+) -> (DWRITE_RENDERING_MODE, DWRITE_MEASURING_MODE) {
+    let measuring_mode = match hinting_options {
+        HintingOptions::Full(_) => DWRITE_MEASURING_MODE_GDI_CLASSIC,
+        _ => DWRITE_MEASURING_MODE_NATURAL,
+    };
+    let rendering_mode = match (rasterization_options, hinting_options) {
+        (RasterizationOptions::Bilevel, _) => DWRITE_RENDERING_MODE_ALIASED,
+        (_, HintingOptions::Full(_)) => DWRITE_RENDERING_MODE_GDI_CLASSIC,
+        (RasterizationOptions::SubpixelAa, HintingOptions::Vertical(_))
+        | (RasterizationOptions::SubpixelAa, HintingOptions::VerticalSubpixel(_)) => {
+            DWRITE_RENDERING_MODE_NATURAL
+        }
+        _ => DWRITE_RENDERING_MODE_NATURAL_SYMMETRIC,
+    };
+    (rendering_mode, measuring_mode)
+}
+
+// Computes the bounds of a glyph in font units from its design metrics.
+fn design_glyph_bounds(metrics: &DWRITE_GLYPH_METRICS) -> Rect<f32> {
+    let advance_width = metrics.advanceWidth as i32;
+    let advance_height = metrics.advanceHeight as i32;
+    let left_side_bearing = metrics.leftSideBearing as i32;
+    let right_side_bearing = metrics.rightSideBearing as i32;
+    let top_side_bearing = metrics.topSideBearing as i32;
+    let bottom_side_bearing = metrics.bottomSideBearing as i32;
+    let vertical_origin_y = metrics.verticalOriginY as i32;
+
+    let y_offset = vertical_origin_y + bottom_side_bearing - advance_height;
+    let width = advance_width - (left_side_bearing + right_side_bearing);
+    let height = advance_height - (top_side_bearing + bottom_side_bearing);
+
+    Rect::new(
+        Point2D::new(left_side_bearing as f32, y_offset as f32),
+        Size2D::new(width as f32, height as f32),
+    )
+}
@@ -19,7 +19,8 @@ use freetype::freetype::{FT_Byte, FT_Done_Face, FT_Error, FT_Face, FT_FACE_FLAG_
 use freetype::freetype::{FT_Fixed, FT_Matrix, FT_UShort, FT_Vector};
 use freetype::freetype::{FT_Get_Char_Index, FT_Get_Name_Index, FT_Get_Postscript_Name};
 use freetype::freetype::{
-    FT_Get_Sfnt_Table, FT_Init_FreeType, FT_Load_Sfnt_Table, FT_LOAD_DEFAULT, FT_LOAD_MONOCHROME,
+    FT_Get_Sfnt_Table, FT_Init_FreeType, FT_Load_Sfnt_Table, FT_Sfnt_Table_Info, FT_LOAD_DEFAULT,
+    FT_LOAD_FORCE_AUTOHINT, FT_LOAD_MONOCHROME,
 };
 use freetype::freetype::{FT_LcdFilter, FT_Library_SetLcdFilter};
 use freetype::freetype::{FT_Library, FT_Load_Glyph, FT_Long, FT_LOAD_NO_HINTING, FT_LOAD_RENDER};
@@ -31,28 +32,48 @@ use lyon_path::builder::PathBuilder;
 use std::f32;
 use std::ffi::{CStr, CString};
 use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
 use std::io::{Seek, SeekFrom};
-use std::iter;
 use std::mem;
+use std::ops::Deref;
 use std::os::raw::{c_char, c_void};
 use std::ptr;
 use std::slice;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use tokio::io::{self as async_io, AsyncRead};
 
-use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::canvas::{
+    AlphaMode, Canvas, Format, RasterizationOptions, SubpixelAaOptions, SubpixelOrder,
+};
+use crate::cmap::ReverseCharMap;
+use crate::color::{ColorGlyphCoverage, ColorLayer};
+use crate::embedding::EmbeddingMode;
 use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
+use crate::font::FaceIndex;
+use crate::font_info::FontInfo;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
-use crate::loader::{FallbackResult, FontTransform, Loader};
-use crate::metrics::Metrics;
+use crate::identity::{self, FontIdentity};
+use crate::loader::{FallbackResult, FontBytesRef, FontTransform, Loader};
+use crate::metrics::{LineMetrics, Metrics};
+use crate::post;
 use crate::properties::{Properties, Stretch, Style, Weight};
+use crate::synthetic::SyntheticFont;
+use crate::unicode_ranges::UnicodeRanges;
 use crate::utils;
 
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
 #[cfg(not(target_arch = "wasm32"))]
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+// A rough estimate of the memory that FreeType allocates for a face, including its size object,
+// glyph slot, and the tables that it parses up front.
+const FREETYPE_FACE_MEMORY_USAGE: usize = 16 * 1024;
 
 const PS_DICT_FULL_NAME: u32 = 38;
 const TT_NAME_ID_FULL_NAME: u16 = 4;
@@ -66,9 +87,11 @@ const FT_RENDER_MODE_NORMAL: u32 = 0;
 const FT_RENDER_MODE_LIGHT: u32 = 1;
 const FT_RENDER_MODE_MONO: u32 = 2;
 const FT_RENDER_MODE_LCD: u32 = 3;
+const FT_RENDER_MODE_LCD_V: u32 = 4;
 
 const FT_LOAD_TARGET_LIGHT: u32 = (FT_RENDER_MODE_LIGHT & 15) << 16;
 const FT_LOAD_TARGET_LCD: u32 = (FT_RENDER_MODE_LCD & 15) << 16;
+const FT_LOAD_TARGET_LCD_V: u32 = (FT_RENDER_MODE_LCD_V & 15) << 16;
 const FT_LOAD_TARGET_MONO: u32 = (FT_RENDER_MODE_MONO & 15) << 16;
 const FT_LOAD_TARGET_NORMAL: u32 = (FT_RENDER_MODE_NORMAL & 15) << 16;
 
@@ -121,22 +144,37 @@ struct BDF_PropertyRec {
 /// loader by default.
 pub struct Font {
     freetype_face: FT_Face,
-    font_data: Arc<Vec<u8>>,
+    font_data: FontData,
+    color_glyph_coverage: Mutex<Option<Arc<ColorGlyphCoverage>>>,
+    font_data_hash: Mutex<Option<u64>>,
+    metrics: Mutex<Option<Metrics>>,
+    reverse_char_map: Mutex<Option<Arc<ReverseCharMap>>>,
 }
 
 impl Font {
     /// Loads a font from raw font data (the contents of a `.ttf`/`.otf`/etc. file).
     ///
     /// If the data represents a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index
-    /// of the font to load from it. If the data represents a single font, pass 0 for `font_index`.
-    pub fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Font, FontLoadingError> {
+    /// of the font to load from it. If the data represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    pub fn from_bytes(
+        font_data: Arc<Vec<u8>>,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError> {
+        Font::from_font_data(FontData::Memory(font_data), font_index)
+    }
+
+    fn from_font_data(
+        font_data: FontData,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError> {
         FREETYPE_LIBRARY.with(|freetype_library| unsafe {
             let mut freetype_face = ptr::null_mut();
             if FT_New_Memory_Face(
                 *freetype_library,
                 (*font_data).as_ptr(),
                 font_data.len() as FT_Long,
-                font_index as FT_Long,
+                font_index.0 as FT_Long,
                 &mut freetype_face,
             ) != 0
             {
@@ -148,6 +186,10 @@ impl Font {
             Ok(Font {
                 freetype_face,
                 font_data,
+                color_glyph_coverage: Mutex::new(None),
+                font_data_hash: Mutex::new(None),
+                metrics: Mutex::new(None),
+                reverse_char_map: Mutex::new(None),
             })
         })
     }
@@ -155,9 +197,10 @@ impl Font {
     /// Loads a font from a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn from_file(file: &mut File, font_index: u32) -> Result<Font, FontLoadingError> {
+    pub fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Font, FontLoadingError> {
         file.seek(SeekFrom::Start(0))?;
         let font_data = Arc::new(utils::slurp_file(file).map_err(FontLoadingError::Io)?);
         Font::from_bytes(font_data, font_index)
@@ -166,38 +209,80 @@ impl Font {
     /// Loads a font from the path to a `.ttf`/`.otf`/etc. file.
     ///
     /// If the file is a collection (`.ttc`/`.otc`/etc.), `font_index` specifies the index of the
-    /// font to load from it. If the file represents a single font, pass 0 for `font_index`.
-    #[inline]
+    /// font to load from it. If the file represents a single font, pass
+    /// `FaceIndex::FIRST` for `font_index`.
+    ///
+    /// The file is memory-mapped instead of being read into memory, so it must not be modified
+    /// while the font or any clone of it is alive.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn from_path<P>(path: P, font_index: u32) -> Result<Font, FontLoadingError>
+    pub fn from_path<P>(path: P, font_index: FaceIndex) -> Result<Font, FontLoadingError>
     where
         P: AsRef<Path>,
     {
-        // TODO(pcwalton): Perhaps use the native FreeType support for opening paths?
-        <Font as Loader>::from_path(path, font_index)
+        let mut file = File::open(path.as_ref())?;
+        // Some files, such as empty ones, can't be mapped. Leave reporting any errors with those
+        // to the buffered path.
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => {
+                let path = identity::canonical_path(path.as_ref());
+                Font::from_font_data(FontData::File(Arc::new(mmap), path), font_index)
+            }
+            Err(_) => Font::from_file(&mut file, font_index),
+        }
+    }
+
+    /// Loads a font by reading the raw font data (the contents of a `.ttf`/`.otf`/etc. file) from
+    /// an asynchronous reader.
+    ///
+    /// The whole stream is read into memory before the font is loaded, so the executor isn't
+    /// blocked on the I/O. If the data represents a collection (`.ttc`/`.otc`/etc.),
+    /// `font_index` specifies the index of the font to load from it. If the data represents a
+    /// single font, pass `FaceIndex::FIRST` for `font_index`.
+    #[cfg(feature = "async")]
+    pub async fn from_async_read<R>(
+        mut reader: R,
+        font_index: FaceIndex,
+    ) -> Result<Font, FontLoadingError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut font_data = vec![];
+        // Importing `AsyncReadExt` would make its methods clash with those of `ReadBytesExt`.
+        async_io::AsyncReadExt::read_to_end(&mut reader, &mut font_data).await?;
+        Font::from_bytes(Arc::new(font_data), font_index)
     }
 
     /// Creates a font from a native API handle.
+    ///
+    /// The font data is copied out of the face, so this function does not take ownership of
+    /// `freetype_face`. The caller remains responsible for freeing it with `FT_Done_Face`.
     pub unsafe fn from_native_font(freetype_face: NativeFont) -> Font {
         // We make an in-memory copy of the underlying font data. This is because the native font
         // does not necessarily hold a strong reference to the memory backing it.
-        const CHUNK_SIZE: usize = 4096;
-        let mut font_data = vec![];
-        loop {
-            font_data.extend(iter::repeat(0).take(CHUNK_SIZE));
-            let freetype_stream = (*freetype_face).stream;
-            let n_read = ((*freetype_stream).read.unwrap())(
-                freetype_stream,
-                font_data.len() as FT_ULong,
-                font_data.as_mut_ptr(),
-                CHUNK_SIZE as FT_ULong,
-            );
-            if n_read < CHUNK_SIZE as FT_ULong {
-                break;
+        let freetype_stream = (*freetype_face).stream;
+        let font_data_len = (*freetype_stream).size as usize;
+        let mut font_data = vec![0; font_data_len];
+        match (*freetype_stream).read {
+            // Memory-based streams have no read function; their contents are simply at `base`.
+            None => font_data.copy_from_slice(slice::from_raw_parts(
+                (*freetype_stream).base,
+                font_data_len,
+            )),
+            Some(read) => {
+                read(
+                    freetype_stream,
+                    0,
+                    font_data.as_mut_ptr(),
+                    font_data_len as FT_ULong,
+                );
             }
         }
 
-        Font::from_bytes(Arc::new(font_data), (*freetype_face).face_index as u32).unwrap()
+        Font::from_bytes(
+            Arc::new(font_data),
+            FaceIndex((*freetype_face).face_index as u32),
+        )
+        .unwrap()
     }
 
     /// Loads the font pointed to by a handle.
@@ -269,10 +354,24 @@ impl Font {
         <Self as Loader>::analyze_path(path)
     }
 
+    /// Reads the names and properties of a font from raw font data without loading it.
+    #[inline]
+    pub fn peek_info(
+        font_data: &[u8],
+        font_index: FaceIndex,
+    ) -> Result<FontInfo, FontLoadingError> {
+        <Self as Loader>::peek_info(font_data, font_index)
+    }
+
     /// Returns the wrapped native font handle.
     ///
     /// This function increments the reference count of the FreeType face before returning it.
     /// Therefore, it is the caller's responsibility to free it with `FT_Done_Face`.
+    ///
+    /// FreeType faces are not internally synchronized, and the returned face shares all of its
+    /// state (character size, transform, glyph slot) with this font. It belongs to the FreeType
+    /// library instance of the thread that loaded this font, so it must only be used on that
+    /// thread, and it must not be used while a method of this font is running.
     pub fn native_font(&self) -> NativeFont {
         unsafe {
             assert_eq!(FT_Reference_Face(self.freetype_face), 0);
@@ -334,35 +433,170 @@ impl Font {
         }
     }
 
+    // Returns the style name of the font, such as "Bold Italic".
+    fn style_name(&self) -> String {
+        unsafe {
+            let ptr = (*self.freetype_face).style_name;
+            if ptr.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(ptr).to_string_lossy().into_owned()
+            }
+        }
+    }
+
     /// Returns true if and only if the font is monospace (fixed-width).
     pub fn is_monospace(&self) -> bool {
         unsafe { (*self.freetype_face).face_flags & (FT_FACE_FLAG_FIXED_WIDTH as FT_Long) != 0 }
     }
 
+    /// Returns true if and only if the font contains color glyphs (e.g. emoji).
+    #[inline]
+    pub fn is_color_font(&self) -> bool {
+        <Self as Loader>::is_color_font(self)
+    }
+
+    /// Returns true if and only if the glyph with the given ID has a color representation in any
+    /// of the `COLR`, `sbix`, `CBDT`, or `SVG ` tables.
+    ///
+    /// The color tables are parsed on the first call, and the result is cached.
+    pub fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        let mut color_glyph_coverage = self.color_glyph_coverage.lock().unwrap();
+        if color_glyph_coverage.is_none() {
+            *color_glyph_coverage = Some(Arc::new(ColorGlyphCoverage::new(self)));
+        }
+        color_glyph_coverage.as_ref().unwrap().contains(glyph_id)
+    }
+
+    /// Returns the layers that make up the given glyph in the font's `COLR` (version 0) table, from
+    /// bottom to top.
+    #[inline]
+    pub fn color_glyph_layers(&self, glyph_id: u32) -> Option<Vec<ColorLayer>> {
+        <Self as Loader>::color_glyph_layers(self, glyph_id)
+    }
+
+    /// Returns the colors of the palette with the given index in the font's `CPAL` table, as
+    /// unpremultiplied RGBA.
+    #[inline]
+    pub fn color_palette(&self, palette_index: u16) -> Option<Vec<[u8; 4]>> {
+        <Self as Loader>::color_palette(self, palette_index)
+    }
+
+    /// Rasterizes a color glyph to a new RGBA canvas that tightly fits it, using the colors of the
+    /// given palette with the given entries replaced, with premultiplied or straight alpha.
+    #[inline]
+    pub fn rasterize_color_glyph(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        palette_index: u16,
+        palette_overrides: &[(u16, [u8; 4])],
+        alpha_mode: AlphaMode,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        <Self as Loader>::rasterize_color_glyph(
+            self,
+            glyph_id,
+            point_size,
+            transform,
+            palette_index,
+            palette_overrides,
+            alpha_mode,
+        )
+    }
+
+    /// Draws the bitmap that the font embeds for the given glyph, in the strike closest to the
+    /// given size, into a new A8 canvas.
+    #[inline]
+    pub fn glyph_bitmap(&self, glyph_id: u32, point_size: f32) -> Option<(Canvas, Point2D<i32>)> {
+        <Self as Loader>::glyph_bitmap(self, glyph_id, point_size)
+    }
+
+    /// Returns the tags of the OpenType scripts that the font supports, such as `*b"latn"` or
+    /// `*b"arab"`.
+    #[inline]
+    pub fn opentype_script_tags(&self) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_script_tags(self)
+    }
+
+    /// Returns the tags of the OpenType language systems that the font defines for the given
+    /// script, such as `*b"TRK "`.
+    #[inline]
+    pub fn opentype_language_tags(&self, script: [u8; 4]) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_language_tags(self, script)
+    }
+
+    /// Returns the tags of the OpenType features that the font defines in its `GSUB` and `GPOS`
+    /// tables, such as `*b"liga"` or `*b"kern"`.
+    #[inline]
+    pub fn opentype_feature_tags(&self) -> Vec<[u8; 4]> {
+        <Self as Loader>::opentype_feature_tags(self)
+    }
+
+    /// Returns true if and only if the font defines the OpenType feature with the given tag in its
+    /// `GSUB` or `GPOS` table.
+    #[inline]
+    pub fn has_opentype_feature(&self, feature: [u8; 4]) -> bool {
+        <Self as Loader>::has_opentype_feature(self, feature)
+    }
+
+    /// Returns true if and only if the font's `GSUB` or `GPOS` table enables the OpenType feature
+    /// with the given tag for the given script and language system.
+    #[inline]
+    pub fn supports_opentype_feature(
+        &self,
+        feature: [u8; 4],
+        script: [u8; 4],
+        language: Option<[u8; 4]>,
+    ) -> bool {
+        <Self as Loader>::supports_opentype_feature(self, feature, script, language)
+    }
+
+    /// Returns the glyph that the font's `liga` (standard ligatures) feature substitutes for
+    /// exactly the given sequence of glyphs, if any.
+    #[inline]
+    pub fn ligature_for(&self, glyphs: &[u32]) -> Option<u32> {
+        <Self as Loader>::ligature_for(self, glyphs)
+    }
+
+    /// Returns the first ligature that the font's `liga` feature forms from the glyphs at the
+    /// start of the given sequence, along with the number of glyphs that it replaces.
+    #[inline]
+    pub fn lookup_ligature(&self, glyph_ids: &[u32]) -> Option<(u32, usize)> {
+        <Self as Loader>::lookup_ligature(self, glyph_ids)
+    }
+
     /// Returns the values of various font properties, corresponding to those defined in CSS.
     pub fn properties(&self) -> Properties {
         unsafe {
             let os2_table = self.get_os2_table();
+            // Fonts without an `OS/2` table, such as Type 1 fonts, may only describe their weight
+            // and width in their style names.
+            let style_name_properties = match os2_table {
+                Some(_) => Properties::default(),
+                None => Properties::from_style_name(&self.style_name()),
+            };
             let style = match os2_table {
                 Some(os2_table) if ((*os2_table).fsSelection & OS2_FS_SELECTION_OBLIQUE) != 0 => {
-                    Style::Oblique
+                    let angle = self
+                        .load_font_table(post::POST_TABLE_TAG)
+                        .and_then(|post_table| post::oblique_angle(&post_table));
+                    Style::Oblique(angle.unwrap_or(Style::OBLIQUE_DEFAULT_ANGLE))
                 }
                 _ if ((*self.freetype_face).style_flags & (FT_STYLE_FLAG_ITALIC) as FT_Long)
                     != 0 =>
                 {
                     Style::Italic
                 }
-                _ => Style::Normal,
+                _ => style_name_properties.style,
             };
             let stretch = match os2_table {
-                Some(os2_table) if (*os2_table).usWidthClass > 0 => {
-                    Stretch(Stretch::MAPPING[((*os2_table).usWidthClass as usize) - 1])
-                }
-                _ => Stretch::NORMAL,
+                Some(os2_table) => Stretch::from_uswidth_class((*os2_table).usWidthClass),
+                None => style_name_properties.stretch,
             };
             let weight = match os2_table {
-                None => Weight::NORMAL,
-                Some(os2_table) => Weight((*os2_table).usWeightClass as u32 as f32),
+                None => style_name_properties.weight,
+                Some(os2_table) => Weight::from((*os2_table).usWeightClass),
             };
             Properties {
                 style,
@@ -372,6 +606,33 @@ impl Font {
         }
     }
 
+    /// Returns the raw embedding permission flags (`fsType`) from the OpenType `OS/2` table, if
+    /// the font has one.
+    #[inline]
+    pub fn embedding_restrictions(&self) -> Option<u16> {
+        <Self as Loader>::embedding_restrictions(self)
+    }
+
+    /// Returns how the font's license permits it to be embedded in documents.
+    #[inline]
+    pub fn embedding_mode(&self) -> EmbeddingMode {
+        <Self as Loader>::embedding_mode(self)
+    }
+
+    /// Returns the 10-byte PANOSE classification from the OpenType `OS/2` table, if the font has
+    /// one.
+    #[inline]
+    pub fn panose(&self) -> Option<[u8; 10]> {
+        <Self as Loader>::panose(self)
+    }
+
+    /// Returns the Unicode blocks that the font claims to cover, from the `ulUnicodeRange` fields
+    /// of the OpenType `OS/2` table.
+    #[inline]
+    pub fn unicode_ranges(&self) -> UnicodeRanges {
+        <Self as Loader>::unicode_ranges(self)
+    }
+
     /// Returns the usual glyph ID for a Unicode character.
     ///
     /// Be careful with this function; typographically correct character-to-glyph mapping must be
@@ -388,6 +649,24 @@ impl Font {
         }
     }
 
+    /// Returns the usual glyph IDs for each Unicode character in a string, in order.
+    #[inline]
+    pub fn glyphs_for_string(&self, text: &str) -> Vec<Option<u32>> {
+        <Self as Loader>::glyphs_for_string(self, text)
+    }
+
+    /// Returns all Unicode characters that the font's `cmap` table maps to the given glyph, in
+    /// code point order.
+    ///
+    /// The reverse mapping is built on the first call, and the result is cached.
+    pub fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        let mut reverse_char_map = self.reverse_char_map.lock().unwrap();
+        if reverse_char_map.is_none() {
+            *reverse_char_map = Some(Arc::new(ReverseCharMap::new(self)));
+        }
+        reverse_char_map.as_ref().unwrap().chars_for_glyph(glyph_id)
+    }
+
     /// Returns the glyph ID for the specified glyph name.
     #[inline]
     pub fn glyph_by_name(&self, name: &str) -> Option<u32> {
@@ -402,6 +681,14 @@ impl Font {
         None
     }
 
+    /// Returns the PostScript name of the glyph with the given ID.
+    ///
+    /// Names are read from the `post` table; returns `None` if it carries no glyph names.
+    #[inline]
+    pub fn glyph_name(&self, glyph_id: u32) -> Option<String> {
+        <Self as Loader>::glyph_name(self, glyph_id)
+    }
+
     /// Returns the number of glyphs in the font.
     ///
     /// Glyph IDs range from 0 inclusive to this value exclusive.
@@ -410,6 +697,25 @@ impl Font {
         unsafe { (*self.freetype_face).num_glyphs as u32 }
     }
 
+    /// Returns true if and only if the font has a glyph with the given ID.
+    #[inline]
+    pub fn glyph_exists(&self, glyph_id: u32) -> bool {
+        <Self as Loader>::glyph_exists(self, glyph_id)
+    }
+
+    /// Returns the ID of the glyph to draw in place of characters that the font has no glyph for.
+    #[inline]
+    pub fn default_glyph(&self) -> u32 {
+        <Self as Loader>::default_glyph(self)
+    }
+
+    /// Returns the number of full horizontal metrics records (`numberOfHMetrics`) in the `hmtx`
+    /// table, as given by the `hhea` table.
+    #[inline]
+    pub fn number_of_h_metrics(&self) -> Option<u16> {
+        <Self as Loader>::number_of_h_metrics(self)
+    }
+
     /// Sends the vector path for a glyph to a path builder.
     ///
     /// If `hinting_mode` is not None, this function performs grid-fitting as requested before
@@ -608,6 +914,27 @@ impl Font {
         }
     }
 
+    /// Returns the boundaries of several glyphs, in order, in font units. Glyphs that aren't in
+    /// the font get zero bounds.
+    #[inline]
+    pub fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        <Self as Loader>::typographic_bounds_for_glyphs(self, glyph_ids)
+    }
+
+    /// Returns the boundaries of a glyph in font units, computed from the points and control
+    /// points of its outline. The origin of the coordinate space is at the bottom left.
+    #[inline]
+    pub fn outline_bounding_box(
+        &self,
+        glyph_id: u32,
+        hinting_options: HintingOptions,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        <Self as Loader>::outline_bounding_box(self, glyph_id, hinting_options)
+    }
+
     /// Returns the distance from the origin of the glyph with the given ID to the next, in font
     /// units.
     pub fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
@@ -629,6 +956,71 @@ impl Font {
         }
     }
 
+    /// Returns the advances of several glyphs, in order, in font units.
+    #[inline]
+    pub fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        <Self as Loader>::advances(self, glyph_ids)
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in pixels
+    /// at the given point size.
+    #[inline]
+    pub fn advance_pixels(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::advance_pixels(self, glyph_id, point_size)
+    }
+
+    /// Returns the ascent, descent, line gap, and line height of the font, in pixels at the given
+    /// point size.
+    #[inline]
+    pub fn line_metrics(&self, point_size: f32) -> LineMetrics {
+        <Self as Loader>::line_metrics(self, point_size)
+    }
+
+    /// Returns the bounds of a run of glyphs laid out left to right, in pixels at the given point
+    /// size. The origin of the coordinate space is at the start of the baseline, at the bottom
+    /// left.
+    #[inline]
+    pub fn run_bounds(
+        &self,
+        glyphs: &[u32],
+        point_size: f32,
+    ) -> Result<Rect<f32>, GlyphLoadingError> {
+        <Self as Loader>::run_bounds(self, glyphs, point_size)
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next in vertical
+    /// writing modes, in font units.
+    #[inline]
+    pub fn vertical_advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::vertical_advance(self, glyph_id)
+    }
+
+    /// Returns the kerning that the font's `GPOS` table applies between the given pair of
+    /// glyphs, in font units.
+    #[inline]
+    pub fn gpos_kerning_for_pair(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::gpos_kerning_for_pair(self, left_glyph, right_glyph)
+    }
+
+    /// Returns the kerning between the given pair of glyphs, in font units, preferring the
+    /// `GPOS` table and falling back to the legacy `kern` table.
+    #[inline]
+    pub fn kerning_for_pair_any(
+        &self,
+        left_glyph: u32,
+        right_glyph: u32,
+    ) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        <Self as Loader>::kerning_for_pair_any(self, left_glyph, right_glyph)
+    }
+
     /// Returns the amount that the given glyph should be displaced from the origin.
     ///
     /// FIXME(pcwalton): This always returns zero on FreeType.
@@ -638,7 +1030,14 @@ impl Font {
     }
 
     /// Retrieves various metrics that apply to the entire font.
+    ///
+    /// The metrics are computed on the first call and cached, since they never change.
     pub fn metrics(&self) -> Metrics {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics.get_or_insert_with(|| self.compute_metrics())
+    }
+
+    fn compute_metrics(&self) -> Metrics {
         let os2_table = self.get_os2_table();
         unsafe {
             let ascender = (*self.freetype_face).ascender;
@@ -678,10 +1077,41 @@ impl Font {
             (HintingOptions::None, _)
             | (HintingOptions::Vertical(_), true)
             | (HintingOptions::VerticalSubpixel(_), true)
-            | (HintingOptions::Full(_), true) => true,
+            | (HintingOptions::Full(_), true)
+            | (HintingOptions::AutoHint(_), true) => true,
             (HintingOptions::Vertical(_), false)
             | (HintingOptions::VerticalSubpixel(_), false)
-            | (HintingOptions::Full(_), false) => false,
+            | (HintingOptions::Full(_), false)
+            | (HintingOptions::AutoHint(_), false) => false,
+        }
+    }
+
+    /// Returns a number that identifies this font: fonts that compare equal have the same identity,
+    /// and different fonts almost certainly don't.
+    ///
+    /// This is an identity of the font's contents, not of this object. It combines the PostScript
+    /// name with a hash of the font data or, for fonts whose data is left in a file, with the
+    /// canonical path of the file and the index of the font within it. It stays the same for the
+    /// lifetime of the process, but may differ between versions of this crate.
+    #[inline]
+    pub fn identity(&self) -> u64 {
+        self.font_identity().to_u64()
+    }
+
+    fn font_identity(&self) -> FontIdentity {
+        let postscript_name = self.postscript_name();
+        match self.font_data {
+            FontData::Memory(ref font_data) => FontIdentity::Data {
+                postscript_name,
+                data_hash: identity::font_data_hash(&self.font_data_hash, font_data),
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            FontData::File(_, ref path) => FontIdentity::File {
+                postscript_name,
+                path: path.clone(),
+                // The upper bits hold the index of the named instance of a variable font.
+                font_index: FaceIndex(unsafe { (*self.freetype_face).face_index as u32 & 0xffff }),
+            },
         }
     }
 
@@ -780,7 +1210,8 @@ impl Font {
     /// options. For example, if bilevel (black and white) rendering is requested to an RGBA
     /// surface, this function will automatically convert the 1-bit raster image to the 32-bit
     /// format of the canvas. Note that this may result in a performance penalty, depending on the
-    /// loader.
+    /// loader. The pixels that the glyph covers on RGBA canvases get premultiplied or straight
+    /// alpha as `alpha_mode` asks.
     ///
     /// If `hinting_options` is not None, the requested grid fitting is performed.
     pub fn rasterize_glyph(
@@ -792,6 +1223,72 @@ impl Font {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
+    ) -> Result<(), GlyphLoadingError> {
+        let load_flags = self.hinting_and_rasterization_options_to_load_flags(
+            hinting_options,
+            rasterization_options,
+        );
+        let render_options = RenderOptions {
+            load_flags,
+            subpixel_order: SubpixelOrder::Rgb,
+            alpha_mode,
+        };
+        self.render_glyph(
+            canvas,
+            glyph_id,
+            point_size,
+            transform,
+            origin,
+            render_options,
+        )
+    }
+
+    /// Rasterizes a glyph to a canvas with subpixel antialiasing for an LCD screen whose color
+    /// subpixels are in the given order.
+    ///
+    /// FreeType renders the vertical orders natively, filtering the subpixel rows with the
+    /// default LCD filter, just as it filters the subpixel columns of the horizontal orders.
+    pub fn rasterize_glyph_with_subpixel_order(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        options: SubpixelAaOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        let mut load_flags = self.hinting_and_rasterization_options_to_load_flags(
+            options.hinting_options,
+            RasterizationOptions::SubpixelAa,
+        );
+        if options.subpixel_order.is_vertical() {
+            load_flags = (load_flags & !FT_LOAD_TARGET_LCD) | FT_LOAD_TARGET_LCD_V;
+        }
+        let render_options = RenderOptions {
+            load_flags,
+            subpixel_order: options.subpixel_order,
+            alpha_mode: AlphaMode::Premultiplied,
+        };
+        self.render_glyph(
+            canvas,
+            glyph_id,
+            point_size,
+            transform,
+            origin,
+            render_options,
+        )
+    }
+
+    // Renders a glyph with the load target of the given options and draws it to the canvas.
+    fn render_glyph(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        render_options: RenderOptions,
     ) -> Result<(), GlyphLoadingError> {
         // TODO(pcwalton): This is woefully incomplete. See WebRender's code for a more complete
         // implementation.
@@ -819,11 +1316,7 @@ impl Font {
                 0
             );
 
-            let mut load_flags = FT_LOAD_DEFAULT | FT_LOAD_RENDER;
-            load_flags |= self.hinting_and_rasterization_options_to_load_flags(
-                hinting_options,
-                rasterization_options,
-            );
+            let load_flags = FT_LOAD_DEFAULT | FT_LOAD_RENDER | render_options.load_flags;
             if FT_Load_Glyph(self.freetype_face, glyph_id, load_flags as i32) != 0 {
                 return Err(GlyphLoadingError::NoSuchGlyph);
             }
@@ -844,25 +1337,42 @@ impl Font {
                 -(*(*self.freetype_face).glyph).bitmap_top,
             );
 
+            let mut dst_size = bitmap_size;
             // FIXME(pcwalton): This function should return a Result instead.
             match (*bitmap).pixel_mode {
                 FT_PIXEL_MODE_GRAY => {
                     canvas.blit_from(dst_point, buffer, &bitmap_size, bitmap_stride, Format::A8);
                 }
                 FT_PIXEL_MODE_LCD | FT_PIXEL_MODE_LCD_V => {
-                    canvas.blit_from(
-                        dst_point,
+                    // LCD bitmaps have three subpixel columns or rows per pixel.
+                    let pixel_size = if (*bitmap).pixel_mode == FT_PIXEL_MODE_LCD {
+                        Size2D::new(bitmap_width / 3, bitmap_height)
+                    } else {
+                        Size2D::new(bitmap_width, bitmap_height / 3)
+                    };
+                    let rgb_canvas = Canvas::from_subpixel_coverage(
+                        &pixel_size,
                         buffer,
-                        &bitmap_size,
                         bitmap_stride,
+                        render_options.subpixel_order,
+                    )?;
+                    canvas.blit_from(
+                        dst_point,
+                        &rgb_canvas.pixels,
+                        &rgb_canvas.size,
+                        rgb_canvas.stride,
                         Format::Rgb24,
                     );
+                    dst_size = pixel_size;
                 }
                 FT_PIXEL_MODE_MONO => {
                     canvas.blit_from_bitmap_1bpp(dst_point, buffer, &bitmap_size, bitmap_stride);
                 }
                 _ => panic!("Unexpected FreeType pixel mode!"),
             }
+            if render_options.alpha_mode == AlphaMode::Straight {
+                canvas.unpremultiply_within(&Rect::new(dst_point, dst_size.to_i32()));
+            }
 
             FT_Set_Transform(self.freetype_face, ptr::null_mut(), ptr::null_mut());
             reset_freetype_face_char_size(self.freetype_face);
@@ -870,6 +1380,35 @@ impl Font {
         }
     }
 
+    /// Rasterizes a glyph at a quantized horizontal subpixel offset to a new canvas that tightly
+    /// fits it.
+    ///
+    /// The glyph origin is placed at `subpixel_x / 2^subpixel_bits` pixels to the right of an
+    /// integral pixel position. `subpixel_bits` should be at most 8, and `subpixel_x` less than
+    /// `2^subpixel_bits`; larger values are clamped to those limits. Rasterizing each glyph once
+    /// per bucket allows callers to cache subpixel-positioned glyphs.
+    ///
+    /// Returns the canvas along with the offset, in whole pixels, from the integral pen position
+    /// to the top left corner of the canvas.
+    #[inline]
+    pub fn rasterize_glyph_subpixel(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        subpixel_x: u8,
+        subpixel_bits: u8,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        <Self as Loader>::rasterize_glyph_subpixel(
+            self,
+            glyph_id,
+            point_size,
+            subpixel_x,
+            subpixel_bits,
+            rasterization_options,
+        )
+    }
+
     fn hinting_and_rasterization_options_to_load_flags(
         &self,
         hinting: HintingOptions,
@@ -881,30 +1420,105 @@ impl Font {
             }
             (HintingOptions::None, _) => FT_LOAD_TARGET_NORMAL | FT_LOAD_NO_HINTING,
             (HintingOptions::Vertical(_), RasterizationOptions::Bilevel)
-            | (HintingOptions::Full(_), RasterizationOptions::Bilevel) => FT_LOAD_TARGET_MONO,
+            | (HintingOptions::Full(_), RasterizationOptions::Bilevel)
+            | (HintingOptions::AutoHint(_), RasterizationOptions::Bilevel) => FT_LOAD_TARGET_MONO,
             (HintingOptions::Vertical(_), _) => FT_LOAD_TARGET_LIGHT,
-            (HintingOptions::Full(_), _) => FT_LOAD_TARGET_NORMAL,
+            (HintingOptions::Full(_), _) | (HintingOptions::AutoHint(_), _) => {
+                FT_LOAD_TARGET_NORMAL
+            }
         };
         if rasterization == RasterizationOptions::Bilevel {
             options |= FT_LOAD_MONOCHROME
         }
+        if let HintingOptions::AutoHint(_) = hinting {
+            options |= FT_LOAD_FORCE_AUTOHINT
+        }
         options
     }
 
     /// Returns a handle to this font, if possible.
     ///
-    /// This is useful if you want to open the font with a different loader.
-    #[inline]
+    /// This is useful if you want to open the font with a different loader. If the font was
+    /// loaded from a path, the handle refers to that path instead of to a copy of the file.
     pub fn handle(&self) -> Option<Handle> {
-        <Self as Loader>::handle(self)
+        let font_index = unsafe { FaceIndex((*self.freetype_face).face_index as u32) };
+        match self.font_data {
+            FontData::Memory(ref font_data) => {
+                Some(Handle::from_memory((*font_data).clone(), font_index))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            FontData::File(_, ref path) => Some(Handle::from_path(path.clone(), font_index)),
+        }
     }
 
     /// Attempts to return the raw font data (contents of the font file).
     ///
     /// If this font is a member of a collection, this function returns the data for the entire
-    /// collection.
+    /// collection. If the font was loaded from a path, the file is copied into memory.
     pub fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
-        Some(self.font_data.clone())
+        match self.font_data {
+            FontData::Memory(ref font_data) => Some((*font_data).clone()),
+            #[cfg(not(target_arch = "wasm32"))]
+            FontData::File(ref mmap, _) => Some(Arc::new(mmap.to_vec())),
+        }
+    }
+
+    /// Attempts to return the raw font data (contents of the font file) without copying it.
+    ///
+    /// If the font was loaded from a path, this borrows the mapped file.
+    pub fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        match self.font_data {
+            FontData::Memory(ref font_data) => Some(FontBytesRef::Owned((*font_data).clone())),
+            #[cfg(not(target_arch = "wasm32"))]
+            FontData::File(ref mmap, _) => Some(FontBytesRef::Slice(&mmap[..])),
+        }
+    }
+
+    /// Returns a rough estimate of the number of bytes of memory that this font uses.
+    ///
+    /// Font data loaded into memory is counted in full, even if it is shared with other fonts.
+    /// Font data mapped from a file isn't counted, since the OS can evict those pages whenever it
+    /// needs to.
+    pub fn approximate_memory_usage(&self) -> usize {
+        let font_data_size = match self.font_data {
+            FontData::Memory(ref font_data) => font_data.capacity(),
+            #[cfg(not(target_arch = "wasm32"))]
+            FontData::File(..) => 0,
+        };
+        let color_glyph_coverage_size = self
+            .color_glyph_coverage
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |color_glyph_coverage| color_glyph_coverage.heap_size());
+        let reverse_char_map_size = self
+            .reverse_char_map
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |reverse_char_map| reverse_char_map.heap_size());
+        mem::size_of::<Font>()
+            + FREETYPE_FACE_MEMORY_USAGE
+            + font_data_size
+            + color_glyph_coverage_size
+            + reverse_char_map_size
+    }
+
+    /// Returns a simulated bold version of this font, whose glyph outlines are stroked outward so
+    /// that they become `extra_weight` font units wider and taller.
+    #[inline]
+    pub fn synthetic_bold(
+        &self,
+        extra_weight: f32,
+    ) -> Result<SyntheticFont<Font>, FontLoadingError> {
+        <Self as Loader>::synthetic_bold(self, extra_weight)
+    }
+
+    /// Returns a simulated italic version of this font, whose glyph outlines are slanted to the
+    /// right by `slant_angle` degrees from the vertical.
+    #[inline]
+    pub fn synthetic_italic(&self, slant_angle: f32) -> SyntheticFont<Font> {
+        <Self as Loader>::synthetic_italic(self, slant_angle)
     }
 
     /// Get font fallback results for the given text and locale.
@@ -953,6 +1567,44 @@ impl Font {
             Some(buf)
         }
     }
+
+    /// Returns the tags of all the OpenType tables in the font, in the order of its table
+    /// directory.
+    ///
+    /// Returns an empty list if the font isn't a TrueType/OpenType font.
+    pub fn table_tags(&self) -> Vec<u32> {
+        unsafe {
+            // With a null tag, FreeType returns the number of tables in the length.
+            let mut table_count = 0;
+            if FT_Sfnt_Table_Info(self.freetype_face, 0, ptr::null_mut(), &mut table_count) != 0 {
+                return vec![];
+            }
+            let mut table_tags = Vec::with_capacity(table_count as usize);
+            for table_index in 0..(table_count as FT_UInt) {
+                let (mut tag, mut length) = (0, 0);
+                if FT_Sfnt_Table_Info(self.freetype_face, table_index, &mut tag, &mut length) == 0 {
+                    table_tags.push(tag as u32);
+                }
+            }
+            table_tags
+        }
+    }
+
+    /// Returns true if and only if the font has an OpenType table with the given tag.
+    ///
+    /// Unlike `load_font_table`, this doesn't copy the table.
+    pub fn has_table(&self, table_tag: u32) -> bool {
+        unsafe {
+            let mut len = 0;
+            FT_Load_Sfnt_Table(
+                self.freetype_face,
+                table_tag as FT_ULong,
+                0,
+                ptr::null_mut(),
+                &mut len,
+            ) == 0
+        }
+    }
 }
 
 impl Clone for Font {
@@ -962,11 +1614,36 @@ impl Clone for Font {
             Font {
                 freetype_face: self.freetype_face,
                 font_data: self.font_data.clone(),
+                color_glyph_coverage: Mutex::new(
+                    (*self.color_glyph_coverage.lock().unwrap()).clone(),
+                ),
+                font_data_hash: Mutex::new(*self.font_data_hash.lock().unwrap()),
+                metrics: Mutex::new(*self.metrics.lock().unwrap()),
+                reverse_char_map: Mutex::new((*self.reverse_char_map.lock().unwrap()).clone()),
             }
         }
     }
 }
 
+// Two fonts are equal if they have the same PostScript name and either the same font data or, for
+// fonts mapped from files, the same file and index within it.
+impl PartialEq for Font {
+    fn eq(&self, other: &Font) -> bool {
+        self.font_identity() == other.font_identity()
+    }
+}
+
+impl Eq for Font {}
+
+impl Hash for Font {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.font_identity().hash(state)
+    }
+}
+
 impl Drop for Font {
     fn drop(&mut self) {
         unsafe {
@@ -987,16 +1664,28 @@ impl Loader for Font {
     type NativeFont = NativeFont;
 
     #[inline]
-    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError> {
+    fn from_bytes(
+        font_data: Arc<Vec<u8>>,
+        font_index: FaceIndex,
+    ) -> Result<Self, FontLoadingError> {
         Font::from_bytes(font_data, font_index)
     }
 
     #[inline]
     #[cfg(not(target_arch = "wasm32"))]
-    fn from_file(file: &mut File, font_index: u32) -> Result<Font, FontLoadingError> {
+    fn from_file(file: &mut File, font_index: FaceIndex) -> Result<Font, FontLoadingError> {
         Font::from_file(file, font_index)
     }
 
+    #[inline]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_path<P>(path: P, font_index: FaceIndex) -> Result<Font, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        Font::from_path(path, font_index)
+    }
+
     #[inline]
     fn analyze_bytes(font_data: Arc<Vec<u8>>) -> Result<FileType, FontLoadingError> {
         Font::analyze_bytes(font_data)
@@ -1037,6 +1726,11 @@ impl Loader for Font {
         self.is_monospace()
     }
 
+    #[inline]
+    fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        self.glyph_is_color(glyph_id)
+    }
+
     #[inline]
     fn properties(&self) -> Properties {
         self.properties()
@@ -1047,6 +1741,11 @@ impl Loader for Font {
         self.glyph_for_char(character)
     }
 
+    #[inline]
+    fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        self.glyph_to_char(glyph_id)
+    }
+
     #[inline]
     fn glyph_by_name(&self, name: &str) -> Option<u32> {
         self.glyph_by_name(name)
@@ -1090,11 +1789,26 @@ impl Loader for Font {
         self.metrics()
     }
 
+    #[inline]
+    fn handle(&self) -> Option<Handle> {
+        self.handle()
+    }
+
     #[inline]
     fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
         self.copy_font_data()
     }
 
+    #[inline]
+    fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        self.font_bytes()
+    }
+
+    #[inline]
+    fn approximate_memory_usage(&self) -> usize {
+        self.approximate_memory_usage()
+    }
+
     #[inline]
     fn supports_hinting_options(
         &self,
@@ -1114,6 +1828,7 @@ impl Loader for Font {
         origin: &Point2D<f32>,
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
     ) -> Result<(), GlyphLoadingError> {
         self.rasterize_glyph(
             canvas,
@@ -1123,6 +1838,22 @@ impl Loader for Font {
             origin,
             hinting_options,
             rasterization_options,
+            alpha_mode,
+        )
+    }
+
+    #[inline]
+    fn rasterize_glyph_with_subpixel_order(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        options: SubpixelAaOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        self.rasterize_glyph_with_subpixel_order(
+            canvas, glyph_id, point_size, transform, origin, options,
         )
     }
 
@@ -1135,6 +1866,45 @@ impl Loader for Font {
     fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
         self.load_font_table(table_tag)
     }
+
+    #[inline]
+    fn table_tags(&self) -> Vec<u32> {
+        self.table_tags()
+    }
+
+    #[inline]
+    fn has_table(&self, table_tag: u32) -> bool {
+        self.has_table(table_tag)
+    }
+}
+
+// How `Font::render_glyph` loads a glyph, in which order it reads the subpixels of LCD bitmaps,
+// and how it leaves the alpha of RGBA canvases.
+#[derive(Clone, Copy)]
+struct RenderOptions {
+    load_flags: u32,
+    subpixel_order: SubpixelOrder,
+    alpha_mode: AlphaMode,
+}
+
+// The data that a FreeType face reads from, which must outlive it.
+#[derive(Clone)]
+enum FontData {
+    Memory(Arc<Vec<u8>>),
+    // A mapped file, along with its canonical path.
+    #[cfg(not(target_arch = "wasm32"))]
+    File(Arc<Mmap>, PathBuf),
+}
+
+impl Deref for FontData {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match *self {
+            FontData::Memory(ref data) => data,
+            #[cfg(not(target_arch = "wasm32"))]
+            FontData::File(ref mmap, _) => mmap,
+        }
+    }
 }
 
 unsafe fn setup_freetype_face(face: FT_Face) {
@@ -1190,14 +1960,40 @@ extern "C" {
 
 #[cfg(test)]
 mod test {
+    use freetype::freetype::FT_Done_Face;
+
+    use crate::error::FontLoadingError;
+    use crate::font::FaceIndex;
     use crate::loaders::freetype::Font;
 
     static PCF_FONT_PATH: &'static str = "resources/tests/times-roman-pcf/timR12.pcf";
     static PCF_FONT_POSTSCRIPT_NAME: &'static str = "Times-Roman";
+    static TEST_FONT_FILE_PATH: &'static str =
+        "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
 
     #[test]
     fn get_pcf_postscript_name() {
-        let font = Font::from_path(PCF_FONT_PATH, 0).unwrap();
+        let font = Font::from_path(PCF_FONT_PATH, FaceIndex::FIRST).unwrap();
         assert_eq!(font.postscript_name().unwrap(), PCF_FONT_POSTSCRIPT_NAME);
     }
+
+    #[test]
+    fn synthetic_bold_needs_outlines() {
+        let font = Font::from_path(PCF_FONT_PATH, FaceIndex::FIRST).unwrap();
+        match font.synthetic_bold(25.0) {
+            Err(FontLoadingError::UnknownFormat) => {}
+            other => panic!("Expected no outlines but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn native_font_round_trip() {
+        let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+        let freetype_face = font.native_font();
+        let round_tripped_font = unsafe { Font::from_native_font(freetype_face) };
+        assert_eq!(round_tripped_font.postscript_name(), font.postscript_name());
+        unsafe {
+            assert_eq!(FT_Done_Face(freetype_face), 0);
+        }
+    }
 }
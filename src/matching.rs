@@ -8,7 +8,8 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! Determines the closest font matching a description per the CSS Fonts Level 3 specification.
+//! Determines the closest font matching a description per the CSS Fonts Level 3 specification,
+//! with the Level 4 rules for oblique angles.
 
 use float_ord::FloatOrd;
 
@@ -23,11 +24,36 @@ pub fn find_best_match(
     query: &Properties,
 ) -> Result<usize, SelectionError> {
     // Step 4.
-    let mut matching_set: Vec<usize> = (0..candidates.len()).collect();
+    let matching_set: Vec<usize> = (0..candidates.len()).collect();
     if matching_set.is_empty() {
         return Err(SelectionError::NotFound);
     }
+    Ok(narrow_matching_set(candidates, matching_set, query)[0])
+}
+
+/// Ranks all of the candidates from best match to worst.
+///
+/// The best match is found as in `find_best_match`; then the best match among the remaining
+/// candidates, and so on. Candidates that match equally well keep their relative order, so the
+/// first index in the ranking is the one that `find_best_match` returns.
+pub fn rank_matches(candidates: &[Properties], query: &Properties) -> Vec<usize> {
+    let mut ranking = Vec::with_capacity(candidates.len());
+    let mut remaining_set: Vec<usize> = (0..candidates.len()).collect();
+    while !remaining_set.is_empty() {
+        let matching_set = narrow_matching_set(candidates, remaining_set.clone(), query);
+        remaining_set.retain(|index| !matching_set.contains(index));
+        ranking.extend(matching_set);
+    }
+    ranking
+}
 
+// Performs steps 4a to 4c on a nonempty set of candidate indices in ascending order, returning the
+// indices of the candidates that match best, still in ascending order.
+fn narrow_matching_set(
+    candidates: &[Properties],
+    mut matching_set: Vec<usize>,
+    query: &Properties,
+) -> Vec<usize> {
     // Step 4a (`font-stretch`).
     let matching_stretch = if matching_set
         .iter()
@@ -71,20 +97,32 @@ pub fn find_best_match(
     matching_set.retain(|&index| candidates[index].stretch == matching_stretch);
 
     // Step 4b (`font-style`).
+    //
+    // Per CSS Fonts Level 4, `italic` falls back to oblique faces as if it were `oblique 14deg`,
+    // and `normal` falls back to them as if it were `oblique 0deg`.
     let style_preference = match query.style {
-        Style::Italic => [Style::Italic, Style::Oblique, Style::Normal],
-        Style::Oblique => [Style::Oblique, Style::Italic, Style::Normal],
-        Style::Normal => [Style::Normal, Style::Oblique, Style::Italic],
+        Style::Italic => [
+            Style::Italic,
+            Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE),
+            Style::Normal,
+        ],
+        Style::Oblique(_) => [query.style, Style::Italic, Style::Normal],
+        Style::Normal => [Style::Normal, Style::Oblique(0.0), Style::Italic],
     };
     let matching_style = *style_preference
         .iter()
-        .filter(|&query_style| {
+        .find(|query_style| {
             matching_set
                 .iter()
-                .any(|&index| candidates[index].style == *query_style)
+                .any(|&index| candidates[index].style.is_same_kind(query_style))
         })
-        .next()
         .unwrap();
+    let matching_style = match matching_style {
+        Style::Oblique(angle) => {
+            Style::Oblique(closest_oblique_angle(candidates, &matching_set, angle))
+        }
+        _ => matching_style,
+    };
     matching_set.retain(|&index| candidates[index].style == matching_style);
 
     // Step 4c (`font-weight`).
@@ -144,9 +182,31 @@ pub fn find_best_match(
 
     // Step 4d concerns `font-size`, but fonts in `font-kit` are unsized, so we ignore that.
 
-    // Return the result.
     matching_set
-        .into_iter()
-        .next()
-        .ok_or(SelectionError::NotFound)
+}
+
+// Chooses the angle of the oblique candidates that best matches the given one, per CSS Fonts
+// Level 4 § 5.2. Angles on the same side of upright as the query that are at least as steep come
+// first, closest first; then shallower angles on the same side, closest first; then angles on the
+// other side, shallowest first.
+fn closest_oblique_angle(candidates: &[Properties], matching_set: &[usize], angle: f32) -> f32 {
+    let direction = if angle < 0.0 { -1.0 } else { 1.0 };
+    let rank = |candidate_angle: f32| {
+        let (candidate_angle, angle) = (candidate_angle * direction, angle * direction);
+        if candidate_angle >= angle {
+            (0, FloatOrd(candidate_angle - angle))
+        } else if candidate_angle >= 0.0 {
+            (1, FloatOrd(angle - candidate_angle))
+        } else {
+            (2, FloatOrd(-candidate_angle))
+        }
+    };
+    matching_set
+        .iter()
+        .filter_map(|&index| match candidates[index].style {
+            Style::Oblique(candidate_angle) => Some(candidate_angle),
+            _ => None,
+        })
+        .min_by_key(|&candidate_angle| rank(candidate_angle))
+        .unwrap()
 }
@@ -49,3 +49,53 @@ pub struct Metrics {
     /// font units.
     pub x_height: f32,
 }
+
+impl Metrics {
+    /// Returns the factor that converts font units to pixels at the given point size.
+    ///
+    /// Multiply any of the metrics above, or a glyph advance or bounding box, by this value to
+    /// get its size in pixels.
+    #[inline]
+    pub fn scale_factor(&self, point_size: f32) -> f32 {
+        point_size / self.units_per_em as f32
+    }
+
+    /// Returns the vertical metrics that lay out lines of text, in pixels at the given point size.
+    #[inline]
+    pub fn line_metrics(&self, point_size: f32) -> LineMetrics {
+        let scale_factor = self.scale_factor(point_size);
+        let (ascent, descent, line_gap) = (
+            self.ascent * scale_factor,
+            self.descent * scale_factor,
+            self.line_gap * scale_factor,
+        );
+        LineMetrics {
+            ascent,
+            descent,
+            line_gap,
+            line_height: ascent - descent + line_gap,
+        }
+    }
+}
+
+/// The vertical metrics that lay out lines of text, in pixels at some point size.
+///
+/// These are the `ascent`, `descent`, and `line_gap` of `Metrics` scaled by
+/// `Metrics::scale_factor`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineMetrics {
+    /// The maximum amount the font rises above the baseline, in pixels.
+    pub ascent: f32,
+
+    /// The maximum amount the font descends below the baseline, in pixels.
+    ///
+    /// As with `Metrics::descent`, this is typically negative.
+    pub descent: f32,
+
+    /// The suggested gap between the bottom of one line and the top of the next, in pixels.
+    pub line_gap: f32,
+
+    /// The distance between the baselines of successive lines, in pixels: `ascent - descent +
+    /// line_gap`.
+    pub line_height: f32,
+}
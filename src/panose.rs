@@ -0,0 +1,127 @@
+// font-kit/src/panose.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The PANOSE classification of a font's visual appearance.
+
+/// The 10-digit PANOSE classification of a font, as stored in the OpenType `OS/2` table.
+///
+/// The first digit is the family type, which determines how the other nine are interpreted. The
+/// accessors are named after the digits of the Latin Text family type, which most fonts use. A
+/// digit of 0 means "any" and 1 means "no fit".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Panose(pub [u8; 10]);
+
+/// The kind of font that a PANOSE classification describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PanoseFamilyType {
+    /// Any family type.
+    Any,
+    /// No family type fits.
+    NoFit,
+    /// Latin text, for setting body copy.
+    LatinText,
+    /// Latin hand-written or script faces.
+    LatinHandWritten,
+    /// Latin decorative faces.
+    LatinDecorative,
+    /// Latin symbol and pictograph faces.
+    LatinSymbol,
+    /// A family type not defined by the specification.
+    Other(u8),
+}
+
+impl Panose {
+    /// Returns the family type, which determines how the other digits are interpreted.
+    #[inline]
+    pub fn family_type(&self) -> PanoseFamilyType {
+        match self.0[0] {
+            0 => PanoseFamilyType::Any,
+            1 => PanoseFamilyType::NoFit,
+            2 => PanoseFamilyType::LatinText,
+            3 => PanoseFamilyType::LatinHandWritten,
+            4 => PanoseFamilyType::LatinDecorative,
+            5 => PanoseFamilyType::LatinSymbol,
+            family_type => PanoseFamilyType::Other(family_type),
+        }
+    }
+
+    /// Returns the serif style digit, from 2 (cove) to 15 (rounded).
+    #[inline]
+    pub fn serif_style(&self) -> u8 {
+        self.0[1]
+    }
+
+    /// Returns the weight digit, from 2 (very light) to 11 (extra black). 6 is book weight and 8
+    /// is bold.
+    #[inline]
+    pub fn weight(&self) -> u8 {
+        self.0[2]
+    }
+
+    /// Returns the proportion digit, from 2 (old style) to 9 (monospaced).
+    #[inline]
+    pub fn proportion(&self) -> u8 {
+        self.0[3]
+    }
+
+    /// Returns the contrast digit, from 2 (none) to 9 (very high).
+    #[inline]
+    pub fn contrast(&self) -> u8 {
+        self.0[4]
+    }
+
+    /// Returns the stroke variation digit, from 2 (no variation) to 10 (instant vertical).
+    #[inline]
+    pub fn stroke_variation(&self) -> u8 {
+        self.0[5]
+    }
+
+    /// Returns the arm style digit, from 2 (straight arms, horizontal) to 11 (non-straight arms,
+    /// double serif).
+    #[inline]
+    pub fn arm_style(&self) -> u8 {
+        self.0[6]
+    }
+
+    /// Returns the letterform digit, from 2 (normal, contact) to 15 (oblique, square).
+    #[inline]
+    pub fn letterform(&self) -> u8 {
+        self.0[7]
+    }
+
+    /// Returns the midline digit, from 2 (standard, trimmed) to 13 (low, serifed).
+    #[inline]
+    pub fn midline(&self) -> u8 {
+        self.0[8]
+    }
+
+    /// Returns the x-height digit, from 2 (constant, small) to 7 (ducking, large).
+    #[inline]
+    pub fn x_height(&self) -> u8 {
+        self.0[9]
+    }
+
+    /// Returns true if and only if the proportion digit is "monospaced" and the family type is
+    /// Latin Text or unspecified.
+    #[inline]
+    pub fn is_monospace(&self) -> bool {
+        match self.family_type() {
+            PanoseFamilyType::Any | PanoseFamilyType::LatinText => self.proportion() == 9,
+            _ => false,
+        }
+    }
+}
+
+impl From<[u8; 10]> for Panose {
+    #[inline]
+    fn from(digits: [u8; 10]) -> Panose {
+        Panose(digits)
+    }
+}
@@ -0,0 +1,334 @@
+// font-kit/src/post.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading of glyph names and the fixed pitch flag from the OpenType `post` table.
+
+use crate::sfnt::{read_u16, read_u32};
+
+pub(crate) const POST_TABLE_TAG: u32 = 0x706f_7374;
+
+// The offset of the glyph name data in version 2.0 tables, past the fixed-size header.
+const POST_HEADER_LENGTH: usize = 32;
+
+// The names of the 258 glyphs of the standard Macintosh character set, which version 1.0 tables
+// use for every glyph and version 2.0 tables refer to by index.
+const STANDARD_GLYPH_NAMES: [&str; 258] = [
+    ".notdef",
+    ".null",
+    "nonmarkingreturn",
+    "space",
+    "exclam",
+    "quotedbl",
+    "numbersign",
+    "dollar",
+    "percent",
+    "ampersand",
+    "quotesingle",
+    "parenleft",
+    "parenright",
+    "asterisk",
+    "plus",
+    "comma",
+    "hyphen",
+    "period",
+    "slash",
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "colon",
+    "semicolon",
+    "less",
+    "equal",
+    "greater",
+    "question",
+    "at",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "bracketleft",
+    "backslash",
+    "bracketright",
+    "asciicircum",
+    "underscore",
+    "grave",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "braceleft",
+    "bar",
+    "braceright",
+    "asciitilde",
+    "Adieresis",
+    "Aring",
+    "Ccedilla",
+    "Eacute",
+    "Ntilde",
+    "Odieresis",
+    "Udieresis",
+    "aacute",
+    "agrave",
+    "acircumflex",
+    "adieresis",
+    "atilde",
+    "aring",
+    "ccedilla",
+    "eacute",
+    "egrave",
+    "ecircumflex",
+    "edieresis",
+    "iacute",
+    "igrave",
+    "icircumflex",
+    "idieresis",
+    "ntilde",
+    "oacute",
+    "ograve",
+    "ocircumflex",
+    "odieresis",
+    "otilde",
+    "uacute",
+    "ugrave",
+    "ucircumflex",
+    "udieresis",
+    "dagger",
+    "degree",
+    "cent",
+    "sterling",
+    "section",
+    "bullet",
+    "paragraph",
+    "germandbls",
+    "registered",
+    "copyright",
+    "trademark",
+    "acute",
+    "dieresis",
+    "notequal",
+    "AE",
+    "Oslash",
+    "infinity",
+    "plusminus",
+    "lessequal",
+    "greaterequal",
+    "yen",
+    "mu",
+    "partialdiff",
+    "summation",
+    "product",
+    "pi",
+    "integral",
+    "ordfeminine",
+    "ordmasculine",
+    "Omega",
+    "ae",
+    "oslash",
+    "questiondown",
+    "exclamdown",
+    "logicalnot",
+    "radical",
+    "florin",
+    "approxequal",
+    "Delta",
+    "guillemotleft",
+    "guillemotright",
+    "ellipsis",
+    "nonbreakingspace",
+    "Agrave",
+    "Atilde",
+    "Otilde",
+    "OE",
+    "oe",
+    "endash",
+    "emdash",
+    "quotedblleft",
+    "quotedblright",
+    "quoteleft",
+    "quoteright",
+    "divide",
+    "lozenge",
+    "ydieresis",
+    "Ydieresis",
+    "fraction",
+    "currency",
+    "guilsinglleft",
+    "guilsinglright",
+    "fi",
+    "fl",
+    "daggerdbl",
+    "periodcentered",
+    "quotesinglbase",
+    "quotedblbase",
+    "perthousand",
+    "Acircumflex",
+    "Ecircumflex",
+    "Aacute",
+    "Edieresis",
+    "Egrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Igrave",
+    "Oacute",
+    "Ocircumflex",
+    "apple",
+    "Ograve",
+    "Uacute",
+    "Ucircumflex",
+    "Ugrave",
+    "dotlessi",
+    "circumflex",
+    "tilde",
+    "macron",
+    "breve",
+    "dotaccent",
+    "ring",
+    "cedilla",
+    "hungarumlaut",
+    "ogonek",
+    "caron",
+    "Lslash",
+    "lslash",
+    "Scaron",
+    "scaron",
+    "Zcaron",
+    "zcaron",
+    "brokenbar",
+    "Eth",
+    "eth",
+    "Yacute",
+    "yacute",
+    "Thorn",
+    "thorn",
+    "minus",
+    "multiply",
+    "onesuperior",
+    "twosuperior",
+    "threesuperior",
+    "onehalf",
+    "onequarter",
+    "threequarters",
+    "franc",
+    "Gbreve",
+    "gbreve",
+    "Idotaccent",
+    "Scedilla",
+    "scedilla",
+    "Cacute",
+    "cacute",
+    "Ccaron",
+    "ccaron",
+    "dcroat",
+];
+
+/// Looks up the PostScript name of a glyph in a `post` table.
+///
+/// Versions 1.0 and 2.0 of the table are supported. Returns `None` for version 3.0 tables, which
+/// carry no glyph names, and for glyphs the table doesn't name.
+pub(crate) fn glyph_name(post: &[u8], glyph_id: u32) -> Option<String> {
+    let version = read_u32(post, 0)?;
+    let name_index = match version {
+        0x0001_0000 => glyph_id as usize,
+        0x0002_0000 => {
+            let glyph_count = read_u16(post, POST_HEADER_LENGTH)? as u32;
+            if glyph_id >= glyph_count {
+                return None;
+            }
+            read_u16(post, POST_HEADER_LENGTH + 2 + glyph_id as usize * 2)? as usize
+        }
+        _ => return None,
+    };
+    if let Some(name) = STANDARD_GLYPH_NAMES.get(name_index) {
+        return Some(name.to_string());
+    }
+    if version != 0x0002_0000 {
+        return None;
+    }
+
+    // The custom names are Pascal strings that follow the name indices, in order.
+    let glyph_count = read_u16(post, POST_HEADER_LENGTH)? as usize;
+    let mut offset = POST_HEADER_LENGTH + 2 + glyph_count * 2;
+    for _ in 0..(name_index - STANDARD_GLYPH_NAMES.len()) {
+        offset += 1 + *post.get(offset)? as usize;
+    }
+    let length = *post.get(offset)? as usize;
+    let name = post.get((offset + 1)..(offset + 1 + length))?;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Returns the `isFixedPitch` flag of a `post` table, which is set for monospace fonts. Returns
+/// `None` if the table is truncated.
+pub(crate) fn is_fixed_pitch(post: &[u8]) -> Option<bool> {
+    Some(read_u32(post, 12)? != 0)
+}
+
+/// Returns the slant that a `post` table gives for the font, in degrees clockwise from upright as
+/// CSS measures it, or `None` if the font is upright or the table is truncated.
+pub(crate) fn oblique_angle(post: &[u8]) -> Option<f32> {
+    // The `italicAngle` field is a 16.16 fixed-point number of degrees counterclockwise.
+    let italic_angle = read_u32(post, 4)? as i32 as f32 / 65536.0;
+    if italic_angle == 0.0 {
+        None
+    } else {
+        Some(-italic_angle)
+    }
+}
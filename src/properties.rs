@@ -13,7 +13,14 @@
 //! Much of the documentation in this modules comes from the CSS 3 Fonts specification:
 //! https://drafts.csswg.org/css-fonts-3/
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Display, Formatter};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::error::PropertyParseError;
 
 /// Properties that specify which font in a family to use: e.g. style, weight, and stretchiness.
 ///
@@ -22,6 +29,7 @@ use std::fmt::{self, Debug, Display, Formatter};
 ///     # use font_kit::properties::{Properties, Style};
 ///     println!("{:?}", Properties::new().style(Style::Italic));
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Properties {
     /// The font style, as defined in CSS.
     pub style: Style,
@@ -59,17 +67,229 @@ impl Properties {
         self.stretch = stretch;
         self
     }
+
+    /// Guesses the properties of a font from its style name (also known as its subfamily name),
+    /// such as "SemiBold Italic", "Cond Black", or "Book Oblique".
+    ///
+    /// The name is split into words at spaces, hyphens, underscores, and changes from lowercase
+    /// to uppercase, so "ExtraLight", "Extra Light", and "extra-light" are all recognized. Common
+    /// abbreviations such as "Cn" and "XBd" are recognized too, and case is ignored. Words that
+    /// describe none of the properties are skipped, and properties that no word describes are
+    /// left at their default values.
+    pub fn from_style_name(name: &str) -> Properties {
+        let words = style_name_words(name);
+        let mut properties = Properties::new();
+        let mut index = 0;
+        while index < words.len() {
+            // Try the word together with the next one first, for names like "Semi Bold".
+            let pair = words
+                .get(index + 1)
+                .and_then(|next_word| style_name_word(&format!("{}{}", words[index], next_word)));
+            let (word, word_count) = match pair {
+                Some(word) => (Some(word), 2),
+                None => (style_name_word(&words[index]), 1),
+            };
+            match word {
+                Some(StyleNameWord::Style(style)) => properties.style = style,
+                Some(StyleNameWord::Weight(weight)) => properties.weight = weight,
+                Some(StyleNameWord::Stretch(stretch)) => properties.stretch = stretch,
+                None => {}
+            }
+            index += word_count;
+        }
+        properties
+    }
+}
+
+// A word in a style name that describes one of the properties.
+enum StyleNameWord {
+    Style(Style),
+    Weight(Weight),
+    Stretch(Stretch),
+}
+
+// Splits a style name into lowercase words.
+fn style_name_words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut previous_was_lowercase = false;
+    for character in name.chars() {
+        if !character.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(word.split_off(0));
+            }
+            previous_was_lowercase = false;
+            continue;
+        }
+        if character.is_uppercase() && previous_was_lowercase {
+            words.push(word.split_off(0));
+        }
+        previous_was_lowercase = character.is_lowercase();
+        word.extend(character.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// Looks up a lowercase word or pair of words from a style name.
+fn style_name_word(word: &str) -> Option<StyleNameWord> {
+    let word = match word {
+        "italic" | "ital" | "it" | "kursiv" | "cursive" => StyleNameWord::Style(Style::Italic),
+        "oblique" | "obl" | "slanted" | "inclined" => {
+            StyleNameWord::Style(Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE))
+        }
+
+        "thin" | "hairline" => StyleNameWord::Weight(Weight::THIN),
+        "extralight" | "ultralight" | "xlight" | "xlt" => {
+            StyleNameWord::Weight(Weight::EXTRA_LIGHT)
+        }
+        "light" | "lt" => StyleNameWord::Weight(Weight::LIGHT),
+        "book" | "regular" | "normal" | "roman" | "plain" => StyleNameWord::Weight(Weight::NORMAL),
+        "medium" | "med" | "md" => StyleNameWord::Weight(Weight::MEDIUM),
+        "semibold" | "demibold" | "demi" | "smbd" | "sbd" | "sb" => {
+            StyleNameWord::Weight(Weight::SEMIBOLD)
+        }
+        "bold" | "bd" => StyleNameWord::Weight(Weight::BOLD),
+        "extrabold" | "ultrabold" | "xbold" | "xbd" => StyleNameWord::Weight(Weight::EXTRA_BOLD),
+        "black" | "blk" | "heavy" | "hvy" | "extrablack" | "ultrablack" => {
+            StyleNameWord::Weight(Weight::BLACK)
+        }
+
+        "ultracondensed" | "ultracond" | "ultracn" => {
+            StyleNameWord::Stretch(Stretch::ULTRA_CONDENSED)
+        }
+        "extracondensed" | "extracond" | "xcondensed" | "xcond" | "xcn" | "compressed" => {
+            StyleNameWord::Stretch(Stretch::EXTRA_CONDENSED)
+        }
+        "condensed" | "cond" | "cn" | "narrow" => StyleNameWord::Stretch(Stretch::CONDENSED),
+        "semicondensed" | "semicond" | "semicn" => StyleNameWord::Stretch(Stretch::SEMI_CONDENSED),
+        "semiexpanded" | "semiextended" => StyleNameWord::Stretch(Stretch::SEMI_EXPANDED),
+        "expanded" | "extended" | "exp" | "ext" | "wide" => {
+            StyleNameWord::Stretch(Stretch::EXPANDED)
+        }
+        "extraexpanded" | "extraextended" | "xexpanded" | "xextended" => {
+            StyleNameWord::Stretch(Stretch::EXTRA_EXPANDED)
+        }
+        "ultraexpanded" | "ultraextended" => StyleNameWord::Stretch(Stretch::ULTRA_EXPANDED),
+        _ => return None,
+    };
+    Some(word)
+}
+
+/// Constraints on font properties, for finding every font that satisfies them with
+/// `Source::select_by_properties`.
+///
+/// Constraints that are `None` accept any value. Like `Properties`, this supports a method
+/// chaining style of initialization; e.g.
+///
+///     # use font_kit::properties::{PropertiesFilter, Weight};
+///     println!("{:?}", PropertiesFilter::new().weight(Weight::BOLD..=Weight::BLACK));
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PropertiesFilter {
+    /// The weights to accept, as an inclusive range.
+    pub weight: Option<RangeInclusive<Weight>>,
+    /// The stretches to accept, as an inclusive range.
+    pub stretch: Option<RangeInclusive<Stretch>>,
+    /// The styles to accept. Oblique styles accept oblique faces at any angle.
+    pub styles: Option<Vec<Style>>,
+    /// Whether to accept only monospace fonts (`true`) or only proportional ones (`false`).
+    pub monospace: Option<bool>,
+}
+
+impl PropertiesFilter {
+    /// Initializes a filter that accepts every font.
+    #[inline]
+    pub fn new() -> PropertiesFilter {
+        PropertiesFilter::default()
+    }
+
+    /// Restricts the weight to the given range and returns this filter for method chaining.
+    #[inline]
+    pub fn weight(&mut self, weight: RangeInclusive<Weight>) -> &mut PropertiesFilter {
+        self.weight = Some(weight);
+        self
+    }
+
+    /// Restricts the stretch to the given range and returns this filter for method chaining.
+    #[inline]
+    pub fn stretch(&mut self, stretch: RangeInclusive<Stretch>) -> &mut PropertiesFilter {
+        self.stretch = Some(stretch);
+        self
+    }
+
+    /// Restricts the style to the given styles and returns this filter for method chaining.
+    #[inline]
+    pub fn styles(&mut self, styles: &[Style]) -> &mut PropertiesFilter {
+        self.styles = Some(styles.to_vec());
+        self
+    }
+
+    /// Restricts the filter to monospace fonts if `monospace` is true, or to proportional fonts
+    /// otherwise, and returns this filter for method chaining.
+    #[inline]
+    pub fn monospace(&mut self, monospace: bool) -> &mut PropertiesFilter {
+        self.monospace = Some(monospace);
+        self
+    }
+
+    /// Returns true if and only if a font with the given properties and spacing satisfies this
+    /// filter.
+    ///
+    /// `is_monospace` is ignored unless the filter constrains it, so callers can skip looking it
+    /// up in that case.
+    pub fn matches(&self, properties: &Properties, is_monospace: bool) -> bool {
+        if let Some(ref weight) = self.weight {
+            if !weight.contains(&properties.weight) {
+                return false;
+            }
+        }
+        if let Some(ref stretch) = self.stretch {
+            if !stretch.contains(&properties.stretch) {
+                return false;
+            }
+        }
+        if let Some(ref styles) = self.styles {
+            if !styles
+                .iter()
+                .any(|style| style.is_same_kind(&properties.style))
+            {
+                return false;
+            }
+        }
+        match self.monospace {
+            Some(monospace) => monospace == is_monospace,
+            None => true,
+        }
+    }
 }
 
 /// Allows italic or oblique faces to be selected.
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Style {
     /// A face that is neither italic not obliqued.
     Normal,
     /// A form that is generally cursive in nature.
     Italic,
-    /// A typically-sloped version of the regular face.
-    Oblique,
+    /// A typically-sloped version of the regular face, with the angle of its slant in degrees
+    /// clockwise from upright, as in CSS. The angle is `OBLIQUE_DEFAULT_ANGLE` if the font
+    /// doesn't say.
+    Oblique(f32),
+}
+
+impl Style {
+    /// The angle of an oblique face whose angle is unknown, and of the CSS `oblique` keyword
+    /// without an angle: 14 degrees.
+    pub const OBLIQUE_DEFAULT_ANGLE: f32 = 14.0;
+
+    /// Returns true if this style and the other one are both normal, both italic, or both
+    /// oblique, regardless of their angles.
+    #[inline]
+    pub fn is_same_kind(&self, other: &Style) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
 }
 
 impl Default for Style {
@@ -79,14 +299,57 @@ impl Default for Style {
 }
 
 impl Display for Style {
+    /// Writes the CSS `font-style` value: `normal`, `italic`, or `oblique`, followed by the angle
+    /// of oblique styles unless it is the default.
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Debug::fmt(self, f)
+        match *self {
+            Style::Normal => f.write_str("normal"),
+            Style::Italic => f.write_str("italic"),
+            Style::Oblique(angle) if angle == Style::OBLIQUE_DEFAULT_ANGLE => {
+                f.write_str("oblique")
+            }
+            Style::Oblique(angle) => write!(f, "oblique {}deg", angle),
+        }
+    }
+}
+
+impl FromStr for Style {
+    type Err = PropertyParseError;
+
+    /// Parses a CSS `font-style` value: `normal`, `italic`, or `oblique`, optionally followed by
+    /// an angle from -90deg to 90deg for `oblique`. Case is ignored.
+    fn from_str(string: &str) -> Result<Style, PropertyParseError> {
+        let mut words = string.split_whitespace();
+        let style = match (
+            &*normalize_keyword(words.next().unwrap_or("")),
+            words.next(),
+        ) {
+            ("normal", None) => Style::Normal,
+            ("italic", None) => Style::Italic,
+            ("oblique", None) => Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE),
+            ("oblique", Some(angle)) => {
+                let angle = match angle.to_ascii_lowercase().strip_suffix("deg") {
+                    Some(angle) => parse_number(angle)?,
+                    None => return Err(PropertyParseError::UnknownKeyword),
+                };
+                if !(-90.0..=90.0).contains(&angle) {
+                    return Err(PropertyParseError::OutOfRange);
+                }
+                Style::Oblique(angle)
+            }
+            _ => return Err(PropertyParseError::UnknownKeyword),
+        };
+        match words.next() {
+            Some(_) => Err(PropertyParseError::UnknownKeyword),
+            None => Ok(style),
+        }
     }
 }
 
 /// The degree of blackness or stroke thickness of a font. This value ranges from 100.0 to 900.0,
 /// with 400.0 as normal.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Weight(pub f32);
 
 impl Default for Weight {
@@ -115,12 +378,85 @@ impl Weight {
     pub const EXTRA_BOLD: Weight = Weight(800.0);
     /// Black weight (900), the thickest value.
     pub const BLACK: Weight = Weight(900.0);
+
+    /// Returns the OpenType `usWeightClass` value for this weight, rounded and clamped to the
+    /// range from 1 to 1000.
+    #[inline]
+    pub fn to_usweight_class(&self) -> u16 {
+        self.0.round().clamp(1.0, 1000.0) as u16
+    }
+}
+
+// The names that weights equal to the constants are displayed with.
+static WEIGHT_KEYWORDS: [(Weight, &str); 9] = [
+    (Weight::THIN, "thin"),
+    (Weight::EXTRA_LIGHT, "extra-light"),
+    (Weight::LIGHT, "light"),
+    (Weight::NORMAL, "normal"),
+    (Weight::MEDIUM, "medium"),
+    (Weight::SEMIBOLD, "semi-bold"),
+    (Weight::BOLD, "bold"),
+    (Weight::EXTRA_BOLD, "extra-bold"),
+    (Weight::BLACK, "black"),
+];
+
+impl From<u16> for Weight {
+    /// Converts an OpenType `usWeightClass` value, which uses the same scale as CSS.
+    #[inline]
+    fn from(weight_class: u16) -> Weight {
+        Weight(weight_class as f32)
+    }
+}
+
+impl Display for Weight {
+    /// Writes the name of the weight constant that this weight is equal to, such as `bold` or
+    /// `extra-light`, or the number otherwise. The output can be parsed back with `FromStr`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match WEIGHT_KEYWORDS.iter().find(|&&(weight, _)| weight == *self) {
+            Some(&(_, keyword)) => f.write_str(keyword),
+            None => Display::fmt(&self.0, f),
+        }
+    }
+}
+
+impl FromStr for Weight {
+    type Err = PropertyParseError;
+
+    /// Parses a CSS `font-weight` value: the keyword `normal` or `bold`, or a number from 1 to
+    /// 1000. The names of the other weight constants, such as `semi-bold` or `black`, are accepted
+    /// too. Case is ignored, as are hyphens and underscores within keywords.
+    ///
+    /// The relative keywords `bolder` and `lighter` depend on the parent element in CSS, so they
+    /// aren't accepted.
+    fn from_str(string: &str) -> Result<Weight, PropertyParseError> {
+        let string = string.trim();
+        let weight = match &*normalize_keyword(string) {
+            "thin" | "hairline" => Weight::THIN,
+            "extralight" | "ultralight" => Weight::EXTRA_LIGHT,
+            "light" => Weight::LIGHT,
+            "normal" | "regular" => Weight::NORMAL,
+            "medium" => Weight::MEDIUM,
+            "semibold" | "demibold" => Weight::SEMIBOLD,
+            "bold" => Weight::BOLD,
+            "extrabold" | "ultrabold" => Weight::EXTRA_BOLD,
+            "black" | "heavy" => Weight::BLACK,
+            _ => {
+                let value = parse_number(string)?;
+                if !(1.0..=1000.0).contains(&value) {
+                    return Err(PropertyParseError::OutOfRange);
+                }
+                Weight(value)
+            }
+        };
+        Ok(weight)
+    }
 }
 
 /// The width of a font as an approximate fraction of the normal width.
 ///
 /// Widths range from 0.5 to 2.0 inclusive, with 1.0 as the normal width.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stretch(pub f32);
 
 impl Default for Stretch {
@@ -162,4 +498,116 @@ impl Stretch {
         Stretch::EXTRA_EXPANDED.0,
         Stretch::ULTRA_EXPANDED.0,
     ];
+
+    /// Converts a percentage of the normal width, as used by CSS `font-stretch`, to a stretch.
+    #[inline]
+    pub fn from_percentage(percentage: f32) -> Stretch {
+        Stretch(percentage / 100.0)
+    }
+
+    /// Returns this width as a percentage of the normal width.
+    #[inline]
+    pub fn to_percentage(&self) -> f32 {
+        self.0 * 100.0
+    }
+
+    /// Converts an OpenType `usWidthClass` value, from 1 (ultra-condensed) to 9
+    /// (ultra-expanded). Values outside that range are treated as normal width.
+    #[inline]
+    pub fn from_uswidth_class(width_class: u16) -> Stretch {
+        match width_class {
+            1..=9 => Stretch(Stretch::MAPPING[width_class as usize - 1]),
+            _ => Stretch::NORMAL,
+        }
+    }
+
+    /// Returns the OpenType `usWidthClass` value whose width is closest to this one.
+    pub fn to_uswidth_class(&self) -> u16 {
+        let mut best_width_class = 1;
+        for (index, &stretch) in Stretch::MAPPING.iter().enumerate() {
+            let best_stretch = Stretch::MAPPING[best_width_class - 1];
+            if (stretch - self.0).abs() < (best_stretch - self.0).abs() {
+                best_width_class = index + 1;
+            }
+        }
+        best_width_class as u16
+    }
+}
+
+// The CSS `font-stretch` keywords, in order of width.
+static STRETCH_KEYWORDS: [(Stretch, &str); 9] = [
+    (Stretch::ULTRA_CONDENSED, "ultra-condensed"),
+    (Stretch::EXTRA_CONDENSED, "extra-condensed"),
+    (Stretch::CONDENSED, "condensed"),
+    (Stretch::SEMI_CONDENSED, "semi-condensed"),
+    (Stretch::NORMAL, "normal"),
+    (Stretch::SEMI_EXPANDED, "semi-expanded"),
+    (Stretch::EXPANDED, "expanded"),
+    (Stretch::EXTRA_EXPANDED, "extra-expanded"),
+    (Stretch::ULTRA_EXPANDED, "ultra-expanded"),
+];
+
+impl Display for Stretch {
+    /// Writes the CSS `font-stretch` keyword that this width is equal to, such as `condensed`, or
+    /// a percentage such as `80%` otherwise. The output can be parsed back with `FromStr`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match STRETCH_KEYWORDS
+            .iter()
+            .find(|&&(stretch, _)| stretch == *self)
+        {
+            Some(&(_, keyword)) => f.write_str(keyword),
+            None => write!(f, "{}%", self.to_percentage()),
+        }
+    }
+}
+
+impl FromStr for Stretch {
+    type Err = PropertyParseError;
+
+    /// Parses a CSS `font-stretch` value: a keyword from `ultra-condensed` to `ultra-expanded`, or
+    /// a percentage from 50% to 200%. A bare number is taken as a fraction of the normal width, as
+    /// in `Stretch` itself. Case is ignored, as are hyphens and underscores within keywords.
+    fn from_str(string: &str) -> Result<Stretch, PropertyParseError> {
+        let string = string.trim();
+        let stretch = match &*normalize_keyword(string) {
+            "ultracondensed" => Stretch::ULTRA_CONDENSED,
+            "extracondensed" => Stretch::EXTRA_CONDENSED,
+            "condensed" => Stretch::CONDENSED,
+            "semicondensed" => Stretch::SEMI_CONDENSED,
+            "normal" => Stretch::NORMAL,
+            "semiexpanded" => Stretch::SEMI_EXPANDED,
+            "expanded" => Stretch::EXPANDED,
+            "extraexpanded" => Stretch::EXTRA_EXPANDED,
+            "ultraexpanded" => Stretch::ULTRA_EXPANDED,
+            _ => {
+                let value = if let Some(percentage) = string.strip_suffix('%') {
+                    parse_number(percentage)? / 100.0
+                } else {
+                    parse_number(string)?
+                };
+                if !(Stretch::ULTRA_CONDENSED.0..=Stretch::ULTRA_EXPANDED.0).contains(&value) {
+                    return Err(PropertyParseError::OutOfRange);
+                }
+                Stretch(value)
+            }
+        };
+        Ok(stretch)
+    }
+}
+
+// Lowercases a trimmed keyword and removes the separators that spellings of it differ in.
+fn normalize_keyword(string: &str) -> String {
+    string
+        .chars()
+        .filter(|&character| character != '-' && character != '_')
+        .map(|character| character.to_ascii_lowercase())
+        .collect()
+}
+
+// Parses a finite number. Anything else is an unknown keyword.
+fn parse_number(string: &str) -> Result<f32, PropertyParseError> {
+    match string.parse::<f32>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(PropertyParseError::UnknownKeyword),
+    }
 }
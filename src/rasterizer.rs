@@ -0,0 +1,229 @@
+// font-kit/src/rasterizer.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A simple software rasterizer for glyph outlines that the platform can't draw for us.
+//!
+//! This computes exact area coverage by accumulating signed areas, in the manner of `font-rs`.
+//! Curves are flattened into lines first.
+
+use euclid::default::{Point2D, Size2D, Transform2D};
+use lyon_path::{Path, PathEvent};
+
+use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::error::GlyphLoadingError;
+
+// The curve flattening tolerance. Larger values produce more line segments per curve.
+const FLATTENING_TOLERANCE: f32 = 3.0;
+
+/// Accumulates the coverage of paths in a pixel grid.
+pub(crate) struct Rasterizer {
+    size: Size2D<usize>,
+    // The change in coverage at each pixel. Two extra entries absorb writes just past the last
+    // pixel.
+    accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub(crate) fn new(size: &Size2D<u32>) -> Result<Rasterizer, GlyphLoadingError> {
+        let size = size.to_usize();
+        let length = size
+            .width
+            .checked_mul(size.height)
+            .and_then(|area| area.checked_add(2))
+            .ok_or(GlyphLoadingError::OutOfMemory)?;
+        let mut accumulation = vec![];
+        accumulation
+            .try_reserve_exact(length)
+            .map_err(|_| GlyphLoadingError::OutOfMemory)?;
+        accumulation.resize(length, 0.0);
+        Ok(Rasterizer { size, accumulation })
+    }
+
+    /// Adds the area enclosed by the path, after transforming it to pixel coordinates.
+    ///
+    /// Contours that aren't explicitly closed are closed with a line.
+    pub(crate) fn draw_path(&mut self, path: &Path, transform: &Transform2D<f32>) {
+        let mut first = Point2D::zero();
+        let mut last = Point2D::zero();
+        for event in path.iter() {
+            match event {
+                PathEvent::MoveTo(to) => {
+                    self.draw_line(last, first);
+                    first = transform.transform_point(to);
+                    last = first;
+                }
+                PathEvent::Line(segment) | PathEvent::Close(segment) => {
+                    let to = transform.transform_point(segment.to);
+                    self.draw_line(last, to);
+                    last = to;
+                }
+                PathEvent::Quadratic(segment) => {
+                    let ctrl = transform.transform_point(segment.ctrl);
+                    let to = transform.transform_point(segment.to);
+                    self.draw_quadratic(last, ctrl, to);
+                    last = to;
+                }
+                PathEvent::Cubic(segment) => {
+                    let ctrl1 = transform.transform_point(segment.ctrl1);
+                    let ctrl2 = transform.transform_point(segment.ctrl2);
+                    let to = transform.transform_point(segment.to);
+                    self.draw_cubic(last, ctrl1, ctrl2, to);
+                    last = to;
+                }
+            }
+        }
+        self.draw_line(last, first);
+    }
+
+    /// Writes the coverage to a new canvas in the format that the rasterization options call for.
+    ///
+    /// For subpixel antialiasing, the rasterizer must be three times as wide as the canvas, and
+    /// each pixel of the canvas takes the coverage of three horizontally adjacent samples.
+    pub(crate) fn to_canvas(
+        &self,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<Canvas, GlyphLoadingError> {
+        let (format, width) = match rasterization_options {
+            RasterizationOptions::Bilevel | RasterizationOptions::GrayscaleAa => {
+                (Format::A8, self.size.width)
+            }
+            RasterizationOptions::SubpixelAa => (Format::Rgb24, self.size.width / 3),
+        };
+        let mut canvas = Canvas::new(&Size2D::new(width, self.size.height).to_u32(), format)?;
+
+        let mut coverage = 0.0;
+        let pixel_count = self.size.width * self.size.height;
+        for (value, &delta) in canvas
+            .pixels
+            .iter_mut()
+            .zip(self.accumulation[0..pixel_count].iter())
+        {
+            coverage += delta;
+            let alpha = f32::min(f32::abs(coverage), 1.0);
+            *value = match rasterization_options {
+                RasterizationOptions::Bilevel if alpha >= 0.5 => 255,
+                RasterizationOptions::Bilevel => 0,
+                _ => (alpha * 255.0).round() as u8,
+            };
+        }
+        Ok(canvas)
+    }
+
+    fn draw_line(&mut self, from: Point2D<f32>, to: Point2D<f32>) {
+        let is_finite = [from.x, from.y, to.x, to.y]
+            .iter()
+            .all(|value| value.is_finite());
+        if from.y == to.y || !is_finite {
+            return;
+        }
+        let (direction, from, to) = if from.y < to.y {
+            (1.0, from, to)
+        } else {
+            (-1.0, to, from)
+        };
+
+        let width = self.size.width as f32;
+        let dxdy = (to.x - from.x) / (to.y - from.y);
+        let mut x = from.x;
+        if from.y < 0.0 {
+            x -= from.y * dxdy;
+        }
+
+        // Casting saturates, so rows above the top of the grid are skipped.
+        let first_row = from.y as usize;
+        let last_row = usize::min(self.size.height, to.y.ceil() as usize);
+        for row in first_row..last_row {
+            let row_start = row * self.size.width;
+            let dy = f32::min((row + 1) as f32, to.y) - f32::max(row as f32, from.y);
+            let next_x = x + dxdy * dy;
+            let d = dy * direction;
+
+            // Parts of the line outside the grid are moved to its edges. The coverage that they
+            // contribute to the pixels inside is unchanged.
+            let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+            let (x0, x1) = (x0.max(0.0).min(width), x1.max(0.0).min(width));
+            let x0_floor = x0.floor();
+            let x0_index = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1_index = x1_ceil as usize;
+
+            if x1_index <= x0_index + 1 {
+                let x_mid = 0.5 * (x0 + x1) - x0_floor;
+                self.accumulation[row_start + x0_index] += d - d * x_mid;
+                self.accumulation[row_start + x0_index + 1] += d * x_mid;
+            } else {
+                let inverse_run = (x1 - x0).recip();
+                let x0_fract = x0 - x0_floor;
+                let area0 = 0.5 * inverse_run * (1.0 - x0_fract) * (1.0 - x0_fract);
+                let x1_fract = x1 - x1_ceil + 1.0;
+                let area_last = 0.5 * inverse_run * x1_fract * x1_fract;
+                self.accumulation[row_start + x0_index] += d * area0;
+                if x1_index == x0_index + 2 {
+                    self.accumulation[row_start + x0_index + 1] += d * (1.0 - area0 - area_last);
+                } else {
+                    let area1 = inverse_run * (1.5 - x0_fract);
+                    self.accumulation[row_start + x0_index + 1] += d * (area1 - area0);
+                    for x_index in (x0_index + 2)..(x1_index - 1) {
+                        self.accumulation[row_start + x_index] += d * inverse_run;
+                    }
+                    let area2 = area1 + (x1_index - x0_index - 3) as f32 * inverse_run;
+                    self.accumulation[row_start + x1_index - 1] += d * (1.0 - area2 - area_last);
+                }
+                self.accumulation[row_start + x1_index] += d * area_last;
+            }
+
+            x = next_x;
+        }
+    }
+
+    fn draw_quadratic(&mut self, from: Point2D<f32>, ctrl: Point2D<f32>, to: Point2D<f32>) {
+        let deviation = (from - ctrl * 2.0 + to.to_vector()).length();
+        let step_count = segment_count(deviation);
+        let mut last = from;
+        for step in 1..=step_count {
+            let t = step as f32 / step_count as f32;
+            let next = from.lerp(ctrl, t).lerp(ctrl.lerp(to, t), t);
+            self.draw_line(last, next);
+            last = next;
+        }
+    }
+
+    fn draw_cubic(
+        &mut self,
+        from: Point2D<f32>,
+        ctrl1: Point2D<f32>,
+        ctrl2: Point2D<f32>,
+        to: Point2D<f32>,
+    ) {
+        let deviation = f32::max(
+            (from - ctrl1 * 2.0 + ctrl2.to_vector()).length(),
+            (ctrl1 - ctrl2 * 2.0 + to.to_vector()).length(),
+        );
+        let step_count = segment_count(deviation);
+        let mut last = from;
+        for step in 1..=step_count {
+            let t = step as f32 / step_count as f32;
+            let (p01, p12, p23) = (from.lerp(ctrl1, t), ctrl1.lerp(ctrl2, t), ctrl2.lerp(to, t));
+            let next = p01.lerp(p12, t).lerp(p12.lerp(p23, t), t);
+            self.draw_line(last, next);
+            last = next;
+        }
+    }
+}
+
+// Returns the number of lines to flatten a curve into, given how far its control points deviate
+// from a straight line, in pixels.
+fn segment_count(deviation: f32) -> u32 {
+    if deviation.is_nan() || deviation <= 0.333 {
+        return 1;
+    }
+    let count = 1.0 + f32::sqrt(f32::sqrt(FLATTENING_TOLERANCE * deviation * deviation));
+    f32::min(count, 256.0) as u32
+}
@@ -0,0 +1,204 @@
+// font-kit/src/render.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A convenience function to render a line of text, falling back to other fonts as necessary.
+
+use euclid::default::{Rect, Size2D};
+use euclid::point2;
+use std::slice;
+
+use crate::canvas::{AlphaMode, Canvas, Format, RasterizationOptions};
+use crate::color::ColorLayer;
+use crate::error::{RenderingError, SelectionError};
+use crate::family_name::FamilyName;
+use crate::font::Font;
+use crate::hinting::HintingOptions;
+use crate::loader::{FontTransform, Loader};
+use crate::properties::Properties;
+use crate::source::Source;
+
+// The locale used to ask the platform for fallback fonts.
+const FALLBACK_LOCALE: &str = "en-US";
+
+const FOREGROUND_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+/// Renders a single line of text to a new premultiplied RGBA canvas that tightly fits its ink.
+///
+/// Each character is drawn with the first of the fonts matching `family_names` that has a glyph
+/// for it. Characters that none of those fonts support are drawn with whatever fallback fonts the
+/// platform suggests. Text is drawn in opaque black, except for color glyphs defined in a `COLR`
+/// table, which are composited layer by layer using the font's default palette. Color glyphs in
+/// other formats, such as `SVG `, `CBDT`, or `sbix`, are drawn in black from their outlines.
+///
+/// This does no shaping: there are no ligatures, kerning, or bidirectional reordering.
+pub fn render_string(
+    source: &dyn Source,
+    family_names: &[FamilyName],
+    properties: &Properties,
+    text: &str,
+    point_size: f32,
+) -> Result<Canvas, RenderingError> {
+    // Each font, along with the scale factor to apply to it.
+    let mut fonts: Vec<(Font, f32)> = vec![];
+    let mut error = RenderingError::Selection(SelectionError::NotFound);
+    for family_name in family_names {
+        if let Ok(handle) = source.select_best_match(slice::from_ref(family_name), properties) {
+            match handle.load() {
+                Ok(font) => fonts.push((font, 1.0)),
+                Err(font_loading_error) => error = RenderingError::FontLoading(font_loading_error),
+            }
+        }
+    }
+    if fonts.is_empty() {
+        return Err(error);
+    }
+
+    // Lay out the glyphs, breaking color glyphs up into their layers.
+    let mut layers = vec![];
+    let mut pen_x = 0.0;
+    for character in text.chars() {
+        let (font_index, glyph_id) = match find_glyph(&fonts, character) {
+            Some(glyph) => glyph,
+            None => {
+                let fallback_result = Loader::get_fallbacks(
+                    &fonts[0].0,
+                    character.encode_utf8(&mut [0; 4]),
+                    FALLBACK_LOCALE,
+                );
+                fonts.extend(
+                    fallback_result
+                        .fonts
+                        .into_iter()
+                        .map(|fallback_font| (fallback_font.font, fallback_font.scale)),
+                );
+                // If all else fails, draw the missing glyph of the primary font.
+                find_glyph(&fonts, character).unwrap_or_else(|| (0, fonts[0].0.default_glyph()))
+            }
+        };
+
+        let (ref font, scale) = fonts[font_index];
+        let size = point_size * scale;
+        let color_layers = if font.glyph_is_color(glyph_id) {
+            match (font.color_glyph_layers(glyph_id), font.color_palette(0)) {
+                (Some(color_layers), Some(palette)) => color_layers
+                    .into_iter()
+                    .map(|color_layer| {
+                        let color = match color_layer.palette_index {
+                            ColorLayer::FOREGROUND_PALETTE_INDEX => FOREGROUND_COLOR,
+                            palette_index => *palette
+                                .get(palette_index as usize)
+                                .unwrap_or(&FOREGROUND_COLOR),
+                        };
+                        (color_layer.glyph_id, color)
+                    })
+                    .collect(),
+                _ => vec![(glyph_id, FOREGROUND_COLOR)],
+            }
+        } else {
+            vec![(glyph_id, FOREGROUND_COLOR)]
+        };
+        for (layer_glyph_id, color) in color_layers {
+            layers.push(Layer {
+                font_index,
+                glyph_id: layer_glyph_id,
+                size,
+                origin_x: pen_x,
+                color,
+            });
+        }
+
+        if let Ok(advance) = font.advance_pixels(glyph_id, size) {
+            pen_x += advance.x;
+        }
+    }
+
+    // Find the bounds of the ink, relative to the start of the baseline.
+    let mut layer_bounds = Vec::with_capacity(layers.len());
+    let mut ink_bounds: Option<Rect<i32>> = None;
+    for layer in &layers {
+        let bounds = fonts[layer.font_index]
+            .0
+            .raster_bounds(
+                layer.glyph_id,
+                layer.size,
+                &FontTransform::identity(),
+                &point2(layer.origin_x, 0.0),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .unwrap_or_else(|_| Rect::zero());
+        if !bounds.is_empty() {
+            ink_bounds = Some(match ink_bounds {
+                Some(ink_bounds) => ink_bounds.union(&bounds),
+                None => bounds,
+            });
+        }
+        layer_bounds.push(bounds);
+    }
+    let ink_bounds = match ink_bounds {
+        Some(ink_bounds) => ink_bounds,
+        None => return Ok(Canvas::new(&Size2D::zero(), Format::Rgba32)?),
+    };
+
+    let mut canvas = Canvas::new(&ink_bounds.size.to_u32(), Format::Rgba32)?;
+    for (layer, bounds) in layers.iter().zip(layer_bounds) {
+        if bounds.is_empty() {
+            continue;
+        }
+        let mut glyph_canvas = Canvas::new(&bounds.size.to_u32(), Format::A8)?;
+        let glyph_origin = point2(
+            layer.origin_x - bounds.origin.x as f32,
+            -bounds.origin.y as f32,
+        );
+        if fonts[layer.font_index]
+            .0
+            .rasterize_glyph(
+                &mut glyph_canvas,
+                layer.glyph_id,
+                layer.size,
+                &FontTransform::identity(),
+                &glyph_origin,
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+                AlphaMode::Premultiplied,
+            )
+            .is_ok()
+        {
+            canvas.composite_from(
+                &glyph_canvas,
+                bounds.origin - ink_bounds.origin.to_vector(),
+                Some(layer.color),
+            );
+        }
+    }
+    Ok(canvas)
+}
+
+// One glyph to draw: either a whole glyph, or one layer of a `COLR` glyph.
+struct Layer {
+    font_index: usize,
+    glyph_id: u32,
+    size: f32,
+    origin_x: f32,
+    // The color to fill an A8 rasterization of the glyph with.
+    color: [u8; 4],
+}
+
+// Returns the index of the first font that supports the given character and the glyph for it.
+fn find_glyph(fonts: &[(Font, f32)], character: char) -> Option<(usize, u32)> {
+    fonts
+        .iter()
+        .enumerate()
+        .filter_map(|(font_index, (font, _))| {
+            font.glyph_for_char(character)
+                .map(|glyph_id| (font_index, glyph_id))
+        })
+        .next()
+}
@@ -0,0 +1,369 @@
+// font-kit/src/sfnt.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Minimal reading of TrueType/OpenType files without going through a platform loader.
+//!
+//! This is used to peek at font metadata cheaply: only the table directory and the requested
+//! tables are read.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::FontLoadingError;
+use crate::font::FaceIndex;
+use crate::post;
+use crate::properties::{Properties, Stretch, Style, Weight};
+use crate::utils::SFNT_VERSIONS;
+
+pub(crate) const HEAD_TABLE_TAG: u32 = 0x6865_6164;
+pub(crate) const NAME_TABLE_TAG: u32 = 0x6e61_6d65;
+pub(crate) const OS2_TABLE_TAG: u32 = 0x4f53_2f32;
+
+const TTC_TAG: [u8; 4] = *b"ttcf";
+
+const NAME_ID_FONT_FAMILY: u16 = 1;
+const NAME_ID_FONT_SUBFAMILY: u16 = 2;
+const NAME_ID_FULL_NAME: u16 = 4;
+const NAME_ID_POSTSCRIPT_NAME: u16 = 6;
+const NAME_ID_TYPOGRAPHIC_FAMILY: u16 = 16;
+const NAME_ID_TYPOGRAPHIC_SUBFAMILY: u16 = 17;
+
+const OS2_FS_SELECTION_ITALIC: u16 = 1 << 0;
+const OS2_FS_SELECTION_OBLIQUE: u16 = 1 << 9;
+const HEAD_MAC_STYLE_ITALIC: u16 = 1 << 1;
+
+/// A source of font data.
+pub(crate) trait ReadSeek: Read + Seek {}
+
+impl<T> ReadSeek for T where T: Read + Seek {}
+
+/// The table directory of a single font in a TrueType/OpenType file or collection.
+pub(crate) struct FontTables<R> {
+    reader: R,
+    // The length of the whole file, which no table may extend past.
+    data_length: u64,
+    // The tag, offset, and length of each table.
+    records: Vec<(u32, u32, u32)>,
+}
+
+impl<R> FontTables<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the table directory of the font with the given index.
+    pub(crate) fn new(mut reader: R, font_index: FaceIndex) -> Result<Self, FontLoadingError> {
+        let mut header = [0; 12];
+        read_exact_at(&mut reader, 0, &mut header)?;
+
+        let mut directory_offset = 0;
+        if header[0..4] == TTC_TAG {
+            let font_count = BigEndian::read_u32(&header[8..12]);
+            if font_index.0 >= font_count {
+                return Err(FontLoadingError::NoSuchFontInCollection);
+            }
+            let mut offset = [0; 4];
+            read_exact_at(&mut reader, 12 + font_index.0 as u64 * 4, &mut offset)?;
+            directory_offset = BigEndian::read_u32(&offset) as u64;
+            read_exact_at(&mut reader, directory_offset, &mut header)?;
+        } else if font_index != FaceIndex::FIRST {
+            return Err(FontLoadingError::NoSuchFontInCollection);
+        }
+        if !SFNT_VERSIONS
+            .iter()
+            .any(|version| header[0..4] == version[..])
+        {
+            return Err(FontLoadingError::UnknownFormat);
+        }
+
+        let table_count = BigEndian::read_u16(&header[4..6]) as usize;
+        let mut directory = vec![0; table_count * 16];
+        read_exact_at(&mut reader, directory_offset + 12, &mut directory)?;
+        let records = directory
+            .chunks(16)
+            .map(|record| {
+                (
+                    BigEndian::read_u32(&record[0..4]),
+                    BigEndian::read_u32(&record[8..12]),
+                    BigEndian::read_u32(&record[12..16]),
+                )
+            })
+            .collect();
+        let data_length = reader.seek(SeekFrom::End(0))?;
+        Ok(FontTables {
+            reader,
+            data_length,
+            records,
+        })
+    }
+
+    /// Returns the tags of the tables in the font, in the order of its table directory.
+    pub(crate) fn table_tags(&self) -> Vec<u32> {
+        self.records
+            .iter()
+            .map(|&(table_tag, _, _)| table_tag)
+            .collect()
+    }
+
+    /// Reads the table with the given tag, if the font has one.
+    pub(crate) fn load_table(
+        &mut self,
+        table_tag: u32,
+    ) -> Result<Option<Vec<u8>>, FontLoadingError> {
+        let (offset, length) = match self.records.iter().find(|record| record.0 == table_tag) {
+            Some(&(_, offset, length)) => (offset, length),
+            None => return Ok(None),
+        };
+        // The length comes from the file, so make sure the table is really there before
+        // allocating room for it.
+        match (offset as u64).checked_add(length as u64) {
+            Some(end) if end <= self.data_length => {}
+            _ => return Err(FontLoadingError::Parse),
+        }
+        let mut table = vec![0; length as usize];
+        read_exact_at(&mut self.reader, offset as u64, &mut table)?;
+        Ok(Some(table))
+    }
+
+    /// Computes the CSS properties of the font from its `OS/2` and `head` tables, as `properties`
+    /// does. The `post` table is read as well for oblique fonts, for the angle of their slant.
+    pub(crate) fn properties(&mut self) -> Result<Properties, FontLoadingError> {
+        let os2_table = self.load_table(OS2_TABLE_TAG)?;
+        let head_table = self.load_table(HEAD_TABLE_TAG)?;
+        let mut properties = properties(
+            os2_table.as_ref().map(|table| &table[..]),
+            head_table.as_ref().map(|table| &table[..]),
+        );
+        if let Style::Oblique(ref mut angle) = properties.style {
+            if let Some(post_table) = self.load_table(post::POST_TABLE_TAG)? {
+                *angle = post::oblique_angle(&post_table).unwrap_or(*angle);
+            }
+        }
+        Ok(properties)
+    }
+}
+
+/// Computes the CSS properties of a font from its `OS/2` and `head` tables, the same way that
+/// FreeType does.
+fn properties(os2_table: Option<&[u8]>, head_table: Option<&[u8]>) -> Properties {
+    let os2_field = |offset: usize| {
+        os2_table
+            .and_then(|os2_table| os2_table.get(offset..(offset + 2)))
+            .map(BigEndian::read_u16)
+    };
+
+    let style = match os2_field(62) {
+        Some(fs_selection) if (fs_selection & OS2_FS_SELECTION_OBLIQUE) != 0 => {
+            Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE)
+        }
+        Some(fs_selection) if (fs_selection & OS2_FS_SELECTION_ITALIC) != 0 => Style::Italic,
+        Some(_) => Style::Normal,
+        None => match head_table.and_then(|head_table| head_table.get(44..46)) {
+            Some(mac_style) if (BigEndian::read_u16(mac_style) & HEAD_MAC_STYLE_ITALIC) != 0 => {
+                Style::Italic
+            }
+            _ => Style::Normal,
+        },
+    };
+    let stretch = match os2_field(6) {
+        Some(width_class) => Stretch::from_uswidth_class(width_class),
+        None => Stretch::NORMAL,
+    };
+    let weight = match os2_field(4) {
+        Some(weight_class) => Weight::from(weight_class),
+        None => Weight::NORMAL,
+    };
+    Properties {
+        style,
+        weight,
+        stretch,
+    }
+}
+
+/// Returns the family name from a `name` table, preferring the typographic family name.
+pub(crate) fn family_name(name_table: &[u8]) -> Option<String> {
+    find_name(name_table, NAME_ID_TYPOGRAPHIC_FAMILY)
+        .or_else(|| find_name(name_table, NAME_ID_FONT_FAMILY))
+}
+
+/// Returns the full name from a `name` table.
+pub(crate) fn full_name(name_table: &[u8]) -> Option<String> {
+    find_name(name_table, NAME_ID_FULL_NAME)
+}
+
+/// Returns the pairs of family and style names from a `name` table: the typographic pair, if the
+/// font has one, followed by the legacy pair, which groups at most four styles into a family.
+///
+/// The family and style of each pair come from records with the same platform, encoding, and
+/// language, so fonts that don't name every style in every language don't get names mixed up.
+pub(crate) fn families_and_styles(name_table: &[u8]) -> Vec<(String, String)> {
+    let records = name_records(name_table);
+    let typographic_pair = find_name_pair(
+        &records,
+        &[NAME_ID_TYPOGRAPHIC_FAMILY, NAME_ID_FONT_FAMILY],
+        &[NAME_ID_TYPOGRAPHIC_SUBFAMILY, NAME_ID_FONT_SUBFAMILY],
+    );
+    let legacy_pair = find_name_pair(&records, &[NAME_ID_FONT_FAMILY], &[NAME_ID_FONT_SUBFAMILY]);
+
+    let mut pairs = vec![];
+    pairs.extend(typographic_pair);
+    if let Some(pair) = legacy_pair {
+        if !pairs.contains(&pair) {
+            pairs.push(pair);
+        }
+    }
+    pairs
+}
+
+/// Returns the PostScript name from a `name` table.
+pub(crate) fn postscript_name(name_table: &[u8]) -> Option<String> {
+    find_name(name_table, NAME_ID_POSTSCRIPT_NAME)
+}
+
+// A name from a `name` table, in a platform and encoding that can be decoded.
+struct NameRecord {
+    name_id: u16,
+    // The platform, encoding, and language IDs.
+    key: (u16, u16, u16),
+    // How preferable the platform, encoding, and language are; lower is better.
+    rank: u32,
+    name: String,
+}
+
+// Looks up the name with the given ID, preferring US English Windows names, then any other Unicode
+// names, then Macintosh Roman names.
+fn find_name(name_table: &[u8], name_id: u16) -> Option<String> {
+    name_records(name_table)
+        .into_iter()
+        .filter(|record| record.name_id == name_id)
+        .min_by_key(|record| record.rank)
+        .map(|record| record.name)
+}
+
+// Looks up a family name and a style name from records with the same platform, encoding, and
+// language, preferring them in the same order as `find_name`. Within those records, name IDs that
+// come earlier in the lists are preferred.
+fn find_name_pair(
+    records: &[NameRecord],
+    family_name_ids: &[u16],
+    style_name_ids: &[u16],
+) -> Option<(String, String)> {
+    let name_with_key = |name_ids: &[u16], key| {
+        name_ids.iter().find_map(|&name_id| {
+            records
+                .iter()
+                .find(|record| record.name_id == name_id && record.key == key)
+                .map(|record| record.name.clone())
+        })
+    };
+    records
+        .iter()
+        .filter(|record| family_name_ids.contains(&record.name_id))
+        .filter_map(|record| {
+            let family = name_with_key(family_name_ids, record.key)?;
+            let style = name_with_key(style_name_ids, record.key)?;
+            Some((record.rank, family, style))
+        })
+        .min_by_key(|&(rank, _, _)| rank)
+        .map(|(_, family, style)| (family, style))
+}
+
+// Decodes the names of a `name` table that are Windows Unicode, Unicode, or Macintosh Roman
+// strings, in the order of their records.
+fn name_records(name_table: &[u8]) -> Vec<NameRecord> {
+    let mut records = vec![];
+    let (record_count, storage_offset) = match (read_u16(name_table, 2), read_u16(name_table, 4)) {
+        (Some(record_count), Some(storage_offset)) => {
+            (record_count as usize, storage_offset as usize)
+        }
+        _ => return records,
+    };
+
+    for record_index in 0..record_count {
+        let record_offset = 6 + record_index * 12;
+        let record = match name_table.get(record_offset..(record_offset + 12)) {
+            Some(record) => record,
+            None => break,
+        };
+        let (platform_id, encoding_id, language_id) = (
+            BigEndian::read_u16(&record[0..2]),
+            BigEndian::read_u16(&record[2..4]),
+            BigEndian::read_u16(&record[4..6]),
+        );
+        let rank = match (platform_id, encoding_id, language_id) {
+            (3, 1, 0x0409) | (3, 10, 0x0409) => 0,
+            (3, 1, _) | (3, 10, _) => 1,
+            (0, _, _) => 2,
+            (1, 0, _) => 3,
+            _ => continue,
+        };
+
+        let string_start = storage_offset + BigEndian::read_u16(&record[10..12]) as usize;
+        let string_end = string_start + BigEndian::read_u16(&record[8..10]) as usize;
+        let bytes = match name_table.get(string_start..string_end) {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let name = if platform_id == 1 {
+            // Non-ASCII Mac Roman characters are rare in family names; approximate them as Latin-1.
+            bytes.iter().map(|&byte| byte as char).collect()
+        } else {
+            let code_units: Vec<u16> = bytes
+                .chunks(2)
+                .filter(|unit| unit.len() == 2)
+                .map(BigEndian::read_u16)
+                .collect();
+            String::from_utf16_lossy(&code_units)
+        };
+        records.push(NameRecord {
+            name_id: BigEndian::read_u16(&record[6..8]),
+            key: (platform_id, encoding_id, language_id),
+            rank,
+            name,
+        });
+    }
+    records
+}
+
+// Reads exactly enough bytes to fill `buffer` from the given offset, reporting truncated data as a
+// parse error.
+fn read_exact_at<R>(reader: &mut R, offset: u64, buffer: &mut [u8]) -> Result<(), FontLoadingError>
+where
+    R: Read + Seek,
+{
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(buffer).map_err(|error| {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            FontLoadingError::Parse
+        } else {
+            FontLoadingError::Io(error)
+        }
+    })
+}
+
+/// Reads the byte at the given offset of a table, or returns `None` if it's out of bounds.
+pub(crate) fn read_u8(table: &[u8], offset: usize) -> Option<u8> {
+    table.get(offset).cloned()
+}
+
+/// Reads a big-endian 16-bit value at the given offset of a table, or returns `None` if it's out
+/// of bounds.
+pub(crate) fn read_u16(table: &[u8], offset: usize) -> Option<u16> {
+    table
+        .get(offset..offset.checked_add(2)?)
+        .map(BigEndian::read_u16)
+}
+
+/// Reads a big-endian 32-bit value at the given offset of a table, or returns `None` if it's out
+/// of bounds.
+pub(crate) fn read_u32(table: &[u8], offset: usize) -> Option<u32> {
+    table
+        .get(offset..offset.checked_add(4)?)
+        .map(BigEndian::read_u32)
+}
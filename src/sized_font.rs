@@ -0,0 +1,141 @@
+// font-kit/src/sized_font.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A font together with the point size to draw it at.
+
+use euclid::default::{Point2D, Rect, Vector2D};
+
+use crate::canvas::{AlphaMode, Canvas, RasterizationOptions};
+use crate::error::GlyphLoadingError;
+use crate::font::Font;
+use crate::hinting::HintingOptions;
+use crate::loader::FontTransform;
+use crate::metrics::LineMetrics;
+
+/// A font together with the point size to draw it at.
+///
+/// The methods of this type are those of `Font` that take a point size, and they pass the stored
+/// point size along. This is convenient when many glyphs are drawn at the same size.
+#[derive(Clone, Debug)]
+pub struct SizedFont {
+    /// The font.
+    pub font: Font,
+    /// The size to draw the font at, in points.
+    pub point_size: f32,
+}
+
+impl SizedFont {
+    /// Creates a sized font that draws the given font at the given size.
+    #[inline]
+    pub fn new(font: Font, point_size: f32) -> SizedFont {
+        SizedFont { font, point_size }
+    }
+
+    /// Returns a copy of this font that draws at a different size.
+    #[inline]
+    pub fn with_point_size(&self, point_size: f32) -> SizedFont {
+        SizedFont::new(self.font.clone(), point_size)
+    }
+
+    /// Returns the factor that converts font units to pixels at this size.
+    #[inline]
+    pub fn scale_factor(&self) -> f32 {
+        self.font.metrics().scale_factor(self.point_size)
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in pixels
+    /// at this size.
+    #[inline]
+    pub fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        self.font.advance_pixels(glyph_id, self.point_size)
+    }
+
+    /// Returns the ascent, descent, line gap, and line height of the font, in pixels at this size.
+    #[inline]
+    pub fn line_metrics(&self) -> LineMetrics {
+        self.font.line_metrics(self.point_size)
+    }
+
+    /// Returns the bounds of a run of glyphs laid out left to right, in pixels at this size.
+    ///
+    /// See `Loader::run_bounds`.
+    #[inline]
+    pub fn run_bounds(&self, glyphs: &[u32]) -> Result<Rect<f32>, GlyphLoadingError> {
+        self.font.run_bounds(glyphs, self.point_size)
+    }
+
+    /// Returns the pixel boundaries that the glyph will take up when rendered at this size with
+    /// the given transform and origin.
+    #[inline]
+    pub fn raster_bounds(
+        &self,
+        glyph_id: u32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<Rect<i32>, GlyphLoadingError> {
+        self.font.raster_bounds(
+            glyph_id,
+            self.point_size,
+            transform,
+            origin,
+            hinting_options,
+            rasterization_options,
+        )
+    }
+
+    /// Rasterizes a glyph at this size to a canvas with the given transform and origin.
+    ///
+    /// See `Loader::rasterize_glyph`.
+    #[inline]
+    pub fn rasterize_glyph(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
+    ) -> Result<(), GlyphLoadingError> {
+        self.font.rasterize_glyph(
+            canvas,
+            glyph_id,
+            self.point_size,
+            transform,
+            origin,
+            hinting_options,
+            rasterization_options,
+            alpha_mode,
+        )
+    }
+
+    /// Rasterizes a glyph at this size and a quantized horizontal subpixel offset to a new canvas
+    /// that tightly fits it.
+    ///
+    /// See `Loader::rasterize_glyph_subpixel`.
+    #[inline]
+    pub fn rasterize_glyph_subpixel(
+        &self,
+        glyph_id: u32,
+        subpixel_x: u8,
+        subpixel_bits: u8,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(Canvas, Point2D<i32>), GlyphLoadingError> {
+        self.font.rasterize_glyph_subpixel(
+            glyph_id,
+            self.point_size,
+            subpixel_x,
+            subpixel_bits,
+            rasterization_options,
+        )
+    }
+}
@@ -10,15 +10,21 @@
 
 //! A database of installed fonts that can be queried.
 
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
 use crate::error::SelectionError;
-use crate::family::Family;
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::font::Font;
 use crate::handle::Handle;
 use crate::matching;
-use crate::properties::Properties;
+use crate::properties::{Properties, PropertiesFilter};
+use crate::sfnt;
+use crate::utils;
 
+#[cfg(all(target_os = "android", not(feature = "source-fontconfig-default")))]
+pub use crate::sources::android::AndroidSource as SystemSource;
 #[cfg(all(
     any(target_os = "macos", target_os = "ios"),
     not(feature = "loader-freetype-default")
@@ -37,83 +43,331 @@ pub use crate::sources::directwrite::DirectWriteSource as SystemSource;
     feature = "source-fontconfig-default"
 ))]
 pub use crate::sources::fontconfig::FontconfigSource as SystemSource;
-#[cfg(all(target_os = "android", not(feature = "source-fontconfig-default")))]
-pub use crate::sources::fs::FsSource as SystemSource;
 
-// FIXME(pcwalton): These could expand to multiple fonts, and they could be language-specific.
+// FIXME(pcwalton): These could be language-specific.
+//
+// Each generic family stands for the first of these families that is installed, or for all of them
+// when ranking matches. Sources can override this with `Source::generic_family_names`.
 #[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_SERIF: &'static str = "Times New Roman";
+const DEFAULT_FONT_FAMILIES_SERIF: &[&str] = &["Times New Roman", "Times", "Georgia"];
 #[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_SANS_SERIF: &'static str = "Arial";
+const DEFAULT_FONT_FAMILIES_SANS_SERIF: &[&str] = &["Arial", "Helvetica", "Verdana"];
 #[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_MONOSPACE: &'static str = "Courier New";
+const DEFAULT_FONT_FAMILIES_MONOSPACE: &[&str] = &["Courier New", "Courier", "Consolas", "Menlo"];
 #[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_CURSIVE: &'static str = "Comic Sans MS";
+const DEFAULT_FONT_FAMILIES_CURSIVE: &[&str] = &["Comic Sans MS", "Apple Chancery"];
 #[cfg(target_family = "windows")]
-const DEFAULT_FONT_FAMILY_FANTASY: &'static str = "Impact";
+const DEFAULT_FONT_FAMILIES_FANTASY: &[&str] = &["Impact"];
 #[cfg(any(target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_FANTASY: &'static str = "Papyrus";
-
-#[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_SERIF: &'static str = "serif";
-#[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_SANS_SERIF: &'static str = "sans-serif";
-#[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_MONOSPACE: &'static str = "monospace";
-#[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_CURSIVE: &'static str = "cursive";
-#[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_FANTASY: &'static str = "fantasy";
+const DEFAULT_FONT_FAMILIES_FANTASY: &[&str] = &["Papyrus", "Impact"];
+
+// These are the families that Android's `fonts.xml` aliases the generic names to.
+#[cfg(target_os = "android")]
+const DEFAULT_FONT_FAMILIES_SERIF: &[&str] = &["Noto Serif", "Droid Serif"];
+#[cfg(target_os = "android")]
+const DEFAULT_FONT_FAMILIES_SANS_SERIF: &[&str] = &["Roboto", "Noto Sans", "Droid Sans"];
+#[cfg(target_os = "android")]
+const DEFAULT_FONT_FAMILIES_MONOSPACE: &[&str] = &["Droid Sans Mono", "Cutive Mono"];
+#[cfg(target_os = "android")]
+const DEFAULT_FONT_FAMILIES_CURSIVE: &[&str] = &["Dancing Script"];
+#[cfg(target_os = "android")]
+const DEFAULT_FONT_FAMILIES_FANTASY: &[&str] = &["Coming Soon", "Roboto"];
+
+// Fontconfig understands the generic names itself; the other families are for sources that don't.
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const DEFAULT_FONT_FAMILIES_SERIF: &[&str] =
+    &["serif", "DejaVu Serif", "Liberation Serif", "Noto Serif"];
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const DEFAULT_FONT_FAMILIES_SANS_SERIF: &[&str] =
+    &["sans-serif", "DejaVu Sans", "Liberation Sans", "Noto Sans"];
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const DEFAULT_FONT_FAMILIES_MONOSPACE: &[&str] = &[
+    "monospace",
+    "DejaVu Sans Mono",
+    "Liberation Mono",
+    "Noto Sans Mono",
+];
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const DEFAULT_FONT_FAMILIES_CURSIVE: &[&str] = &["cursive"];
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+const DEFAULT_FONT_FAMILIES_FANTASY: &[&str] = &["fantasy"];
+
+// Style names that some people append to the name of a family to mean its regular face.
+const REGULAR_STYLE_SUFFIXES: &[&str] = &["regular", "normal", "book"];
 
 /// A database of installed fonts that can be queried.
 ///
 /// This trait is object-safe.
 pub trait Source {
     /// Returns paths of all fonts installed on the system.
-    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError>;
+    ///
+    /// The default implementation collects the fonts in every family returned by
+    /// `all_families`.
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for family_name in self.all_families()? {
+            if let Ok(family_handle) = self.select_family_by_name(&family_name) {
+                handles.extend(family_handle.fonts().iter().cloned());
+            }
+        }
+        Ok(handles)
+    }
 
     /// Returns the names of all families installed on the system.
     fn all_families(&self) -> Result<Vec<String>, SelectionError>;
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// The comparison is case-insensitive for all of Unicode, not just ASCII.
     fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError>;
 
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    ///
+    /// `select_family_by_name` is tried first, so any name that it resolves gives the same family
+    /// here. Otherwise, the names returned by `all_families` are compared ignoring case,
+    /// whitespace, hyphens, and underscores, and a trailing style name such as "Regular" is
+    /// dropped from both sides.
+    fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        match self.select_family_by_name(family_name) {
+            Err(SelectionError::NotFound) => {}
+            result => return result,
+        }
+
+        let fuzzy_name = fuzzy_family_name(family_name);
+        if fuzzy_name.is_empty() {
+            return Err(SelectionError::NotFound);
+        }
+        match self
+            .all_families()?
+            .into_iter()
+            .find(|name| fuzzy_family_name(name) == fuzzy_name)
+        {
+            Some(name) => self.select_family_by_name(&name),
+            None => Err(SelectionError::NotFound),
+        }
+    }
+
     /// Selects a font by PostScript name, which should be a unique identifier.
     ///
-    /// The default implementation, which is used by the DirectWrite and the filesystem backends,
-    /// does a brute-force search of installed fonts to find the one that matches.
+    /// The default implementation, which is used by the filesystem backend, does a brute-force
+    /// search of the fonts returned by `all_fonts` to find the one that matches. Only the `name`
+    /// table of each font is read, except for fonts in formats other than TrueType and OpenType,
+    /// which have to be loaded.
     fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
-        // TODO(pcwalton): Optimize this by searching for families with similar names first.
+        for handle in self.all_fonts()? {
+            if peek_postscript_name(&handle).as_ref().map(|name| &name[..]) == Some(postscript_name)
+            {
+                return Ok(handle);
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    ///
+    /// Matching is case-insensitive, and a family name followed by a style name is accepted even
+    /// if the font spells its full name differently. The default implementation, like that of
+    /// `select_by_postscript_name`, does a brute-force search of the fonts returned by
+    /// `all_fonts`.
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        select_by_face_names_by_scanning(self, |face_names| face_names.matches_full_name(full_name))
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    ///
+    /// Both the typographic and the legacy naming of the font are accepted, as is a font whose
+    /// full name is the family name followed by the style name. Matching is case-insensitive. The
+    /// default implementation does a brute-force search of the fonts returned by `all_fonts`.
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        select_by_face_names_by_scanning(self, |face_names| {
+            face_names.matches_family_and_style(family, style)
+        })
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    ///
+    /// Returns `SelectionError::NotFound` if no font matches. The default implementation, like
+    /// that of `select_by_postscript_name`, does a brute-force search of the fonts returned by
+    /// `all_fonts`.
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for handle in self.all_fonts()? {
+            if let Some(font_postscript_name) = peek_postscript_name(&handle) {
+                if font_postscript_name.starts_with(prefix) {
+                    handles.push(handle);
+                }
+            }
+        }
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    ///
+    /// This is useful for building font pickers. Returns `SelectionError::NotFound` if no font
+    /// matches. The default implementation reads the properties of each font returned by
+    /// `all_fonts` from its `OS/2`, `head`, and `post` tables, only loading fonts in other
+    /// formats.
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        filter_by_properties(self.all_fonts()?, filter)
+    }
+
+    /// Selects a font that has a glyph for the given character, preferring fonts whose properties
+    /// match `properties` most closely.
+    ///
+    /// This is useful for finding a font to draw text with when none of the fonts requested for it
+    /// support some character. The `locale` argument is a language tag such as `"en-US"` or
+    /// `"zh-Hans-CN"`; backends that understand it use it to choose among fonts that support the
+    /// character, which matters for e.g. Han ideographs.
+    ///
+    /// The default implementation, which is used by the filesystem backend, ignores `locale` and
+    /// loads every font returned by `all_fonts` to check its coverage.
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        _locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        select_fallback_for_char_by_scanning(self, character, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    ///
+    /// A family covers a character if any of its fonts has a glyph for it. This is useful for
+    /// finding fonts to draw text with when none of the fonts requested for it support all of its
+    /// characters. Returns `SelectionError::NotFound` if no family covers any of the characters.
+    ///
+    /// The default implementation, which is used by the filesystem backend, loads every font of
+    /// every family returned by `all_families` to check its coverage. Families that cover equally
+    /// many characters keep the order of `all_families`.
+    fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        let mut families = vec![];
         for family_name in self.all_families()? {
-            if let Ok(family_handle) = self.select_family_by_name(&family_name) {
-                if let Ok(family) = Family::<Font>::from_handle(&family_handle) {
-                    for (handle, font) in family_handle.fonts().iter().zip(family.fonts().iter()) {
-                        if let Some(font_postscript_name) = font.postscript_name() {
-                            if font_postscript_name == postscript_name {
-                                return Ok((*handle).clone());
-                            }
-                        }
-                    }
+            let family_handle = match self.select_family_by_name(&family_name) {
+                Ok(family_handle) => family_handle,
+                Err(_) => continue,
+            };
+            let mut covered_characters = BTreeSet::new();
+            for handle in family_handle.fonts() {
+                if let Ok(font) = Font::from_handle(handle) {
+                    covered_characters.extend(
+                        characters
+                            .iter()
+                            .cloned()
+                            .filter(|&character| font.glyph_for_char(character).is_some()),
+                    );
                 }
             }
+            families.push((covered_characters.len(), family_handle));
         }
-        Err(SelectionError::NotFound)
+        sort_families_by_coverage(families)
+    }
+
+    /// Returns the names of the families that a generic family stands for, in order of
+    /// preference. A specific family just stands for itself.
+    ///
+    /// Generic families are matched by trying each of these families in turn, skipping those that
+    /// aren't installed. The default implementation returns a fixed list of families that usually
+    /// come with the platform.
+    fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        default_generic_family_names(family_name)
     }
 
-    // FIXME(pcwalton): This only returns one family instead of multiple families for the generic
-    // family names.
     #[doc(hidden)]
     fn select_family_by_generic_name(
         &self,
         family_name: &FamilyName,
     ) -> Result<FamilyHandle, SelectionError> {
         match *family_name {
-            FamilyName::Title(ref title) => self.select_family_by_name(title),
-            FamilyName::Serif => self.select_family_by_name(DEFAULT_FONT_FAMILY_SERIF),
-            FamilyName::SansSerif => self.select_family_by_name(DEFAULT_FONT_FAMILY_SANS_SERIF),
-            FamilyName::Monospace => self.select_family_by_name(DEFAULT_FONT_FAMILY_MONOSPACE),
-            FamilyName::Cursive => self.select_family_by_name(DEFAULT_FONT_FAMILY_CURSIVE),
-            FamilyName::Fantasy => self.select_family_by_name(DEFAULT_FONT_FAMILY_FANTASY),
+            FamilyName::Title(ref title) => self.select_family_by_name(title).or_else(|_| {
+                // Not every backend compares family names case-insensitively, so look for a
+                // family whose name differs only in case.
+                let folded_title = utils::fold_case(title);
+                let family_name = self
+                    .all_families()?
+                    .into_iter()
+                    .find(|family_name| utils::fold_case(family_name) == folded_title)
+                    .ok_or(SelectionError::NotFound)?;
+                self.select_family_by_name(&family_name)
+            }),
+            _ => self
+                .generic_family_names(family_name)
+                .iter()
+                .filter_map(|family_name| self.select_family_by_name(family_name).ok())
+                .next()
+                .ok_or(SelectionError::NotFound),
+        }
+    }
+
+    /// Like `select_family_by_generic_name`, but expands generic families to every installed
+    /// family that they stand for, in order of preference.
+    ///
+    /// The first family is always the one that `select_family_by_generic_name` returns.
+    #[doc(hidden)]
+    fn select_families_by_generic_name(
+        &self,
+        family_name: &FamilyName,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        let family_handles: Vec<_> = match *family_name {
+            FamilyName::Title(_) => vec![self.select_family_by_generic_name(family_name)?],
+            _ => self
+                .generic_family_names(family_name)
+                .iter()
+                .filter_map(|family_name| self.select_family_by_name(family_name).ok())
+                .collect(),
+        };
+        if family_handles.is_empty() {
+            Err(SelectionError::NotFound)
+        } else {
+            Ok(family_handles)
         }
     }
 
@@ -136,6 +390,59 @@ pub trait Source {
         Err(SelectionError::NotFound)
     }
 
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    ///
+    /// Candidates are grouped by family, in the order that the families are listed, with generic
+    /// families expanded to every family that they stand for on this platform. Within a family,
+    /// fonts are ranked by how closely they match `properties`, and fonts that match equally well
+    /// keep the order that the source lists them in. The first handle is the one that
+    /// `select_best_match` returns.
+    ///
+    /// A family that has no font close to `properties` still contributes its fonts ahead of the
+    /// families after it, so callers that need a glyph which the best match lacks can try each
+    /// handle in turn.
+    fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for family_name in family_names {
+            if let Ok(family_handles) = self.select_families_by_generic_name(family_name) {
+                for family_handle in family_handles {
+                    let candidates = self.select_descriptions_in_family(&family_handle)?;
+                    handles.extend(
+                        matching::rank_matches(&candidates, properties)
+                            .into_iter()
+                            .map(|index| family_handle.fonts[index].clone()),
+                    );
+                }
+            }
+        }
+        if handles.is_empty() {
+            Err(SelectionError::NotFound)
+        } else {
+            Ok(handles)
+        }
+    }
+
+    /// Returns a number that increases whenever the fonts that this source contains change, such
+    /// as when the user installs or removes a font.
+    ///
+    /// This is meant to be polled, for example once per frame or whenever the application regains
+    /// focus; when the number changes, results obtained from the source earlier may be stale.
+    /// Sources that keep an index of their fonts bring it up to date as part of the check. The
+    /// default implementation returns 0, which suits sources whose fonts never change.
+    ///
+    /// How much a check costs depends on the source. Sources that ask the system for its fonts
+    /// are usually cheap to poll, but `FsSource` and `AndroidSource` look at every file in their
+    /// directories, which is I/O proportional to the number of installed fonts, so they should be
+    /// polled less often, such as on focus changes rather than every frame.
+    fn generation(&self) -> u64 {
+        0
+    }
+
     #[doc(hidden)]
     fn select_descriptions_in_family(
         &self,
@@ -149,3 +456,233 @@ pub trait Source {
         Ok(fields)
     }
 }
+
+// Returns the families from the built-in list for the platform that a generic family stands for,
+// or just the family itself if it's specific.
+pub(crate) fn default_generic_family_names(family_name: &FamilyName) -> Vec<String> {
+    let family_names = match *family_name {
+        FamilyName::Title(ref title) => return vec![title.clone()],
+        FamilyName::Serif => DEFAULT_FONT_FAMILIES_SERIF,
+        FamilyName::SansSerif => DEFAULT_FONT_FAMILIES_SANS_SERIF,
+        FamilyName::Monospace => DEFAULT_FONT_FAMILIES_MONOSPACE,
+        FamilyName::Cursive => DEFAULT_FONT_FAMILIES_CURSIVE,
+        FamilyName::Fantasy => DEFAULT_FONT_FAMILIES_FANTASY,
+    };
+    family_names
+        .iter()
+        .map(|&family_name| family_name.to_owned())
+        .collect()
+}
+
+// Reads the PostScript name of a font from its `name` table, falling back to loading the font if
+// that fails.
+fn peek_postscript_name(handle: &Handle) -> Option<String> {
+    match handle.postscript_name() {
+        Ok(postscript_name) => Some(postscript_name),
+        Err(_) => Font::from_handle(handle).ok()?.postscript_name(),
+    }
+}
+
+// Keeps the fonts whose properties satisfy `filter`, reading them from the font tables where
+// possible. Returns `SelectionError::NotFound` if none do.
+pub(crate) fn filter_by_properties(
+    handles: Vec<Handle>,
+    filter: &PropertiesFilter,
+) -> Result<Vec<Handle>, SelectionError> {
+    let handles: Vec<_> = handles
+        .into_iter()
+        .filter(
+            |handle| match peek_properties(handle, filter.monospace.is_some()) {
+                Some((properties, is_monospace)) => filter.matches(&properties, is_monospace),
+                None => false,
+            },
+        )
+        .collect();
+    if !handles.is_empty() {
+        Ok(handles)
+    } else {
+        Err(SelectionError::NotFound)
+    }
+}
+
+// Reads the properties of a font, and whether it's monospace if `needs_monospace` is set, falling
+// back to loading the font if that fails.
+fn peek_properties(handle: &Handle, needs_monospace: bool) -> Option<(Properties, bool)> {
+    let properties = handle.properties();
+    let is_monospace = if needs_monospace {
+        handle.is_monospace()
+    } else {
+        Ok(false)
+    };
+    match (properties, is_monospace) {
+        (Ok(properties), Ok(is_monospace)) => Some((properties, is_monospace)),
+        _ => {
+            let font = Font::from_handle(handle).ok()?;
+            Some((font.properties(), font.is_monospace()))
+        }
+    }
+}
+
+// Reduces a family name to the form that `select_family_by_name_fuzzy` compares: case-folded,
+// without a trailing regular style name, and without separators.
+pub(crate) fn fuzzy_family_name(family_name: &str) -> String {
+    let is_separator =
+        |character: char| character.is_whitespace() || character == '-' || character == '_';
+
+    let mut name = utils::fold_case(family_name.trim());
+    for suffix in REGULAR_STYLE_SUFFIXES {
+        if !name.ends_with(suffix) {
+            continue;
+        }
+        let stem_length = name[..(name.len() - suffix.len())]
+            .trim_end_matches(is_separator)
+            .len();
+        if stem_length > 0 && stem_length < name.len() - suffix.len() {
+            name.truncate(stem_length);
+            break;
+        }
+    }
+    name.chars()
+        .filter(|&character| !is_separator(character))
+        .collect()
+}
+
+// Finds the first font in `source` whose names satisfy `predicate`.
+pub(crate) fn select_by_face_names_by_scanning<S, F>(
+    source: &S,
+    predicate: F,
+) -> Result<Handle, SelectionError>
+where
+    S: Source + ?Sized,
+    F: Fn(&FaceNames) -> bool,
+{
+    for handle in source.all_fonts()? {
+        if let Some(face_names) = peek_face_names(&handle) {
+            if predicate(&face_names) {
+                return Ok(handle);
+            }
+        }
+    }
+    Err(SelectionError::NotFound)
+}
+
+// Reads the names of a font from its `name` table, falling back to loading the font if that fails.
+fn peek_face_names(handle: &Handle) -> Option<FaceNames> {
+    if let Ok(name_table) = handle.name_table() {
+        let full_names = sfnt::full_name(&name_table).into_iter().collect();
+        let families_and_styles = sfnt::families_and_styles(&name_table);
+        if !families_and_styles.is_empty() {
+            return Some(FaceNames::new(full_names, families_and_styles));
+        }
+    }
+
+    // Loaders don't report style names, so take whatever follows the family name in the full
+    // name.
+    let font = Font::from_handle(handle).ok()?;
+    let (full_name, family_name) = (font.full_name(), font.family_name());
+    let style_name = match full_name.get(family_name.len()..) {
+        Some(style_name)
+            if full_name.starts_with(&family_name) && !style_name.trim().is_empty() =>
+        {
+            style_name.trim().to_owned()
+        }
+        _ => "Regular".to_owned(),
+    };
+    Some(FaceNames::new(
+        vec![full_name],
+        vec![(family_name, style_name)],
+    ))
+}
+
+/// The names that `select_by_full_name` and `select_by_family_and_style` match against.
+pub(crate) struct FaceNames {
+    // Case-folded full names, including the concatenation of each family and style name.
+    full_names: Vec<String>,
+    // Case-folded pairs of family and style names.
+    families_and_styles: Vec<(String, String)>,
+}
+
+impl FaceNames {
+    /// Collects the names of a font. Each family name followed by its style name is accepted as a
+    /// full name too, as is the family name alone if the style name is "Regular".
+    pub(crate) fn new(
+        full_names: Vec<String>,
+        families_and_styles: Vec<(String, String)>,
+    ) -> FaceNames {
+        let families_and_styles: Vec<_> = families_and_styles
+            .iter()
+            .map(|(family, style)| (utils::fold_case(family), utils::fold_case(style)))
+            .collect();
+        let mut full_names: Vec<_> = full_names
+            .iter()
+            .map(|full_name| utils::fold_case(full_name))
+            .collect();
+        for (family, style) in &families_and_styles {
+            full_names.push(format!("{} {}", family, style));
+            if style == "regular" {
+                full_names.push(family.clone());
+            }
+        }
+        FaceNames {
+            full_names,
+            families_and_styles,
+        }
+    }
+
+    /// Returns true if the font goes by the given full name.
+    pub(crate) fn matches_full_name(&self, full_name: &str) -> bool {
+        let full_name = utils::fold_case(full_name.trim());
+        self.full_names.contains(&full_name)
+    }
+
+    /// Returns true if the font goes by the given family and style names.
+    pub(crate) fn matches_family_and_style(&self, family: &str, style: &str) -> bool {
+        let (family, style) = (
+            utils::fold_case(family.trim()),
+            utils::fold_case(style.trim()),
+        );
+        self.families_and_styles
+            .iter()
+            .any(|pair| pair.0 == family && pair.1 == style)
+            || self.full_names.contains(&format!("{} {}", family, style))
+    }
+}
+
+// Orders families by the number of characters that they cover, most first and otherwise keeping
+// their order, and drops those that cover none.
+pub(crate) fn sort_families_by_coverage(
+    mut families: Vec<(usize, FamilyHandle)>,
+) -> Result<Vec<FamilyHandle>, SelectionError> {
+    families.retain(|&(covered_count, _)| covered_count > 0);
+    if families.is_empty() {
+        return Err(SelectionError::NotFound);
+    }
+    families.sort_by_key(|&(covered_count, _)| Reverse(covered_count));
+    Ok(families
+        .into_iter()
+        .map(|(_, family_handle)| family_handle)
+        .collect())
+}
+
+// Finds the font that best matches `properties` among all the fonts in `source` that have a glyph
+// for `character`.
+pub(crate) fn select_fallback_for_char_by_scanning<S>(
+    source: &S,
+    character: char,
+    properties: &Properties,
+) -> Result<Handle, SelectionError>
+where
+    S: Source + ?Sized,
+{
+    let (mut handles, mut candidates) = (vec![], vec![]);
+    for handle in source.all_fonts()? {
+        if let Ok(font) = Font::from_handle(&handle) {
+            if font.glyph_for_char(character).is_some() {
+                candidates.push(font.properties());
+                handles.push(handle);
+            }
+        }
+    }
+    let index = matching::find_best_match(&candidates, properties)?;
+    Ok(handles.swap_remove(index))
+}
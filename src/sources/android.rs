@@ -0,0 +1,850 @@
+// font-kit/src/sources/android.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A source that loads the fonts that Android's font configuration declares.
+//!
+//! Android lists its fonts in `/system/etc/fonts.xml`, or before Android 5.0 in
+//! `/system/etc/system_fonts.xml` and `/system/etc/fallback_fonts.xml`. These files name the
+//! families that apps ask for, such as "sans-serif" and "monospace", give them aliases, and order
+//! the families that are searched for characters that the default family lacks.
+//!
+//! This is the native source on Android.
+
+use log::warn;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::SelectionError;
+use crate::family_handle::FamilyHandle;
+use crate::family_name::FamilyName;
+use crate::font::{FaceIndex, Font};
+use crate::handle::Handle;
+use crate::matching;
+use crate::properties::{Properties, PropertiesFilter, Stretch, Style, Weight};
+use crate::source::{self, Source};
+use crate::sources::fs::FsSource;
+use crate::utils;
+
+const FONTS_XML_PATH: &str = "/system/etc/fonts.xml";
+const LEGACY_FONTS_XML_PATHS: [&str; 2] = [
+    "/system/etc/system_fonts.xml",
+    "/system/etc/fallback_fonts.xml",
+];
+const FONT_DIRECTORY: &str = "/system/fonts";
+
+/// A source that loads the fonts that Android's font configuration declares.
+///
+/// The families and aliases that the configuration declares, such as "sans-serif" and
+/// "sans-serif-medium", come first when looking up families by name. Like `FsSource`, this source
+/// also finds every font in the font directory under the family name that the font itself claims.
+#[allow(missing_debug_implementations)]
+pub struct AndroidSource {
+    fs_source: FsSource,
+    // The declared families that have fonts, in the order of the configuration.
+    families: Vec<DeclaredFamily>,
+    aliases: Vec<Alias>,
+}
+
+// A family that a configuration file declares.
+struct DeclaredFamily {
+    // Families without names only serve as fallbacks.
+    name: Option<String>,
+    // The BCP 47 language tags of the text that the family is meant for, such as "ja" or
+    // "und-Arab".
+    languages: Vec<String>,
+    fonts: Vec<DeclaredFont>,
+}
+
+// A font in a declared family.
+struct DeclaredFont {
+    path: PathBuf,
+    font_index: FaceIndex,
+    // Configuration files older than Android 5.0 don't give the properties of fonts.
+    properties: Option<Properties>,
+}
+
+// Another name for a declared family, optionally for only its fonts of one weight.
+struct Alias {
+    name: String,
+    family_index: usize,
+    weight: Option<Weight>,
+}
+
+// An alias as a configuration file declares it, before the family it refers to is looked up.
+struct AliasDeclaration {
+    name: String,
+    to: String,
+    weight: Option<Weight>,
+}
+
+impl AndroidSource {
+    /// Opens the fonts that the system's font configuration declares, along with the other fonts
+    /// in `/system/fonts`.
+    ///
+    /// On devices without a readable configuration file, this finds the same fonts as `FsSource`.
+    pub fn new() -> AndroidSource {
+        let config_paths = if Path::new(FONTS_XML_PATH).is_file() {
+            vec![FONTS_XML_PATH]
+        } else {
+            LEGACY_FONTS_XML_PATHS
+                .iter()
+                .cloned()
+                .filter(|path| Path::new(path).is_file())
+                .collect()
+        };
+        AndroidSource::from_config_files(config_paths, FONT_DIRECTORY)
+    }
+
+    /// Opens the fonts that the given configuration files declare, looking for their files in
+    /// `font_directory`, along with the other fonts in that directory.
+    ///
+    /// Both the `fonts.xml` format of Android 5.0 and later and the older format of
+    /// `system_fonts.xml` and `fallback_fonts.xml` are understood. The files are read in order, so
+    /// that the families of later files are fallbacks for those of earlier ones. Files that can't
+    /// be read or parsed are skipped with a warning, and fonts whose files don't exist are left
+    /// out.
+    pub fn from_config_files<I, P, Q>(config_paths: I, font_directory: Q) -> AndroidSource
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let font_directory = font_directory.as_ref();
+        let (mut families, mut alias_declarations) = (vec![], vec![]);
+        for config_path in config_paths {
+            let config_path = config_path.as_ref();
+            let configuration = fs::read_to_string(config_path)
+                .ok()
+                .and_then(|xml| parse_font_configuration(&xml, font_directory));
+            match configuration {
+                Some((config_families, config_aliases)) => {
+                    families.extend(config_families);
+                    alias_declarations.extend(config_aliases);
+                }
+                None => warn!(
+                    "failed to read font configuration {}",
+                    config_path.display()
+                ),
+            }
+        }
+
+        for family in &mut families {
+            family.fonts.retain(|font| font.path.is_file());
+        }
+        families.retain(|family| !family.fonts.is_empty());
+        let aliases = alias_declarations
+            .into_iter()
+            .filter_map(|alias| {
+                let family_index = find_family(&families, &alias.to)?;
+                Some(Alias {
+                    name: alias.name,
+                    family_index,
+                    weight: alias.weight,
+                })
+            })
+            .collect();
+
+        AndroidSource {
+            fs_source: FsSource::in_path(font_directory),
+            families,
+            aliases,
+        }
+    }
+
+    /// Returns paths of all fonts installed on the system.
+    #[inline]
+    pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.fs_source.all_fonts()
+    }
+
+    /// Returns the names of all families installed on the system: the declared families and
+    /// aliases, followed by the families that the fonts claim.
+    pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        let mut family_names: Vec<String> = self
+            .families
+            .iter()
+            .filter_map(|family| family.name.clone())
+            .chain(self.aliases.iter().map(|alias| alias.name.clone()))
+            .collect();
+        for family_name in self.fs_source.all_families()? {
+            if !family_names.contains(&family_name) {
+                family_names.push(family_name);
+            }
+        }
+        Ok(family_names)
+    }
+
+    /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// Declared families and aliases are matched regardless of case. An alias with a weight, such
+    /// as "sans-serif-medium", stands for the fonts of its family that have that weight.
+    pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        if let Some(family_index) = find_family(&self.families, family_name) {
+            return Ok(self.families[family_index].family_handle(None));
+        }
+        if let Some(alias) = self.find_alias(family_name) {
+            // Aliases are families in their own right as far as `all_families` is concerned.
+            let mut family_handle = self.families[alias.family_index].family_handle(alias.weight);
+            family_handle.set_name(alias.name.clone());
+            return Ok(family_handle);
+        }
+        self.fs_source.select_family_by_name(family_name)
+    }
+
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
+    }
+
+    /// Selects a font by PostScript name, which should be a unique identifier.
+    #[inline]
+    pub fn select_by_postscript_name(
+        &self,
+        postscript_name: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.fs_source.select_by_postscript_name(postscript_name)
+    }
+
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    #[inline]
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.fs_source.select_by_full_name(full_name)
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    #[inline]
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.fs_source.select_by_family_and_style(family, style)
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    #[inline]
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.fs_source.select_by_postscript_name_prefix(prefix)
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    #[inline]
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.fs_source.select_by_properties(filter)
+    }
+
+    /// Selects a font that has a glyph for the given character, preferring fonts whose properties
+    /// match `properties` most closely.
+    ///
+    /// The families are searched in the order of the configuration's fallback chain: the default
+    /// family, which is the first one declared, and then each family without a name. A family
+    /// meant for `locale`, such as one declared with `lang="ja"` for `"ja-JP"`, takes precedence
+    /// over the families before it. If none of them has the character, every font is searched.
+    pub fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        let fallback_families: Vec<&DeclaredFamily> = self
+            .families
+            .iter()
+            .enumerate()
+            .filter(|&(family_index, family)| family_index == 0 || family.name.is_none())
+            .map(|(_, family)| family)
+            .collect();
+        let locale_families = fallback_families.iter().filter(|family| {
+            family
+                .languages
+                .iter()
+                .any(|language| language_suits_locale(language, locale))
+        });
+        for family in locale_families.chain(fallback_families.iter()) {
+            if let Some(handle) = family.select_font_for_char(character, properties) {
+                return Ok(handle);
+            }
+        }
+        source::select_fallback_for_char_by_scanning(self, character, properties)
+    }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handle.
+    #[inline]
+    pub fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        <Self as Source>::select_best_match(self, family_names, properties)
+    }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    #[inline]
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_matches(self, family_names, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    #[inline]
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        <Self as Source>::select_families_by_unicode_coverage(self, characters)
+    }
+
+    /// Returns the names of the families that a generic family stands for, in order of
+    /// preference. A specific family just stands for itself.
+    ///
+    /// The family that the configuration declares under the generic name, such as "sans-serif"
+    /// or "monospace", comes first, followed by the families that usually come with Android.
+    pub fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        let declared_family_name = match *family_name {
+            FamilyName::Title(ref title) => return vec![title.clone()],
+            FamilyName::Serif => "serif",
+            FamilyName::SansSerif => "sans-serif",
+            FamilyName::Monospace => "monospace",
+            FamilyName::Cursive => "cursive",
+            FamilyName::Fantasy => "fantasy",
+        };
+        let mut family_names = vec![];
+        if find_family(&self.families, declared_family_name).is_some()
+            || self.find_alias(declared_family_name).is_some()
+        {
+            family_names.push(declared_family_name.to_owned());
+        }
+        for default_family_name in source::default_generic_family_names(family_name) {
+            if !family_names.contains(&default_family_name) {
+                family_names.push(default_family_name);
+            }
+        }
+        family_names
+    }
+
+    /// Returns a number that increases whenever fonts are added to or removed from the font
+    /// directory.
+    ///
+    /// Like `FsSource::generation`, every call reads the metadata of every file in the directory.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.fs_source.generation()
+    }
+
+    // Returns the alias with the given name, ignoring case.
+    fn find_alias(&self, alias_name: &str) -> Option<&Alias> {
+        let folded_alias_name = utils::fold_case(alias_name);
+        self.aliases
+            .iter()
+            .find(|alias| utils::fold_case(&alias.name) == folded_alias_name)
+    }
+
+    // Returns the properties that the configuration declares for a font, if it does.
+    fn declared_properties(&self, handle: &Handle) -> Option<Properties> {
+        let (path, font_index) = match *handle {
+            Handle::Path {
+                ref path,
+                font_index,
+            } => (path, font_index),
+            Handle::Memory { .. } => return None,
+        };
+        self.families
+            .iter()
+            .flat_map(|family| family.fonts.iter())
+            .find(|font| font.path == *path && font.font_index == font_index)
+            .and_then(|font| font.properties)
+    }
+}
+
+impl Default for AndroidSource {
+    #[inline]
+    fn default() -> AndroidSource {
+        AndroidSource::new()
+    }
+}
+
+impl Source for AndroidSource {
+    #[inline]
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.all_fonts()
+    }
+
+    #[inline]
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families()
+    }
+
+    #[inline]
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.select_family_by_name(family_name)
+    }
+
+    #[inline]
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name(postscript_name)
+    }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_postscript_name_prefix(prefix)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_fallback_for_char(character, locale, properties)
+    }
+
+    #[inline]
+    fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        self.generic_family_names(family_name)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
+
+    // Uses the properties that the configuration declares, which saves loading the fonts.
+    fn select_descriptions_in_family(
+        &self,
+        family: &FamilyHandle,
+    ) -> Result<Vec<Properties>, SelectionError> {
+        let mut fields = vec![];
+        for font_handle in family.fonts() {
+            fields.push(match self.declared_properties(font_handle) {
+                Some(properties) => properties,
+                None => Font::from_handle(font_handle)
+                    .map_err(|_| SelectionError::CannotAccessSource)?
+                    .properties(),
+            })
+        }
+        Ok(fields)
+    }
+}
+
+impl DeclaredFamily {
+    // Returns the fonts of this family, or just those of the given weight if it has any.
+    fn family_handle(&self, weight: Option<Weight>) -> FamilyHandle {
+        let has_weight = |font: &&DeclaredFont| match (weight, font.properties) {
+            (Some(weight), Some(properties)) => properties.weight == weight,
+            _ => false,
+        };
+        let mut fonts: Vec<&DeclaredFont> = self.fonts.iter().filter(has_weight).collect();
+        if fonts.is_empty() {
+            fonts = self.fonts.iter().collect();
+        }
+        let mut family_handle =
+            FamilyHandle::from_font_handles(fonts.into_iter().map(DeclaredFont::handle));
+        if let Some(ref name) = self.name {
+            family_handle.set_name(name.clone());
+        }
+        family_handle
+    }
+
+    // Returns the font of this family that has a glyph for the character and matches the
+    // properties most closely.
+    fn select_font_for_char(&self, character: char, properties: &Properties) -> Option<Handle> {
+        let (mut handles, mut candidates) = (vec![], vec![]);
+        for declared_font in &self.fonts {
+            let handle = declared_font.handle();
+            if let Ok(font) = Font::from_handle(&handle) {
+                if font.glyph_for_char(character).is_some() {
+                    candidates.push(
+                        declared_font
+                            .properties
+                            .unwrap_or_else(|| font.properties()),
+                    );
+                    handles.push(handle);
+                }
+            }
+        }
+        let index = matching::find_best_match(&candidates, properties).ok()?;
+        Some(handles.swap_remove(index))
+    }
+}
+
+impl DeclaredFont {
+    // Reads a `font` or `file` element, whose text is the name of the font file.
+    fn new(
+        element: &Element,
+        font_directory: &Path,
+        properties: Option<Properties>,
+    ) -> Option<DeclaredFont> {
+        let file_name = element.text.trim();
+        if file_name.is_empty() {
+            return None;
+        }
+        let font_index = element
+            .attribute("index")
+            .and_then(|index| index.parse().ok())
+            .unwrap_or(0);
+        Some(DeclaredFont {
+            path: font_directory.join(file_name),
+            font_index: FaceIndex(font_index),
+            properties,
+        })
+    }
+
+    fn handle(&self) -> Handle {
+        Handle::from_path(self.path.clone(), self.font_index)
+    }
+}
+
+// Returns the index of the named family among the given ones, ignoring case.
+fn find_family(families: &[DeclaredFamily], family_name: &str) -> Option<usize> {
+    let folded_family_name = utils::fold_case(family_name);
+    families.iter().position(|family| match family.name {
+        Some(ref name) => utils::fold_case(name) == folded_family_name,
+        None => false,
+    })
+}
+
+// Returns whether a family meant for text in the given language suits text in the given locale.
+//
+// Both are BCP 47 language tags. The primary language subtags must be the same, unless the
+// family's is "und", and the locale must have each of the family's other subtags, so that for
+// example "zh-Hant" suits "zh-Hant-TW" and "und-Arab" suits "ar-Arab".
+fn language_suits_locale(language: &str, locale: &str) -> bool {
+    let locale_subtags: Vec<String> = locale.split('-').map(str::to_ascii_lowercase).collect();
+    let mut language_subtags = language.split('-').map(str::to_ascii_lowercase);
+    let primary_language = match language_subtags.next() {
+        Some(primary_language) => primary_language,
+        None => return false,
+    };
+    (primary_language == "und" || primary_language == locale_subtags[0])
+        && language_subtags.all(|subtag| locale_subtags[1..].contains(&subtag))
+}
+
+// Reads the families and aliases that a font configuration file declares, in either format.
+//
+// Android 5.0 and later name each family with an attribute and describe each font with a `font`
+// element:
+//
+//     <family name="sans-serif">
+//         <font weight="400" style="normal">Roboto-Regular.ttf</font>
+//     </family>
+//     <alias name="sans-serif-medium" to="sans-serif" weight="500" />
+//
+// Older versions list the names of a family, of which all but the first are aliases, and its font
+// files, whose properties have to be read from the files:
+//
+//     <family>
+//         <nameset><name>sans-serif</name><name>arial</name></nameset>
+//         <fileset><file>Roboto-Regular.ttf</file></fileset>
+//     </family>
+fn parse_font_configuration(
+    xml: &str,
+    font_directory: &Path,
+) -> Option<(Vec<DeclaredFamily>, Vec<AliasDeclaration>)> {
+    let familyset = parse_xml(xml)?;
+    if familyset.name != "familyset" {
+        return None;
+    }
+
+    let (mut families, mut aliases) = (vec![], vec![]);
+    for element in &familyset.children {
+        match &*element.name {
+            "family" => {
+                let mut names = element
+                    .attribute("name")
+                    .map(str::to_owned)
+                    .into_iter()
+                    .chain(
+                        element
+                            .children_named("nameset")
+                            .flat_map(|nameset| nameset.children_named("name"))
+                            .map(|name| name.text.trim().to_owned()),
+                    )
+                    .filter(|name| !name.is_empty());
+                let mut languages = split_languages(element);
+                let mut fonts = vec![];
+                for font in element.children_named("font") {
+                    let weight = font
+                        .attribute("weight")
+                        .and_then(|weight| weight.parse().ok())
+                        .unwrap_or(Weight::NORMAL.0);
+                    let style = match font.attribute("style") {
+                        Some("italic") => Style::Italic,
+                        _ => Style::Normal,
+                    };
+                    let properties = Properties {
+                        style,
+                        weight: Weight(weight),
+                        stretch: Stretch::NORMAL,
+                    };
+                    fonts.extend(DeclaredFont::new(font, font_directory, Some(properties)));
+                }
+                for file in element
+                    .children_named("fileset")
+                    .flat_map(|fileset| fileset.children_named("file"))
+                {
+                    languages.extend(split_languages(file));
+                    fonts.extend(DeclaredFont::new(file, font_directory, None));
+                }
+
+                let name = names.next();
+                if let Some(ref name) = name {
+                    aliases.extend(names.map(|alias_name| AliasDeclaration {
+                        name: alias_name,
+                        to: name.clone(),
+                        weight: None,
+                    }));
+                }
+                families.push(DeclaredFamily {
+                    name,
+                    languages,
+                    fonts,
+                });
+            }
+            "alias" => {
+                if let (Some(name), Some(to)) = (element.attribute("name"), element.attribute("to"))
+                {
+                    aliases.push(AliasDeclaration {
+                        name: name.to_owned(),
+                        to: to.to_owned(),
+                        weight: element
+                            .attribute("weight")
+                            .and_then(|weight| weight.parse().ok())
+                            .map(Weight),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Some((families, aliases))
+}
+
+// Returns the language tags in the `lang` attribute of an element, which lists them separated by
+// spaces.
+fn split_languages(element: &Element) -> Vec<String> {
+    element
+        .attribute("lang")
+        .into_iter()
+        .flat_map(str::split_whitespace)
+        .map(str::to_owned)
+        .collect()
+}
+
+// An element of an XML document, with the text directly inside it.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute_name, _)| attribute_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+// Parses an XML document and returns its root element, or `None` if it isn't well formed.
+//
+// This understands the parts of XML that font configuration files use: elements, attributes,
+// text, CDATA sections, and character and entity references. Comments, processing instructions,
+// and document type declarations are skipped.
+fn parse_xml(xml: &str) -> Option<Element> {
+    let (mut open_elements, mut root): (Vec<Element>, Option<Element>) = (vec![], None);
+    let mut rest = xml;
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            rest = &rest[(rest.find("-->")? + 3)..];
+        } else if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>")?;
+            open_elements.last_mut()?.text.push_str(&rest[9..end]);
+            rest = &rest[(end + 3)..];
+        } else if rest.starts_with("<?") {
+            rest = &rest[(rest.find("?>")? + 2)..];
+        } else if rest.starts_with("<!") {
+            rest = &rest[(rest.find('>')? + 1)..];
+        } else if rest.starts_with("</") {
+            let end = rest.find('>')?;
+            let element = open_elements.pop()?;
+            if element.name != rest[2..end].trim() {
+                return None;
+            }
+            rest = &rest[(end + 1)..];
+            close_element(&mut open_elements, &mut root, element)?;
+        } else if rest.starts_with('<') {
+            let end = find_tag_end(rest)?;
+            let tag = &rest[1..end];
+            rest = &rest[(end + 1)..];
+            match tag.strip_suffix('/') {
+                Some(tag) => {
+                    let element = parse_start_tag(tag)?;
+                    close_element(&mut open_elements, &mut root, element)?;
+                }
+                None => open_elements.push(parse_start_tag(tag)?),
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = unescape_xml(&rest[..end])?;
+            match open_elements.last_mut() {
+                Some(element) => element.text.push_str(&text),
+                None if text.trim().is_empty() => {}
+                None => return None,
+            }
+            rest = &rest[end..];
+        }
+    }
+    if !open_elements.is_empty() {
+        return None;
+    }
+    root
+}
+
+// Adds an element whose end tag has been read to its parent, or makes it the root element.
+fn close_element(
+    open_elements: &mut [Element],
+    root: &mut Option<Element>,
+    element: Element,
+) -> Option<()> {
+    match open_elements.last_mut() {
+        Some(parent) => parent.children.push(element),
+        None if root.is_none() => *root = Some(element),
+        None => return None,
+    }
+    Some(())
+}
+
+// Returns the position of the `>` that ends the tag at the start of `xml`, skipping any in
+// attribute values.
+fn find_tag_end(xml: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, character) in xml.char_indices() {
+        match (quote, character) {
+            (None, '>') => return Some(index),
+            (None, '"') | (None, '\'') => quote = Some(character),
+            (Some(quote_character), _) if character == quote_character => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+// Parses the name and attributes of a start tag, given what is between its angle brackets.
+fn parse_start_tag(tag: &str) -> Option<Element> {
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut attributes = vec![];
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let equals = rest.find('=')?;
+        let attribute_name = rest[..equals].trim();
+        rest = rest[(equals + 1)..].trim_start();
+        let quote = rest.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        let value_end = rest[1..].find(quote)? + 1;
+        attributes.push((
+            attribute_name.to_owned(),
+            unescape_xml(&rest[1..value_end])?,
+        ));
+        rest = rest[(value_end + 1)..].trim_start();
+    }
+
+    Some(Element {
+        name: name.to_owned(),
+        attributes,
+        children: vec![],
+        text: String::new(),
+    })
+}
+
+// Replaces the character and entity references in text or an attribute value with the characters
+// they stand for.
+fn unescape_xml(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = start + rest[start..].find(';')?;
+        let reference = &rest[(start + 1)..end];
+        let character = match reference {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ if reference.starts_with("#x") => {
+                std::char::from_u32(u32::from_str_radix(&reference[2..], 16).ok()?)?
+            }
+            _ if reference.starts_with('#') => std::char::from_u32(reference[1..].parse().ok()?)?,
+            _ => return None,
+        };
+        unescaped.push(character);
+        rest = &rest[(end + 1)..];
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
@@ -0,0 +1,356 @@
+// font-kit/src/sources/cache.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A source that remembers the results of queries to another source.
+//!
+//! This is useful when an application queries the same fonts over and over, for example on every
+//! relayout, and the platform source is slow to answer.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::error::SelectionError;
+use crate::family_handle::FamilyHandle;
+use crate::family_name::FamilyName;
+use crate::handle::Handle;
+use crate::properties::{Properties, PropertiesFilter, Style};
+use crate::source::Source;
+
+/// A source that remembers the results of queries to another source.
+///
+/// The families, family lookups, PostScript name lookups, and best matches that the wrapped
+/// source returns are kept until `invalidate` is called. Queries that found nothing are kept too,
+/// but they can be forgotten on their own with `invalidate_not_found`, for example after the
+/// application has registered new fonts. Other errors are never kept, since they may be
+/// transient. All other queries go straight to the wrapped source.
+///
+/// Polling `generation` also polls the wrapped source, and the cache is invalidated if the fonts
+/// in the wrapped source have changed.
+///
+/// The cache can be shared between threads.
+#[allow(missing_debug_implementations)]
+pub struct CachingSource<S> {
+    source: S,
+    all_families: QueryCache<(), Vec<String>>,
+    families_by_name: QueryCache<String, FamilyHandle>,
+    fonts_by_postscript_name: QueryCache<String, Handle>,
+    best_matches: QueryCache<BestMatchQuery, Handle>,
+    generation: AtomicU64,
+    // The generation of the wrapped source when it was last polled.
+    source_generation: AtomicU64,
+}
+
+impl<S> CachingSource<S>
+where
+    S: Source,
+{
+    /// Creates a new source that remembers the results of queries to the given source.
+    pub fn new(source: S) -> CachingSource<S> {
+        CachingSource {
+            source_generation: AtomicU64::new(source.generation()),
+            source,
+            all_families: QueryCache::new(),
+            families_by_name: QueryCache::new(),
+            fonts_by_postscript_name: QueryCache::new(),
+            best_matches: QueryCache::new(),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the wrapped source.
+    #[inline]
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns the wrapped source, discarding the cache.
+    #[inline]
+    pub fn into_source(self) -> S {
+        self.source
+    }
+
+    /// Forgets the results of all queries, so that they are asked of the wrapped source again.
+    ///
+    /// Call this when the fonts available to the wrapped source change.
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.all_families.clear();
+        self.families_by_name.clear();
+        self.fonts_by_postscript_name.clear();
+        self.best_matches.clear();
+    }
+
+    /// Forgets only which queries found nothing, keeping the fonts that were found.
+    ///
+    /// This is enough when fonts have been added to the wrapped source but none removed. The
+    /// list of all families is asked for again too, since it may have grown.
+    pub fn invalidate_not_found(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.all_families.clear();
+        self.families_by_name.clear_not_found();
+        self.fonts_by_postscript_name.clear_not_found();
+        self.best_matches.clear_not_found();
+    }
+
+    /// Returns the number of times that the cache has been invalidated, after invalidating it if
+    /// the generation of the wrapped source has changed since the last call.
+    ///
+    /// Results obtained while the generation stays the same are consistent with one another.
+    pub fn generation(&self) -> u64 {
+        let source_generation = self.source.generation();
+        if self
+            .source_generation
+            .swap(source_generation, Ordering::SeqCst)
+            != source_generation
+        {
+            self.invalidate();
+        }
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Returns the names of all families installed on the system.
+    pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families
+            .get_or_query(&(), &self.generation, || self.source.all_families())
+    }
+
+    /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.families_by_name
+            .get_or_query(family_name, &self.generation, || {
+                self.source.select_family_by_name(family_name)
+            })
+    }
+
+    /// Selects a font by PostScript name, which should be a unique identifier.
+    pub fn select_by_postscript_name(
+        &self,
+        postscript_name: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.fonts_by_postscript_name
+            .get_or_query(postscript_name, &self.generation, || {
+                self.source.select_by_postscript_name(postscript_name)
+            })
+    }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handle.
+    pub fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        let query = BestMatchQuery::new(family_names, properties);
+        self.best_matches
+            .get_or_query(&query, &self.generation, || {
+                self.source.select_best_match(family_names, properties)
+            })
+    }
+}
+
+impl<S> Source for CachingSource<S>
+where
+    S: Source,
+{
+    #[inline]
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.source.all_fonts()
+    }
+
+    #[inline]
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families()
+    }
+
+    #[inline]
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.select_family_by_name(family_name)
+    }
+
+    #[inline]
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name(postscript_name)
+    }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.source.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.source.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.source.select_by_postscript_name_prefix(prefix)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.source.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.source
+            .select_fallback_for_char(character, locale, properties)
+    }
+
+    #[inline]
+    fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        self.source.select_families_by_unicode_coverage(characters)
+    }
+
+    #[inline]
+    fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        self.source.generic_family_names(family_name)
+    }
+
+    #[inline]
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match(family_names, properties)
+    }
+
+    #[inline]
+    fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.source.select_matches(family_names, properties)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
+}
+
+// The results of one kind of query, keyed by the query.
+struct QueryCache<K, V> {
+    found: RwLock<HashMap<K, V>>,
+    not_found: RwLock<HashSet<K>>,
+}
+
+impl<K, V> QueryCache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    fn new() -> QueryCache<K, V> {
+        QueryCache {
+            found: RwLock::new(HashMap::new()),
+            not_found: RwLock::new(HashSet::new()),
+        }
+    }
+
+    // Returns the remembered result of the given query, or runs it and remembers the result.
+    //
+    // A result is only remembered if the cache hasn't been invalidated while the query ran, so
+    // that a slow query can't bring back a result from before the invalidation.
+    fn get_or_query<Q, F>(
+        &self,
+        key: &Q,
+        generation: &AtomicU64,
+        query: F,
+    ) -> Result<V, SelectionError>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ToOwned<Owned = K> + ?Sized,
+        F: FnOnce() -> Result<V, SelectionError>,
+    {
+        if let Some(value) = self.found.read().unwrap().get(key) {
+            return Ok(value.clone());
+        }
+        if self.not_found.read().unwrap().contains(key) {
+            return Err(SelectionError::NotFound);
+        }
+
+        let query_generation = generation.load(Ordering::SeqCst);
+        let result = query();
+        match result {
+            Ok(ref value) => {
+                let mut found = self.found.write().unwrap();
+                if generation.load(Ordering::SeqCst) == query_generation {
+                    found.insert(key.to_owned(), value.clone());
+                }
+            }
+            Err(SelectionError::NotFound) => {
+                let mut not_found = self.not_found.write().unwrap();
+                if generation.load(Ordering::SeqCst) == query_generation {
+                    not_found.insert(key.to_owned());
+                }
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn clear(&self) {
+        self.found.write().unwrap().clear();
+        self.clear_not_found();
+    }
+
+    fn clear_not_found(&self) {
+        self.not_found.write().unwrap().clear();
+    }
+}
+
+// The arguments to `select_best_match`, in a form that can be hashed.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct BestMatchQuery {
+    family_names: Vec<FamilyName>,
+    // The kind of style, and the angle of oblique styles.
+    style_bits: (u8, u32),
+    weight_bits: u32,
+    stretch_bits: u32,
+}
+
+impl BestMatchQuery {
+    fn new(family_names: &[FamilyName], properties: &Properties) -> BestMatchQuery {
+        BestMatchQuery {
+            family_names: family_names.to_vec(),
+            style_bits: match properties.style {
+                Style::Normal => (0, 0),
+                Style::Italic => (1, 0),
+                Style::Oblique(angle) => (2, angle.to_bits()),
+            },
+            weight_bits: properties.weight.0.to_bits(),
+            stretch_bits: properties.stretch.0.to_bits(),
+        }
+    }
+}
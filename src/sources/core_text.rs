@@ -11,28 +11,114 @@
 //! A source that contains the installed fonts on macOS.
 
 use core_foundation::array::CFArray;
-use core_foundation::base::{CFType, TCFType};
-use core_foundation::dictionary::CFDictionary;
-use core_foundation::string::CFString;
+use core_foundation::base::{CFIndex, CFType, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_graphics::data_provider::CGDataProvider;
+use core_graphics::font::CGFont;
+use core_text::font as ct_font;
 use core_text::font_collection::{self, CTFontCollection};
 use core_text::font_descriptor::{self, CTFontDescriptor};
 use core_text::font_manager;
+use foreign_types::ForeignType;
+use lazy_static::lazy_static;
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::f32;
+use std::io;
+use std::os::raw::c_void;
 use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{self, AtomicU64};
+use std::sync::{Arc, Mutex, Once};
 
-use crate::error::SelectionError;
+use crate::error::{FontLoadingError, SelectionError};
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::file_type::FileType;
-use crate::font::Font;
+use crate::font::{FaceIndex, Font};
 use crate::handle::Handle;
-use crate::properties::{Properties, Stretch, Weight};
-use crate::source::Source;
+use crate::loaders::core_text::Font as CoreTextFont;
+use crate::properties::{Properties, PropertiesFilter, Stretch, Weight};
+use crate::source::{self, Source};
 use crate::utils;
 
 pub(crate) static FONT_WEIGHT_MAPPING: [f32; 9] = [-0.7, -0.5, -0.23, 0.0, 0.2, 0.3, 0.4, 0.6, 0.8];
 
+// The number of times that Core Text has announced a change to the registered fonts.
+static FONT_CHANGE_COUNT: AtomicU64 = AtomicU64::new(0);
+static OBSERVE_FONT_CHANGES: Once = Once::new();
+
+const CF_NOTIFICATION_SUSPENSION_BEHAVIOR_DELIVER_IMMEDIATELY: CFIndex = 4;
+
+// From `CTFontTraits.h`.
+#[allow(non_upper_case_globals)]
+const kCTFontMonoSpaceTrait: u32 = 1 << 10;
+
+// From `CTFontManager.h`.
+type CTFontManagerScope = u32;
+const CT_FONT_MANAGER_SCOPE_PROCESS: CTFontManagerScope = 1;
+const CT_FONT_MANAGER_ERROR_FILE_NOT_FOUND: CFIndex = 101;
+const CT_FONT_MANAGER_ERROR_INSUFFICIENT_PERMISSIONS: CFIndex = 102;
+const CT_FONT_MANAGER_ERROR_UNRECOGNIZED_FORMAT: CFIndex = 103;
+const CT_FONT_MANAGER_ERROR_INVALID_FONT_DATA: CFIndex = 104;
+const CT_FONT_MANAGER_ERROR_ALREADY_REGISTERED: CFIndex = 105;
+const CT_FONT_MANAGER_ERROR_NOT_REGISTERED: CFIndex = 201;
+
+lazy_static! {
+    // The fonts that `register_font_data` registered, which Core Text has no files for.
+    static ref REGISTERED_FONT_DATA: Mutex<Vec<RegisteredFontData>> = Mutex::new(vec![]);
+}
+
+struct RegisteredFontData {
+    postscript_name: String,
+    data: Arc<Vec<u8>>,
+    core_graphics_font: CGFont,
+}
+
+type CFNotificationCenterRef = *mut c_void;
+type CFNotificationCallback = extern "C" fn(
+    center: CFNotificationCenterRef,
+    observer: *mut c_void,
+    name: CFStringRef,
+    object: *const c_void,
+    user_info: CFDictionaryRef,
+);
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFNotificationCenterGetLocalCenter() -> CFNotificationCenterRef;
+    fn CFNotificationCenterAddObserver(
+        center: CFNotificationCenterRef,
+        observer: *const c_void,
+        callback: CFNotificationCallback,
+        name: CFStringRef,
+        object: *const c_void,
+        suspension_behavior: CFIndex,
+    );
+}
+
+#[link(name = "CoreText", kind = "framework")]
+extern "C" {
+    static kCTFontManagerRegisteredFontsChangedNotification: CFStringRef;
+
+    fn CTFontManagerRegisterGraphicsFont(font: *mut c_void, error: *mut CFErrorRef) -> bool;
+    fn CTFontManagerUnregisterGraphicsFont(font: *mut c_void, error: *mut CFErrorRef) -> bool;
+    fn CTFontManagerRegisterFontsForURL(
+        font_url: CFURLRef,
+        scope: CTFontManagerScope,
+        error: *mut CFErrorRef,
+    ) -> bool;
+    fn CTFontManagerUnregisterFontsForURL(
+        font_url: CFURLRef,
+        scope: CTFontManagerScope,
+        error: *mut CFErrorRef,
+    ) -> bool;
+}
+
 /// A source that contains the installed fonts on macOS.
 #[allow(missing_debug_implementations)]
 #[allow(missing_copy_implementations)]
@@ -45,9 +131,110 @@ impl CoreTextSource {
     /// lazily on demand by the Core Text/Core Graphics API.)
     #[inline]
     pub fn new() -> CoreTextSource {
+        observe_font_changes();
         CoreTextSource
     }
 
+    /// Returns a number that increases whenever fonts are registered or unregistered, whether by
+    /// this process or system-wide.
+    ///
+    /// Core Text announces changes through the local notification center, which only delivers
+    /// system-wide changes while the main run loop runs, as it does in GUI applications.
+    pub fn generation(&self) -> u64 {
+        observe_font_changes();
+        FONT_CHANGE_COUNT.load(atomic::Ordering::SeqCst)
+    }
+
+    /// Makes the font in the given data available to this process, as though it were installed on
+    /// the system.
+    ///
+    /// The font takes part in matching, in Core Text's fallback lists, and in `all_families` until
+    /// `unregister_font_data` is called. Handles to it refer to the data in memory. Registering a
+    /// font whose PostScript name is already registered fails.
+    pub fn register_font_data(&self, data: Arc<Vec<u8>>) -> Result<(), FontLoadingError> {
+        let data_provider = CGDataProvider::from_buffer(data.clone());
+        let core_graphics_font =
+            CGFont::from_data_provider(data_provider).map_err(|_| FontLoadingError::Parse)?;
+        let postscript_name = core_graphics_font.postscript_name().to_string();
+        unsafe {
+            let mut error = ptr::null_mut();
+            if !CTFontManagerRegisterGraphicsFont(
+                core_graphics_font.as_ptr() as *mut c_void,
+                &mut error,
+            ) {
+                return Err(font_manager_error(error));
+            }
+        }
+        REGISTERED_FONT_DATA
+            .lock()
+            .unwrap()
+            .push(RegisteredFontData {
+                postscript_name,
+                data,
+                core_graphics_font,
+            });
+        Ok(())
+    }
+
+    /// Removes a font that `register_font_data` made available.
+    pub fn unregister_font_data(&self, data: &[u8]) -> Result<(), FontLoadingError> {
+        let mut registered_font_data = REGISTERED_FONT_DATA.lock().unwrap();
+        let index = registered_font_data
+            .iter()
+            .position(|registered| &registered.data[..] == data)
+            .ok_or_else(|| {
+                font_manager_error_from_code(CT_FONT_MANAGER_ERROR_NOT_REGISTERED, None)
+            })?;
+        unsafe {
+            let mut error = ptr::null_mut();
+            if !CTFontManagerUnregisterGraphicsFont(
+                registered_font_data[index].core_graphics_font.as_ptr() as *mut c_void,
+                &mut error,
+            ) {
+                return Err(font_manager_error(error));
+            }
+        }
+        registered_font_data.remove(index);
+        Ok(())
+    }
+
+    /// Makes the fonts in the file at the given path available to this process, as though they
+    /// were installed on the system.
+    ///
+    /// The fonts take part in matching, in Core Text's fallback lists, and in `all_families` until
+    /// `unregister_font_url` is called. Registering a font whose PostScript name is already
+    /// registered fails.
+    pub fn register_font_url(&self, path: &Path) -> Result<(), FontLoadingError> {
+        let url = font_file_url(path)?;
+        unsafe {
+            let mut error = ptr::null_mut();
+            if !CTFontManagerRegisterFontsForURL(
+                url.as_concrete_TypeRef(),
+                CT_FONT_MANAGER_SCOPE_PROCESS,
+                &mut error,
+            ) {
+                return Err(font_manager_error(error));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the fonts that `register_font_url` made available from the file at the given path.
+    pub fn unregister_font_url(&self, path: &Path) -> Result<(), FontLoadingError> {
+        let url = font_file_url(path)?;
+        unsafe {
+            let mut error = ptr::null_mut();
+            if !CTFontManagerUnregisterFontsForURL(
+                url.as_concrete_TypeRef(),
+                CT_FONT_MANAGER_SCOPE_PROCESS,
+                &mut error,
+            ) {
+                return Err(font_manager_error(error));
+            }
+        }
+        Ok(())
+    }
+
     /// Returns paths of all fonts installed on the system.
     pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
         let collection = font_collection::create_for_all_families();
@@ -65,17 +252,34 @@ impl CoreTextSource {
     }
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// The comparison is case-insensitive, but a family whose name matches exactly is preferred.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
-        let attributes: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(&[(
-            CFString::new("NSFontFamilyAttribute"),
-            CFString::new(family_name).as_CFType(),
-        )]);
+        match select_family_by_exact_name(family_name) {
+            Err(SelectionError::NotFound) => {}
+            result => return result,
+        }
 
-        let descriptor = font_descriptor::new_from_attributes(&attributes);
-        let descriptors = CFArray::from_CFTypes(&[descriptor]);
-        let collection = font_collection::new_from_descriptors(&descriptors);
-        let handles = create_handles_from_core_text_collection(collection)?;
-        Ok(FamilyHandle::from_font_handles(handles.into_iter()))
+        // Core Text compares family names exactly, so look for one that differs only in case.
+        let folded_family_name = utils::fold_case(family_name);
+        match self
+            .all_families()?
+            .into_iter()
+            .find(|name| name != family_name && utils::fold_case(name) == folded_family_name)
+        {
+            Some(name) => select_family_by_exact_name(&name),
+            None => Err(SelectionError::NotFound),
+        }
+    }
+
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
     }
 
     /// Selects a font by PostScript name, which should be a unique identifier.
@@ -88,15 +292,136 @@ impl CoreTextSource {
             CFString::new(postscript_name).as_CFType(),
         )]);
 
-        let descriptor = font_descriptor::new_from_attributes(&attributes);
-        let descriptors = CFArray::from_CFTypes(&[descriptor]);
-        let collection = font_collection::new_from_descriptors(&descriptors);
-        match collection.get_descriptors() {
-            None => Err(SelectionError::NotFound),
-            Some(descriptors) => Ok(create_handle_from_descriptor(&*descriptors.get(0).unwrap())),
+        select_by_attributes(&attributes).ok_or(SelectionError::NotFound)
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    ///
+    /// On the Core Text backend, this does a brute-force search of installed fonts.
+    #[inline]
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_by_postscript_name_prefix(self, prefix)
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    ///
+    /// On the Core Text backend, a filter for monospace fonts becomes the monospace symbolic trait
+    /// of the font descriptor that installed fonts are matched against. The other properties are
+    /// read from the font tables of the fonts that Core Text returns.
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let collection = match filter.monospace {
+            Some(true) => {
+                let traits: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(&[(
+                    CFString::new("NSCTFontSymbolicTrait"),
+                    CFNumber::from(kCTFontMonoSpaceTrait as i64).as_CFType(),
+                )]);
+                let attributes: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(
+                    &[(CFString::new("NSCTFontTraitsAttribute"), traits.as_CFType())],
+                );
+                let descriptor = font_descriptor::new_from_attributes(&attributes);
+                font_collection::new_from_descriptors(&CFArray::from_CFTypes(&[descriptor]))
+            }
+            _ => font_collection::create_for_all_families(),
+        };
+        let handles = create_handles_from_core_text_collection(collection)?;
+
+        // Core Text has already checked the spacing of the fonts that it matched.
+        let filter = PropertiesFilter {
+            monospace: filter.monospace.filter(|&monospace| !monospace),
+            ..filter.clone()
+        };
+        source::filter_by_properties(handles, &filter)
+    }
+
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    ///
+    /// On the Core Text backend, this matches on `kCTFontDisplayNameAttribute`, falling back to a
+    /// brute-force search of installed fonts for names that Core Text doesn't recognize.
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        let attributes: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(&[(
+            CFString::new("NSFontVisibleNameAttribute"),
+            CFString::new(full_name).as_CFType(),
+        )]);
+        match select_by_attributes(&attributes) {
+            Some(handle) => Ok(handle),
+            None => source::select_by_face_names_by_scanning(self, |face_names| {
+                face_names.matches_full_name(full_name)
+            }),
+        }
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    ///
+    /// On the Core Text backend, this matches on `kCTFontFamilyNameAttribute` and
+    /// `kCTFontStyleNameAttribute`, falling back to a brute-force search of installed fonts for
+    /// names that Core Text doesn't recognize.
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        let attributes: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(&[
+            (
+                CFString::new("NSFontFamilyAttribute"),
+                CFString::new(family).as_CFType(),
+            ),
+            (
+                CFString::new("NSFontFaceAttribute"),
+                CFString::new(style).as_CFType(),
+            ),
+        ]);
+        match select_by_attributes(&attributes) {
+            Some(handle) => Ok(handle),
+            None => source::select_by_face_names_by_scanning(self, |face_names| {
+                face_names.matches_family_and_style(family, style)
+            }),
         }
     }
 
+    /// Selects a font that has a glyph for the given character, preferring fonts whose properties
+    /// match `properties` most closely.
+    ///
+    /// On the Core Text backend, this goes through the cascade list that Core Text uses for the
+    /// default sans-serif font in the given locale.
+    pub fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        let base_handle = self.select_best_match(&[FamilyName::SansSerif], properties)?;
+        let base_font =
+            CoreTextFont::from_handle(&base_handle).map_err(|_| SelectionError::NotFound)?;
+        if base_font.glyph_for_char(character).is_some() {
+            return Ok(base_handle);
+        }
+
+        let languages = CFArray::from_CFTypes(&[CFString::new(locale)]);
+        let cascade_list =
+            ct_font::cascade_list_for_languages(&base_font.native_font(), &languages);
+        for index in 0..cascade_list.len() {
+            let descriptor = cascade_list.get(index).unwrap();
+            let handle = match create_handle_from_descriptor(&*descriptor) {
+                Some(handle) => handle,
+                None => continue,
+            };
+            if let Ok(font) = CoreTextFont::from_handle(&handle) {
+                if font.glyph_for_char(character).is_some() {
+                    return Ok(handle);
+                }
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
     /// handle.
     #[inline]
@@ -107,6 +432,27 @@ impl CoreTextSource {
     ) -> Result<Handle, SelectionError> {
         <Self as Source>::select_best_match(self, family_names, properties)
     }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    #[inline]
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_matches(self, family_names, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    #[inline]
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        <Self as Source>::select_families_by_unicode_coverage(self, characters)
+    }
 }
 
 impl Source for CoreTextSource {
@@ -125,6 +471,68 @@ impl Source for CoreTextSource {
     fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
         self.select_by_postscript_name(postscript_name)
     }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_fallback_for_char(character, locale, properties)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
+}
+
+// Starts counting the changes to the registered fonts that Core Text announces, if that hasn't
+// been done already.
+fn observe_font_changes() {
+    OBSERVE_FONT_CHANGES.call_once(|| unsafe {
+        CFNotificationCenterAddObserver(
+            CFNotificationCenterGetLocalCenter(),
+            ptr::null(),
+            count_font_change,
+            kCTFontManagerRegisteredFontsChangedNotification,
+            ptr::null(),
+            CF_NOTIFICATION_SUSPENSION_BEHAVIOR_DELIVER_IMMEDIATELY,
+        )
+    });
+}
+
+extern "C" fn count_font_change(
+    _: CFNotificationCenterRef,
+    _: *mut c_void,
+    _: CFStringRef,
+    _: *const c_void,
+    _: CFDictionaryRef,
+) {
+    FONT_CHANGE_COUNT.fetch_add(1, atomic::Ordering::SeqCst);
 }
 
 pub(crate) fn piecewise_linear_lookup(index: f32, mapping: &[f32]) -> f32 {
@@ -170,7 +578,7 @@ fn create_handles_from_core_text_collection(
     if let Some(descriptors) = collection.get_descriptors() {
         for index in 0..descriptors.len() {
             let descriptor = descriptors.get(index).unwrap();
-            fonts.push(create_handle_from_descriptor(&*descriptor));
+            fonts.extend(create_handle_from_descriptor(&*descriptor));
         }
     }
     if fonts.is_empty() {
@@ -180,22 +588,89 @@ fn create_handles_from_core_text_collection(
     }
 }
 
-fn create_handle_from_descriptor(descriptor: &CTFontDescriptor) -> Handle {
-    let font_path = Path::new(&descriptor.font_path().unwrap()).to_owned();
+fn select_family_by_exact_name(family_name: &str) -> Result<FamilyHandle, SelectionError> {
+    let attributes: CFDictionary<CFString, CFType> = CFDictionary::from_CFType_pairs(&[(
+        CFString::new("NSFontFamilyAttribute"),
+        CFString::new(family_name).as_CFType(),
+    )]);
+
+    let descriptor = font_descriptor::new_from_attributes(&attributes);
+    let descriptors = CFArray::from_CFTypes(&[descriptor]);
+    let collection = font_collection::new_from_descriptors(&descriptors);
+    let handles = create_handles_from_core_text_collection(collection)?;
+    let mut family_handle = FamilyHandle::from_font_handles(handles.into_iter());
+    family_handle.set_name(family_name.to_owned());
+    Ok(family_handle)
+}
+
+// Returns the first installed font that matches the given descriptor attributes.
+fn select_by_attributes(attributes: &CFDictionary<CFString, CFType>) -> Option<Handle> {
+    let descriptor = font_descriptor::new_from_attributes(attributes);
+    let descriptors = CFArray::from_CFTypes(&[descriptor]);
+    let collection = font_collection::new_from_descriptors(&descriptors);
+    let descriptors = collection.get_descriptors()?;
+    let descriptor = descriptors.get(0)?;
+    create_handle_from_descriptor(&*descriptor)
+}
+
+// Returns `None` for fonts that have no file and weren't registered by `register_font_data`.
+fn create_handle_from_descriptor(descriptor: &CTFontDescriptor) -> Option<Handle> {
+    let font_path = match descriptor.font_path() {
+        Some(font_path) => font_path,
+        None => {
+            let postscript_name = descriptor.font_name();
+            return REGISTERED_FONT_DATA
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|registered| registered.postscript_name == postscript_name)
+                .map(|registered| Handle::from_memory(registered.data.clone(), FaceIndex::FIRST));
+        }
+    };
     if let Ok(FileType::Collection(font_count)) = Font::analyze_path(font_path.clone()) {
         let postscript_name = descriptor.font_name();
         for font_index in 0..font_count {
-            let font_handle = Handle::from_path(font_path.clone(), font_index);
+            let font_handle = Handle::from_path(font_path.clone(), FaceIndex(font_index));
             if let Ok(font) = Font::from_handle(&font_handle) {
                 if let Some(font_postscript_name) = font.postscript_name() {
                     if postscript_name == font_postscript_name {
-                        return font_handle;
+                        return Some(font_handle);
                     }
                 }
             }
         }
     }
-    Handle::from_path(font_path, 0)
+    Some(Handle::from_path(font_path, FaceIndex::FIRST))
+}
+
+fn font_file_url(path: &Path) -> Result<CFURL, FontLoadingError> {
+    let path = path.canonicalize()?;
+    CFURL::from_path(&path, false).ok_or(FontLoadingError::NoFilesystem)
+}
+
+// Converts an error that a `CTFontManager` function returned, releasing it.
+unsafe fn font_manager_error(error: CFErrorRef) -> FontLoadingError {
+    if error.is_null() {
+        return font_manager_error_from_code(0, None);
+    }
+    let error = CFError::wrap_under_create_rule(error);
+    font_manager_error_from_code(error.code(), Some(error.description().to_string()))
+}
+
+fn font_manager_error_from_code(code: CFIndex, description: Option<String>) -> FontLoadingError {
+    let kind = match code {
+        CT_FONT_MANAGER_ERROR_UNRECOGNIZED_FORMAT => return FontLoadingError::UnknownFormat,
+        CT_FONT_MANAGER_ERROR_INVALID_FONT_DATA => return FontLoadingError::Parse,
+        CT_FONT_MANAGER_ERROR_FILE_NOT_FOUND | CT_FONT_MANAGER_ERROR_NOT_REGISTERED => {
+            io::ErrorKind::NotFound
+        }
+        CT_FONT_MANAGER_ERROR_INSUFFICIENT_PERMISSIONS => io::ErrorKind::PermissionDenied,
+        CT_FONT_MANAGER_ERROR_ALREADY_REGISTERED => io::ErrorKind::AlreadyExists,
+        _ => io::ErrorKind::Other,
+    };
+    let description =
+        description.unwrap_or_else(|| format!("Core Text font manager error {}", code));
+    FontLoadingError::Io(io::Error::new(kind, description))
 }
 
 #[cfg(test)]
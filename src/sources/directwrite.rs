@@ -10,35 +10,125 @@
 
 //! A source that contains the installed fonts on Windows.
 
+use dwrote::CustomFontCollectionLoaderImpl;
 use dwrote::Font as DWriteFont;
 use dwrote::FontCollection as DWriteFontCollection;
+use dwrote::FontFallback as DWriteFontFallback;
+use dwrote::FontFamily as DWriteFontFamily;
+use dwrote::FontFile as DWriteFontFile;
+use dwrote::InformationalStringId as DWriteInformationalStringId;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::error::SelectionError;
+use crate::error::{FontLoadingError, SelectionError};
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
+use crate::font::FaceIndex;
 use crate::handle::Handle;
-use crate::properties::Properties;
-use crate::source::Source;
+use crate::loader::Loader;
+use crate::loaders::directwrite::{properties_for_dwrite_font, Font as DirectWriteFont};
+use crate::properties::{Properties, PropertiesFilter};
+use crate::source::{self, FaceNames, Source};
+use crate::utils;
 
 /// A source that contains the installed fonts on Windows.
+///
+/// It can also contain, alongside or instead of the installed fonts, a private collection of
+/// fonts that the application supplies.
 #[allow(missing_debug_implementations)]
 pub struct DirectWriteSource {
-    system_font_collection: DWriteFontCollection,
+    system_font_collection: Option<RefCell<DWriteFontCollection>>,
+    custom_font_collection: Option<DWriteFontCollection>,
+    // The data of the in-memory fonts in the private collection, which DirectWrite reads from as
+    // long as the collection exists.
+    custom_font_data: Vec<Arc<Vec<u8>>>,
+    generation: Cell<u64>,
 }
 
 impl DirectWriteSource {
     /// Opens the system font collection.
     pub fn new() -> DirectWriteSource {
         DirectWriteSource {
-            system_font_collection: DWriteFontCollection::system(),
+            system_font_collection: Some(RefCell::new(DWriteFontCollection::system())),
+            custom_font_collection: None,
+            custom_font_data: vec![],
+            generation: Cell::new(0),
         }
     }
 
+    /// Creates a source that contains only the given fonts, in a private font collection.
+    ///
+    /// The fonts may be on disk or in memory. All the faces of each font file or collection
+    /// become part of the source, whatever the font index of its handle.
+    pub fn from_fonts<I>(fonts: I) -> Result<DirectWriteSource, FontLoadingError>
+    where
+        I: Iterator<Item = Handle>,
+    {
+        let (custom_font_collection, custom_font_data) = create_custom_font_collection(fonts)?;
+        Ok(DirectWriteSource {
+            system_font_collection: None,
+            custom_font_collection: Some(custom_font_collection),
+            custom_font_data,
+            generation: Cell::new(0),
+        })
+    }
+
+    /// Creates a source that contains the installed fonts as well as the given fonts, which are
+    /// in a private font collection as with `from_fonts`.
+    ///
+    /// A family of the given fonts hides an installed family with the same name.
+    pub fn system_and_custom<I>(fonts: I) -> Result<DirectWriteSource, FontLoadingError>
+    where
+        I: Iterator<Item = Handle>,
+    {
+        let (custom_font_collection, custom_font_data) = create_custom_font_collection(fonts)?;
+        Ok(DirectWriteSource {
+            system_font_collection: Some(RefCell::new(DWriteFontCollection::system())),
+            custom_font_collection: Some(custom_font_collection),
+            custom_font_data,
+            generation: Cell::new(0),
+        })
+    }
+
+    /// Returns a number that increases whenever the fonts installed on the system change.
+    ///
+    /// This asks DirectWrite to check for changes to the installed fonts. If there are any, the
+    /// system font collection is opened again before this returns. The private font collection
+    /// never changes.
+    pub fn generation(&self) -> u64 {
+        let current_font_collection = match self.system_font_collection {
+            Some(ref current_font_collection) => current_font_collection,
+            None => return self.generation.get(),
+        };
+        let system_font_collection = DWriteFontCollection::get_system(true);
+        let mut current_font_collection = current_font_collection.borrow_mut();
+        // DirectWrite hands out the same collection until the installed fonts change.
+        if unsafe { system_font_collection.as_ptr() != current_font_collection.as_ptr() } {
+            *current_font_collection = system_font_collection;
+            self.generation.set(self.generation.get() + 1);
+        }
+        self.generation.get()
+    }
+
+    /// Brings the system font collection up to date with the installed fonts, and returns true
+    /// if they changed since the collection was last opened.
+    ///
+    /// This is the check that `generation` does. Handles that the source returned earlier stay
+    /// valid, as they refer to font files or data rather than to the collection. Sources without
+    /// the installed fonts never change.
+    pub fn refresh(&mut self) -> Result<bool, SelectionError> {
+        let old_generation = self.generation.get();
+        Ok(self.generation() != old_generation)
+    }
+
     /// Returns paths of all fonts installed on the system.
     pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
         let mut handles = Vec::new();
 
-        for dwrite_family in self.system_font_collection.families_iter() {
+        for dwrite_family in self.families() {
             for font_index in 0..dwrite_family.get_font_count() {
                 let dwrite_font = dwrite_family.get_font(font_index);
                 handles.push(self.create_handle_from_dwrite_font(dwrite_font))
@@ -50,25 +140,33 @@ impl DirectWriteSource {
 
     /// Returns the names of all families installed on the system.
     pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        let mut seen_family_names = HashSet::new();
         Ok(self
-            .system_font_collection
-            .families_iter()
+            .families()
+            .into_iter()
             .map(|dwrite_family| dwrite_family.name())
+            .filter(|family_name| seen_family_names.insert(family_name.clone()))
             .collect())
     }
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
     ///
-    /// TODO(pcwalton): Case-insensitivity.
+    /// The comparison is case-insensitive, but a family whose name matches exactly is preferred.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
         let mut family = FamilyHandle::new();
-        let dwrite_family = match self
-            .system_font_collection
-            .get_font_family_by_name(family_name)
-        {
+        let dwrite_family = match self.family_by_name(family_name) {
             Some(dwrite_family) => dwrite_family,
-            None => return Err(SelectionError::NotFound),
+            None => {
+                let folded_family_name = utils::fold_case(family_name);
+                match self.families().into_iter().find(|dwrite_family| {
+                    utils::fold_case(&dwrite_family.name()) == folded_family_name
+                }) {
+                    Some(dwrite_family) => dwrite_family,
+                    None => return Err(SelectionError::NotFound),
+                }
+            }
         };
+        family.set_name(dwrite_family.name());
         for font_index in 0..dwrite_family.get_font_count() {
             let dwrite_font = dwrite_family.get_font(font_index);
             family.push(self.create_handle_from_dwrite_font(dwrite_font))
@@ -76,15 +174,154 @@ impl DirectWriteSource {
         Ok(family)
     }
 
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
+    }
+
     /// Selects a font by PostScript name, which should be a unique identifier.
     ///
-    /// On the DirectWrite backend, this does a brute-force search of installed fonts to find the
-    /// one that matches.
+    /// On the DirectWrite backend, this searches the PostScript names that DirectWrite keeps for
+    /// each installed font, without opening any font files.
     pub fn select_by_postscript_name(
         &self,
         postscript_name: &str,
     ) -> Result<Handle, SelectionError> {
-        <Self as Source>::select_by_postscript_name(self, postscript_name)
+        for dwrite_family in self.families() {
+            for font_index in 0..dwrite_family.get_font_count() {
+                let dwrite_font = dwrite_family.get_font(font_index);
+                let font_postscript_name =
+                    dwrite_font.informational_string(DWriteInformationalStringId::PostscriptName);
+                if font_postscript_name.as_ref().map(|name| &name[..]) == Some(postscript_name) {
+                    return Ok(self.create_handle_from_dwrite_font(dwrite_font));
+                }
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    ///
+    /// On the DirectWrite backend, this searches the full, family, and face names that
+    /// DirectWrite keeps for each installed font, without opening any font files.
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_face_names(|face_names| face_names.matches_full_name(full_name))
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    ///
+    /// On the DirectWrite backend, this searches the full, family, and face names that
+    /// DirectWrite keeps for each installed font, without opening any font files.
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_face_names(|face_names| face_names.matches_family_and_style(family, style))
+    }
+
+    fn select_by_face_names<F>(&self, predicate: F) -> Result<Handle, SelectionError>
+    where
+        F: Fn(&FaceNames) -> bool,
+    {
+        for dwrite_family in self.families() {
+            for font_index in 0..dwrite_family.get_font_count() {
+                let dwrite_font = dwrite_family.get_font(font_index);
+                let full_names = dwrite_font
+                    .informational_string(DWriteInformationalStringId::FullName)
+                    .into_iter()
+                    .collect();
+                let face_names = FaceNames::new(
+                    full_names,
+                    vec![(dwrite_font.family_name(), dwrite_font.face_name())],
+                );
+                if predicate(&face_names) {
+                    return Ok(self.create_handle_from_dwrite_font(dwrite_font));
+                }
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    ///
+    /// On the DirectWrite backend, this does a brute-force search of installed fonts.
+    #[inline]
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_by_postscript_name_prefix(self, prefix)
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    ///
+    /// On the DirectWrite backend, this compares the weight, stretch, style, and spacing that
+    /// DirectWrite reports for each font, so no font files are read.
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for dwrite_family in self.families() {
+            for font_index in 0..dwrite_family.get_font_count() {
+                let dwrite_font = dwrite_family.get_font(font_index);
+                let properties = properties_for_dwrite_font(&dwrite_font);
+                let is_monospace = dwrite_font.is_monospace().unwrap_or(false);
+                if filter.matches(&properties, is_monospace) {
+                    handles.push(self.create_handle_from_dwrite_font(dwrite_font));
+                }
+            }
+        }
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects a font that has a glyph for the given character, preferring fonts whose properties
+    /// match `properties` most closely.
+    ///
+    /// On the DirectWrite backend, this uses the system font fallback, starting from the default
+    /// sans-serif font. Versions of Windows before 8.1 don't have a system font fallback, and
+    /// sources without the installed fonts can't use it; there, every font is loaded to check its
+    /// coverage.
+    pub fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        if self.system_font_collection.is_none()
+            || DWriteFontFallback::get_system_fallback().is_none()
+        {
+            return source::select_fallback_for_char_by_scanning(self, character, properties);
+        }
+
+        let base_handle = self.select_best_match(&[FamilyName::SansSerif], properties)?;
+        let base_font =
+            DirectWriteFont::from_handle(&base_handle).map_err(|_| SelectionError::NotFound)?;
+        if base_font.glyph_for_char(character).is_some() {
+            return Ok(base_handle);
+        }
+
+        let fallback_result =
+            Loader::get_fallbacks(&base_font, character.encode_utf8(&mut [0; 4]), locale);
+        for fallback_font in fallback_result.fonts {
+            if fallback_font.font.glyph_for_char(character).is_some() {
+                let dwrite_font = fallback_font.font.native_font().dwrite_font;
+                return Ok(self.create_handle_from_dwrite_font(dwrite_font));
+            }
+        }
+        Err(SelectionError::NotFound)
     }
 
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
@@ -98,14 +335,131 @@ impl DirectWriteSource {
         <Self as Source>::select_best_match(self, family_names, properties)
     }
 
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    #[inline]
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_matches(self, family_names, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    #[inline]
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        <Self as Source>::select_families_by_unicode_coverage(self, characters)
+    }
+
+    // Returns the families of the private font collection, followed by those of the system font
+    // collection.
+    fn families(&self) -> Vec<DWriteFontFamily> {
+        let mut families: Vec<_> = self
+            .custom_font_collection
+            .iter()
+            .flat_map(|custom_font_collection| custom_font_collection.families_iter())
+            .collect();
+        if let Some(ref system_font_collection) = self.system_font_collection {
+            families.extend(system_font_collection.borrow().families_iter());
+        }
+        families
+    }
+
+    fn family_by_name(&self, family_name: &str) -> Option<DWriteFontFamily> {
+        if let Some(ref custom_font_collection) = self.custom_font_collection {
+            if let Some(dwrite_family) = custom_font_collection.get_font_family_by_name(family_name)
+            {
+                return Some(dwrite_family);
+            }
+        }
+        self.system_font_collection
+            .as_ref()
+            .and_then(|system_font_collection| {
+                system_font_collection
+                    .borrow()
+                    .get_font_family_by_name(family_name)
+            })
+    }
+
     fn create_handle_from_dwrite_font(&self, dwrite_font: DWriteFont) -> Handle {
         let dwrite_font_face = dwrite_font.create_font_face();
         let dwrite_font_files = dwrite_font_face.get_files();
-        Handle::Path {
-            path: dwrite_font_files[0].get_font_file_path().unwrap(),
-            font_index: dwrite_font_face.get_index(),
+        let font_index = FaceIndex(dwrite_font_face.get_index());
+        if let Some(path) = dwrite_font_files[0].get_font_file_path() {
+            return Handle::Path { path, font_index };
+        }
+
+        // DirectWrite only hands out copies of in-memory fonts, so find the data that the font
+        // was created from in order to share it.
+        let font_file_bytes = dwrite_font_files[0].get_font_file_bytes();
+        let bytes = match self
+            .custom_font_data
+            .iter()
+            .find(|font_data| font_data[..] == font_file_bytes[..])
+        {
+            Some(font_data) => font_data.clone(),
+            None => Arc::new(font_file_bytes),
+        };
+        Handle::Memory { bytes, font_index }
+    }
+}
+
+// Creates a private font collection that contains the files or in-memory data of the given fonts,
+// and returns it along with the in-memory data.
+fn create_custom_font_collection<I>(
+    fonts: I,
+) -> Result<(DWriteFontCollection, Vec<Arc<Vec<u8>>>), FontLoadingError>
+where
+    I: Iterator<Item = Handle>,
+{
+    let mut font_files = vec![];
+    let mut font_paths: Vec<PathBuf> = vec![];
+    let mut font_data: Vec<Arc<Vec<u8>>> = vec![];
+    for handle in fonts {
+        match handle {
+            Handle::Path { path, .. } => {
+                // Handles of the faces of a font collection share its file.
+                if font_paths.contains(&path) {
+                    continue;
+                }
+                let font_file = match DWriteFontFile::new_from_path(&path) {
+                    Some(font_file) => font_file,
+                    None => {
+                        // Report why the file couldn't be opened, if that's what went wrong.
+                        return Err(match fs::metadata(&path) {
+                            Err(io_error) => FontLoadingError::Io(io_error),
+                            Ok(_) => FontLoadingError::Parse,
+                        });
+                    }
+                };
+                font_files.push(font_file);
+                font_paths.push(path);
+            }
+            Handle::Memory { bytes, .. } => {
+                if font_data
+                    .iter()
+                    .any(|existing_bytes| Arc::ptr_eq(existing_bytes, &bytes))
+                {
+                    continue;
+                }
+                let font_file =
+                    DWriteFontFile::new_from_data(bytes.clone()).ok_or(FontLoadingError::Parse)?;
+                font_files.push(font_file);
+                font_data.push(bytes);
+            }
         }
     }
+
+    let collection_loader = CustomFontCollectionLoaderImpl::new(&font_files);
+    Ok((
+        DWriteFontCollection::from_loader(collection_loader),
+        font_data,
+    ))
 }
 
 impl Source for DirectWriteSource {
@@ -123,4 +477,46 @@ impl Source for DirectWriteSource {
     fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
         self.select_family_by_name(family_name)
     }
+
+    #[inline]
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name(postscript_name)
+    }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_fallback_for_char(character, locale, properties)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
 }
@@ -15,12 +15,86 @@
 //! support. To prefer it over the native font source (only if you know what you're doing), use the
 //! `source-fontconfig-default` feature.
 
-use crate::error::SelectionError;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::{FontLoadingError, SelectionError};
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
+use crate::font::FaceIndex;
+use crate::font::Font;
 use crate::handle::Handle;
-use crate::properties::Properties;
-use crate::source::Source;
+use crate::properties::{Properties, PropertiesFilter, Stretch, Style, Weight};
+use crate::source::{self, FaceNames, Source};
+use crate::utils;
+
+// The generic family names that fontconfig understands.
+static GENERIC_FAMILY_NAMES: [&str; 5] = ["serif", "sans-serif", "monospace", "cursive", "fantasy"];
+
+// The CSS weights 100, 200, ..., 900.
+static CSS_WEIGHT_MAPPING: [f32; 9] = [
+    Weight::THIN.0,
+    Weight::EXTRA_LIGHT.0,
+    Weight::LIGHT.0,
+    Weight::NORMAL.0,
+    Weight::MEDIUM.0,
+    Weight::SEMIBOLD.0,
+    Weight::BOLD.0,
+    Weight::EXTRA_BOLD.0,
+    Weight::BLACK.0,
+];
+
+// The fontconfig weights that correspond to the CSS weights 100, 200, ..., 900.
+static FONT_WEIGHT_MAPPING: [f32; 9] = [
+    fc::FC_WEIGHT_THIN as f32,
+    fc::FC_WEIGHT_EXTRALIGHT as f32,
+    fc::FC_WEIGHT_LIGHT as f32,
+    fc::FC_WEIGHT_REGULAR as f32,
+    fc::FC_WEIGHT_MEDIUM as f32,
+    fc::FC_WEIGHT_DEMIBOLD as f32,
+    fc::FC_WEIGHT_BOLD as f32,
+    fc::FC_WEIGHT_EXTRABOLD as f32,
+    fc::FC_WEIGHT_BLACK as f32,
+];
+
+// The fontconfig widths that correspond to the CSS stretch values in `Stretch::MAPPING`.
+static FONT_WIDTH_MAPPING: [f32; 9] = [
+    fc::FC_WIDTH_ULTRACONDENSED as f32,
+    fc::FC_WIDTH_EXTRACONDENSED as f32,
+    fc::FC_WIDTH_CONDENSED as f32,
+    fc::FC_WIDTH_SEMICONDENSED as f32,
+    fc::FC_WIDTH_NORMAL as f32,
+    fc::FC_WIDTH_SEMIEXPANDED as f32,
+    fc::FC_WIDTH_EXPANDED as f32,
+    fc::FC_WIDTH_EXTRAEXPANDED as f32,
+    fc::FC_WIDTH_ULTRAEXPANDED as f32,
+];
+
+/// A query for `FontconfigSource::sort`.
+#[derive(Clone, Debug, Default)]
+pub struct Spec {
+    /// The families to look for, in order of preference.
+    ///
+    /// Generic families stand for the families that the fontconfig configuration substitutes for
+    /// them. If this is empty, the configuration's default family is used.
+    pub family_names: Vec<FamilyName>,
+    /// The weight, stretch, and style to look for.
+    pub properties: Properties,
+    /// The language to prefer fonts for, such as `"en-US"`.
+    pub locale: Option<String>,
+    /// The characters that the fonts should have glyphs for.
+    pub characters: BTreeSet<char>,
+    /// Whether to leave out the fonts that cover no characters beyond those of the fonts sorted
+    /// before them.
+    pub trim: bool,
+}
 
 /// A source that contains the fonts installed on the system, as reported by the Fontconfig
 /// library.
@@ -30,15 +104,135 @@ use crate::source::Source;
 /// `source-fontconfig-default` feature.
 #[allow(missing_debug_implementations)]
 pub struct FontconfigSource {
-    config: fc::Config,
+    config: RefCell<fc::Config>,
+    generation: Cell<u64>,
+    app_fonts: RefCell<Vec<AppFont>>,
+    // The configuration file that `with_config_file` was given, if any.
+    config_file: Option<PathBuf>,
+}
+
+// A font file registered with `add_app_font_file` or `add_app_font_bytes`.
+struct AppFont {
+    path: PathBuf,
+    // Whether the file is one that `add_app_font_bytes` wrote, which is deleted along with it.
+    is_temporary: bool,
 }
 
+// Numbers the temporary files that `add_app_font_bytes` writes within this process.
+static NEXT_APP_FONT_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+
 impl FontconfigSource {
     /// Initializes Fontconfig and prepares it for queries.
     pub fn new() -> FontconfigSource {
         FontconfigSource {
-            config: fc::Config::new(),
+            config: RefCell::new(fc::Config::new()),
+            generation: Cell::new(0),
+            app_fonts: RefCell::new(vec![]),
+            config_file: None,
+        }
+    }
+
+    /// Prepares Fontconfig for queries with the configuration in the given file, instead of the
+    /// configuration of the system.
+    ///
+    /// Only the fonts in the directories that the file lists, and those that the files it
+    /// includes list, are available. The configuration belongs to this source alone and is freed
+    /// along with it, so other sources and the rest of the process are unaffected.
+    pub fn with_config_file(path: &Path) -> Result<FontconfigSource, SelectionError> {
+        let config = fc::Config::from_file(path).ok_or(SelectionError::CannotAccessSource)?;
+        Ok(FontconfigSource {
+            config: RefCell::new(config),
+            generation: Cell::new(0),
+            app_fonts: RefCell::new(vec![]),
+            config_file: Some(path.to_owned()),
+        })
+    }
+
+    /// Returns a number that increases whenever the fonts installed on the system change.
+    ///
+    /// This asks Fontconfig whether its configuration files or font directories have changed
+    /// since it loaded them, and loads them again if so. Fontconfig only checks as often as its
+    /// rescan interval allows, which is 30 seconds by default.
+    pub fn generation(&self) -> u64 {
+        if !self.config.borrow().is_up_to_date() {
+            let config = match self.config_file {
+                None => fc::Config::new(),
+                Some(ref config_file) => match fc::Config::from_file(config_file) {
+                    Some(config) => config,
+                    // Keep the old configuration until the file can be read again.
+                    None => return self.generation.get(),
+                },
+            };
+            for app_font in &*self.app_fonts.borrow() {
+                config.app_font_add_file(&app_font.path);
+            }
+            *self.config.borrow_mut() = config;
+            self.generation.set(self.generation.get() + 1);
+        }
+        self.generation.get()
+    }
+
+    /// Makes the font file at the given path available to this source, as though it were
+    /// installed on the system, without installing it.
+    ///
+    /// The fonts in the file take part in matching, so the fallback and alias rules of the
+    /// fontconfig configuration apply to them. They stay available until `clear_app_fonts` is
+    /// called.
+    pub fn add_app_font_file(&self, path: &Path) -> Result<(), FontLoadingError> {
+        let path = fs::canonicalize(path)?;
+        Font::analyze_path(&path)?;
+        self.add_app_font(AppFont {
+            path,
+            is_temporary: false,
+        })
+    }
+
+    /// Makes the font in the given data available to this source, as though it were installed on
+    /// the system, without installing it.
+    ///
+    /// Fontconfig can only read fonts from files, so the data is written to a temporary file,
+    /// which is deleted when `clear_app_fonts` is called or the source is dropped. Handles to the
+    /// font refer to that file.
+    pub fn add_app_font_bytes(&self, data: Arc<Vec<u8>>) -> Result<(), FontLoadingError> {
+        Font::analyze_bytes(data.clone())?;
+        let path = env::temp_dir().join(format!(
+            "font-kit-app-font-{}-{}",
+            process::id(),
+            NEXT_APP_FONT_FILE_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        // The name is predictable, so refuse to write through a file that's already there.
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let app_font = AppFont {
+            path,
+            is_temporary: true,
+        };
+        if let Err(error) = file.write_all(&data) {
+            app_font.remove_if_temporary();
+            return Err(error.into());
+        }
+        self.add_app_font(app_font)
+    }
+
+    fn add_app_font(&self, app_font: AppFont) -> Result<(), FontLoadingError> {
+        if !self.config.borrow().app_font_add_file(&app_font.path) {
+            app_font.remove_if_temporary();
+            return Err(FontLoadingError::UnknownFormat);
+        }
+        self.app_fonts.borrow_mut().push(app_font);
+        self.generation.set(self.generation.get() + 1);
+        Ok(())
+    }
+
+    /// Removes all the fonts that `add_app_font_file` and `add_app_font_bytes` made available.
+    pub fn clear_app_fonts(&self) {
+        self.config.borrow().app_font_clear();
+        for app_font in self.app_fonts.borrow_mut().drain(..) {
+            app_font.remove_if_temporary();
         }
+        self.generation.set(self.generation.get() + 1);
     }
 
     /// Returns paths of all fonts installed on the system.
@@ -51,7 +245,7 @@ impl FontconfigSource {
         object_set.push_string(fc::Object::Index);
 
         let patterns = pattern
-            .list(&self.config, object_set)
+            .list(&self.config.borrow(), object_set)
             .map_err(|_| SelectionError::NotFound)?;
 
         let mut handles = vec![];
@@ -68,7 +262,7 @@ impl FontconfigSource {
 
             handles.push(Handle::Path {
                 path: path.into(),
-                font_index: index as u32,
+                font_index: FaceIndex(index as u32),
             });
         }
 
@@ -88,7 +282,7 @@ impl FontconfigSource {
         object_set.push_string(fc::Object::Family);
 
         let patterns = pattern
-            .list(&self.config, object_set)
+            .list(&self.config.borrow(), object_set)
             .map_err(|_| SelectionError::NotFound)?;
 
         let mut result_families = vec![];
@@ -109,39 +303,112 @@ impl FontconfigSource {
     }
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// Fontconfig compares family names case-insensitively and ignoring whitespace. Names that
+    /// the fontconfig configuration aliases to other families, such as "Helvetica" to "Liberation
+    /// Sans", resolve to the first of those families that is installed, as with `fc-match`. The
+    /// generic fallbacks that the configuration adds to every name are not followed, so names
+    /// that fontconfig knows nothing about are still not found.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
         use std::borrow::Cow;
 
-        let family_name = match family_name {
-            "serif" | "sans-serif" | "monospace" | "cursive" | "fantasy" => {
-                Cow::from(self.select_generic_font(family_name)?)
-            }
-            _ => Cow::from(family_name),
+        let family_name = if GENERIC_FAMILY_NAMES.contains(&family_name) {
+            Cow::from(self.select_generic_font(family_name)?)
+        } else {
+            Cow::from(family_name)
         };
 
-        let pattern = fc::Pattern::from_name(family_name.as_ref());
+        let mut family = self.fonts_in_family(&family_name)?;
+        if family.is_empty() {
+            for substitute_name in self.substitute_family_names(&family_name) {
+                family = self.fonts_in_family(&substitute_name)?;
+                if !family.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        if !family.is_empty() {
+            Ok(family)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    // Lists the fonts in the family with exactly the given name.
+    //
+    // The family is named as the fonts spell it, which may differ from `family_name` in case and
+    // whitespace.
+    fn fonts_in_family(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        let pattern = fc::Pattern::from_name(family_name);
 
         let mut object_set = fc::ObjectSet::new();
+        object_set.push_string(fc::Object::Family);
         object_set.push_string(fc::Object::File);
         object_set.push_string(fc::Object::Index);
 
         let patterns = pattern
-            .list(&self.config, object_set)
+            .list(&self.config.borrow(), object_set)
             .map_err(|_| SelectionError::NotFound)?;
 
-        let mut handles = vec![];
+        let mut family = FamilyHandle::new();
+        let comparable_family_name = comparable_fontconfig_family_name(family_name);
         for patt in patterns {
+            if family.name().is_none() {
+                if let Some(name) = patt
+                    .get_strings(fc::Object::Family)
+                    .into_iter()
+                    .find(|name| comparable_fontconfig_family_name(name) == comparable_family_name)
+                {
+                    family.set_name(name);
+                }
+            }
             let font_path = patt.get_string(fc::Object::File).unwrap();
-            let font_index = patt.get_integer(fc::Object::Index).unwrap() as u32;
+            let font_index = FaceIndex(patt.get_integer(fc::Object::Index).unwrap() as u32);
             let handle = Handle::from_path(std::path::PathBuf::from(font_path), font_index);
-            handles.push(handle);
+            family.push(handle);
         }
+        Ok(family)
+    }
 
-        if !handles.is_empty() {
-            Ok(FamilyHandle::from_font_handles(handles.into_iter()))
-        } else {
-            Err(SelectionError::NotFound)
+    // Returns the families that the fontconfig configuration substitutes for the given family, in
+    // order of preference, leaving out the generic fallbacks that it adds to every family.
+    //
+    // Fontconfig appends the families that a generic family stands for after a family's aliases,
+    // so the aliases are the families before the first run of those.
+    fn substitute_family_names(&self, family_name: &str) -> Vec<String> {
+        let mut pattern = fc::Pattern::from_name(family_name);
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+        let family_names = pattern.get_strings(fc::Object::Family);
+
+        let generic_family_names: Vec<Vec<String>> = GENERIC_FAMILY_NAMES
+            .iter()
+            .map(|generic_name| {
+                let mut pattern = fc::Pattern::from_name(generic_name);
+                pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+                pattern.get_strings(fc::Object::Family)
+            })
+            .filter(|family_names| !family_names.is_empty())
+            .collect();
+        let fallback_start = (1..family_names.len())
+            .find(|&index| {
+                let rest = &family_names[index..];
+                generic_family_names.iter().any(|generic_family_names| {
+                    let length = generic_family_names.len().min(rest.len());
+                    rest[..length] == generic_family_names[..length]
+                })
+            })
+            .unwrap_or(family_names.len());
+
+        let mut substitute_names: Vec<String> = vec![];
+        for substitute_name in family_names.into_iter().take(fallback_start).skip(1) {
+            if !substitute_name.eq_ignore_ascii_case(family_name)
+                && !substitute_names.contains(&substitute_name)
+            {
+                substitute_names.push(substitute_name);
+            }
         }
+        substitute_names
     }
 
     /// Selects a font by a generic name.
@@ -149,11 +416,11 @@ impl FontconfigSource {
     /// Accepts: serif, sans-serif, monospace, cursive and fantasy.
     fn select_generic_font(&self, name: &str) -> Result<String, SelectionError> {
         let mut pattern = fc::Pattern::from_name(name);
-        pattern.config_substitute(fc::MatchKind::Pattern);
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
         pattern.default_substitute();
 
         let patterns = pattern
-            .sorted(&self.config)
+            .sorted(&self.config.borrow(), true)
             .map_err(|_| SelectionError::NotFound)?;
 
         if let Some(patt) = patterns.into_iter().next() {
@@ -165,10 +432,42 @@ impl FontconfigSource {
         Err(SelectionError::NotFound)
     }
 
+    /// Returns the names of the families that a generic family stands for, in order of
+    /// preference. A specific family just stands for itself.
+    ///
+    /// On the fontconfig backend, these are the family that fontconfig picks for the generic name,
+    /// followed by the rest of the families that the configuration substitutes for it.
+    pub fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        let generic_name = match *family_name {
+            FamilyName::Title(ref title) => return vec![title.clone()],
+            _ => fontconfig_family_name(family_name),
+        };
+
+        let mut family_names: Vec<String> =
+            self.select_generic_font(generic_name).into_iter().collect();
+        let mut pattern = fc::Pattern::from_name(generic_name);
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+        for family_name in pattern.get_strings(fc::Object::Family) {
+            if family_name != generic_name && !family_names.contains(&family_name) {
+                family_names.push(family_name);
+            }
+        }
+        family_names
+    }
+
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
+    }
+
     /// Selects a font by PostScript name, which should be a unique identifier.
     ///
-    /// The default implementation, which is used by the DirectWrite and the filesystem backends,
-    /// does a brute-force search of installed fonts to find the one that matches.
+    /// On the fontconfig backend, this matches on `FC_POSTSCRIPT_NAME`.
     pub fn select_by_postscript_name(
         &self,
         postscript_name: &str,
@@ -182,12 +481,12 @@ impl FontconfigSource {
         object_set.push_string(fc::Object::Index);
 
         let patterns = pattern
-            .list(&self.config, object_set)
+            .list(&self.config.borrow(), object_set)
             .map_err(|_| SelectionError::NotFound)?;
 
         if let Some(patt) = patterns.into_iter().next() {
             let font_path = patt.get_string(fc::Object::File).unwrap();
-            let font_index = patt.get_integer(fc::Object::Index).unwrap() as u32;
+            let font_index = FaceIndex(patt.get_integer(fc::Object::Index).unwrap() as u32);
             let handle = Handle::from_path(std::path::PathBuf::from(font_path), font_index);
             Ok(handle)
         } else {
@@ -195,15 +494,499 @@ impl FontconfigSource {
         }
     }
 
-    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
-    /// handle.
-    #[inline]
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let pattern = fc::Pattern::new();
+
+        // We want the file path, the font index, and the PostScript name.
+        let mut object_set = fc::ObjectSet::new();
+        object_set.push_string(fc::Object::File);
+        object_set.push_string(fc::Object::Index);
+        object_set.push_string(fc::Object::PostScriptName);
+
+        let patterns = pattern
+            .list(&self.config.borrow(), object_set)
+            .map_err(|_| SelectionError::NotFound)?;
+
+        let mut handles = vec![];
+        for patt in patterns {
+            match patt.get_string(fc::Object::PostScriptName) {
+                Some(ref postscript_name) if postscript_name.starts_with(prefix) => {}
+                _ => continue,
+            }
+
+            let path = match patt.get_string(fc::Object::File) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let index = match patt.get_integer(fc::Object::Index) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            handles.push(Handle::Path {
+                path: path.into(),
+                font_index: FaceIndex(index as u32),
+            });
+        }
+
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    ///
+    /// On the fontconfig backend, the properties are read from the fontconfig cache, and a filter
+    /// for monospace fonts becomes an `FC_SPACING` constraint in the query, so no font files are
+    /// read. Monospace fonts are those whose spacing is `FC_MONO`. The few fonts that the cache
+    /// has no single weight or slant for, such as variable fonts, are loaded instead.
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut pattern = fc::Pattern::new();
+        if filter.monospace == Some(true) {
+            pattern.push_integer(fc::Object::Spacing, fc::FC_MONO);
+        }
+
+        let mut object_set = fc::ObjectSet::new();
+        object_set.push_string(fc::Object::File);
+        object_set.push_string(fc::Object::Index);
+        object_set.push_string(fc::Object::Weight);
+        object_set.push_string(fc::Object::Width);
+        object_set.push_string(fc::Object::Slant);
+        object_set.push_string(fc::Object::Spacing);
+
+        let patterns = pattern
+            .list(&self.config.borrow(), object_set)
+            .map_err(|_| SelectionError::NotFound)?;
+
+        let (mut handles, mut uncached_handles) = (vec![], vec![]);
+        for patt in patterns {
+            let handle = match (
+                patt.get_string(fc::Object::File),
+                patt.get_integer(fc::Object::Index),
+            ) {
+                (Some(path), Some(index)) => {
+                    Handle::from_path(path.into(), FaceIndex(index as u32))
+                }
+                _ => continue,
+            };
+            let (weight, slant) = match (
+                patt.get_integer(fc::Object::Weight),
+                patt.get_integer(fc::Object::Slant),
+            ) {
+                (Some(weight), Some(slant)) => (weight, slant),
+                _ => {
+                    uncached_handles.push(handle);
+                    continue;
+                }
+            };
+
+            let properties = Properties {
+                weight: fontconfig_to_css_weight(weight),
+                stretch: fontconfig_to_css_stretch(
+                    patt.get_integer(fc::Object::Width)
+                        .unwrap_or(fc::FC_WIDTH_NORMAL),
+                ),
+                style: fontconfig_to_css_style(slant),
+            };
+            let is_monospace = patt.get_integer(fc::Object::Spacing) == Some(fc::FC_MONO);
+            if filter.matches(&properties, is_monospace) {
+                handles.push(handle);
+            }
+        }
+        if let Ok(uncached_handles) = source::filter_by_properties(uncached_handles, filter) {
+            handles.extend(uncached_handles);
+        }
+
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    ///
+    /// On the fontconfig backend, this matches on `FC_FULLNAME`, `FC_FAMILY`, and `FC_STYLE` as
+    /// recorded in the fontconfig cache, so no font files are read.
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_face_names(|face_names| face_names.matches_full_name(full_name))
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    ///
+    /// On the fontconfig backend, this matches on `FC_FAMILY`, `FC_STYLE`, and `FC_FULLNAME` as
+    /// recorded in the fontconfig cache, so no font files are read.
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_face_names(|face_names| face_names.matches_family_and_style(family, style))
+    }
+
+    fn select_by_face_names<F>(&self, predicate: F) -> Result<Handle, SelectionError>
+    where
+        F: Fn(&FaceNames) -> bool,
+    {
+        let pattern = fc::Pattern::new();
+
+        // We want the file path, the font index, and all the names of each font.
+        let mut object_set = fc::ObjectSet::new();
+        object_set.push_string(fc::Object::File);
+        object_set.push_string(fc::Object::Index);
+        object_set.push_string(fc::Object::FullName);
+        object_set.push_string(fc::Object::Family);
+        object_set.push_string(fc::Object::FamilyLang);
+        object_set.push_string(fc::Object::Style);
+        object_set.push_string(fc::Object::StyleLang);
+
+        let patterns = pattern
+            .list(&self.config.borrow(), object_set)
+            .map_err(|_| SelectionError::NotFound)?;
+
+        for patt in patterns {
+            let families_and_styles = pair_families_and_styles(
+                patt.get_strings(fc::Object::Family),
+                patt.get_strings(fc::Object::FamilyLang),
+                patt.get_strings(fc::Object::Style),
+                patt.get_strings(fc::Object::StyleLang),
+            );
+            let face_names =
+                FaceNames::new(patt.get_strings(fc::Object::FullName), families_and_styles);
+            if !predicate(&face_names) {
+                continue;
+            }
+
+            if let (Some(path), Some(index)) = (
+                patt.get_string(fc::Object::File),
+                patt.get_integer(fc::Object::Index),
+            ) {
+                return Ok(Handle::from_path(path.into(), FaceIndex(index as u32)));
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects a font that has a glyph for the given character, preferring fonts whose properties
+    /// match `properties` most closely.
+    ///
+    /// On the fontconfig backend, this goes through the fonts that fontconfig sorts by how well
+    /// they match the character, locale, and properties.
+    pub fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        let mut char_set = fc::CharSet::new();
+        char_set.add_char(character);
+
+        let mut pattern = fc::Pattern::new();
+        pattern.push_char_set(fc::Object::CharSet, &char_set);
+        pattern.push_string(fc::Object::Lang, locale.to_lowercase());
+        push_properties(&mut pattern, properties);
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+        pattern.default_substitute();
+
+        let patterns = pattern
+            .sorted(&self.config.borrow(), true)
+            .map_err(|_| SelectionError::NotFound)?;
+
+        for patt in patterns {
+            match patt.get_char_set(fc::Object::CharSet) {
+                Some(ref char_set) if char_set.has_char(character) => {}
+                _ => continue,
+            }
+
+            let (path, index) = match (
+                patt.get_string(fc::Object::File),
+                patt.get_integer(fc::Object::Index),
+            ) {
+                (Some(path), Some(index)) => (path, index),
+                _ => continue,
+            };
+
+            // Fontconfig's character sets aren't always accurate, so double-check.
+            let handle = Handle::from_path(path.into(), FaceIndex(index as u32));
+            if let Ok(font) = handle.load() {
+                if font.glyph_for_char(character).is_some() {
+                    return Ok(handle);
+                }
+            }
+        }
+
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    ///
+    /// On the fontconfig backend, coverage is read from the character sets that fontconfig keeps
+    /// for each font, and families that cover equally many characters are in the order in which
+    /// fontconfig sorts them for the characters.
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        let mut char_set = fc::CharSet::new();
+        for &character in characters {
+            char_set.add_char(character);
+        }
+
+        let mut pattern = fc::Pattern::new();
+        pattern.push_char_set(fc::Object::CharSet, &char_set);
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+        pattern.default_substitute();
+
+        // Don't trim the fonts that add no coverage to those before them, since they may still
+        // belong to families that haven't come up yet.
+        let patterns = pattern
+            .sorted(&self.config.borrow(), false)
+            .map_err(|_| SelectionError::NotFound)?;
+
+        // The fonts of each family, and the characters that they cover between them.
+        let mut families: Vec<(String, Vec<Handle>, BTreeSet<char>)> = vec![];
+        let mut family_indices = HashMap::new();
+        for patt in patterns {
+            let (family_name, path, index) = match (
+                patt.get_string(fc::Object::Family),
+                patt.get_string(fc::Object::File),
+                patt.get_integer(fc::Object::Index),
+            ) {
+                (Some(family_name), Some(path), Some(index)) => (family_name, path, index),
+                _ => continue,
+            };
+            let family_index = *family_indices
+                .entry(family_name.clone())
+                .or_insert_with(|| {
+                    families.push((family_name, vec![], BTreeSet::new()));
+                    families.len() - 1
+                });
+            let (_, ref mut handles, ref mut covered_characters) = families[family_index];
+            handles.push(Handle::from_path(path.into(), FaceIndex(index as u32)));
+            if let Some(ref font_char_set) = patt.get_char_set(fc::Object::CharSet) {
+                covered_characters.extend(
+                    characters
+                        .iter()
+                        .cloned()
+                        .filter(|&character| font_char_set.has_char(character)),
+                );
+            }
+        }
+
+        source::sort_families_by_coverage(
+            families
+                .into_iter()
+                .map(|(family_name, handles, covered_characters)| {
+                    let mut family = FamilyHandle::from_font_handles(handles.into_iter());
+                    family.set_name(family_name);
+                    (covered_characters.len(), family)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the fonts that fontconfig considers the best matches for a query, best match first,
+    /// as `fc-match --sort` does.
+    ///
+    /// The query goes through the substitutions in the fontconfig configuration first, so aliased
+    /// and generic families expand to the families that they stand for. Setting `spec.trim`
+    /// leaves out the fonts that add no coverage of `spec.characters` (or of all of Unicode, if
+    /// no characters are given) to the fonts before them.
+    pub fn sort(&self, spec: &Spec) -> Result<Vec<Handle>, SelectionError> {
+        let mut pattern = fc::Pattern::new();
+        for family_name in &spec.family_names {
+            pattern.push_string(
+                fc::Object::Family,
+                fontconfig_family_name(family_name).to_owned(),
+            );
+        }
+        push_properties(&mut pattern, &spec.properties);
+        if let Some(ref locale) = spec.locale {
+            pattern.push_string(fc::Object::Lang, locale.to_lowercase());
+        }
+        if !spec.characters.is_empty() {
+            let mut char_set = fc::CharSet::new();
+            for &character in &spec.characters {
+                char_set.add_char(character);
+            }
+            pattern.push_char_set(fc::Object::CharSet, &char_set);
+        }
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+        pattern.default_substitute();
+
+        let patterns = pattern
+            .sorted(&self.config.borrow(), spec.trim)
+            .map_err(|_| SelectionError::NotFound)?;
+
+        let mut handles = vec![];
+        for patt in patterns {
+            if let (Some(path), Some(index)) = (
+                patt.get_string(fc::Object::File),
+                patt.get_integer(fc::Object::Index),
+            ) {
+                handles.push(Handle::from_path(path.into(), FaceIndex(index as u32)));
+            }
+        }
+
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    // Reads the properties of a font from the fontconfig cache, without opening the font file.
+    fn cached_properties(&self, handle: &Handle) -> Option<Properties> {
+        let (path, font_index) = match *handle {
+            Handle::Path {
+                ref path,
+                font_index,
+            } => (path.to_str()?, font_index),
+            Handle::Memory { .. } => return None,
+        };
+
+        let mut pattern = fc::Pattern::new();
+        pattern.push_string(fc::Object::File, path.to_owned());
+        pattern.push_integer(fc::Object::Index, font_index.0 as i32);
+
+        let mut object_set = fc::ObjectSet::new();
+        object_set.push_string(fc::Object::Weight);
+        object_set.push_string(fc::Object::Width);
+        object_set.push_string(fc::Object::Slant);
+
+        // The pattern borrows from the font set, so keep the set alive while reading it.
+        let mut patterns = pattern.list(&self.config.borrow(), object_set).ok()?;
+        let patt = patterns.next()?;
+        Some(Properties {
+            weight: fontconfig_to_css_weight(patt.get_integer(fc::Object::Weight)?),
+            stretch: fontconfig_to_css_stretch(
+                patt.get_integer(fc::Object::Width)
+                    .unwrap_or(fc::FC_WIDTH_NORMAL),
+            ),
+            style: fontconfig_to_css_style(patt.get_integer(fc::Object::Slant)?),
+        })
+    }
+
+    /// Performs font matching and returns the handle of the best match.
+    ///
+    /// On the fontconfig backend, families are chosen as the CSS Fonts Level 3 specification
+    /// says, but the font within a family is the one that fontconfig considers the best match
+    /// for the weight, style, and stretch, so the matching rules in its configuration apply.
     pub fn select_best_match(
         &self,
         family_names: &[FamilyName],
         properties: &Properties,
     ) -> Result<Handle, SelectionError> {
-        <Self as Source>::select_best_match(self, family_names, properties)
+        for family_name in family_names {
+            if let Ok(family_handle) = self.select_family_by_generic_name(family_name) {
+                let handles = self.sort_family(family_name, &family_handle, properties);
+                if let Some(handle) = handles.into_iter().next() {
+                    return Ok(handle);
+                }
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Performs font matching and returns the handles of all the candidate fonts, best match
+    /// first.
+    ///
+    /// Generic families expand to every installed family that the fontconfig configuration
+    /// substitutes for them, as listed by `generic_family_names`. Within a family, fonts are in
+    /// the order that fontconfig sorts them for the weight, style, and stretch.
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for family_name in family_names {
+            if let Ok(family_handles) = self.select_families_by_generic_name(family_name) {
+                for family_handle in family_handles {
+                    handles.extend(self.sort_family(family_name, &family_handle, properties));
+                }
+            }
+        }
+        if handles.is_empty() {
+            Err(SelectionError::NotFound)
+        } else {
+            Ok(handles)
+        }
+    }
+
+    // Orders the fonts in a family by how well fontconfig thinks they match the properties, best
+    // match first. The family must be one that `family_name` resolves to.
+    fn sort_family(
+        &self,
+        family_name: &FamilyName,
+        family_handle: &FamilyHandle,
+        properties: &Properties,
+    ) -> Vec<Handle> {
+        let mut pattern = fc::Pattern::new();
+        pattern.push_string(
+            fc::Object::Family,
+            fontconfig_family_name(family_name).to_owned(),
+        );
+        push_properties(&mut pattern, properties);
+        pattern.config_substitute(&self.config.borrow(), fc::MatchKind::Pattern);
+        pattern.default_substitute();
+
+        let mut unsorted_handles = family_handle.fonts().to_vec();
+        let mut handles = vec![];
+        if let Ok(patterns) = pattern.sorted(&self.config.borrow(), false) {
+            for patt in patterns {
+                let (path, index) = match (
+                    patt.get_string(fc::Object::File),
+                    patt.get_integer(fc::Object::Index),
+                ) {
+                    (Some(path), Some(index)) => (PathBuf::from(path), FaceIndex(index as u32)),
+                    _ => continue,
+                };
+                let position = unsorted_handles.iter().position(|handle| match *handle {
+                    Handle::Path {
+                        path: ref font_path,
+                        font_index,
+                    } => *font_path == path && font_index == index,
+                    Handle::Memory { .. } => false,
+                });
+                if let Some(position) = position {
+                    handles.push(unsorted_handles.remove(position));
+                }
+            }
+        }
+
+        // Fonts that fontconfig left out keep their order, after the rest.
+        handles.extend(unsorted_handles);
+        handles
+    }
+}
+
+impl Drop for FontconfigSource {
+    fn drop(&mut self) {
+        for app_font in self.app_fonts.borrow_mut().drain(..) {
+            app_font.remove_if_temporary();
+        }
+    }
+}
+
+impl AppFont {
+    fn remove_if_temporary(&self) {
+        if self.is_temporary {
+            let _ = fs::remove_file(&self.path);
+        }
     }
 }
 
@@ -227,9 +1010,225 @@ impl Source for FontconfigSource {
     fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
         self.select_by_postscript_name(postscript_name)
     }
+
+    #[inline]
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_postscript_name_prefix(prefix)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_fallback_for_char(character, locale, properties)
+    }
+
+    #[inline]
+    fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        self.select_families_by_unicode_coverage(characters)
+    }
+
+    #[inline]
+    fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        self.generic_family_names(family_name)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
+
+    #[inline]
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match(family_names, properties)
+    }
+
+    #[inline]
+    fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_matches(family_names, properties)
+    }
+
+    // The properties that fontconfig has cached are used, so that the fonts in the family needn't
+    // be opened.
+    fn select_descriptions_in_family(
+        &self,
+        family: &FamilyHandle,
+    ) -> Result<Vec<Properties>, SelectionError> {
+        let mut descriptions = vec![];
+        for font_handle in family.fonts() {
+            let properties = match self.cached_properties(font_handle) {
+                Some(properties) => properties,
+                None => Font::from_handle(font_handle)
+                    .map_err(|_| SelectionError::CannotAccessSource)?
+                    .properties(),
+            };
+            descriptions.push(properties);
+        }
+        Ok(descriptions)
+    }
+}
+
+// Returns the name that fontconfig knows a family by.
+fn fontconfig_family_name(family_name: &FamilyName) -> &str {
+    match *family_name {
+        FamilyName::Title(ref title) => title,
+        FamilyName::Serif => "serif",
+        FamilyName::SansSerif => "sans-serif",
+        FamilyName::Monospace => "monospace",
+        FamilyName::Cursive => "cursive",
+        FamilyName::Fantasy => "fantasy",
+    }
+}
+
+// Reduces a family name to the form that fontconfig compares, ignoring case and whitespace.
+fn comparable_fontconfig_family_name(family_name: &str) -> String {
+    utils::fold_case(family_name)
+        .chars()
+        .filter(|character| !character.is_whitespace())
+        .collect()
+}
+
+fn push_properties(pattern: &mut fc::Pattern, properties: &Properties) {
+    pattern.push_integer(
+        fc::Object::Weight,
+        css_to_fontconfig_weight(properties.weight),
+    );
+    pattern.push_integer(
+        fc::Object::Width,
+        css_stretchiness_to_fontconfig_width(properties.stretch),
+    );
+    pattern.push_integer(fc::Object::Slant, css_to_fontconfig_slant(properties.style));
+}
+
+fn css_to_fontconfig_weight(css_weight: Weight) -> i32 {
+    piecewise_linear_map(css_weight.0, &CSS_WEIGHT_MAPPING, &FONT_WEIGHT_MAPPING) as i32
+}
+
+fn fontconfig_to_css_weight(fontconfig_weight: i32) -> Weight {
+    Weight(piecewise_linear_map(
+        fontconfig_weight as f32,
+        &FONT_WEIGHT_MAPPING,
+        &CSS_WEIGHT_MAPPING,
+    ))
+}
+
+fn css_stretchiness_to_fontconfig_width(css_stretchiness: Stretch) -> i32 {
+    piecewise_linear_map(css_stretchiness.0, &Stretch::MAPPING, &FONT_WIDTH_MAPPING).round() as i32
+}
+
+fn fontconfig_to_css_stretch(fontconfig_width: i32) -> Stretch {
+    Stretch(piecewise_linear_map(
+        fontconfig_width as f32,
+        &FONT_WIDTH_MAPPING,
+        &Stretch::MAPPING,
+    ))
+}
+
+fn css_to_fontconfig_slant(css_style: Style) -> i32 {
+    match css_style {
+        Style::Normal => fc::FC_SLANT_ROMAN,
+        Style::Italic => fc::FC_SLANT_ITALIC,
+        Style::Oblique(_) => fc::FC_SLANT_OBLIQUE,
+    }
+}
+
+fn fontconfig_to_css_style(fontconfig_slant: i32) -> Style {
+    if fontconfig_slant >= fc::FC_SLANT_OBLIQUE {
+        Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE)
+    } else if fontconfig_slant >= fc::FC_SLANT_ITALIC {
+        Style::Italic
+    } else {
+        Style::Normal
+    }
+}
+
+// Maps a value through the piecewise linear function that takes each value in `from` to the value
+// at the same index in `to`. Values outside the range of `from` are clamped to it.
+fn piecewise_linear_map(value: f32, from: &[f32], to: &[f32]) -> f32 {
+    let value = utils::clamp(value, from[0], from[from.len() - 1]);
+    let upper_index = from
+        .iter()
+        .position(|&from_value| from_value >= value)
+        .unwrap_or(from.len() - 1)
+        .max(1);
+    let lower_index = upper_index - 1;
+    let t = (value - from[lower_index]) / (from[upper_index] - from[lower_index]);
+    utils::lerp(to[lower_index], to[upper_index], t)
 }
 
 // A minimal fontconfig wrapper.
+// Pairs the family and style names of a font, given the languages of the names. Fontconfig lists
+// the typographic and legacy names of each language in the same order for families and styles, so
+// the nth family of a language goes with the nth style of that language. Without languages for
+// every name, the names are paired by position.
+fn pair_families_and_styles(
+    families: Vec<String>,
+    family_langs: Vec<String>,
+    styles: Vec<String>,
+    style_langs: Vec<String>,
+) -> Vec<(String, String)> {
+    if family_langs.len() != families.len() || style_langs.len() != styles.len() {
+        return families.into_iter().zip(styles).collect();
+    }
+
+    let mut pairs = vec![];
+    for (family_index, (family, lang)) in families.iter().zip(&family_langs).enumerate() {
+        let index_in_lang = family_langs[..family_index]
+            .iter()
+            .filter(|other_lang| *other_lang == lang)
+            .count();
+        let style = styles
+            .iter()
+            .zip(&style_langs)
+            .filter(|&(_, style_lang)| style_lang == lang)
+            .map(|(style, _)| style)
+            .nth(index_in_lang);
+        if let Some(style) = style {
+            pairs.push((family.clone(), style.clone()));
+        }
+    }
+    pairs
+}
+
 mod fc {
     #![allow(dead_code)]
 
@@ -237,8 +1236,31 @@ mod fc {
 
     use std::ffi::{CStr, CString};
     use std::os::raw::{c_char, c_uchar};
+    use std::path::Path;
     use std::ptr;
 
+    pub use ffi::FC_WEIGHT_THIN;
+    pub use ffi::{FC_SLANT_ITALIC, FC_SLANT_OBLIQUE, FC_SLANT_ROMAN};
+    pub use ffi::{FC_WEIGHT_BLACK, FC_WEIGHT_BOLD, FC_WEIGHT_DEMIBOLD, FC_WEIGHT_EXTRABOLD};
+    pub use ffi::{FC_WEIGHT_EXTRALIGHT, FC_WEIGHT_LIGHT, FC_WEIGHT_MEDIUM, FC_WEIGHT_REGULAR};
+
+    // The widths from `fontconfig.h`, which the `fontconfig` crate doesn't define.
+    pub const FC_WIDTH_ULTRACONDENSED: i32 = 50;
+    pub const FC_WIDTH_EXTRACONDENSED: i32 = 63;
+    pub const FC_WIDTH_CONDENSED: i32 = 75;
+    pub const FC_WIDTH_SEMICONDENSED: i32 = 87;
+    pub const FC_WIDTH_NORMAL: i32 = 100;
+    pub const FC_WIDTH_SEMIEXPANDED: i32 = 113;
+    pub const FC_WIDTH_EXPANDED: i32 = 125;
+    pub const FC_WIDTH_EXTRAEXPANDED: i32 = 150;
+    pub const FC_WIDTH_ULTRAEXPANDED: i32 = 200;
+
+    // The spacings from `fontconfig.h`, which the `fontconfig` crate doesn't define either.
+    pub const FC_PROPORTIONAL: i32 = 0;
+    pub const FC_DUAL: i32 = 90;
+    pub const FC_MONO: i32 = 100;
+    pub const FC_CHARCELL: i32 = 110;
+
     #[derive(Clone, Copy)]
     pub enum Error {
         NoMatch,
@@ -267,19 +1289,39 @@ mod fc {
     // https://www.freedesktop.org/software/fontconfig/fontconfig-devel/x19.html
     #[derive(Clone, Copy)]
     pub enum Object {
+        CharSet,
         Family,
+        FamilyLang,
         File,
+        FullName,
         Index,
+        Lang,
         PostScriptName,
+        Slant,
+        Spacing,
+        Style,
+        StyleLang,
+        Weight,
+        Width,
     }
 
     impl Object {
         fn as_bytes(&self) -> &[u8] {
             match self {
+                Object::CharSet => b"charset\0",
                 Object::Family => b"family\0",
+                Object::FamilyLang => b"familylang\0",
                 Object::File => b"file\0",
+                Object::FullName => b"fullname\0",
                 Object::Index => b"index\0",
+                Object::Lang => b"lang\0",
                 Object::PostScriptName => b"postscriptname\0",
+                Object::Slant => b"slant\0",
+                Object::Spacing => b"spacing\0",
+                Object::Style => b"style\0",
+                Object::StyleLang => b"stylelang\0",
+                Object::Weight => b"weight\0",
+                Object::Width => b"width\0",
             }
         }
 
@@ -288,6 +1330,28 @@ mod fc {
         }
     }
 
+    // FcPatternGetString, for each value of the object in turn. Values that aren't UTF-8 are
+    // skipped.
+    unsafe fn pattern_get_strings(pattern: *mut ffi::FcPattern, object: Object) -> Vec<String> {
+        let mut strings = vec![];
+        for id in 0.. {
+            let mut string = ptr::null_mut();
+            let res = ffi::FcPatternGetString(pattern, object.as_ptr(), id, &mut string);
+            if res != ffi::FcResultMatch || string.is_null() {
+                break;
+            }
+
+            if let Ok(string) = CStr::from_ptr(string as *const c_char).to_str() {
+                strings.push(string.to_owned());
+            }
+        }
+        strings
+    }
+
+    fn path_to_c_string(path: &Path) -> Option<CString> {
+        path.to_str().and_then(|path| CString::new(path).ok())
+    }
+
     pub struct Config {
         d: *mut ffi::FcConfig,
     }
@@ -301,6 +1365,49 @@ mod fc {
                 }
             }
         }
+
+        // FcConfigCreate, FcConfigParseAndLoad, FcConfigBuildFonts
+        pub fn from_file(path: &Path) -> Option<Self> {
+            let path = path_to_c_string(path)?;
+            unsafe {
+                let d = ffi::FcConfigCreate();
+                if d.is_null() {
+                    return None;
+                }
+                let config = Config { d };
+                if ffi::FcConfigParseAndLoad(
+                    d,
+                    path.as_ptr() as *const c_uchar,
+                    true as ffi::FcBool,
+                ) == 0
+                    || ffi::FcConfigBuildFonts(d) == 0
+                {
+                    return None;
+                }
+                Some(config)
+            }
+        }
+
+        // FcConfigUptoDate
+        pub fn is_up_to_date(&self) -> bool {
+            unsafe { ffi::FcConfigUptoDate(self.d) != 0 }
+        }
+
+        // FcConfigAppFontAddFile
+        pub fn app_font_add_file(&self, path: &Path) -> bool {
+            let path = match path_to_c_string(path) {
+                Some(path) => path,
+                None => return false,
+            };
+            unsafe { ffi::FcConfigAppFontAddFile(self.d, path.as_ptr() as *const c_uchar) != 0 }
+        }
+
+        // FcConfigAppFontClear
+        pub fn app_font_clear(&self) {
+            unsafe {
+                ffi::FcConfigAppFontClear(self.d);
+            }
+        }
     }
 
     impl Drop for Config {
@@ -350,10 +1457,31 @@ mod fc {
             }
         }
 
+        // FcPatternAddInteger
+        pub fn push_integer(&mut self, object: Object, value: i32) {
+            unsafe {
+                ffi::FcPatternAddInteger(self.d, object.as_ptr(), value);
+            }
+        }
+
+        // FcPatternAddCharSet
+        pub fn push_char_set(&mut self, object: Object, value: &CharSet) {
+            unsafe {
+                // The pattern takes its own reference to the character set.
+                ffi::FcPatternAddCharSet(self.d, object.as_ptr(), value.d);
+            }
+        }
+
+        // FcPatternGetString, for each value of the object in turn
+        #[inline]
+        pub fn get_strings(&self, object: Object) -> Vec<String> {
+            unsafe { pattern_get_strings(self.d, object) }
+        }
+
         // FcConfigSubstitute
-        pub fn config_substitute(&mut self, match_kind: MatchKind) {
+        pub fn config_substitute(&mut self, config: &Config, match_kind: MatchKind) {
             unsafe {
-                ffi::FcConfigSubstitute(ptr::null_mut(), self.d, match_kind.to_u32());
+                ffi::FcConfigSubstitute(config.d, self.d, match_kind.to_u32());
             }
         }
 
@@ -365,9 +1493,17 @@ mod fc {
         }
 
         // FcFontSort
-        pub fn sorted(&self, config: &Config) -> Result<FontSet, Error> {
+        pub fn sorted(&self, config: &Config, trim: bool) -> Result<FontSet, Error> {
             let mut res = ffi::FcResultMatch;
-            let d = unsafe { ffi::FcFontSort(config.d, self.d, 1, ptr::null_mut(), &mut res) };
+            let d = unsafe {
+                ffi::FcFontSort(
+                    config.d,
+                    self.d,
+                    trim as ffi::FcBool,
+                    ptr::null_mut(),
+                    &mut res,
+                )
+            };
 
             match res {
                 ffi::FcResultMatch => Ok(FontSet { d, idx: 0 }),
@@ -422,6 +1558,12 @@ mod fc {
             }
         }
 
+        // FcPatternGetString, for each value of the object in turn
+        #[inline]
+        pub fn get_strings(&self, object: Object) -> Vec<String> {
+            unsafe { pattern_get_strings(self.d, object) }
+        }
+
         // FcPatternGetInteger
         pub fn get_integer(&self, object: Object) -> Option<i32> {
             unsafe {
@@ -434,6 +1576,59 @@ mod fc {
                 Some(integer)
             }
         }
+
+        // FcPatternGetCharSet
+        pub fn get_char_set(&self, object: Object) -> Option<CharSetRef> {
+            unsafe {
+                let mut char_set = ptr::null_mut();
+                let res = ffi::FcPatternGetCharSet(self.d, object.as_ptr(), 0, &mut char_set);
+                if res != ffi::FcResultMatch || char_set.is_null() {
+                    return None;
+                }
+
+                Some(CharSetRef { d: char_set })
+            }
+        }
+    }
+
+    pub struct CharSet {
+        d: *mut ffi::FcCharSet,
+    }
+
+    impl CharSet {
+        // FcCharSetCreate
+        pub fn new() -> Self {
+            unsafe {
+                CharSet {
+                    d: ffi::FcCharSetCreate(),
+                }
+            }
+        }
+
+        // FcCharSetAddChar
+        pub fn add_char(&mut self, character: char) {
+            unsafe {
+                ffi::FcCharSetAddChar(self.d, character as u32);
+            }
+        }
+    }
+
+    impl Drop for CharSet {
+        fn drop(&mut self) {
+            unsafe { ffi::FcCharSetDestroy(self.d) }
+        }
+    }
+
+    // A read-only `FcCharSet` without a destructor.
+    pub struct CharSetRef {
+        d: *mut ffi::FcCharSet,
+    }
+
+    impl CharSetRef {
+        // FcCharSetHasChar
+        pub fn has_char(&self, character: char) -> bool {
+            unsafe { ffi::FcCharSetHasChar(self.d, character as u32) != 0 }
+        }
     }
 
     pub struct FontSet {
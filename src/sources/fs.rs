@@ -12,14 +12,25 @@
 //!
 //! This source uses the WalkDir abstraction from the `walkdir` crate to locate fonts.
 //!
-//! This is the native source on Android.
+//! On Android, `AndroidSource` uses this source for the fonts that the system configuration
+//! doesn't declare.
 
-use std::fs::File;
-use std::path::PathBuf;
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
 #[cfg(not(any(target_os = "android", target_family = "windows")))]
 use dirs;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 #[cfg(target_family = "windows")]
 use std::ffi::OsString;
 #[cfg(target_family = "windows")]
@@ -33,22 +44,105 @@ use crate::error::SelectionError;
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::file_type::FileType;
-use crate::font::Font;
+use crate::font::{FaceIndex, Font};
 use crate::handle::Handle;
-use crate::properties::Properties;
+use crate::properties::{Properties, PropertiesFilter};
 use crate::source::Source;
-use crate::sources::mem::MemSource;
+use crate::sources::mem::{FamilyEntry, MemSource};
 
 /// A source that loads fonts from a directory or directories on disk.
 ///
 /// This source uses the WalkDir abstraction from the `walkdir` crate to locate fonts.
-///
-/// This is the native source on Android.
 #[allow(missing_debug_implementations)]
 pub struct FsSource {
-    mem_source: MemSource,
+    paths: Vec<PathBuf>,
+    options: FsSourceOptions,
+    cache_path: Option<PathBuf>,
+    mem_source: RwLock<MemSource>,
+    directory_state: Mutex<DirectoryState>,
+}
+
+/// Options that control how an `FsSource` searches its directories.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FsSourceOptions {
+    /// Whether to search the subdirectories of the given directories. The default is true.
+    pub recursive: bool,
+    /// Whether to search directories that symbolic links point to. The default is false.
+    ///
+    /// Links to font files are indexed either way. Links that lead back to a directory that is
+    /// already being searched are skipped, so cycles can't make the search go on forever.
+    pub follow_symlinks: bool,
+    /// How many levels of subdirectories to search, if `recursive` is true. `Some(0)` searches
+    /// only the files directly in the given directories, and `None`, the default, searches
+    /// subdirectories at any depth.
+    pub max_depth: Option<usize>,
+    /// Whether to read font files on Rayon's global thread pool. The default is true.
+    ///
+    /// Either way, the fonts are indexed in order of their paths, so the results of queries are
+    /// the same. Without the `rayon` feature, the files are always read on the calling thread.
+    #[cfg(feature = "rayon")]
+    pub parallel: bool,
+}
+
+impl Default for FsSourceOptions {
+    #[inline]
+    fn default() -> FsSourceOptions {
+        FsSourceOptions {
+            recursive: true,
+            follow_symlinks: false,
+            max_depth: None,
+            #[cfg(feature = "rayon")]
+            parallel: true,
+        }
+    }
+}
+
+impl FsSourceOptions {
+    // Returns a directory walker that searches the given path according to these options.
+    fn walk_dir(&self, path: &Path) -> WalkDir {
+        let mut walk_dir = WalkDir::new(path).follow_links(self.follow_symlinks);
+        // The path itself has depth 0, and the files directly in it have depth 1.
+        match (self.recursive, self.max_depth) {
+            (false, _) => walk_dir = walk_dir.max_depth(1),
+            (true, Some(max_depth)) => walk_dir = walk_dir.max_depth(max_depth.saturating_add(1)),
+            (true, None) => {}
+        }
+        walk_dir
+    }
+}
+
+// What the directories of an `FsSource` looked like when its fonts were last indexed.
+struct DirectoryState {
+    entries: Vec<DirectoryEntry>,
+    generation: u64,
+    // The number of files that were read, rather than taken from the cache, the last time.
+    analyzed_file_count: usize,
+}
+
+// The path and modification time of a file or directory.
+type DirectoryEntry = (PathBuf, Option<SystemTime>);
+
+// The size and modification time of a file, which tell whether its cached fonts are still valid.
+type Fingerprint = (u64, Duration);
+
+// A font in a file, with the names that the index needs.
+#[derive(Clone)]
+struct IndexedFont {
+    font_index: u32,
+    family_name: String,
+    postscript_name: String,
+}
+
+// What a cache knows about a file. Files that aren't fonts are cached too, with no fonts.
+struct CachedFile {
+    fingerprint: Fingerprint,
+    fonts: Vec<IndexedFont>,
 }
 
+// The first bytes of a cache file, followed by the version of the format.
+const CACHE_MAGIC: [u8; 8] = *b"fkfscach";
+const CACHE_VERSION: u32 = 1;
+
 impl FsSource {
     /// Opens the default set of directories on this platform and indexes the fonts found within.
     ///
@@ -56,48 +150,154 @@ impl FsSource {
     /// locate fonts in the typical platform directories, but it is too simple to pick up fonts
     /// that are stored in unusual locations but nevertheless properly installed.
     pub fn new() -> FsSource {
-        let mut fonts = vec![];
-        for font_directory in default_font_directories() {
-            for directory_entry in WalkDir::new(font_directory).into_iter() {
-                let directory_entry = match directory_entry {
-                    Ok(directory_entry) => directory_entry,
-                    Err(_) => continue,
-                };
-                let path = directory_entry.path();
-                let mut file = match File::open(path) {
-                    Err(_) => continue,
-                    Ok(file) => file,
-                };
-                match Font::analyze_file(&mut file) {
-                    Err(_) => continue,
-                    Ok(FileType::Single) => fonts.push(Handle::from_path(path.to_owned(), 0)),
-                    Ok(FileType::Collection(font_count)) => {
-                        for font_index in 0..font_count {
-                            fonts.push(Handle::from_path(path.to_owned(), font_index))
-                        }
-                    }
-                }
-            }
-        }
+        FsSource::from_paths(default_font_directories())
+    }
+
+    /// Indexes all fonts found in the given directory and its subdirectories.
+    pub fn in_path<P: AsRef<Path>>(path: P) -> FsSource {
+        FsSource::from_paths(vec![path.as_ref().to_owned()])
+    }
+
+    /// Indexes all fonts found at the given paths, each of which may be a font file or a
+    /// directory to search along with its subdirectories.
+    ///
+    /// Paths that can't be read and fonts that fail to load are skipped with a warning, and files
+    /// that aren't fonts are ignored.
+    pub fn from_paths<I>(paths: I) -> FsSource
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        FsSource::with_options(paths, FsSourceOptions::default())
+    }
+
+    /// Indexes all fonts found at the given paths, each of which may be a font file or a
+    /// directory, searching directories as the options specify.
+    ///
+    /// Paths that can't be read and fonts that fail to load are skipped with a warning, and files
+    /// that aren't fonts are ignored.
+    pub fn with_options<I>(paths: I, options: FsSourceOptions) -> FsSource
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        FsSource::index(paths.into_iter().collect(), options, None)
+    }
+
+    /// Opens the default set of directories on this platform and indexes the fonts found within,
+    /// keeping what it finds in a cache file at the given path.
+    ///
+    /// Like `with_options_and_cache`, this only reads the font files that were added or changed
+    /// since the cache was written.
+    pub fn with_cache<P: AsRef<Path>>(cache_path: P) -> FsSource {
+        FsSource::with_options_and_cache(
+            default_font_directories(),
+            FsSourceOptions::default(),
+            cache_path,
+        )
+    }
+
+    /// Indexes all fonts found at the given paths like `with_options`, keeping what it finds in a
+    /// cache file at the given path.
+    ///
+    /// Fonts in files whose size and modification time are the same as when the cache was written
+    /// are taken from the cache instead of being read again. The cache is written whenever the
+    /// fonts are indexed and a file had to be read or was removed, including when `generation`
+    /// finds that the directories changed. A cache file that is missing, corrupt, or from another
+    /// version of this library is rebuilt, and failures to write it are only logged.
+    pub fn with_options_and_cache<I, P>(
+        paths: I,
+        options: FsSourceOptions,
+        cache_path: P,
+    ) -> FsSource
+    where
+        I: IntoIterator<Item = PathBuf>,
+        P: AsRef<Path>,
+    {
+        FsSource::index(
+            paths.into_iter().collect(),
+            options,
+            Some(cache_path.as_ref().to_owned()),
+        )
+    }
 
+    fn index(
+        paths: Vec<PathBuf>,
+        options: FsSourceOptions,
+        cache_path: Option<PathBuf>,
+    ) -> FsSource {
+        let entries = directory_entries(&paths, &options);
+        let (mem_source, analyzed_file_count) =
+            index_fonts(&paths, &options, cache_path.as_deref());
         FsSource {
-            mem_source: MemSource::from_fonts(fonts.into_iter()).unwrap(),
+            mem_source: RwLock::new(mem_source),
+            paths,
+            options,
+            cache_path,
+            directory_state: Mutex::new(DirectoryState {
+                entries,
+                generation: 0,
+                analyzed_file_count,
+            }),
         }
     }
 
+    /// Returns a number that increases whenever fonts are added to, removed from, or modified in
+    /// the directories of this source.
+    ///
+    /// This walks the directories and compares the modification times of everything in them with
+    /// those from when the fonts were last indexed. If anything changed, the fonts are indexed
+    /// again before this returns.
+    ///
+    /// Every call reads the metadata of every file in the directories, which takes I/O
+    /// proportional to the number of fonts, so avoid calling this once per frame.
+    pub fn generation(&self) -> u64 {
+        let mut directory_state = self.directory_state.lock().unwrap();
+        let entries = directory_entries(&self.paths, &self.options);
+        if entries != directory_state.entries {
+            let (mem_source, analyzed_file_count) =
+                index_fonts(&self.paths, &self.options, self.cache_path.as_deref());
+            *self.mem_source.write().unwrap() = mem_source;
+            directory_state.entries = entries;
+            directory_state.generation += 1;
+            directory_state.analyzed_file_count = analyzed_file_count;
+        }
+        directory_state.generation
+    }
+
+    // Returns the number of font files that were read the last time the fonts were indexed.
+    #[cfg(test)]
+    pub(crate) fn analyzed_file_count(&self) -> usize {
+        self.directory_state.lock().unwrap().analyzed_file_count
+    }
+
+    fn mem_source(&self) -> RwLockReadGuard<'_, MemSource> {
+        self.mem_source.read().unwrap()
+    }
+
     /// Returns paths of all fonts installed on the system.
     pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
-        self.mem_source.all_fonts()
+        self.mem_source().all_fonts()
     }
 
     /// Returns the names of all families installed on the system.
     pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
-        self.mem_source.all_families()
+        self.mem_source().all_families()
     }
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// The comparison is case-insensitive, but a family whose name matches exactly is preferred.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
-        self.mem_source.select_family_by_name(family_name)
+        self.mem_source().select_family_by_name(family_name)
+    }
+
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
     }
 
     /// Selects a font by PostScript name, which should be a unique identifier.
@@ -108,7 +308,45 @@ impl FsSource {
         &self,
         postscript_name: &str,
     ) -> Result<Handle, SelectionError> {
-        self.mem_source.select_by_postscript_name(postscript_name)
+        self.mem_source().select_by_postscript_name(postscript_name)
+    }
+
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    ///
+    /// This implementation does a brute-force search of installed fonts to find the one that
+    /// matches.
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.mem_source().select_by_full_name(full_name)
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    ///
+    /// This implementation does a brute-force search of installed fonts to find the one that
+    /// matches.
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.mem_source().select_by_family_and_style(family, style)
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.mem_source().select_by_postscript_name_prefix(prefix)
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.mem_source().select_by_properties(filter)
     }
 
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
@@ -121,6 +359,27 @@ impl FsSource {
     ) -> Result<Handle, SelectionError> {
         <Self as Source>::select_best_match(self, family_names, properties)
     }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    #[inline]
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_matches(self, family_names, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    #[inline]
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        <Self as Source>::select_families_by_unicode_coverage(self, characters)
+    }
 }
 
 impl Source for FsSource {
@@ -141,6 +400,359 @@ impl Source for FsSource {
     fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
         self.select_by_postscript_name(postscript_name)
     }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_postscript_name_prefix(prefix)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
+}
+
+// Indexes the fonts at the given paths, taking those in files that haven't changed from the cache
+// if there is one and then updating it. Also returns the number of files that were read.
+fn index_fonts(
+    paths: &[PathBuf],
+    options: &FsSourceOptions,
+    cache_path: Option<&Path>,
+) -> (MemSource, usize) {
+    let mut file_paths = vec![];
+    for path in paths {
+        discover_files(path, options, &mut file_paths);
+    }
+    // Directories list their files in no particular order, so sort the files to make the order of
+    // the fonts within each family predictable.
+    file_paths.sort();
+
+    let (mut cached_files, fingerprints) = match cache_path {
+        Some(cache_path) => (
+            read_cache(cache_path),
+            file_paths.iter().map(|path| fingerprint(path)).collect(),
+        ),
+        None => (BTreeMap::new(), vec![None; file_paths.len()]),
+    };
+    let mut file_fonts: Vec<Option<Vec<IndexedFont>>> = file_paths
+        .iter()
+        .zip(&fingerprints)
+        .map(|(path, fingerprint)| match cached_files.remove(path) {
+            Some(cached_file) if Some(cached_file.fingerprint) == *fingerprint => {
+                Some(cached_file.fonts)
+            }
+            _ => None,
+        })
+        .collect();
+
+    let stale_paths: Vec<PathBuf> = file_paths
+        .iter()
+        .zip(&file_fonts)
+        .filter(|&(_, fonts)| fonts.is_none())
+        .map(|(path, _)| path.clone())
+        .collect();
+    let analyzed_file_count = stale_paths.len();
+    let analyzed_fonts = analyze_files(stale_paths, options);
+    for (fonts, analyzed_fonts) in file_fonts
+        .iter_mut()
+        .filter(|fonts| fonts.is_none())
+        .zip(analyzed_fonts)
+    {
+        *fonts = Some(analyzed_fonts);
+    }
+    let file_fonts: Vec<Vec<IndexedFont>> = file_fonts.into_iter().map(Option::unwrap).collect();
+
+    // The cache only needs writing if a file was read or a file that it knows about is gone.
+    let cache_is_current = analyzed_file_count == 0 && cached_files.is_empty();
+    if let (Some(cache_path), false) = (cache_path, cache_is_current) {
+        let cached_files = file_paths
+            .iter()
+            .zip(fingerprints)
+            .zip(&file_fonts)
+            .filter_map(|((path, fingerprint), fonts)| {
+                Some((
+                    path.clone(),
+                    CachedFile {
+                        fingerprint: fingerprint?,
+                        fonts: fonts.clone(),
+                    },
+                ))
+            })
+            .collect();
+        if let Err(error) = write_cache(cache_path, &cached_files) {
+            warn!(
+                "failed to write font cache {}: {}",
+                cache_path.display(),
+                error
+            );
+        }
+    }
+
+    let family_entries = file_paths
+        .iter()
+        .zip(file_fonts)
+        .flat_map(|(path, fonts)| {
+            fonts.into_iter().map(move |font| FamilyEntry {
+                family_name: font.family_name,
+                postscript_name: font.postscript_name,
+                font: Handle::from_path(path.clone(), FaceIndex(font.font_index)),
+            })
+        })
+        .collect();
+    (
+        MemSource::from_family_entries(family_entries),
+        analyzed_file_count,
+    )
+}
+
+// Lists everything at the given paths, in a stable order.
+fn directory_entries(paths: &[PathBuf], options: &FsSourceOptions) -> Vec<DirectoryEntry> {
+    let mut entries = vec![];
+    for path in paths {
+        for directory_entry in options
+            .walk_dir(path)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            .into_iter()
+            .flatten()
+        {
+            let modified = directory_entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok());
+            entries.push((directory_entry.into_path(), modified));
+        }
+    }
+    entries
+}
+
+fn discover_files(path: &Path, options: &FsSourceOptions, file_paths: &mut Vec<PathBuf>) {
+    for directory_entry in options.walk_dir(path) {
+        let directory_entry = match directory_entry {
+            Ok(directory_entry) => directory_entry,
+            // Links back to a directory that is being searched are skipped, as the options
+            // promise; they aren't unreadable.
+            Err(ref error) if error.loop_ancestor().is_some() => continue,
+            Err(error) => {
+                warn!("skipping unreadable font path: {}", error);
+                continue;
+            }
+        };
+        if !directory_entry.file_type().is_dir() {
+            file_paths.push(directory_entry.into_path());
+        }
+    }
+}
+
+// Loads the fonts in a file. Files that aren't fonts yield nothing, and fonts that fail to load are
+// skipped with a warning.
+fn analyze_file(path: &Path) -> Vec<IndexedFont> {
+    let mut file = match File::open(path) {
+        Err(_) => return vec![],
+        Ok(file) => file,
+    };
+    let font_count = match Font::analyze_file(&mut file) {
+        Err(_) => return vec![],
+        Ok(FileType::Single) => 1,
+        Ok(FileType::Collection(font_count)) => font_count,
+    };
+    (0..font_count)
+        .filter_map(|font_index| {
+            let handle = Handle::from_path(path.to_owned(), FaceIndex(font_index));
+            match FamilyEntry::new(handle) {
+                Ok(family_entry) => family_entry.map(|family_entry| IndexedFont {
+                    font_index,
+                    family_name: family_entry.family_name,
+                    postscript_name: family_entry.postscript_name,
+                }),
+                Err(error) => {
+                    warn!(
+                        "skipping font {} in {}: {}",
+                        font_index,
+                        path.display(),
+                        error
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// Loads the fonts in the files, on Rayon's thread pool if the options ask for it, returning those
+// of each file in the order of the files.
+#[cfg(feature = "rayon")]
+fn analyze_files(file_paths: Vec<PathBuf>, options: &FsSourceOptions) -> Vec<Vec<IndexedFont>> {
+    if options.parallel {
+        file_paths
+            .par_iter()
+            .map(|path| analyze_file(path))
+            .collect()
+    } else {
+        file_paths.iter().map(|path| analyze_file(path)).collect()
+    }
+}
+
+// Loads the fonts in the files, returning those of each file in the order of the files.
+#[cfg(not(feature = "rayon"))]
+fn analyze_files(file_paths: Vec<PathBuf>, _: &FsSourceOptions) -> Vec<Vec<IndexedFont>> {
+    file_paths.iter().map(|path| analyze_file(path)).collect()
+}
+
+// Fingerprints a file, if its size and modification time can be read.
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified))
+}
+
+// Reads a cache file, returning nothing if it doesn't exist or can't be parsed.
+fn read_cache(cache_path: &Path) -> BTreeMap<PathBuf, CachedFile> {
+    let data = match fs::read(cache_path) {
+        Ok(data) => data,
+        Err(_) => return BTreeMap::new(),
+    };
+    match parse_cache(&data) {
+        Some(cached_files) => cached_files,
+        None => {
+            warn!("ignoring invalid font cache {}", cache_path.display());
+            BTreeMap::new()
+        }
+    }
+}
+
+// Parses a cache file. All numbers are big-endian, and strings are prefixed with their length in
+// bytes as a `u32`:
+//
+//     magic: [u8; 8], version: u32, file_count: u32, files: [File; file_count]
+//     File: path: String, size: u64, seconds: u64, nanoseconds: u32, font_count: u32,
+//           fonts: [Font; font_count]
+//     Font: font_index: u32, family_name: String, postscript_name: String
+fn parse_cache(mut data: &[u8]) -> Option<BTreeMap<PathBuf, CachedFile>> {
+    if take_bytes(&mut data, 8)? != CACHE_MAGIC || take_u32(&mut data)? != CACHE_VERSION {
+        return None;
+    }
+    let mut cached_files = BTreeMap::new();
+    for _ in 0..take_u32(&mut data)? {
+        let path = PathBuf::from(take_string(&mut data)?);
+        let size = take_u64(&mut data)?;
+        let (seconds, nanoseconds) = (take_u64(&mut data)?, take_u32(&mut data)?);
+        if nanoseconds >= 1_000_000_000 {
+            return None;
+        }
+        let mut fonts = vec![];
+        for _ in 0..take_u32(&mut data)? {
+            fonts.push(IndexedFont {
+                font_index: take_u32(&mut data)?,
+                family_name: take_string(&mut data)?,
+                postscript_name: take_string(&mut data)?,
+            });
+        }
+        let fingerprint = (size, Duration::new(seconds, nanoseconds));
+        cached_files.insert(path, CachedFile { fingerprint, fonts });
+    }
+    if data.is_empty() {
+        Some(cached_files)
+    } else {
+        None
+    }
+}
+
+// Writes a cache file in the format that `parse_cache` reads. Files whose paths aren't valid
+// Unicode are left out.
+fn write_cache(cache_path: &Path, cached_files: &BTreeMap<PathBuf, CachedFile>) -> io::Result<()> {
+    let cached_files: Vec<_> = cached_files
+        .iter()
+        .filter_map(|(path, cached_file)| Some((path.to_str()?, cached_file)))
+        .collect();
+    let mut data = CACHE_MAGIC.to_vec();
+    data.write_u32::<BigEndian>(CACHE_VERSION)?;
+    data.write_u32::<BigEndian>(cached_files.len() as u32)?;
+    for (path, cached_file) in cached_files {
+        let (size, modified) = cached_file.fingerprint;
+        write_string(&mut data, path)?;
+        data.write_u64::<BigEndian>(size)?;
+        data.write_u64::<BigEndian>(modified.as_secs())?;
+        data.write_u32::<BigEndian>(modified.subsec_nanos())?;
+        data.write_u32::<BigEndian>(cached_file.fonts.len() as u32)?;
+        for font in &cached_file.fonts {
+            data.write_u32::<BigEndian>(font.font_index)?;
+            write_string(&mut data, &font.family_name)?;
+            write_string(&mut data, &font.postscript_name)?;
+        }
+    }
+
+    // Write to a temporary file first, so that a source that starts up meanwhile doesn't read a
+    // partly written cache. Each write gets its own temporary file, so that sources in other
+    // processes or on other threads that write the same cache don't interleave their data.
+    static WRITE_COUNT: AtomicUsize = AtomicUsize::new(0);
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary_path = cache_path.as_os_str().to_owned();
+    temporary_path.push(format!(
+        ".{}-{}.tmp",
+        process::id(),
+        WRITE_COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result =
+        fs::write(&temporary_path, data).and_then(|()| fs::rename(&temporary_path, cache_path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    result
+}
+
+fn take_bytes<'a>(data: &mut &'a [u8], length: usize) -> Option<&'a [u8]> {
+    if data.len() < length {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(length);
+    *data = rest;
+    Some(bytes)
+}
+
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+    take_bytes(data, 4).map(BigEndian::read_u32)
+}
+
+fn take_u64(data: &mut &[u8]) -> Option<u64> {
+    take_bytes(data, 8).map(BigEndian::read_u64)
+}
+
+fn take_string(data: &mut &[u8]) -> Option<String> {
+    let length = take_u32(data)? as usize;
+    String::from_utf8(take_bytes(data, length)?.to_vec()).ok()
+}
+
+fn write_string(data: &mut Vec<u8>, string: &str) -> io::Result<()> {
+    data.write_u32::<BigEndian>(string.len() as u32)?;
+    data.extend_from_slice(string.as_bytes());
+    Ok(())
 }
 
 #[cfg(target_os = "android")]
@@ -183,9 +795,9 @@ fn default_font_directories() -> Vec<PathBuf> {
         PathBuf::from("/usr/share/fonts"),
         PathBuf::from("/usr/local/share/fonts"),
     ];
-    if let Some(mut path) = dirs::home_dir() {
-        path.push(".fonts");
-        directories.push(path);
+    if let Some(home_directory) = dirs::home_dir() {
+        directories.push(home_directory.join(".fonts"));
+        directories.push(home_directory.join(".local").join("share").join("fonts"));
     }
     directories
 }
@@ -10,18 +10,29 @@
 
 //! A source that keeps fonts in memory.
 
+use std::collections::BTreeSet;
+use std::iter;
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use crate::error::{FontLoadingError, SelectionError};
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
-use crate::font::Font;
+use crate::file_type::FileType;
+use crate::font::{FaceIndex, Font};
 use crate::handle::Handle;
-use crate::properties::Properties;
+use crate::properties::{Properties, PropertiesFilter};
 use crate::source::Source;
+use crate::utils;
 
 /// A source that keeps fonts in memory.
+///
+/// Fonts can be added and removed after the source is created; queries reflect the change
+/// immediately.
 #[allow(missing_debug_implementations)]
 pub struct MemSource {
     families: Vec<FamilyEntry>,
+    generation: u64,
 }
 
 impl MemSource {
@@ -34,17 +45,123 @@ impl MemSource {
     {
         let mut families = vec![];
         for handle in fonts {
-            let font = Font::from_handle(&handle)?;
-            if let Some(postscript_name) = font.postscript_name() {
-                families.push(FamilyEntry {
-                    family_name: font.family_name(),
-                    postscript_name: postscript_name,
-                    font: handle,
-                })
+            families.extend(FamilyEntry::new(handle)?);
+        }
+        Ok(MemSource::from_family_entries(families))
+    }
+
+    /// Creates a new memory source that contains exactly the fonts in the given files.
+    ///
+    /// Every font in a font collection is included. Unlike `FsSource`, which skips what it can't
+    /// load, this fails with `SelectionError::CannotAccessSource` if any of the files can't be
+    /// read or isn't a font, so the fonts available for selection never depend on the system.
+    pub fn from_paths(paths: &[PathBuf]) -> Result<MemSource, SelectionError> {
+        let mut families = vec![];
+        for path in paths {
+            let font_count = match Font::analyze_path(path) {
+                Ok(FileType::Single) => 1,
+                Ok(FileType::Collection(font_count)) => font_count,
+                Err(_) => return Err(SelectionError::CannotAccessSource),
+            };
+            for font_index in 0..font_count {
+                let handle = Handle::from_path(path.clone(), FaceIndex(font_index));
+                match FamilyEntry::new(handle) {
+                    Ok(family_entry) => families.extend(family_entry),
+                    Err(_) => return Err(SelectionError::CannotAccessSource),
+                }
+            }
+        }
+        Ok(MemSource::from_family_entries(families))
+    }
+
+    /// Creates a new memory source that contains the fonts in the given buffers of font data.
+    ///
+    /// Each buffer may hold a single font or a collection, all of whose fonts are included. Only
+    /// the names of the fonts are read up front, with `Loader::peek_info`, and queries return
+    /// memory handles that share the buffers, so this source never touches the file system. As
+    /// with `from_fonts`, fonts without a PostScript name are skipped.
+    pub fn from_bytes(fonts: Vec<Arc<Vec<u8>>>) -> Result<MemSource, FontLoadingError> {
+        let mut families = vec![];
+        for font_data in fonts {
+            let font_count = match Font::analyze_bytes(font_data.clone())? {
+                FileType::Single => 1,
+                FileType::Collection(font_count) => font_count,
+            };
+            for font_index in 0..font_count {
+                let font_info = Font::peek_info(&font_data, FaceIndex(font_index))?;
+                if let Some(postscript_name) = font_info.postscript_name {
+                    families.push(FamilyEntry {
+                        family_name: font_info.family_name,
+                        postscript_name,
+                        font: Handle::from_memory(font_data.clone(), FaceIndex(font_index)),
+                    });
+                }
             }
         }
+        Ok(MemSource::from_family_entries(families))
+    }
+
+    // Creates a memory source from fonts whose names have already been read.
+    pub(crate) fn from_family_entries(mut families: Vec<FamilyEntry>) -> MemSource {
         families.sort_by(|a, b| a.family_name.cmp(&b.family_name));
-        Ok(MemSource { families })
+        MemSource {
+            families,
+            generation: 0,
+        }
+    }
+
+    /// Adds the font referenced by the given handle to this source.
+    ///
+    /// The font is eagerly loaded into memory. If it fails to load, the error is returned and the
+    /// source is left unchanged. As with `from_fonts`, fonts without a PostScript name are
+    /// skipped.
+    pub fn add_font(&mut self, handle: Handle) -> Result<(), FontLoadingError> {
+        self.add_fonts(iter::once(handle))
+    }
+
+    /// Adds the fonts referenced by the given handles to this source.
+    ///
+    /// The fonts are eagerly loaded into memory. If any of them fails to load, the error is
+    /// returned and none of them are added.
+    pub fn add_fonts<I>(&mut self, fonts: I) -> Result<(), FontLoadingError>
+    where
+        I: Iterator<Item = Handle>,
+    {
+        let mut new_families = vec![];
+        for handle in fonts {
+            new_families.extend(FamilyEntry::new(handle)?);
+        }
+        if new_families.is_empty() {
+            return Ok(());
+        }
+
+        // The sort is stable, so fonts added to an existing family come after its other members.
+        self.families.extend(new_families);
+        self.families
+            .sort_by(|a, b| a.family_name.cmp(&b.family_name));
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Removes the fonts with the given PostScript name from this source.
+    ///
+    /// Returns true if any font was removed. A family disappears from the source once its last
+    /// font is removed.
+    pub fn remove_font_by_postscript_name(&mut self, postscript_name: &str) -> bool {
+        let family_count = self.families.len();
+        self.families
+            .retain(|family_entry| family_entry.postscript_name != postscript_name);
+        if self.families.len() == family_count {
+            return false;
+        }
+        self.generation += 1;
+        true
+    }
+
+    /// Returns a number that increases whenever fonts are added to or removed from this source.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
     /// Returns paths of all fonts installed on the system.
@@ -72,12 +189,22 @@ impl MemSource {
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
     ///
-    /// FIXME(pcwalton): Case-insensitive comparison.
+    /// The comparison is case-insensitive, but a family whose name matches exactly is preferred.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
-        let mut first_family_index = self
+        let mut first_family_index = match self
             .families
             .binary_search_by(|family| (&*family.family_name).cmp(family_name))
-            .map_err(|_| SelectionError::NotFound)?;
+        {
+            Ok(family_index) => family_index,
+            Err(_) => {
+                let folded_family_name = utils::fold_case(family_name);
+                self.families
+                    .iter()
+                    .position(|family| utils::fold_case(&family.family_name) == folded_family_name)
+                    .ok_or(SelectionError::NotFound)?
+            }
+        };
+        let family_name = &*self.families[first_family_index].family_name;
 
         while first_family_index > 0
             && self.families[first_family_index - 1].family_name == family_name
@@ -92,9 +219,20 @@ impl MemSource {
         }
 
         let families = &self.families[first_family_index..(last_family_index + 1)];
-        Ok(FamilyHandle::from_font_handles(
-            families.iter().map(|family| family.font.clone()),
-        ))
+        let mut family_handle =
+            FamilyHandle::from_font_handles(families.iter().map(|family| family.font.clone()));
+        family_handle.set_name(family_name.to_owned());
+        Ok(family_handle)
+    }
+
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
     }
 
     /// Selects a font by PostScript name, which should be a unique identifier.
@@ -113,6 +251,57 @@ impl MemSource {
             .ok_or(SelectionError::NotFound)
     }
 
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    ///
+    /// This implementation does a brute-force search of the fonts in the source.
+    #[inline]
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        <Self as Source>::select_by_full_name(self, full_name)
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    ///
+    /// This implementation does a brute-force search of the fonts in the source.
+    #[inline]
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        <Self as Source>::select_by_family_and_style(self, family, style)
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let handles: Vec<_> = self
+            .families
+            .iter()
+            .filter(|family_entry| family_entry.postscript_name.starts_with(prefix))
+            .map(|family_entry| family_entry.font.clone())
+            .collect();
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    ///
+    /// This implementation does a brute-force search of the fonts in the source.
+    #[inline]
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_by_properties(self, filter)
+    }
+
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
     /// handle.
     #[inline]
@@ -123,6 +312,27 @@ impl MemSource {
     ) -> Result<Handle, SelectionError> {
         <Self as Source>::select_best_match(self, family_names, properties)
     }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    #[inline]
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_matches(self, family_names, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    #[inline]
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        <Self as Source>::select_families_by_unicode_coverage(self, characters)
+    }
 }
 
 impl Source for MemSource {
@@ -143,10 +353,35 @@ impl Source for MemSource {
     fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
         self.select_by_postscript_name(postscript_name)
     }
+
+    #[inline]
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_postscript_name_prefix(prefix)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
 }
 
-struct FamilyEntry {
-    family_name: String,
-    postscript_name: String,
-    font: Handle,
+pub(crate) struct FamilyEntry {
+    pub(crate) family_name: String,
+    pub(crate) postscript_name: String,
+    pub(crate) font: Handle,
+}
+
+impl FamilyEntry {
+    // Loads the font to read its names. Returns `None` if the font has no PostScript name.
+    pub(crate) fn new(handle: Handle) -> Result<Option<FamilyEntry>, FontLoadingError> {
+        let font = Font::from_handle(&handle)?;
+        Ok(font.postscript_name().map(|postscript_name| FamilyEntry {
+            family_name: font.family_name(),
+            postscript_name,
+            font: handle,
+        }))
+    }
 }
@@ -10,9 +10,13 @@
 
 //! Various databases of installed fonts that can be queried.
 //!
-//! The system-specific sources (Core Text, DirectWrite, and Fontconfig) contain the fonts that are
-//! installed on the system. The remaining databases (`fs`, `mem`, and `multi`) allow `font-kit` to
-//! query fonts not installed on the system.
+//! The system-specific sources (Android, Core Text, DirectWrite, and Fontconfig) contain the fonts
+//! that are installed on the system. The remaining databases (`fs`, `mem`, and `multi`) allow
+//! `font-kit` to query fonts not installed on the system, and `cache` speeds up queries to any of
+//! the others.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod android;
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod core_text;
@@ -31,6 +35,8 @@ pub mod directwrite;
 ))]
 pub mod fontconfig;
 
+pub mod cache;
+
 #[cfg(not(target_arch = "wasm32"))]
 pub mod fs;
 
@@ -13,26 +13,89 @@
 //! This is useful when an application wants a library of fonts consisting of the installed system
 //! fonts plus some other application-supplied fonts.
 
+use std::collections::{BTreeSet, HashSet};
+
 use crate::error::SelectionError;
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::handle::Handle;
-use crate::properties::Properties;
+use crate::properties::{Properties, PropertiesFilter};
 use crate::source::Source;
+use crate::utils;
 
 /// A source that encapsulates multiple sources and allows them to be queried as a group.
 ///
 /// This is useful when an application wants a library of fonts consisting of the installed system
 /// fonts plus some other application-supplied fonts.
+///
+/// The subsources are in order of priority: queries that return a single result return the first
+/// one that a subsource finds, so a family in an earlier subsource hides a family with the same
+/// name in a later one.
 #[allow(missing_debug_implementations)]
 pub struct MultiSource {
     subsources: Vec<Box<dyn Source>>,
+    // Application-supplied mappings from generic families to family names.
+    generic_families: Vec<(FamilyName, Vec<String>)>,
+    merge_families: bool,
 }
 
 impl MultiSource {
-    /// Creates a new source that contains all the fonts in the supplied sources.
+    /// Creates a new source that contains all the fonts in the supplied sources, which are in
+    /// order of priority.
     pub fn from_sources(subsources: Vec<Box<dyn Source>>) -> MultiSource {
-        MultiSource { subsources }
+        MultiSource {
+            subsources,
+            generic_families: vec![],
+            merge_families: false,
+        }
+    }
+
+    /// Sets whether a family that several subsources have is made up of the fonts from all of
+    /// them, rather than just those from the first subsource that has it.
+    ///
+    /// Merging lets matching pick a style that only a later subsource has, such as a bold face
+    /// that the bundled fonts lack. The fonts of earlier subsources come first in the family, so
+    /// they still win over fonts in later subsources that match equally well. Merging is off by
+    /// default.
+    pub fn set_merge_families(&mut self, merge_families: bool) {
+        self.merge_families = merge_families;
+    }
+
+    /// Sets the names of the families that a generic family stands for, in order of preference.
+    ///
+    /// This replaces the mapping that the subsources provide, so that applications can choose
+    /// their own default fonts. Setting an empty list restores the subsources' mapping.
+    pub fn set_generic_families(&mut self, generic_family: FamilyName, family_names: Vec<String>) {
+        self.generic_families
+            .retain(|(family_name, _)| *family_name != generic_family);
+        if !family_names.is_empty() {
+            self.generic_families.push((generic_family, family_names));
+        }
+    }
+
+    /// Returns the names of the families that a generic family stands for, in order of
+    /// preference. A specific family just stands for itself.
+    ///
+    /// Unless the mapping has been set with `set_generic_families`, these are the families that
+    /// each subsource lists, in turn.
+    pub fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        if let Some((_, family_names)) = self
+            .generic_families
+            .iter()
+            .find(|(generic_family, _)| generic_family == family_name)
+        {
+            return family_names.clone();
+        }
+
+        let mut family_names = vec![];
+        for subsource in &self.subsources {
+            for subsource_family_name in subsource.generic_family_names(family_name) {
+                if !family_names.contains(&subsource_family_name) {
+                    family_names.push(subsource_family_name);
+                }
+            }
+        }
+        family_names
     }
 
     /// Returns paths of all fonts installed on the system.
@@ -45,24 +108,55 @@ impl MultiSource {
     }
 
     /// Returns the names of all families installed on the system.
+    ///
+    /// Families that several subsources have are listed once, under the name that the first of
+    /// them gives; names that differ only in case are considered the same.
     pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
         let mut families = vec![];
+        let mut folded_families = HashSet::new();
         for subsource in &self.subsources {
-            families.extend(subsource.all_families()?.into_iter())
+            for family_name in subsource.all_families()? {
+                if folded_families.insert(utils::fold_case(&family_name)) {
+                    families.push(family_name);
+                }
+            }
         }
         Ok(families)
     }
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// The family comes from the first subsource that has it, unless `set_merge_families` has
+    /// turned on merging.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        let mut merged_family: Option<FamilyHandle> = None;
         for subsource in &self.subsources {
             match subsource.select_family_by_name(family_name) {
-                Ok(family) => return Ok(family),
+                Ok(family) if !self.merge_families => return Ok(family),
+                Ok(family) => match merged_family {
+                    Some(ref mut merged_family) => {
+                        if merged_family.name.is_none() {
+                            merged_family.name = family.name;
+                        }
+                        merged_family.fonts.extend(family.fonts)
+                    }
+                    None => merged_family = Some(family),
+                },
                 Err(SelectionError::NotFound) => {}
                 Err(err) => return Err(err),
             }
         }
-        Err(SelectionError::NotFound)
+        merged_family.ok_or(SelectionError::NotFound)
+    }
+
+    /// Looks up a font family by a name that may be spelled loosely, such as "Helvetica-Neue" or
+    /// "Segoe UI Regular", and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name_fuzzy(
+        &self,
+        family_name: &str,
+    ) -> Result<FamilyHandle, SelectionError> {
+        <Self as Source>::select_family_by_name_fuzzy(self, family_name)
     }
 
     /// Selects a font by PostScript name, which should be a unique identifier.
@@ -80,6 +174,98 @@ impl MultiSource {
         Err(SelectionError::NotFound)
     }
 
+    /// Selects a font by its full name, such as "Fira Sans SemiBold Italic".
+    pub fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        for subsource in &self.subsources {
+            match subsource.select_by_full_name(full_name) {
+                Ok(font) => return Ok(font),
+                Err(SelectionError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects a font by its family name and style name, such as "Fira Sans" and "SemiBold
+    /// Italic".
+    pub fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        for subsource in &self.subsources {
+            match subsource.select_by_family_and_style(family, style) {
+                Ok(font) => return Ok(font),
+                Err(SelectionError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects all fonts whose PostScript names start with the given prefix.
+    pub fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for subsource in &self.subsources {
+            match subsource.select_by_postscript_name_prefix(prefix) {
+                Ok(subsource_handles) => handles.extend(subsource_handles),
+                Err(SelectionError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects all fonts whose properties satisfy `filter`, such as every monospace font or every
+    /// font at least as heavy as bold.
+    ///
+    /// The fonts of each subsource come in the order of the subsources.
+    pub fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let mut handles = vec![];
+        for subsource in &self.subsources {
+            match subsource.select_by_properties(filter) {
+                Ok(subsource_handles) => handles.extend(subsource_handles),
+                Err(SelectionError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if !handles.is_empty() {
+            Ok(handles)
+        } else {
+            Err(SelectionError::NotFound)
+        }
+    }
+
+    /// Selects a font that has a glyph for the given character, preferring fonts whose properties
+    /// match `properties` most closely.
+    ///
+    /// Subsources are tried in order.
+    pub fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        for subsource in &self.subsources {
+            match subsource.select_fallback_for_char(character, locale, properties) {
+                Ok(font) => return Ok(font),
+                Err(SelectionError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
     /// handle.
     #[inline]
@@ -90,6 +276,34 @@ impl MultiSource {
     ) -> Result<Handle, SelectionError> {
         <Self as Source>::select_best_match(self, family_names, properties)
     }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handles of all the candidate fonts, best match first.
+    #[inline]
+    pub fn select_matches(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        <Self as Source>::select_matches(self, family_names, properties)
+    }
+
+    /// Selects the families that have glyphs for any of the given characters, those that cover
+    /// the most characters first.
+    #[inline]
+    pub fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        <Self as Source>::select_families_by_unicode_coverage(self, characters)
+    }
+
+    /// Returns a number that increases whenever the fonts in any of the subsources change.
+    pub fn generation(&self) -> u64 {
+        self.subsources.iter().fold(0, |generation, subsource| {
+            generation.wrapping_add(subsource.generation())
+        })
+    }
 }
 
 impl Source for MultiSource {
@@ -112,4 +326,54 @@ impl Source for MultiSource {
     fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
         self.select_by_postscript_name(postscript_name)
     }
+
+    #[inline]
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_full_name(full_name)
+    }
+
+    #[inline]
+    fn select_by_family_and_style(
+        &self,
+        family: &str,
+        style: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.select_by_family_and_style(family, style)
+    }
+
+    #[inline]
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_postscript_name_prefix(prefix)
+    }
+
+    #[inline]
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        self.select_by_properties(filter)
+    }
+
+    #[inline]
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        locale: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_fallback_for_char(character, locale, properties)
+    }
+
+    #[inline]
+    fn generic_family_names(&self, family_name: &FamilyName) -> Vec<String> {
+        self.generic_family_names(family_name)
+    }
+
+    #[inline]
+    fn generation(&self) -> u64 {
+        self.generation()
+    }
 }
@@ -0,0 +1,724 @@
+// font-kit/src/synthetic.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fonts with simulated styles, for when a family doesn't have the face that was asked for.
+//!
+//! The simulation works on glyph outlines, so it is the same on every platform; the underlying
+//! platform font is not modified.
+
+use euclid::default::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use lyon_path::builder::PathBuilder;
+use lyon_path::{Path as GlyphPath, PathEvent};
+use std::sync::Arc;
+
+use crate::canvas::{AlphaMode, Canvas, RasterizationOptions};
+use crate::embedding::EmbeddingMode;
+use crate::error::{FontLoadingError, GlyphLoadingError};
+use crate::file_type::FileType;
+use crate::font::{FaceIndex, Font};
+use crate::font_info::FontInfo;
+use crate::handle::Handle;
+use crate::hinting::HintingOptions;
+use crate::loader::{FallbackFont, FallbackResult, FontBytesRef, FontTransform, Loader};
+use crate::metrics::Metrics;
+use crate::properties::Properties;
+use crate::rasterizer::Rasterizer;
+use crate::unicode_ranges::UnicodeRanges;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// The extra weight, in font units, that is suitable for simulating a bold face of a regular font.
+///
+/// For a font with 1,000 units per em, this thickens stems by one pixel at 40 pixels per em.
+pub const DEFAULT_EXTRA_WEIGHT: f32 = 25.0;
+
+/// The slant, in degrees, that is suitable for simulating an italic face of an upright font.
+pub const DEFAULT_SLANT_ANGLE: f32 = 12.0;
+
+const GLYF_TABLE_TAG: u32 = 0x676c_7966;
+const CFF_TABLE_TAG: u32 = 0x4346_4620;
+const CFF2_TABLE_TAG: u32 = 0x4346_4632;
+
+// Corners sharper than this, given as the cosine of the angle between the normals of the two
+// edges, are not moved, since the offset point would shoot off far from the glyph.
+const MIN_CORNER_COSINE: f32 = -0.9375;
+
+/// A font whose glyphs are drawn with a simulated style, as returned by `Loader::synthetic_bold`
+/// and `Loader::synthetic_italic`.
+///
+/// Both simulations can be combined, as in `font.synthetic_bold(25.0)?.with_slant_angle(12.0)`.
+/// Outlines, bounds, and advances are those of the wrapped font with the simulation applied, and
+/// glyphs are rasterized from the simulated outlines in software. The names, properties, tables,
+/// and font data are those of the wrapped font.
+#[derive(Clone, Debug)]
+pub struct SyntheticFont<L = Font> {
+    font: L,
+    extra_weight: f32,
+    slant_angle: f32,
+}
+
+impl<L> SyntheticFont<L>
+where
+    L: Loader,
+{
+    /// Wraps a font without simulating anything.
+    #[inline]
+    pub fn new(font: L) -> SyntheticFont<L> {
+        SyntheticFont {
+            font,
+            extra_weight: 0.0,
+            slant_angle: 0.0,
+        }
+    }
+
+    /// Returns a simulated bold version of the given font, whose glyph outlines are stroked
+    /// outward so that they become `extra_weight` font units wider and taller.
+    ///
+    /// Fails with `FontLoadingError::UnknownFormat` if the font has no `glyf`, `CFF `, or `CFF2`
+    /// table of outlines to embolden, as is the case for bitmap fonts.
+    pub fn bold(font: L, extra_weight: f32) -> Result<SyntheticFont<L>, FontLoadingError> {
+        if ![GLYF_TABLE_TAG, CFF_TABLE_TAG, CFF2_TABLE_TAG]
+            .iter()
+            .any(|&table_tag| font.has_table(table_tag))
+        {
+            return Err(FontLoadingError::UnknownFormat);
+        }
+        Ok(SyntheticFont {
+            extra_weight,
+            ..SyntheticFont::new(font)
+        })
+    }
+
+    /// Returns a simulated italic version of the given font, whose glyph outlines are slanted to
+    /// the right by `slant_angle` degrees from the vertical.
+    #[inline]
+    pub fn italic(font: L, slant_angle: f32) -> SyntheticFont<L> {
+        SyntheticFont::new(font).with_slant_angle(slant_angle)
+    }
+
+    /// Returns this font with its glyph outlines slanted to the right by `slant_angle` degrees
+    /// from the vertical, replacing any slant it already had.
+    #[inline]
+    pub fn with_slant_angle(self, slant_angle: f32) -> SyntheticFont<L> {
+        SyntheticFont {
+            slant_angle,
+            ..self
+        }
+    }
+
+    /// Returns the wrapped font.
+    #[inline]
+    pub fn font(&self) -> &L {
+        &self.font
+    }
+
+    /// Returns the wrapped font, discarding the simulation.
+    #[inline]
+    pub fn into_font(self) -> L {
+        self.font
+    }
+
+    /// Returns the amount, in font units, by which glyphs are made wider and taller.
+    #[inline]
+    pub fn extra_weight(&self) -> f32 {
+        self.extra_weight
+    }
+
+    /// Returns the angle, in degrees from the vertical, by which glyphs are slanted to the right.
+    #[inline]
+    pub fn slant_angle(&self) -> f32 {
+        self.slant_angle
+    }
+
+    /// Sends the simulated vector path for a glyph to a path builder.
+    ///
+    /// Each contour is offset outward by half of the extra weight. The result is then moved up
+    /// and to the right by the same amount, so that the left and bottom edges of the glyph stay
+    /// where they were. Finally, the glyph is sheared so that each point moves right by its height
+    /// times the tangent of the slant angle, which leaves the baseline in place.
+    pub fn outline<B>(
+        &self,
+        glyph_id: u32,
+        hinting_mode: HintingOptions,
+        path_builder: &mut B,
+    ) -> Result<(), GlyphLoadingError>
+    where
+        B: PathBuilder,
+    {
+        let mut glyph_path_builder = GlyphPath::builder();
+        self.font
+            .outline(glyph_id, hinting_mode, &mut glyph_path_builder)?;
+        let contours = Contour::from_path(&glyph_path_builder.build());
+
+        // Outer contours enclose the holes, so the overall direction is that of the outer ones.
+        let direction = contours
+            .iter()
+            .map(|contour| contour.signed_area())
+            .sum::<f32>()
+            .signum();
+        let strength = self.extra_weight * 0.5;
+        let skew = self.slant_angle.to_radians().tan();
+        for contour in &contours {
+            let mut points = if self.extra_weight != 0.0 {
+                contour.embolden(strength * direction, strength)
+            } else {
+                contour.points.clone()
+            };
+            if skew != 0.0 {
+                for point in &mut points {
+                    point.x += point.y * skew;
+                }
+            }
+            contour.send_to(&points, path_builder);
+        }
+        Ok(())
+    }
+
+    /// Returns the boundaries of a simulated glyph in font units.
+    ///
+    /// These are the bounds of the wrapped font made wider and taller by the extra weight. If the
+    /// glyph is slanted, they are computed from the points of its slanted outline instead, like
+    /// `Loader::outline_bounding_box`.
+    pub fn typographic_bounds(&self, glyph_id: u32) -> Result<Rect<f32>, GlyphLoadingError> {
+        if self.slant_angle != 0.0 {
+            return <Self as Loader>::outline_bounding_box(self, glyph_id, HintingOptions::None);
+        }
+
+        let bounds = self.font.typographic_bounds(glyph_id)?;
+        if bounds.is_empty() {
+            return Ok(bounds);
+        }
+        Ok(Rect::new(
+            bounds.origin,
+            bounds.size + Size2D::new(self.extra_weight, self.extra_weight),
+        ))
+    }
+
+    /// Returns the boundaries of several simulated glyphs, in order, in font units. Glyphs that
+    /// aren't in the font get zero bounds.
+    #[inline]
+    pub fn typographic_bounds_for_glyphs(
+        &self,
+        glyph_ids: &[u32],
+    ) -> Result<Vec<Rect<f32>>, GlyphLoadingError> {
+        <Self as Loader>::typographic_bounds_for_glyphs(self, glyph_ids)
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in font
+    /// units. Glyphs that advance are widened by the extra weight.
+    pub fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        let mut advance = self.font.advance(glyph_id)?;
+        if advance.x != 0.0 {
+            advance.x += self.extra_weight;
+        }
+        Ok(advance)
+    }
+
+    /// Returns the advances of several glyphs, in order, in font units, widened like those that
+    /// `advance` returns.
+    pub fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        let mut advances = self.font.advances(glyph_ids)?;
+        for advance in &mut advances {
+            if advance.x != 0.0 {
+                advance.x += self.extra_weight;
+            }
+        }
+        Ok(advances)
+    }
+
+    /// Returns true if and only if the font loader can perform hinting in the requested way.
+    ///
+    /// Since glyphs are rasterized from their outlines, this is the same for rasterization as for
+    /// outlines.
+    #[inline]
+    pub fn supports_hinting_options(&self, hinting_options: HintingOptions, _: bool) -> bool {
+        self.font.supports_hinting_options(hinting_options, false)
+    }
+
+    /// Get font fallback results for the given text and locale.
+    ///
+    /// The fallback fonts are simulated in the same way as this font.
+    pub fn get_fallbacks(&self, text: &str, locale: &str) -> FallbackResult<SyntheticFont<L>> {
+        let fallback_result = self.font.get_fallbacks(text, locale);
+        FallbackResult {
+            fonts: fallback_result
+                .fonts
+                .into_iter()
+                .map(|fallback_font| FallbackFont {
+                    font: SyntheticFont {
+                        font: fallback_font.font,
+                        extra_weight: self.extra_weight,
+                        slant_angle: self.slant_angle,
+                    },
+                    scale: fallback_font.scale,
+                })
+                .collect(),
+            valid_len: fallback_result.valid_len,
+        }
+    }
+}
+
+impl<L> Loader for SyntheticFont<L>
+where
+    L: Loader,
+{
+    type NativeFont = L::NativeFont;
+
+    #[inline]
+    fn from_bytes(
+        font_data: Arc<Vec<u8>>,
+        font_index: FaceIndex,
+    ) -> Result<SyntheticFont<L>, FontLoadingError> {
+        L::from_bytes(font_data, font_index).map(SyntheticFont::new)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    fn from_file(
+        file: &mut File,
+        font_index: FaceIndex,
+    ) -> Result<SyntheticFont<L>, FontLoadingError> {
+        L::from_file(file, font_index).map(SyntheticFont::new)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    fn from_path<P>(path: P, font_index: FaceIndex) -> Result<SyntheticFont<L>, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        L::from_path(path, font_index).map(SyntheticFont::new)
+    }
+
+    #[inline]
+    unsafe fn from_native_font(native_font: Self::NativeFont) -> SyntheticFont<L> {
+        SyntheticFont::new(L::from_native_font(native_font))
+    }
+
+    #[inline]
+    fn analyze_bytes(font_data: Arc<Vec<u8>>) -> Result<FileType, FontLoadingError> {
+        L::analyze_bytes(font_data)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    fn analyze_file(file: &mut File) -> Result<FileType, FontLoadingError> {
+        L::analyze_file(file)
+    }
+
+    #[inline]
+    fn peek_info(font_data: &[u8], font_index: FaceIndex) -> Result<FontInfo, FontLoadingError> {
+        L::peek_info(font_data, font_index)
+    }
+
+    #[inline]
+    fn native_font(&self) -> Self::NativeFont {
+        self.font.native_font()
+    }
+
+    #[inline]
+    fn postscript_name(&self) -> Option<String> {
+        self.font.postscript_name()
+    }
+
+    #[inline]
+    fn full_name(&self) -> String {
+        self.font.full_name()
+    }
+
+    #[inline]
+    fn family_name(&self) -> String {
+        self.font.family_name()
+    }
+
+    #[inline]
+    fn is_monospace(&self) -> bool {
+        self.font.is_monospace()
+    }
+
+    #[inline]
+    fn is_color_font(&self) -> bool {
+        self.font.is_color_font()
+    }
+
+    #[inline]
+    fn glyph_is_color(&self, glyph_id: u32) -> bool {
+        self.font.glyph_is_color(glyph_id)
+    }
+
+    #[inline]
+    fn opentype_script_tags(&self) -> Vec<[u8; 4]> {
+        self.font.opentype_script_tags()
+    }
+
+    #[inline]
+    fn opentype_language_tags(&self, script: [u8; 4]) -> Vec<[u8; 4]> {
+        self.font.opentype_language_tags(script)
+    }
+
+    #[inline]
+    fn opentype_feature_tags(&self) -> Vec<[u8; 4]> {
+        self.font.opentype_feature_tags()
+    }
+
+    #[inline]
+    fn properties(&self) -> Properties {
+        self.font.properties()
+    }
+
+    #[inline]
+    fn embedding_restrictions(&self) -> Option<u16> {
+        self.font.embedding_restrictions()
+    }
+
+    #[inline]
+    fn embedding_mode(&self) -> EmbeddingMode {
+        self.font.embedding_mode()
+    }
+
+    #[inline]
+    fn panose(&self) -> Option<[u8; 10]> {
+        self.font.panose()
+    }
+
+    #[inline]
+    fn unicode_ranges(&self) -> UnicodeRanges {
+        self.font.unicode_ranges()
+    }
+
+    #[inline]
+    fn glyph_count(&self) -> u32 {
+        self.font.glyph_count()
+    }
+
+    #[inline]
+    fn number_of_h_metrics(&self) -> Option<u16> {
+        self.font.number_of_h_metrics()
+    }
+
+    #[inline]
+    fn glyph_for_char(&self, character: char) -> Option<u32> {
+        self.font.glyph_for_char(character)
+    }
+
+    #[inline]
+    fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        self.font.glyph_to_char(glyph_id)
+    }
+
+    #[inline]
+    fn glyph_by_name(&self, name: &str) -> Option<u32> {
+        self.font.glyph_by_name(name)
+    }
+
+    #[inline]
+    fn glyph_name(&self, glyph_id: u32) -> Option<String> {
+        self.font.glyph_name(glyph_id)
+    }
+
+    #[inline]
+    fn outline<B>(
+        &self,
+        glyph_id: u32,
+        hinting_mode: HintingOptions,
+        path_builder: &mut B,
+    ) -> Result<(), GlyphLoadingError>
+    where
+        B: PathBuilder,
+    {
+        self.outline(glyph_id, hinting_mode, path_builder)
+    }
+
+    #[inline]
+    fn typographic_bounds(&self, glyph_id: u32) -> Result<Rect<f32>, GlyphLoadingError> {
+        self.typographic_bounds(glyph_id)
+    }
+
+    #[inline]
+    fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        self.advance(glyph_id)
+    }
+
+    #[inline]
+    fn advances(&self, glyph_ids: &[u32]) -> Result<Vec<Vector2D<f32>>, GlyphLoadingError> {
+        self.advances(glyph_ids)
+    }
+
+    #[inline]
+    fn vertical_advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        self.font.vertical_advance(glyph_id)
+    }
+
+    #[inline]
+    fn origin(&self, glyph_id: u32) -> Result<Point2D<f32>, GlyphLoadingError> {
+        self.font.origin(glyph_id)
+    }
+
+    #[inline]
+    fn metrics(&self) -> Metrics {
+        self.font.metrics()
+    }
+
+    #[inline]
+    fn handle(&self) -> Option<Handle> {
+        self.font.handle()
+    }
+
+    #[inline]
+    fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
+        self.font.copy_font_data()
+    }
+
+    #[inline]
+    fn font_bytes(&self) -> Option<FontBytesRef<'_>> {
+        self.font.font_bytes()
+    }
+
+    #[inline]
+    fn approximate_memory_usage(&self) -> usize {
+        self.font.approximate_memory_usage()
+    }
+
+    #[inline]
+    fn supports_hinting_options(
+        &self,
+        hinting_options: HintingOptions,
+        for_rasterization: bool,
+    ) -> bool {
+        self.supports_hinting_options(hinting_options, for_rasterization)
+    }
+
+    // This uses a software rasterizer, since the platform rasterizer only knows the unmodified
+    // font. As with the other loaders, the glyph is drawn over the pixels that it covers.
+    // Subpixel antialiasing is done without any filtering.
+    fn rasterize_glyph(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: &FontTransform,
+        origin: &Point2D<f32>,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        alpha_mode: AlphaMode,
+    ) -> Result<(), GlyphLoadingError> {
+        let mut path_builder = GlyphPath::builder();
+        self.outline(glyph_id, hinting_options, &mut path_builder)?;
+        let path = path_builder.build();
+
+        // Font units have Y pointing up, and pixels have Y pointing down.
+        let scale = self.font.metrics().scale_factor(point_size);
+        let pixel_transform = Transform2D::column_major(
+            transform.scale_x * scale,
+            -transform.skew_x * scale,
+            origin.x,
+            transform.skew_y * scale,
+            -transform.scale_y * scale,
+            origin.y,
+        );
+
+        let mut points = vec![];
+        for event in path.iter() {
+            match event {
+                PathEvent::MoveTo(to) => points.push(to),
+                PathEvent::Line(segment) | PathEvent::Close(segment) => points.push(segment.to),
+                PathEvent::Quadratic(segment) => {
+                    points.extend_from_slice(&[segment.ctrl, segment.to])
+                }
+                PathEvent::Cubic(segment) => {
+                    points.extend_from_slice(&[segment.ctrl1, segment.ctrl2, segment.to])
+                }
+            }
+        }
+        let pixel_points: Vec<_> = points
+            .iter()
+            .map(|&point| pixel_transform.transform_point(point))
+            .collect();
+        let bounds = Rect::from_points(&pixel_points).round_out();
+        let canvas_rect = Rect::new(Point2D::zero(), canvas.size.to_f32());
+        let bounds: Rect<i32> = match bounds.intersection(&canvas_rect) {
+            Some(bounds) if !bounds.is_empty() => {
+                bounds.try_cast().ok_or(GlyphLoadingError::OutOfMemory)?
+            }
+            _ => return Ok(()),
+        };
+
+        let mut pixel_transform =
+            pixel_transform.post_translate(-bounds.origin.to_f32().to_vector());
+        let mut rasterizer_size = bounds.size.to_u32();
+        if rasterization_options == RasterizationOptions::SubpixelAa {
+            pixel_transform = pixel_transform.post_scale(3.0, 1.0);
+            rasterizer_size.width *= 3;
+        }
+        let mut rasterizer = Rasterizer::new(&rasterizer_size)?;
+        rasterizer.draw_path(&path, &pixel_transform);
+        let glyph_canvas = rasterizer.to_canvas(rasterization_options)?;
+        canvas.blit_from(
+            bounds.origin,
+            &glyph_canvas.pixels,
+            &glyph_canvas.size,
+            glyph_canvas.stride,
+            glyph_canvas.format,
+        );
+        if alpha_mode == AlphaMode::Straight {
+            canvas.unpremultiply_within(&bounds);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn get_fallbacks(&self, text: &str, locale: &str) -> FallbackResult<SyntheticFont<L>> {
+        self.get_fallbacks(text, locale)
+    }
+
+    #[inline]
+    fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
+        self.font.load_font_table(table_tag)
+    }
+
+    #[inline]
+    fn table_tags(&self) -> Vec<u32> {
+        self.font.table_tags()
+    }
+
+    #[inline]
+    fn has_table(&self, table_tag: u32) -> bool {
+        self.font.has_table(table_tag)
+    }
+}
+
+// One closed contour of a glyph outline, as a list of points with the curves that join them.
+struct Contour {
+    // The on-curve and control points, starting with the first on-curve point.
+    points: Vec<Point2D<f32>>,
+    // The segments that join the points, in order.
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Copy)]
+enum Segment {
+    Line,
+    Quadratic,
+    Cubic,
+}
+
+impl Contour {
+    fn from_path(path: &GlyphPath) -> Vec<Contour> {
+        let mut contours: Vec<Contour> = vec![];
+        for event in path.iter() {
+            let (points, segment): (&[Point2D<f32>], Segment) = match event {
+                PathEvent::MoveTo(to) => {
+                    contours.push(Contour {
+                        points: vec![to],
+                        segments: vec![],
+                    });
+                    continue;
+                }
+                // The contour is closed by the path builder when it is sent on.
+                PathEvent::Close(_) => continue,
+                PathEvent::Line(ref segment) => (&[segment.to], Segment::Line),
+                PathEvent::Quadratic(ref segment) => {
+                    (&[segment.ctrl, segment.to], Segment::Quadratic)
+                }
+                PathEvent::Cubic(ref segment) => {
+                    (&[segment.ctrl1, segment.ctrl2, segment.to], Segment::Cubic)
+                }
+            };
+            if let Some(contour) = contours.last_mut() {
+                contour.points.extend_from_slice(points);
+                contour.segments.push(segment);
+            }
+        }
+        contours
+    }
+
+    // Returns twice the area of the polygon through the points. It is positive for contours that
+    // run counterclockwise, with Y pointing up.
+    fn signed_area(&self) -> f32 {
+        let point_count = self.points.len();
+        (0..point_count)
+            .map(|index| {
+                let (point, next_point) =
+                    (self.points[index], self.points[(index + 1) % point_count]);
+                point.x * next_point.y - next_point.x * point.y
+            })
+            .sum()
+    }
+
+    // Moves every point outward along the bisector of the normals of the edges of the polygon
+    // through the points, so that the edges move `strength` font units away from where they were,
+    // and then adds `offset` to both coordinates. Contours that run clockwise with Y pointing up
+    // move inward for a positive `strength`.
+    //
+    // Control points are moved like the on-curve points, which is how FreeType emboldens glyphs.
+    fn embolden(&self, strength: f32, offset: f32) -> Vec<Point2D<f32>> {
+        let point_count = self.points.len();
+        let offset = Vector2D::new(offset, offset);
+        (0..point_count)
+            .map(|index| {
+                let point = self.points[index];
+                let distinct_point = |step: usize| {
+                    (1..point_count)
+                        .map(|distance| self.points[(index + distance * step) % point_count])
+                        .find(|&other_point| other_point != point)
+                };
+                let (previous_point, next_point) =
+                    match (distinct_point(point_count - 1), distinct_point(1)) {
+                        (Some(previous_point), Some(next_point)) => (previous_point, next_point),
+                        _ => return point + offset,
+                    };
+
+                // With Y pointing up, the right side of the edges of a counterclockwise contour
+                // is the outside.
+                let normal = |edge: Vector2D<f32>| {
+                    let edge = edge.normalize();
+                    Vector2D::new(edge.y, -edge.x)
+                };
+                let (in_normal, out_normal) =
+                    (normal(point - previous_point), normal(next_point - point));
+                let cosine = in_normal.dot(out_normal);
+                if cosine <= MIN_CORNER_COSINE {
+                    return point + offset;
+                }
+                point + (in_normal + out_normal) * (strength / (1.0 + cosine)) + offset
+            })
+            .collect()
+    }
+
+    // Sends the contour to a path builder, with its points replaced by the given ones.
+    fn send_to<B>(&self, points: &[Point2D<f32>], path_builder: &mut B)
+    where
+        B: PathBuilder,
+    {
+        path_builder.move_to(points[0]);
+        let mut points = points[1..].iter().cloned();
+        for segment in &self.segments {
+            match *segment {
+                Segment::Line => path_builder.line_to(points.next().unwrap()),
+                Segment::Quadratic => {
+                    let (ctrl, to) = (points.next().unwrap(), points.next().unwrap());
+                    path_builder.quadratic_bezier_to(ctrl, to)
+                }
+                Segment::Cubic => {
+                    let (ctrl1, ctrl2, to) = (
+                        points.next().unwrap(),
+                        points.next().unwrap(),
+                        points.next().unwrap(),
+                    );
+                    path_builder.cubic_bezier_to(ctrl1, ctrl2, to)
+                }
+            }
+        }
+        path_builder.close();
+    }
+}
@@ -8,22 +8,62 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use euclid::default::{Point2D, Rect, Size2D, Vector2D};
 use euclid::point2;
 use lyon_path::{Path, PathEvent};
-use std::fs::File;
-use std::io::Read;
+use std::collections::{BTreeSet, HashSet};
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::iter;
+use std::path::Path as FilePath;
+use std::process;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::thread;
 
-use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::canvas::{
+    AlphaMode, Canvas, CompositeMode, Format, RasterizationOptions, SubpixelAaOptions,
+    SubpixelOrder,
+};
+use crate::cmap::ReverseCharMap;
+use crate::color::ColorLayer;
+use crate::embedding::EmbeddingMode;
+use crate::error::{
+    FontLoadingError, GlyphLoadingError, PropertyParseError, RenderingError, SelectionError,
+};
+use crate::family::Family;
+use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::file_type::FileType;
-use crate::font::Font;
+use crate::font::{FaceIndex, Font};
+use crate::handle::Handle;
 use crate::hinting::HintingOptions;
-use crate::loader::FontTransform;
-use crate::properties::{Properties, Stretch, Weight};
-use crate::source::SystemSource;
+use crate::kern;
+use crate::layout;
+use crate::loader::{FontBytesRef, FontTransform, Loader};
+use crate::matching;
+use crate::metrics::Metrics;
+use crate::panose::{Panose, PanoseFamilyType};
+use crate::post;
+use crate::properties::{Properties, PropertiesFilter, Stretch, Style, Weight};
+use crate::render::render_string;
+use crate::sfnt;
+use crate::sized_font::SizedFont;
+use crate::source::{self, Source, SystemSource};
+use crate::sources::android::AndroidSource;
+use crate::sources::cache::CachingSource;
+use crate::sources::fs::{FsSource, FsSourceOptions};
+use crate::sources::mem::MemSource;
+use crate::sources::multi::MultiSource;
+use crate::synthetic::{SyntheticFont, DEFAULT_EXTRA_WEIGHT, DEFAULT_SLANT_ANGLE};
+use crate::testing::{MockFont, MockSource};
+use crate::unicode_ranges::UnicodeRanges;
 use crate::utils;
+use crate::validate;
 
 static TEST_FONT_FILE_PATH: &'static str = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
 static TEST_FONT_POSTSCRIPT_NAME: &'static str = "EBGaramond12-Regular";
@@ -42,6 +82,19 @@ static KNOWN_SYSTEM_FONT_NAME: &'static str = "Arial";
 #[cfg(target_os = "linux")]
 static KNOWN_SYSTEM_FONT_NAME: &'static str = "DejaVu Sans";
 
+// The style name of the bold italic face of `KNOWN_SYSTEM_FONT_NAME`.
+#[cfg(not(target_os = "linux"))]
+static KNOWN_SYSTEM_BOLD_ITALIC_STYLE_NAME: &'static str = "Bold Italic";
+#[cfg(target_os = "linux")]
+static KNOWN_SYSTEM_BOLD_ITALIC_STYLE_NAME: &'static str = "Bold Oblique";
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+static KNOWN_SYSTEM_EMOJI_FONT_NAME: &'static str = "Apple Color Emoji";
+#[cfg(target_family = "windows")]
+static KNOWN_SYSTEM_EMOJI_FONT_NAME: &'static str = "Segoe UI Emoji";
+#[cfg(not(any(target_os = "macos", target_os = "ios", target_family = "windows")))]
+static KNOWN_SYSTEM_EMOJI_FONT_NAME: &'static str = "Noto Color Emoji";
+
 #[test]
 pub fn get_font_full_name() {
     let font = SystemSource::new()
@@ -58,7 +111,7 @@ pub fn get_font_full_name() {
 #[test]
 pub fn load_font_from_file() {
     let mut file = File::open(TEST_FONT_FILE_PATH).unwrap();
-    let font = Font::from_file(&mut file, 0).unwrap();
+    let font = Font::from_file(&mut file, FaceIndex::FIRST).unwrap();
     assert_eq!(font.postscript_name().unwrap(), TEST_FONT_POSTSCRIPT_NAME);
 }
 
@@ -67,8 +120,104 @@ pub fn load_font_from_memory() {
     let mut file = File::open(TEST_FONT_FILE_PATH).unwrap();
     let mut font_data = vec![];
     file.read_to_end(&mut font_data).unwrap();
-    let font = Font::from_bytes(Arc::new(font_data), 0).unwrap();
+    let font = Font::from_bytes(Arc::new(font_data), FaceIndex::FIRST).unwrap();
+    assert_eq!(font.postscript_name().unwrap(), TEST_FONT_POSTSCRIPT_NAME);
+}
+
+#[cfg(feature = "async")]
+#[test]
+pub fn load_font_from_async_read() {
+    use futures::executor;
+
+    let font_data = fs::read(TEST_FONT_FILE_PATH).unwrap();
+    let font = executor::block_on(Font::from_async_read(
+        io::Cursor::new(font_data),
+        FaceIndex::FIRST,
+    ))
+    .unwrap();
     assert_eq!(font.postscript_name().unwrap(), TEST_FONT_POSTSCRIPT_NAME);
+
+    let font_data = fs::read(TEST_FONT_COLLECTION_FILE_PATH).unwrap();
+    let font = executor::block_on(Font::from_async_read(&font_data[..], FaceIndex(1))).unwrap();
+    assert_eq!(
+        font.postscript_name().unwrap(),
+        TEST_FONT_COLLECTION_POSTSCRIPT_NAME[1]
+    );
+}
+
+// The FreeType loader requires the caller to release the native face, so it has its own version of
+// this test.
+#[cfg(all(
+    any(target_family = "windows", target_os = "macos", target_os = "ios"),
+    not(feature = "loader-freetype-default")
+))]
+#[test]
+pub fn native_font_round_trip() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let round_tripped_font = unsafe { Font::from_native_font(font.native_font()) };
+    assert_eq!(round_tripped_font.postscript_name(), font.postscript_name());
+}
+
+#[test]
+pub fn load_font_from_missing_file() {
+    match Font::from_path("resources/tests/nonexistent.ttf", FaceIndex::FIRST) {
+        Err(FontLoadingError::Io(ref error)) => assert_eq!(error.kind(), io::ErrorKind::NotFound),
+        other => panic!("Expected an I/O error but got {:?}", other),
+    }
+}
+
+#[test]
+pub fn error_display_and_source() {
+    let error = FontLoadingError::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+    assert_eq!(error.to_string(), "I/O error: gone");
+    let io_error = error
+        .source()
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+    assert!(FontLoadingError::Parse.source().is_none());
+    assert_eq!(
+        FontLoadingError::Parse.to_string(),
+        "failed to parse font data"
+    );
+
+    let error = SelectionError::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+    assert_eq!(error.to_string(), "I/O error: denied");
+    let io_error = error
+        .source()
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(error.clone(), error);
+    assert!(SelectionError::NotFound.source().is_none());
+
+    // Error reporting crates such as `anyhow` need errors to be `Send` and `Sync`.
+    let error: Box<dyn Error + Send + Sync> = Box::new(GlyphLoadingError::NoSuchGlyph);
+    assert_eq!(error.to_string(), "no such glyph");
+    assert_eq!(
+        error.downcast_ref::<GlyphLoadingError>(),
+        Some(&GlyphLoadingError::NoSuchGlyph)
+    );
+
+    let error: Box<dyn Error + Send + Sync> = Box::new(SelectionError::NotFound);
+    assert_eq!(error.to_string(), "no font found");
+    assert_eq!(
+        error.downcast_ref::<SelectionError>(),
+        Some(&SelectionError::NotFound)
+    );
+
+    let error: Box<dyn Error + Send + Sync> =
+        Box::new(RenderingError::from(GlyphLoadingError::OutOfMemory));
+    assert_eq!(
+        error.to_string(),
+        "glyph loading failed: out of memory for the glyph bitmap"
+    );
+    assert_eq!(
+        error
+            .source()
+            .and_then(|source| source.downcast_ref::<GlyphLoadingError>()),
+        Some(&GlyphLoadingError::OutOfMemory)
+    );
 }
 
 #[test]
@@ -99,6 +248,129 @@ pub fn get_glyph_for_char() {
     assert_eq!(glyph, 68);
 }
 
+#[test]
+pub fn get_chars_for_glyph() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    for character in "Ab\u{e9}&?".chars() {
+        let glyph = font.glyph_for_char(character).unwrap();
+        assert_eq!(font.glyph_to_char(glyph), [character]);
+        assert_eq!(Loader::glyph_to_char(&font, glyph), [character]);
+    }
+    assert!(font.glyph_to_char(0).is_empty());
+}
+
+#[test]
+pub fn chars_for_glyph_shared_by_several_chars() {
+    // A `cmap` table with one format 4 subtable that maps both the space and the no-break space
+    // to glyph 3.
+    let id_deltas = [3u16.wrapping_sub(0x20), 3u16.wrapping_sub(0xa0), 1];
+    let words = [
+        // The table header and encoding record.
+        &[0, 1, 3, 1, 0, 12][..],
+        // The subtable header.
+        &[4, 40, 0, 6, 4, 1, 2],
+        // The end codes, padding, and start codes.
+        &[0x20, 0xa0, 0xffff, 0, 0x20, 0xa0, 0xffff],
+        // The ID deltas and ID range offsets.
+        &id_deltas,
+        &[0, 0, 0],
+    ]
+    .concat();
+    let cmap_table: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+    let reverse_char_map = ReverseCharMap::from_cmap_table(&cmap_table, 4);
+    assert_eq!(reverse_char_map.chars_for_glyph(3), [' ', '\u{a0}']);
+    assert!(reverse_char_map.chars_for_glyph(0).is_empty());
+    assert!(reverse_char_map.chars_for_glyph(4).is_empty());
+}
+
+#[test]
+pub fn chars_for_glyph_skips_malformed_format_12_groups() {
+    // A `cmap` table with one format 12 subtable, for a font with four glyphs.
+    let groups: [[u32; 3]; 5] = [
+        // A valid group that maps 'A' and 'B' to glyphs 1 and 2.
+        [0x41, 0x42, 1],
+        // A backwards group.
+        [0x50, 0x4f, 1],
+        // A group that runs past the last code point.
+        [0x10_ffff, 0x11_0000, 1],
+        // A group that maps 'b' to glyph 4, which the font doesn't have.
+        [0x61, 0x62, 3],
+        // A group whose glyph IDs overflow.
+        [0x63, 0x64, u32::MAX],
+    ];
+    let mut cmap_table = [0u16, 1, 3, 10, 0, 12, 12, 0]
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect::<Vec<u8>>();
+    let header = [16 + groups.len() as u32 * 12, 0, groups.len() as u32];
+    for value in header.iter().chain(groups.iter().flatten()) {
+        cmap_table.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let reverse_char_map = ReverseCharMap::from_cmap_table(&cmap_table, 4);
+    assert_eq!(reverse_char_map.chars_for_glyph(1), ['A']);
+    assert_eq!(reverse_char_map.chars_for_glyph(2), ['B']);
+    assert!(reverse_char_map.chars_for_glyph(3).is_empty());
+    assert!(reverse_char_map.chars_for_glyph(u32::MAX).is_empty());
+}
+
+#[test]
+pub fn get_glyphs_for_string() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    // Include characters outside the Basic Multilingual Plane to check that lookups stay aligned
+    // with the characters after them.
+    let text = "Ab\u{1F600}c\u{20AC}\u{10348}d";
+    let glyphs = font.glyphs_for_string(text);
+    let expected_glyphs: Vec<_> = text
+        .chars()
+        .map(|character| font.glyph_for_char(character))
+        .collect();
+    assert_eq!(glyphs, expected_glyphs);
+    assert!(glyphs[0].is_some());
+    assert!(glyphs[3].is_some());
+    assert!(glyphs[6].is_some());
+}
+
+#[test]
+pub fn font_is_monospace() {
+    for &(path, is_monospace) in &[
+        (FILE_PATH_INCONSOLATA_TTF, true),
+        (TEST_FONT_FILE_PATH, false),
+        (FILE_PATH_EB_GARAMOND_TTF, false),
+    ] {
+        let font = Font::from_path(path, FaceIndex::FIRST).unwrap();
+        assert_eq!(font.is_monospace(), is_monospace);
+        // The `post` table, which the default implementation reads, agrees.
+        let post_table = font.load_font_table(post::POST_TABLE_TAG).unwrap();
+        assert_eq!(post::is_fixed_pitch(&post_table), Some(is_monospace));
+    }
+    assert_eq!(post::is_fixed_pitch(&[0; 12]), None);
+}
+
+#[test]
+pub fn get_glyph_name() {
+    let font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    let glyph = font.glyph_for_char('A').unwrap();
+    assert_eq!(
+        font.glyph_name(glyph).as_ref().map(|name| &**name),
+        Some("A")
+    );
+    assert_eq!(
+        font.glyph_name(0).as_ref().map(|name| &**name),
+        Some(".notdef")
+    );
+    for glyph_id in 1..font.glyph_count() {
+        let name = font.glyph_name(glyph_id).expect("No name for glyph!");
+        assert_eq!(font.glyph_by_name(&name), Some(glyph_id), "{}", name);
+    }
+    assert_eq!(font.glyph_name(font.glyph_count()), None);
+
+    // EB Garamond's `post` table is version 3.0, which names no glyphs.
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert_eq!(font.glyph_name(font.glyph_for_char('A').unwrap()), None);
+}
+
 macro_rules! assert_line_to {
     ($event:expr, $pt:expr) => {
         match $event {
@@ -339,7 +611,7 @@ pub fn get_fully_hinted_glyph_outline() {
 pub fn get_empty_glyph_outline() {
     let mut path_builder = Path::builder();
     let mut file = File::open(TEST_FONT_FILE_PATH).unwrap();
-    let font = Font::from_file(&mut file, 0).unwrap();
+    let font = Font::from_file(&mut file, FaceIndex::FIRST).unwrap();
     let glyph = font.glyph_for_char(' ').expect("No glyph for char!");
     font.outline(glyph, HintingOptions::None, &mut path_builder)
         .unwrap();
@@ -385,6 +657,30 @@ pub fn get_glyph_typographic_bounds() {
     );
 }
 
+#[test]
+pub fn get_glyph_outline_bounding_box() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    for &character in &['A', 'o', 'g'] {
+        let glyph = font.glyph_for_char(character).unwrap();
+        let typographic_bounds = font.typographic_bounds(glyph).unwrap();
+        let outline_bounds = font
+            .outline_bounding_box(glyph, HintingOptions::None)
+            .unwrap();
+        // The font has points at the extrema of its curves, so the control points don't reach
+        // past the outline.
+        assert!((outline_bounds.min_x() - typographic_bounds.min_x()).abs() <= 1.0);
+        assert!((outline_bounds.min_y() - typographic_bounds.min_y()).abs() <= 1.0);
+        assert!((outline_bounds.max_x() - typographic_bounds.max_x()).abs() <= 1.0);
+        assert!((outline_bounds.max_y() - typographic_bounds.max_y()).abs() <= 1.0);
+    }
+
+    let space = font.glyph_for_char(' ').unwrap();
+    assert_eq!(
+        font.outline_bounding_box(space, HintingOptions::None),
+        Ok(Rect::zero())
+    );
+}
+
 #[cfg(target_family = "windows")]
 #[test]
 pub fn get_glyph_advance_and_origin() {
@@ -424,6 +720,144 @@ pub fn get_glyph_advance_and_origin() {
     assert_eq!(font.origin(glyph), Ok(Point2D::zero()));
 }
 
+#[test]
+pub fn metrics_scale_factor() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let metrics = font.metrics();
+    assert_eq!(metrics.units_per_em, 1000);
+    assert_eq!(metrics.ascent, 710.0);
+    assert_eq!(metrics.scale_factor(16.0), 0.016);
+    assert!((metrics.scale_factor(16.0) * metrics.ascent - 11.36).abs() < 0.0001);
+}
+
+#[test]
+pub fn get_glyph_advances() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_ids: Vec<u32> = "Hello, world!"
+        .chars()
+        .map(|character| font.glyph_for_char(character).unwrap())
+        .collect();
+    let advances = font.advances(&glyph_ids).unwrap();
+    assert_eq!(advances.len(), glyph_ids.len());
+    for (&glyph_id, &advance) in glyph_ids.iter().zip(advances.iter()) {
+        assert_eq!(font.advance(glyph_id), Ok(advance));
+    }
+    assert_eq!(font.advances(&[]), Ok(vec![]));
+}
+
+#[test]
+pub fn get_glyph_typographic_bounds_for_glyphs() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let mut glyph_ids: Vec<u32> = "Hello, world!"
+        .chars()
+        .map(|character| font.glyph_for_char(character).unwrap())
+        .collect();
+    glyph_ids.insert(3, font.glyph_count());
+    let bounds = font.typographic_bounds_for_glyphs(&glyph_ids).unwrap();
+    assert_eq!(bounds.len(), glyph_ids.len());
+    for (&glyph_id, &bounds) in glyph_ids.iter().zip(bounds.iter()) {
+        if glyph_id == font.glyph_count() {
+            assert_eq!(bounds, Rect::zero());
+        } else {
+            assert_eq!(font.typographic_bounds(glyph_id), Ok(bounds));
+        }
+    }
+}
+
+#[test]
+pub fn get_glyph_advance_pixels() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph = font.glyph_for_char('m').expect("No glyph for char!");
+    let advance = font.advance(glyph).unwrap();
+    let units_per_em = font.metrics().units_per_em as f32;
+    let advance_pixels = font.advance_pixels(glyph, 16.0).unwrap();
+    assert!((advance_pixels - advance * 16.0 / units_per_em).length() < 0.0001);
+}
+
+#[test]
+pub fn get_line_metrics() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let metrics = font.metrics();
+    let line_metrics = font.line_metrics(16.0);
+    let scale_factor = 16.0 / metrics.units_per_em as f32;
+    assert!((line_metrics.ascent - metrics.ascent * scale_factor).abs() < 0.0001);
+    assert!((line_metrics.descent - metrics.descent * scale_factor).abs() < 0.0001);
+    assert!(line_metrics.descent < 0.0);
+    assert!((line_metrics.line_gap - metrics.line_gap * scale_factor).abs() < 0.0001);
+    assert_eq!(
+        line_metrics.line_height,
+        line_metrics.ascent - line_metrics.descent + line_metrics.line_gap
+    );
+}
+
+#[test]
+pub fn get_run_bounds() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyphs: Vec<u32> = "AB"
+        .chars()
+        .map(|character| font.glyph_for_char(character).unwrap())
+        .collect();
+    let run_bounds = font.run_bounds(&glyphs, 16.0).unwrap();
+
+    let advance_sum: f32 = glyphs
+        .iter()
+        .map(|&glyph| font.advance_pixels(glyph, 16.0).unwrap().x.max(0.0))
+        .sum();
+    assert!(run_bounds.size.width >= advance_sum);
+    assert!(run_bounds.min_y() <= 0.0);
+    assert!(run_bounds.max_y() > 0.0);
+
+    // The second glyph sits after the advance of the first.
+    let scale_factor = font.metrics().scale_factor(16.0);
+    let b_bounds = font.typographic_bounds(glyphs[1]).unwrap();
+    let b_max_x = font.advance(glyphs[0]).unwrap().x + b_bounds.max_x();
+    assert!(run_bounds.max_x() >= b_max_x * scale_factor - 0.0001);
+
+    assert_eq!(font.run_bounds(&[], 16.0).unwrap(), Rect::zero());
+    match font.run_bounds(&[glyphs[0], font.glyph_count()], 16.0) {
+        Err(GlyphLoadingError::NoSuchGlyph) => {}
+        other => panic!("expected NoSuchGlyph, got {:?}", other),
+    }
+}
+
+#[test]
+pub fn get_glyph_vertical_advance() {
+    // Give the font vertical metrics: glyph 0 advances by 1000 units and every other glyph by 1100.
+    let mut vhea_table = vec![0; 36];
+    BigEndian::write_u16(&mut vhea_table[34..36], 2);
+    let vmtx_table = vec![0x03, 0xe8, 0x00, 0x00, 0x04, 0x4c, 0x00, 0x00];
+    let font_data = font_data_with_tables(
+        TEST_FONT_FILE_PATH,
+        vec![(*b"vhea", vhea_table), (*b"vmtx", vmtx_table)],
+    );
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('a').unwrap();
+    assert!(glyph_id > 1);
+    assert_eq!(
+        font.vertical_advance(0).unwrap(),
+        Vector2D::new(0.0, 1000.0)
+    );
+    assert_eq!(
+        font.vertical_advance(glyph_id).unwrap(),
+        Vector2D::new(0.0, 1100.0)
+    );
+    assert_eq!(
+        font.vertical_advance(font.glyph_count()),
+        Err(GlyphLoadingError::NoSuchGlyph)
+    );
+}
+
+#[test]
+pub fn get_synthesized_glyph_vertical_advance() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('a').unwrap();
+    let metrics = font.metrics();
+    assert_eq!(
+        font.vertical_advance(glyph_id).unwrap(),
+        Vector2D::new(0.0, metrics.ascent - metrics.descent)
+    );
+}
+
 #[cfg(any(target_family = "windows", target_os = "macos"))]
 #[test]
 pub fn get_font_metrics() {
@@ -475,92 +909,855 @@ pub fn get_font_properties() {
 }
 
 #[test]
-pub fn get_font_data() {
+pub fn text_font_is_not_color() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert!(!font.is_color_font());
+}
+
+#[test]
+pub fn emoji_font_is_color() {
+    // A font whose `A` is painted in the only color of a `CPAL` palette, as in emoji fonts.
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = plain_font.glyph_for_char('A').unwrap();
+    let colr_table = colr_v0_table(glyph_id, &[(glyph_id, 0)]);
+    let mut cpal_table = vec![];
+    for &value in &[0, 1, 1, 1, 0, 14, 0] {
+        cpal_table.write_u16::<BigEndian>(value).unwrap();
+    }
+    cpal_table.extend_from_slice(&[0x00, 0xcc, 0xff, 0xff]);
+    let font_data = font_data_with_tables(
+        TEST_FONT_FILE_PATH,
+        vec![(*b"COLR", colr_table), (*b"CPAL", cpal_table)],
+    );
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+    assert!(font.is_color_font());
+    assert!(font.glyph_is_color(glyph_id));
+    assert!(!font.glyph_is_color(plain_font.glyph_for_char('B').unwrap()));
+}
+
+// This depends on the emoji font that the system usually has being installed.
+#[test]
+#[ignore]
+pub fn system_emoji_font_is_color() {
     let font = SystemSource::new()
-        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .select_best_match(
+            &[FamilyName::Title(KNOWN_SYSTEM_EMOJI_FONT_NAME.to_string())],
+            &Properties::new(),
+        )
         .unwrap()
         .load()
         .unwrap();
-    let data = font.copy_font_data().unwrap();
-    debug_assert!(utils::SFNT_VERSIONS
-        .iter()
-        .any(|version| data[0..4] == *version));
+    assert!(font.is_color_font());
+    assert!(font.glyph_is_color(font.glyph_for_char('😀').unwrap()));
 }
 
+// Core Text decides whether a font is color on its own, so it won't necessarily be fooled by an
+// empty `COLR` table.
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
 #[test]
-pub fn rasterize_glyph_with_grayscale_aa() {
-    let font = SystemSource::new()
-        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
-        .unwrap()
-        .load()
+pub fn font_with_color_table_is_color() {
+    let font_data = font_data_with_tables(TEST_FONT_FILE_PATH, vec![(*b"COLR", vec![0; 14])]);
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+    assert!(font.is_color_font());
+}
+
+#[test]
+pub fn glyph_is_color() {
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let color_glyph_id = plain_font.glyph_for_char('A').unwrap();
+    let plain_glyph_id = plain_font.glyph_for_char('B').unwrap();
+    assert!(!plain_font.glyph_is_color(color_glyph_id));
+
+    let colr_table = colr_v0_table(color_glyph_id, &[(color_glyph_id, 0)]);
+    let font_data = font_data_with_tables(TEST_FONT_FILE_PATH, vec![(*b"COLR", colr_table)]);
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+    assert!(font.glyph_is_color(color_glyph_id));
+    assert!(!font.glyph_is_color(plain_glyph_id));
+    // Ask again to hit the cache.
+    assert!(font.glyph_is_color(color_glyph_id));
+    assert!(!font.clone().glyph_is_color(plain_glyph_id));
+}
+
+#[test]
+pub fn get_color_glyph_layers() {
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_ids: Vec<u32> = "AOo"
+        .chars()
+        .map(|character| plain_font.glyph_for_char(character).unwrap())
+        .collect();
+    assert_eq!(plain_font.color_glyph_layers(glyph_ids[0]), None);
+    assert_eq!(plain_font.color_palette(0), None);
+
+    // Paint `A` as an `O` in palette color 1 with an `o` in the foreground color on top.
+    let colr_table = colr_v0_table(
+        glyph_ids[0],
+        &[
+            (glyph_ids[1], 1),
+            (glyph_ids[2], ColorLayer::FOREGROUND_PALETTE_INDEX),
+        ],
+    );
+    // Two palettes of two colors each, sharing their second color.
+    let mut cpal_table = vec![];
+    for &value in &[0, 2, 2, 3, 0, 16, 0, 1] {
+        cpal_table.write_u16::<BigEndian>(value).unwrap();
+    }
+    // Colors are stored as BGRA.
+    cpal_table.extend_from_slice(&[
+        0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0x80, 0x00, 0xff, 0x00, 0xff,
+    ]);
+    let font_data = font_data_with_tables(
+        TEST_FONT_FILE_PATH,
+        vec![(*b"COLR", colr_table), (*b"CPAL", cpal_table)],
+    );
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+
+    assert_eq!(
+        font.color_glyph_layers(glyph_ids[0]),
+        Some(vec![
+            ColorLayer {
+                glyph_id: glyph_ids[1],
+                palette_index: 1,
+            },
+            ColorLayer {
+                glyph_id: glyph_ids[2],
+                palette_index: ColorLayer::FOREGROUND_PALETTE_INDEX,
+            },
+        ])
+    );
+    assert_eq!(font.color_glyph_layers(glyph_ids[1]), None);
+    assert_eq!(
+        font.color_palette(0),
+        Some(vec![[0xff, 0x00, 0x00, 0xff], [0x00, 0x00, 0xff, 0x80]])
+    );
+    assert_eq!(
+        font.color_palette(1),
+        Some(vec![[0x00, 0x00, 0xff, 0x80], [0x00, 0xff, 0x00, 0xff]])
+    );
+    assert_eq!(font.color_palette(2), None);
+}
+
+#[test]
+pub fn get_glyph_bitmap() {
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = plain_font.glyph_for_char('A').unwrap();
+    assert!(plain_font.glyph_bitmap(glyph_id, 12.0).is_none());
+
+    let (eblc_table, ebdt_table) = eblc_and_ebdt_tables(glyph_id);
+    let font_data = font_data_with_tables(
+        TEST_FONT_FILE_PATH,
+        vec![(*b"EBDT", ebdt_table), (*b"EBLC", eblc_table)],
+    );
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+    let bitmap = |point_size| {
+        let (canvas, origin) = font.glyph_bitmap(glyph_id, point_size).unwrap();
+        assert_eq!(canvas.format, Format::A8);
+        let pixels: Vec<_> = (0..canvas.size.height as usize)
+            .flat_map(|y| {
+                let row_start = y * canvas.stride;
+                canvas.pixels[row_start..(row_start + canvas.size.width as usize)].to_vec()
+            })
+            .collect();
+        (canvas.size, origin, pixels)
+    };
+
+    // The 1-bit strike at 12 pixels per em.
+    assert_eq!(
+        bitmap(12.0),
+        (
+            Size2D::new(3, 2),
+            Point2D::new(1, -9),
+            vec![255, 0, 255, 0, 255, 0]
+        )
+    );
+    assert_eq!(bitmap(11.0), bitmap(12.0));
+    // The 8-bit strike at 16 pixels per em, which also wins the tie at 14.
+    assert_eq!(
+        bitmap(16.0),
+        (Size2D::new(2, 1), Point2D::new(0, -5), vec![0x40, 0xff])
+    );
+    assert_eq!(bitmap(14.0), bitmap(16.0));
+
+    let other_glyph_id = font.glyph_for_char('B').unwrap();
+    assert!(font.glyph_bitmap(other_glyph_id, 12.0).is_none());
+}
+
+#[test]
+pub fn get_glyph_bitmap_with_bogus_glyph_count() {
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = plain_font.glyph_for_char('A').unwrap();
+    let (eblc_table, ebdt_table) = eblc_and_ebdt_tables(glyph_id);
+
+    // Turn the index subtable of the 16 pixels per em strike into a format 4 or 5 one that
+    // doesn't list the glyph but claims to have 2³² - 1 glyphs.
+    let mut format_4_eblc_table = eblc_table.clone();
+    BigEndian::write_u16(&mut format_4_eblc_table[136..138], 4);
+    BigEndian::write_u32(&mut format_4_eblc_table[144..148], u32::MAX);
+    let mut format_5_eblc_table = eblc_table;
+    BigEndian::write_u16(&mut format_5_eblc_table[136..138], 5);
+    format_5_eblc_table
+        .write_u32::<BigEndian>(u32::MAX)
         .unwrap();
-    let glyph_id = font.glyph_for_char('L').unwrap();
-    let size = 32.0;
-    let raster_rect = font
-        .raster_bounds(
-            glyph_id,
-            size,
+    format_5_eblc_table.extend_from_slice(&[0; 4]);
+
+    for eblc_table in vec![format_4_eblc_table, format_5_eblc_table] {
+        let font_data = font_data_with_tables(
+            TEST_FONT_FILE_PATH,
+            vec![(*b"EBDT", ebdt_table.clone()), (*b"EBLC", eblc_table)],
+        );
+        let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+        // The glyph is only found in the 1-bit, 3×2 strike at 12 pixels per em.
+        let (canvas, _) = font.glyph_bitmap(glyph_id, 16.0).unwrap();
+        assert_eq!(canvas.size, Size2D::new(3, 2));
+    }
+}
+
+#[test]
+pub fn rasterize_color_glyph_with_palettes() {
+    // Paint `A` in palette color 0, which is red in palette 0 and green in palette 1.
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = plain_font.glyph_for_char('A').unwrap();
+    let colr_table = colr_v0_table(glyph_id, &[(glyph_id, 0)]);
+    let mut cpal_table = vec![];
+    for &value in &[0, 1, 2, 2, 0, 16, 0, 1] {
+        cpal_table.write_u16::<BigEndian>(value).unwrap();
+    }
+    cpal_table.extend_from_slice(&[0x00, 0x00, 0xff, 0xff, 0x00, 0xff, 0x00, 0xff]);
+    let font_data = font_data_with_tables(
+        TEST_FONT_FILE_PATH,
+        vec![(*b"COLR", colr_table), (*b"CPAL", cpal_table)],
+    );
+    let font = Font::from_bytes(font_data, FaceIndex::FIRST).unwrap();
+
+    // Returns the color of the most opaque pixel of the glyph.
+    let ink_color = |palette_index: u16, palette_overrides: &[(u16, [u8; 4])]| {
+        let (canvas, _) = font
+            .rasterize_color_glyph(
+                glyph_id,
+                32.0,
+                &FontTransform::identity(),
+                palette_index,
+                palette_overrides,
+                AlphaMode::Premultiplied,
+            )
+            .unwrap();
+        assert_eq!(canvas.format, Format::Rgba32);
+        let mut color = [0; 4];
+        for pixel in canvas.pixels.chunks(4) {
+            if pixel[3] > color[3] {
+                color.copy_from_slice(pixel);
+            }
+        }
+        color
+    };
+    assert_eq!(ink_color(0, &[]), [0xff, 0x00, 0x00, 0xff]);
+    assert_eq!(ink_color(1, &[]), [0x00, 0xff, 0x00, 0xff]);
+    // Out-of-range palettes fall back to the default palette.
+    assert_eq!(ink_color(2, &[]), [0xff, 0x00, 0x00, 0xff]);
+    assert_eq!(
+        ink_color(1, &[(0, [0x00, 0x00, 0xff, 0xff])]),
+        [0x00, 0x00, 0xff, 0xff]
+    );
+
+    // Glyphs without layers are drawn in the foreground color.
+    let plain_glyph_id = font.glyph_for_char('B').unwrap();
+    let foreground_color = [0x10, 0x20, 0x30, 0xff];
+    let (canvas, origin) = font
+        .rasterize_color_glyph(
+            plain_glyph_id,
+            32.0,
+            &FontTransform::identity(),
+            0,
+            &[(ColorLayer::FOREGROUND_PALETTE_INDEX, foreground_color)],
+            AlphaMode::Premultiplied,
+        )
+        .unwrap();
+    assert!(canvas
+        .pixels
+        .chunks(4)
+        .any(|pixel| pixel == foreground_color));
+    assert_eq!(
+        Rect::new(origin, canvas.size.to_i32()),
+        font.raster_bounds(
+            plain_glyph_id,
+            32.0,
             &FontTransform::identity(),
             &Point2D::zero(),
             HintingOptions::None,
             RasterizationOptions::GrayscaleAa,
         )
-        .unwrap();
-    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
-    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8);
-    font.rasterize_glyph(
-        &mut canvas,
-        glyph_id,
-        size,
-        &FontTransform::identity(),
-        &origin,
-        HintingOptions::None,
-        RasterizationOptions::GrayscaleAa,
-    )
-    .unwrap();
-    check_L_shape(&canvas);
+        .unwrap()
+    );
 }
 
 #[test]
-pub fn rasterize_glyph_bilevel() {
-    let font = SystemSource::new()
-        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+pub fn rasterize_color_glyph_with_straight_alpha() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    let foreground_color = [0xff, 0x00, 0x00, 0x80];
+    let rasterize = |alpha_mode| {
+        font.rasterize_color_glyph(
+            glyph_id,
+            32.0,
+            &FontTransform::identity(),
+            0,
+            &[(ColorLayer::FOREGROUND_PALETTE_INDEX, foreground_color)],
+            alpha_mode,
+        )
         .unwrap()
-        .load()
+        .0
+    };
+    let premultiplied_canvas = rasterize(AlphaMode::Premultiplied);
+    let straight_canvas = rasterize(AlphaMode::Straight);
+    assert_eq!(premultiplied_canvas.size, straight_canvas.size);
+
+    // At an antialiased edge, premultiplied red shrinks with coverage while straight red doesn't.
+    let (premultiplied_pixel, straight_pixel) = premultiplied_canvas
+        .pixels
+        .chunks(4)
+        .zip(straight_canvas.pixels.chunks(4))
+        .find(|&(pixel, _)| pixel[3] > 0 && pixel[3] < 0x80)
         .unwrap();
-    let glyph_id = font.glyph_for_char('L').unwrap();
-    let size = 16.0;
-    let raster_rect = font
-        .raster_bounds(
+    let alpha = premultiplied_pixel[3];
+    assert_eq!(premultiplied_pixel, [alpha, 0x00, 0x00, alpha]);
+    assert_eq!(straight_pixel, [0xff, 0x00, 0x00, alpha]);
+
+    // Fully covered pixels differ the same way.
+    assert!(premultiplied_canvas
+        .pixels
+        .chunks(4)
+        .any(|pixel| pixel == [0x80, 0x00, 0x00, 0x80]));
+    assert!(straight_canvas
+        .pixels
+        .chunks(4)
+        .any(|pixel| pixel == [0xff, 0x00, 0x00, 0x80]));
+}
+
+#[test]
+pub fn rasterize_glyph_with_straight_alpha() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    // A pixel far from the glyph, which neither mode should touch.
+    let untouched_pixel = [0x10, 0x10, 0x10, 0x20];
+    let rasterize = |alpha_mode| {
+        let mut canvas = Canvas::new(&Size2D::new(48, 48), Format::Rgba32).unwrap();
+        let last_pixel_start = canvas.pixels.len() - 4;
+        canvas.pixels[last_pixel_start..].copy_from_slice(&untouched_pixel);
+        font.rasterize_glyph(
+            &mut canvas,
             glyph_id,
-            size,
+            32.0,
             &FontTransform::identity(),
-            &Point2D::zero(),
+            &Point2D::new(0.0, 32.0),
             HintingOptions::None,
-            RasterizationOptions::Bilevel,
+            RasterizationOptions::GrayscaleAa,
+            alpha_mode,
         )
         .unwrap();
-    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
-    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8);
-    font.rasterize_glyph(
-        &mut canvas,
+        canvas
+    };
+    let premultiplied_canvas = rasterize(AlphaMode::Premultiplied);
+    let straight_canvas = rasterize(AlphaMode::Straight);
+
+    // The glyph is white, so premultiplied pixels have the coverage in every channel, while
+    // straight pixels are opaque white with the coverage as alpha.
+    let mut antialiased_pixel_count = 0;
+    for (premultiplied_pixel, straight_pixel) in premultiplied_canvas
+        .pixels
+        .chunks(4)
+        .zip(straight_canvas.pixels.chunks(4))
+        .take(48 * 47)
+    {
+        let alpha = premultiplied_pixel[3];
+        assert_eq!(premultiplied_pixel, [alpha; 4]);
+        if alpha == 0 {
+            assert_eq!(straight_pixel, [0; 4]);
+        } else {
+            assert_eq!(straight_pixel, [0xff, 0xff, 0xff, alpha]);
+        }
+        if alpha > 0 && alpha < 0xff {
+            antialiased_pixel_count += 1;
+        }
+    }
+    assert!(antialiased_pixel_count > 0);
+    assert_eq!(
+        premultiplied_canvas.pixels[(48 * 48 - 1) * 4..],
+        untouched_pixel
+    );
+    assert_eq!(straight_canvas.pixels[(48 * 48 - 1) * 4..], untouched_pixel);
+}
+
+#[test]
+pub fn render_string_with_fallback() {
+    // Make the interrobang, which Inconsolata lacks, a red color glyph in EB Garamond.
+    let plain_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let interrobang_glyph_id = plain_font.glyph_for_char('‽').unwrap();
+    let colr_table = colr_v0_table(interrobang_glyph_id, &[(interrobang_glyph_id, 0)]);
+    let mut cpal_table = vec![];
+    for &value in &[0, 1, 1, 1, 0, 14, 0] {
+        cpal_table.write_u16::<BigEndian>(value).unwrap();
+    }
+    cpal_table.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+    let font_data = font_data_with_tables(
+        TEST_FONT_FILE_PATH,
+        vec![(*b"COLR", colr_table), (*b"CPAL", cpal_table)],
+    );
+    let source = MemSource::from_fonts(
+        vec![
+            Handle::from_path(FILE_PATH_INCONSOLATA_TTF.into(), FaceIndex::FIRST),
+            Handle::from_memory(font_data, FaceIndex::FIRST),
+        ]
+        .into_iter(),
+    )
+    .unwrap();
+    let family_names = [
+        FamilyName::Title("Inconsolata".to_owned()),
+        FamilyName::Title(plain_font.family_name()),
+    ];
+
+    let canvas = render_string(&source, &family_names, &Properties::new(), "Hi‽", 32.0).unwrap();
+    assert_eq!(canvas.format, Format::Rgba32);
+    let column_has_ink = |x: u32, red: bool| {
+        (0..canvas.size.height).any(|y| {
+            let pixel = &canvas.pixels[(y as usize * canvas.stride + x as usize * 4)..];
+            pixel[3] > 0 && (pixel[0] > 0) == red
+        })
+    };
+    // Black ink from Inconsolata at the left edge and red ink from EB Garamond at the right edge.
+    assert!(column_has_ink(0, false));
+    assert!(column_has_ink(canvas.size.width - 1, true));
+    assert!(!(0..canvas.size.width / 2).any(|x| column_has_ink(x, true)));
+
+    // Three Inconsolata glyphs are wider than two glyphs and a narrow interrobang.
+    let inconsolata = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    let inconsolata_advance = inconsolata
+        .advance_pixels(inconsolata.glyph_for_char('H').unwrap(), 32.0)
+        .unwrap()
+        .x;
+    assert!(canvas.size.width as f32 > inconsolata_advance * 1.5);
+    assert!((canvas.size.width as f32) < inconsolata_advance * 3.0);
+
+    match render_string(
+        &source,
+        &[FamilyName::Title("Nonexistent".to_owned())],
+        &Properties::new(),
+        "Hi",
+        32.0,
+    ) {
+        Err(RenderingError::Selection(SelectionError::NotFound)) => {}
+        other => panic!(
+            "Expected no font to be found but got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+pub fn render_string_with_color_glyph_without_colr_table() {
+    // An `SVG ` table makes the glyphs color without giving them `COLR` layers.
+    let font_data = font_data_with_tables(TEST_FONT_FILE_PATH, vec![(*b"SVG ", vec![0; 10])]);
+    let font = Font::from_bytes(font_data.clone(), FaceIndex::FIRST).unwrap();
+    let source =
+        MemSource::from_fonts(iter::once(Handle::from_memory(font_data, FaceIndex::FIRST)))
+            .unwrap();
+    let family_names = [FamilyName::Title(font.family_name())];
+
+    let canvas = render_string(&source, &family_names, &Properties::new(), "A", 32.0).unwrap();
+    assert_eq!(canvas.format, Format::Rgba32);
+    assert!(canvas.pixels.chunks(4).any(|pixel| pixel == [0, 0, 0, 255]));
+    assert!(canvas
+        .pixels
+        .chunks(4)
+        .all(|pixel| pixel[0..3] == [0, 0, 0]));
+
+    // Rasterizing into an RGBA canvas directly gives premultiplied white coverage.
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    let mut canvas = Canvas::new(&Size2D::new(32, 32), Format::Rgba32).unwrap();
+    font.rasterize_glyph(
+        &mut canvas,
         glyph_id,
-        size,
+        24.0,
         &FontTransform::identity(),
-        &origin,
+        &point2(4.0, 28.0),
         HintingOptions::None,
-        RasterizationOptions::Bilevel,
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
     )
     .unwrap();
+    assert!(canvas.pixels.chunks(4).any(|pixel| pixel == [255; 4]));
     assert!(canvas
         .pixels
+        .chunks(4)
+        .all(|pixel| pixel.iter().all(|&value| value == pixel[3])));
+}
+
+#[test]
+pub fn has_table() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    // `CFF `
+    assert!(font.has_table(0x4346_4620));
+    // `glyf`
+    assert!(!font.has_table(0x676c_7966));
+}
+
+#[test]
+pub fn get_table_tags() {
+    let glyf_tag = BigEndian::read_u32(b"glyf");
+    let loca_tag = BigEndian::read_u32(b"loca");
+    let cff_tag = BigEndian::read_u32(b"CFF ");
+
+    let font = Font::from_path(FILE_PATH_EB_GARAMOND_TTF, FaceIndex::FIRST).unwrap();
+    let table_tags = font.table_tags();
+    assert!(table_tags.contains(&glyf_tag));
+    assert!(table_tags.contains(&loca_tag));
+    assert!(!table_tags.contains(&cff_tag));
+    for &table_tag in &table_tags {
+        assert!(font.has_table(table_tag));
+    }
+
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let table_tags = font.table_tags();
+    assert!(table_tags.contains(&cff_tag));
+    assert!(!table_tags.contains(&glyf_tag));
+    assert!(!table_tags.contains(&loca_tag));
+
+    // The default implementation of `Loader::table_tags` reads the same tables from the table
+    // directory.
+    let mut directory_table_tags = font.handle().unwrap().table_tags().unwrap();
+    let mut table_tags = table_tags;
+    directory_table_tags.sort();
+    table_tags.sort();
+    assert_eq!(directory_table_tags, table_tags);
+}
+
+#[test]
+pub fn get_opentype_script_and_language_tags() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert_eq!(
+        font.opentype_script_tags(),
+        [*b"DFLT", *b"cyrl", *b"grek", *b"latn"]
+    );
+    assert_eq!(
+        font.opentype_language_tags(*b"latn"),
+        [*b"AZE ", *b"CAT ", *b"CRT ", *b"DEU ", *b"LAT ", *b"TRK "]
+    );
+    assert_eq!(font.opentype_language_tags(*b"cyrl"), [*b"MKD ", *b"SRB "]);
+    assert!(font.opentype_language_tags(*b"grek").is_empty());
+    assert!(font.opentype_language_tags(*b"arab").is_empty());
+}
+
+#[test]
+pub fn get_opentype_feature_tags() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let feature_tags = font.opentype_feature_tags();
+    for feature_tag in &[*b"liga", *b"kern", *b"calt"] {
+        assert!(feature_tags.contains(feature_tag));
+        assert!(font.has_opentype_feature(*feature_tag));
+    }
+    assert!(!font.has_opentype_feature(*b"zero"));
+
+    // `xtex` is defined in both `GSUB` and `GPOS`.
+    assert_eq!(feature_tags.iter().filter(|&tag| tag == b"xtex").count(), 1);
+    let mut sorted_feature_tags = feature_tags.clone();
+    sorted_feature_tags.sort();
+    assert_eq!(feature_tags, sorted_feature_tags);
+}
+
+#[test]
+pub fn opentype_feature_support_by_script_and_language() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert!(font.supports_opentype_feature(*b"liga", *b"latn", None));
+    assert!(font.supports_opentype_feature(*b"liga", *b"latn", Some(*b"TRK ")));
+    assert!(!font.supports_opentype_feature(*b"zero", *b"latn", None));
+
+    // `locl` only applies to specific language systems.
+    assert!(!font.supports_opentype_feature(*b"locl", *b"latn", None));
+    assert!(font.supports_opentype_feature(*b"locl", *b"latn", Some(*b"TRK ")));
+
+    // Unlisted scripts fall back to `DFLT`.
+    assert!(font.supports_opentype_feature(*b"liga", *b"arab", None));
+
+    let font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    assert!(!font.supports_opentype_feature(*b"liga", *b"latn", None));
+    assert!(font.supports_opentype_feature(*b"zero", *b"latn", None));
+}
+
+#[test]
+pub fn get_ligature_for_glyphs() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyphs = |font: &Font, text: &str| -> Vec<u32> {
+        text.chars()
+            .map(|character| font.glyph_for_char(character).unwrap())
+            .collect()
+    };
+    // EB Garamond forms its "fi" ligatures contextually, but has plain ligatures of the long s.
+    let long_s_long_s_t = font
+        .ligature_for(&glyphs(&font, "\u{17f}\u{17f}t"))
+        .unwrap();
+    let long_s_t = font.ligature_for(&glyphs(&font, "\u{17f}t")).unwrap();
+    assert_ne!(long_s_long_s_t, long_s_t);
+    assert_eq!(font.ligature_for(&glyphs(&font, "t\u{17f}")), None);
+    assert_eq!(font.ligature_for(&glyphs(&font, "\u{17f}")), None);
+    assert_eq!(font.ligature_for(&[]), None);
+
+    let font = Font::from_path(FILE_PATH_EB_GARAMOND_TTF, FaceIndex::FIRST).unwrap();
+    let ligature = font
+        .ligature_for(&glyphs(&font, "\u{17f}\u{17f}t"))
+        .unwrap();
+    assert_eq!(font.glyph_name(ligature).unwrap(), "longs_longs_t");
+
+    let font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    assert_eq!(font.ligature_for(&glyphs(&font, "ffi")), None);
+}
+
+#[test]
+pub fn ligature_for_glyphs_in_gsub_table() {
+    // A `GSUB` table whose `liga` feature has one ligature substitution lookup, which turns glyphs
+    // 10, 10, 11 ("ffi") into glyph 100 and glyphs 10, 11 ("fi") into glyph 101.
+    let words = [
+        // The header, an empty ScriptList, and a FeatureList with one Feature table.
+        &[1, 0, 10, 12, 26, 0, 1][..],
+        &[
+            u16::from_be_bytes(*b"li"),
+            u16::from_be_bytes(*b"ga"),
+            8,
+            0,
+            1,
+            0,
+        ],
+        // The LookupList and its Lookup table.
+        &[1, 4, 4, 0, 1, 8],
+        // The ligature substitution subtable and its Coverage table.
+        &[1, 8, 1, 14, 1, 1, 10],
+        // The LigatureSet table and its Ligature tables.
+        &[2, 6, 14, 100, 3, 10, 11, 101, 2, 11],
+    ]
+    .concat();
+    let gsub_table: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+    assert_eq!(
+        layout::ligature_for(&gsub_table, *b"liga", &[10, 10, 11]),
+        Some(100)
+    );
+    assert_eq!(
+        layout::ligature_for(&gsub_table, *b"liga", &[10, 11]),
+        Some(101)
+    );
+    assert_eq!(layout::ligature_for(&gsub_table, *b"liga", &[10, 10]), None);
+    assert_eq!(layout::ligature_for(&gsub_table, *b"liga", &[11, 10]), None);
+    assert_eq!(layout::ligature_for(&gsub_table, *b"dlig", &[10, 11]), None);
+
+    assert_eq!(
+        layout::ligature_at_start(&gsub_table, *b"liga", &[10, 10, 11, 12]),
+        Some((100, 3))
+    );
+    assert_eq!(
+        layout::ligature_at_start(&gsub_table, *b"liga", &[10, 11, 0x10000]),
+        Some((101, 2))
+    );
+    assert_eq!(
+        layout::ligature_at_start(&gsub_table, *b"liga", &[10, 10, 12]),
+        None
+    );
+    assert_eq!(layout::ligature_at_start(&gsub_table, *b"liga", &[]), None);
+}
+
+#[test]
+pub fn lookup_ligature_at_start_of_glyphs() {
+    let font = Font::from_path(FILE_PATH_EB_GARAMOND_TTF, FaceIndex::FIRST).unwrap();
+    let glyphs: Vec<u32> = "\u{17f}\u{17f}tst"
+        .chars()
+        .map(|character| font.glyph_for_char(character).unwrap())
+        .collect();
+    let (ligature, glyph_count) = font.lookup_ligature(&glyphs).unwrap();
+    assert_eq!(font.glyph_name(ligature).unwrap(), "longs_longs_t");
+    assert_eq!(glyph_count, 3);
+    let (ligature, glyph_count) = font.lookup_ligature(&glyphs[1..]).unwrap();
+    assert_eq!(font.glyph_name(ligature).unwrap(), "longs_t");
+    assert_eq!(glyph_count, 2);
+    assert_eq!(font.lookup_ligature(&glyphs[2..]), None);
+}
+
+#[test]
+pub fn get_gpos_kerning_for_pair() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let a = font.glyph_for_char('A').unwrap();
+    let v = font.glyph_for_char('V').unwrap();
+    let kerning = font.gpos_kerning_for_pair(a, v).unwrap();
+    assert!(kerning.x < 0.0);
+    assert_eq!(kerning.y, 0.0);
+    // The font has no `kern` table, so the `GPOS` kerning is used either way.
+    assert_eq!(font.kerning_for_pair_any(a, v).unwrap(), kerning);
+    assert_eq!(
+        font.gpos_kerning_for_pair(a, font.glyph_count()),
+        Err(GlyphLoadingError::NoSuchGlyph)
+    );
+}
+
+#[test]
+pub fn gpos_kerning_for_pair_in_gpos_table() {
+    // A `GPOS` table whose `kern` feature has one pair adjustment lookup with two subtables: one
+    // that kerns glyph 10 with glyphs 11 and 12 individually, and one that kerns the class of
+    // glyphs 10 to 20 with the class of glyph 13.
+    let words = [
+        // The header, an empty ScriptList, and a FeatureList with one Feature table.
+        &[1, 0, 10, 12, 26, 0, 1][..],
+        &[
+            u16::from_be_bytes(*b"ke"),
+            u16::from_be_bytes(*b"rn"),
+            8,
+            0,
+            1,
+            0,
+        ],
+        // The LookupList and its Lookup table.
+        &[1, 4, 2, 0, 2, 10, 38],
+        // The format 1 subtable, its Coverage table, and its PairSet table.
+        &[1, 12, 4, 0, 1, 18],
+        &[1, 1, 10],
+        &[2, 11, (-50i16) as u16, 12, 30],
+        // The format 2 subtable, whose ValueRecords hold an XPlacement before the XAdvance.
+        &[2, 32, 5, 0, 42, 52, 2, 2],
+        &[0, 0, 0, 0, 0, 0, 7, (-80i16) as u16],
+        // Its Coverage table and its two ClassDef tables.
+        &[2, 1, 10, 20, 0],
+        &[2, 1, 10, 20, 1],
+        &[1, 13, 2, 1, 0],
+    ]
+    .concat();
+    let gpos_table: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+    let kern = |left_glyph, right_glyph| {
+        layout::pair_adjustment(&gpos_table, *b"kern", left_glyph, right_glyph)
+    };
+    assert_eq!(kern(10, 11), Some(-50));
+    assert_eq!(kern(10, 12), Some(30));
+    assert_eq!(kern(10, 13), Some(-80));
+    assert_eq!(kern(15, 13), Some(-80));
+    assert_eq!(kern(10, 14), Some(0));
+    assert_eq!(kern(21, 13), None);
+    assert_eq!(layout::pair_adjustment(&gpos_table, *b"dist", 10, 11), None);
+}
+
+#[test]
+pub fn kerning_for_pair_in_kern_table() {
+    // The same pairs in an OpenType `kern` table and in an Apple one.
+    let pairs: &[u16] = &[2, 12, 1, 0, 10, 11, (-40i16) as u16, 10, 13, 25];
+    let opentype_words = [&[0, 1, 0, 26, 0x0001][..], pairs].concat();
+    let apple_words = [&[1, 0, 0, 1, 0, 28, 0x0000, 0][..], pairs].concat();
+    for words in &[opentype_words, apple_words] {
+        let kern_table: Vec<u8> = words.iter().flat_map(|word| word.to_be_bytes()).collect();
+        assert_eq!(kern::kerning_for_pair(&kern_table, 10, 11), Some(-40));
+        assert_eq!(kern::kerning_for_pair(&kern_table, 10, 13), Some(25));
+        assert_eq!(kern::kerning_for_pair(&kern_table, 10, 12), None);
+        assert_eq!(kern::kerning_for_pair(&kern_table, 11, 10), None);
+    }
+}
+
+#[test]
+pub fn get_embedding_mode() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert_eq!(font.embedding_restrictions(), Some(0));
+    assert_eq!(font.embedding_mode(), EmbeddingMode::FullOutline);
+}
+
+#[test]
+pub fn get_panose() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let panose = Panose(font.panose().unwrap());
+    assert_eq!(panose.0, [2, 2, 5, 2, 6, 2, 6, 2, 4, 3]);
+    assert_eq!(panose.family_type(), PanoseFamilyType::LatinText);
+    assert_eq!(panose.serif_style(), 2);
+    assert_eq!(panose.weight(), 5);
+    assert_eq!(panose.proportion(), 2);
+    assert_eq!(panose.x_height(), 3);
+    assert!(!panose.is_monospace());
+
+    let font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    let panose = Panose::from(font.panose().unwrap());
+    assert_eq!(panose.family_type(), PanoseFamilyType::Any);
+    assert!(panose.is_monospace());
+}
+
+#[test]
+pub fn get_unicode_ranges() {
+    let font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    let unicode_ranges = font.unicode_ranges();
+    assert_eq!(unicode_ranges, UnicodeRanges([0x2000_0007, 0x1, 0, 0]));
+    assert!(unicode_ranges.supports_basic_latin());
+    assert!(unicode_ranges.supports_latin_extended_a());
+    assert!(!unicode_ranges.supports_cyrillic());
+    assert!(!unicode_ranges.supports_cjk_unified_ideographs());
+    assert!(unicode_ranges.has_bit(32));
+    assert!(!unicode_ranges.has_bit(128));
+    // Basic Latin through Latin Extended-A are adjacent, so they merge into one range.
+    assert_eq!(
+        unicode_ranges.to_set(),
+        [
+            '\u{0}'..='\u{17f}',
+            '\u{1e00}'..='\u{1eff}',
+            '\u{2070}'..='\u{209f}',
+            '\u{2c60}'..='\u{2c7f}',
+            '\u{a720}'..='\u{a7ff}',
+        ]
+    );
+
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let unicode_ranges = font.unicode_ranges();
+    assert!(unicode_ranges.supports_greek());
+    assert!(unicode_ranges.supports_cyrillic());
+    assert!(unicode_ranges.supports_non_bmp());
+    assert!(!unicode_ranges.supports_hebrew());
+    for character in "Aé\u{3b1}\u{436}".chars() {
+        assert!(unicode_ranges
+            .to_set()
+            .iter()
+            .any(|range| range.contains(&character)));
+    }
+}
+
+#[test]
+pub fn embedding_mode_from_fs_type() {
+    let expectations = [
+        // Installable.
+        (0x0000, EmbeddingMode::FullOutline),
+        // Restricted license.
+        (0x0002, EmbeddingMode::Forbidden),
+        // Preview & print, and editable with subsetting disallowed.
+        (0x0004, EmbeddingMode::FullOutline),
+        (0x0108, EmbeddingMode::FullOutline),
+        // Bitmap embedding only.
+        (0x0200, EmbeddingMode::BitmapOnly),
+        (0x0204, EmbeddingMode::BitmapOnly),
+        // A restricted license overrides bitmap embedding.
+        (0x0202, EmbeddingMode::Forbidden),
+        // Legacy fonts with several usage bits set get the least restrictive permission.
+        (0x0006, EmbeddingMode::FullOutline),
+    ];
+    for &(fs_type, embedding_mode) in &expectations {
+        assert_eq!(
+            EmbeddingMode::from_fs_type(fs_type),
+            embedding_mode,
+            "unexpected embedding mode for fsType {:#06x}",
+            fs_type
+        );
+    }
+}
+
+#[test]
+pub fn get_font_data() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let data = font.copy_font_data().unwrap();
+    debug_assert!(utils::SFNT_VERSIONS
         .iter()
-        .all(|&value| value == 0 || value == 0xff));
-    check_L_shape(&canvas);
+        .any(|version| data[0..4] == *version));
 }
 
 #[test]
-pub fn rasterize_glyph_bilevel_offset() {
+pub fn rasterize_glyph_with_grayscale_aa() {
     let font = SystemSource::new()
         .select_best_match(&[FamilyName::SansSerif], &Properties::new())
         .unwrap()
@@ -573,13 +1770,13 @@ pub fn rasterize_glyph_bilevel_offset() {
             glyph_id,
             size,
             &FontTransform::identity(),
-            &point2(30., 100.),
+            &Point2D::zero(),
             HintingOptions::None,
-            RasterizationOptions::Bilevel,
+            RasterizationOptions::GrayscaleAa,
         )
         .unwrap();
-    let origin = Point2D::new(-raster_rect.origin.x + 30, -raster_rect.origin.y + 100).to_f32();
-    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8);
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8).unwrap();
     font.rasterize_glyph(
         &mut canvas,
         glyph_id,
@@ -587,81 +1784,115 @@ pub fn rasterize_glyph_bilevel_offset() {
         &FontTransform::identity(),
         &origin,
         HintingOptions::None,
-        RasterizationOptions::Bilevel,
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
     )
     .unwrap();
-
-    assert!(canvas
-        .pixels
-        .iter()
-        .all(|&value| value == 0 || value == 0xff));
     check_L_shape(&canvas);
 }
 
-#[cfg(any(
-    not(any(target_os = "macos", target_os = "ios", target_family = "windows")),
-    feature = "loader-freetype-default"
-))]
+// DirectWrite rasterizes subpixel antialiased glyphs with ClearType, which covers each color
+// channel separately.
+#[cfg(target_family = "windows")]
 #[test]
-pub fn rasterize_glyph_with_full_hinting() {
+pub fn rasterize_glyph_with_subpixel_aa() {
     let font = SystemSource::new()
         .select_best_match(&[FamilyName::SansSerif], &Properties::new())
         .unwrap()
         .load()
         .unwrap();
-    let glyph_id = font.glyph_for_char('L').unwrap();
+    let glyph_id = font.glyph_for_char('O').unwrap();
     let size = 32.0;
+    let hinting_options = HintingOptions::VerticalSubpixel(size);
     let raster_rect = font
         .raster_bounds(
             glyph_id,
             size,
             &FontTransform::identity(),
             &Point2D::zero(),
-            HintingOptions::None,
-            RasterizationOptions::Bilevel,
+            hinting_options,
+            RasterizationOptions::SubpixelAa,
         )
         .unwrap();
     let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
-    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8);
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::Rgb24).unwrap();
     font.rasterize_glyph(
         &mut canvas,
         glyph_id,
         size,
         &FontTransform::identity(),
         &origin,
-        HintingOptions::Full(size),
-        RasterizationOptions::GrayscaleAa,
+        hinting_options,
+        RasterizationOptions::SubpixelAa,
+        AlphaMode::Premultiplied,
     )
     .unwrap();
-    check_L_shape(&canvas);
+    assert!(canvas
+        .pixels
+        .chunks(3)
+        .any(|rgb| rgb[0] != rgb[1] || rgb[1] != rgb[2]));
+}
 
-    // Make sure the top and bottom (non-blank) rows have some fully black pixels in them.
-    let mut top_row = &canvas.pixels[0..canvas.stride];
-    if top_row.iter().all(|&value| value == 0) {
-        top_row = &canvas.pixels[(1 * canvas.stride)..(2 * canvas.stride)];
-    }
+#[test]
+pub fn rasterize_glyph_with_subpixel_order() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let glyph_id = font.glyph_for_char('O').unwrap();
+    let size = 32.0;
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::SubpixelAa,
+        )
+        .unwrap()
+        .inflate(1, 1);
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let rasterize = |subpixel_order| {
+        let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::Rgb24).unwrap();
+        font.rasterize_glyph_with_subpixel_order(
+            &mut canvas,
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &origin,
+            SubpixelAaOptions {
+                hinting_options: HintingOptions::None,
+                subpixel_order,
+            },
+        )
+        .unwrap();
+        canvas
+    };
 
-    assert!(top_row.iter().any(|&value| value == 0xff));
-    for y in (0..(canvas.size.height as usize)).rev() {
-        let bottom_row = &canvas.pixels[(y * canvas.stride)..((y + 1) * canvas.stride)];
-        if bottom_row.iter().all(|&value| value == 0) {
-            continue;
+    for &(rgb_order, bgr_order) in &[
+        (SubpixelOrder::Rgb, SubpixelOrder::Bgr),
+        (SubpixelOrder::Vrgb, SubpixelOrder::Vbgr),
+    ] {
+        let rgb_canvas = rasterize(rgb_order);
+        let bgr_canvas = rasterize(bgr_order);
+        assert!(rgb_canvas.pixels.iter().any(|&value| value != 0));
+        for (rgb, bgr) in rgb_canvas.pixels.chunks(3).zip(bgr_canvas.pixels.chunks(3)) {
+            assert_eq!(rgb, [bgr[2], bgr[1], bgr[0]]);
         }
-        assert!(bottom_row.iter().any(|&value| value == 0xff));
-        break;
     }
 }
 
-#[cfg(target_family = "windows")]
 #[test]
-pub fn rasterize_glyph() {
+pub fn rasterize_glyph_bilevel() {
     let font = SystemSource::new()
         .select_best_match(&[FamilyName::SansSerif], &Properties::new())
         .unwrap()
         .load()
         .unwrap();
-    let glyph_id = font.glyph_for_char('{').unwrap();
-    let size = 32.0;
+    let glyph_id = font.glyph_for_char('L').unwrap();
+    let size = 16.0;
     let raster_rect = font
         .raster_bounds(
             glyph_id,
@@ -669,11 +1900,11 @@ pub fn rasterize_glyph() {
             &FontTransform::identity(),
             &Point2D::zero(),
             HintingOptions::None,
-            RasterizationOptions::GrayscaleAa,
+            RasterizationOptions::Bilevel,
         )
         .unwrap();
     let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
-    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8);
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8).unwrap();
     font.rasterize_glyph(
         &mut canvas,
         glyph_id,
@@ -681,383 +1912,3678 @@ pub fn rasterize_glyph() {
         &FontTransform::identity(),
         &origin,
         HintingOptions::None,
-        RasterizationOptions::GrayscaleAa,
+        RasterizationOptions::Bilevel,
+        AlphaMode::Premultiplied,
     )
     .unwrap();
-    check_curly_shape(&canvas);
+    assert!(canvas
+        .pixels
+        .iter()
+        .all(|&value| value == 0 || value == 0xff));
+    check_L_shape(&canvas);
 }
 
 #[test]
-pub fn font_transform() {
+pub fn rasterize_glyph_bilevel_offset() {
     let font = SystemSource::new()
         .select_best_match(&[FamilyName::SansSerif], &Properties::new())
         .unwrap()
         .load()
         .unwrap();
     let glyph_id = font.glyph_for_char('L').unwrap();
-    let size = 16.0;
+    let size = 32.0;
     let raster_rect = font
         .raster_bounds(
             glyph_id,
             size,
             &FontTransform::identity(),
-            &point2(8., 8.),
-            HintingOptions::None,
-            RasterizationOptions::Bilevel,
-        )
-        .unwrap();
-    let raster_rect2 = font
-        .raster_bounds(
-            glyph_id,
-            size,
-            &FontTransform::new(3., 0., 0., 3.),
-            &point2(8., 8.),
+            &point2(30., 100.),
             HintingOptions::None,
             RasterizationOptions::Bilevel,
         )
         .unwrap();
-    assert!((raster_rect2.size.width - raster_rect.size.width * 3).abs() <= 3);
-    assert!((raster_rect2.size.height - raster_rect.size.height * 3).abs() <= 3);
-    assert!((raster_rect2.origin.x - ((raster_rect.origin.x - 8) * 3 + 8)).abs() <= 3);
-    assert!((raster_rect2.origin.y - ((raster_rect.origin.y - 8) * 3 + 8)).abs() <= 3);
-}
+    let origin = Point2D::new(-raster_rect.origin.x + 30, -raster_rect.origin.y + 100).to_f32();
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8).unwrap();
+    font.rasterize_glyph(
+        &mut canvas,
+        glyph_id,
+        size,
+        &FontTransform::identity(),
+        &origin,
+        HintingOptions::None,
+        RasterizationOptions::Bilevel,
+        AlphaMode::Premultiplied,
+    )
+    .unwrap();
 
-#[test]
-fn load_fonts_from_opentype_collection() {
-    let mut file = File::open(TEST_FONT_COLLECTION_FILE_PATH).unwrap();
-    {
-        let font = Font::from_file(&mut file, 0).unwrap();
+    assert!(canvas
+        .pixels
+        .iter()
+        .all(|&value| value == 0 || value == 0xff));
+    check_L_shape(&canvas);
+}
+
+#[test]
+pub fn rasterize_glyph_subpixel() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let glyph_id = font.glyph_for_char('L').unwrap();
+    let size = 32.0;
+    let (canvas_0, offset_0) = font
+        .rasterize_glyph_subpixel(glyph_id, size, 0, 2, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    let (canvas_1, offset_1) = font
+        .rasterize_glyph_subpixel(glyph_id, size, 1, 2, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    check_L_shape(&canvas_0);
+    check_L_shape(&canvas_1);
+
+    // A quarter-pixel shift should change the coverage, but only slightly.
+    assert_eq!(offset_0, offset_1);
+    assert_ne!(canvas_0.pixels, canvas_1.pixels);
+    let coverage_0: u32 = canvas_0.pixels.iter().map(|&value| value as u32).sum();
+    let coverage_1: u32 = canvas_1.pixels.iter().map(|&value| value as u32).sum();
+    assert!((coverage_0 as f32 - coverage_1 as f32).abs() / (coverage_0 as f32) < 0.1);
+
+    // Out-of-range offsets and precisions are clamped rather than shifting the glyph further.
+    let (canvas_3, offset_3) = font
+        .rasterize_glyph_subpixel(glyph_id, size, 3, 2, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    let (canvas_7, offset_7) = font
+        .rasterize_glyph_subpixel(glyph_id, size, 7, 2, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    assert_eq!((canvas_7.pixels, offset_7), (canvas_3.pixels, offset_3));
+    let (canvas_8_bits, _) = font
+        .rasterize_glyph_subpixel(glyph_id, size, 255, 8, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    let (canvas_9_bits, _) = font
+        .rasterize_glyph_subpixel(glyph_id, size, 255, 9, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    assert_eq!(canvas_9_bits.pixels, canvas_8_bits.pixels);
+}
+
+#[test]
+pub fn sized_font() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    let sized_font = SizedFont::new(font.clone(), 16.0);
+    assert_eq!(
+        sized_font.advance(glyph_id).unwrap(),
+        font.advance_pixels(glyph_id, 16.0).unwrap()
+    );
+    assert_eq!(
+        sized_font.run_bounds(&[glyph_id]).unwrap(),
+        font.run_bounds(&[glyph_id], 16.0).unwrap()
+    );
+    assert_eq!(sized_font.line_metrics(), font.line_metrics(16.0));
+
+    let raster_bounds = sized_font
+        .raster_bounds(
+            glyph_id,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+    assert_eq!(
+        raster_bounds,
+        font.raster_bounds(
+            glyph_id,
+            16.0,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap()
+    );
+    let mut canvas = Canvas::new(&raster_bounds.size.to_u32(), Format::A8).unwrap();
+    sized_font
+        .rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            &FontTransform::identity(),
+            &(-raster_bounds.origin.to_f32()),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+            AlphaMode::Premultiplied,
+        )
+        .unwrap();
+    assert!(canvas.pixels.iter().any(|&value| value != 0));
+
+    // Doubling the size doubles the advance.
+    let larger_font = sized_font.with_point_size(32.0);
+    assert_eq!(larger_font.point_size, 32.0);
+    assert_eq!(larger_font.scale_factor(), sized_font.scale_factor() * 2.0);
+    assert_eq!(
+        larger_font.advance(glyph_id).unwrap(),
+        sized_font.advance(glyph_id).unwrap() * 2.0
+    );
+}
+
+#[test]
+pub fn synthetic_bold() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let bold_font = font.synthetic_bold(DEFAULT_EXTRA_WEIGHT).unwrap();
+    assert_eq!(bold_font.postscript_name(), font.postscript_name());
+    let glyph_id = font.glyph_for_char('o').unwrap();
+
+    assert_eq!(
+        bold_font.advance(glyph_id).unwrap(),
+        font.advance(glyph_id).unwrap() + Vector2D::new(DEFAULT_EXTRA_WEIGHT, 0.0)
+    );
+    let bounds = font.typographic_bounds(glyph_id).unwrap();
+    assert_eq!(
+        bold_font.typographic_bounds(glyph_id).unwrap(),
+        Rect::new(
+            bounds.origin,
+            bounds.size + Size2D::new(DEFAULT_EXTRA_WEIGHT, DEFAULT_EXTRA_WEIGHT)
+        )
+    );
+
+    // The curves of the "o" have points at their extremes, which move straight outward, so the
+    // left and bottom edges stay put and the others move by the extra weight.
+    let outline_bounds = font
+        .outline_bounding_box(glyph_id, HintingOptions::None)
+        .unwrap();
+    let bold_outline_bounds = bold_font
+        .outline_bounding_box(glyph_id, HintingOptions::None)
+        .unwrap();
+    assert!((bold_outline_bounds.min_x() - outline_bounds.min_x()).abs() < 0.5);
+    assert!((bold_outline_bounds.min_y() - outline_bounds.min_y()).abs() < 0.5);
+    assert!(
+        (bold_outline_bounds.max_x() - outline_bounds.max_x() - DEFAULT_EXTRA_WEIGHT).abs() < 0.5
+    );
+    assert!(
+        (bold_outline_bounds.max_y() - outline_bounds.max_y() - DEFAULT_EXTRA_WEIGHT).abs() < 0.5
+    );
+
+    // The software rasterizer should agree with the platform one on the unmodified glyph, and the
+    // bold glyph should cover more pixels.
+    let coverage = |canvas: &Canvas| canvas.pixels.iter().map(|&value| value as f32).sum::<f32>();
+    let (canvas, _) = font
+        .rasterize_glyph_subpixel(glyph_id, 32.0, 0, 0, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    let (software_canvas, _) = SyntheticFont::new(font.clone())
+        .rasterize_glyph_subpixel(glyph_id, 32.0, 0, 0, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    let (bold_canvas, _) = bold_font
+        .rasterize_glyph_subpixel(glyph_id, 32.0, 0, 0, RasterizationOptions::GrayscaleAa)
+        .unwrap();
+    assert!((coverage(&software_canvas) - coverage(&canvas)).abs() / coverage(&canvas) < 0.05);
+    assert!(coverage(&bold_canvas) > coverage(&canvas) * 1.1);
+}
+
+#[test]
+pub fn synthetic_italic() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let italic_font = font.synthetic_italic(DEFAULT_SLANT_ANGLE);
+    assert_eq!(italic_font.slant_angle(), DEFAULT_SLANT_ANGLE);
+    let glyph_id = font.glyph_for_char('A').unwrap();
+    assert_eq!(
+        italic_font.advance(glyph_id).unwrap(),
+        font.advance(glyph_id).unwrap()
+    );
+
+    // Every point of the outline moves right in proportion to its height.
+    let skew = DEFAULT_SLANT_ANGLE.to_radians().tan();
+    let mut path_builder = Path::builder();
+    font.outline(glyph_id, HintingOptions::None, &mut path_builder)
+        .unwrap();
+    let mut points = vec![];
+    for event in path_builder.build().iter() {
+        match event {
+            PathEvent::MoveTo(to) => points.push(to),
+            PathEvent::Line(segment) | PathEvent::Close(segment) => points.push(segment.to),
+            PathEvent::Quadratic(segment) => points.extend(&[segment.ctrl, segment.to]),
+            PathEvent::Cubic(segment) => points.extend(&[segment.ctrl1, segment.ctrl2, segment.to]),
+        }
+    }
+    let sheared_bounds = Rect::from_points(
+        points
+            .iter()
+            .map(|point| Point2D::new(point.x + point.y * skew, point.y)),
+    );
+    let italic_bounds = italic_font.typographic_bounds(glyph_id).unwrap();
+    assert_eq!(
+        italic_font
+            .outline_bounding_box(glyph_id, HintingOptions::None)
+            .unwrap(),
+        italic_bounds
+    );
+    assert!((italic_bounds.min_x() - sheared_bounds.min_x()).abs() < 0.01);
+    assert!((italic_bounds.max_x() - sheared_bounds.max_x()).abs() < 0.01);
+    assert_eq!(italic_bounds.min_y(), sheared_bounds.min_y());
+    assert_eq!(italic_bounds.max_y(), sheared_bounds.max_y());
+    assert!(italic_bounds.max_x() > font.typographic_bounds(glyph_id).unwrap().max_x());
+
+    // Slanting an emboldened font keeps the extra weight.
+    let bold_italic_font = font
+        .synthetic_bold(DEFAULT_EXTRA_WEIGHT)
+        .unwrap()
+        .with_slant_angle(DEFAULT_SLANT_ANGLE);
+    assert_eq!(bold_italic_font.extra_weight(), DEFAULT_EXTRA_WEIGHT);
+    assert!(
+        bold_italic_font
+            .typographic_bounds(glyph_id)
+            .unwrap()
+            .max_x()
+            > italic_bounds.max_x()
+    );
+}
+
+#[test]
+pub fn rasterize_glyph_at_fractional_origin() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let glyph_id = font.glyph_for_char('l').unwrap();
+    let rasterize = |origin_x| {
+        let mut canvas = Canvas::new(&Size2D::new(32, 32), Format::A8).unwrap();
+        font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            16.0,
+            &FontTransform::identity(),
+            &Point2D::new(origin_x, 24.0),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+            AlphaMode::Premultiplied,
+        )
+        .unwrap();
+        canvas
+    };
+
+    // Moving the origin by half a pixel should move the center of the coverage by about as much.
+    // Antialiasing doesn't make the movement exactly linear, so allow some slack.
+    let center_x = |canvas: &Canvas| {
+        let (mut weighted_sum, mut total) = (0.0, 0.0);
+        for row in canvas.pixels.chunks(canvas.stride) {
+            for (x, &value) in row.iter().enumerate() {
+                weighted_sum += (x as f32 + 0.5) * value as f32;
+                total += value as f32;
+            }
+        }
+        weighted_sum / total
+    };
+    let (canvas_0, canvas_1) = (rasterize(8.0), rasterize(8.5));
+    assert_ne!(canvas_0.pixels, canvas_1.pixels);
+    let shift = center_x(&canvas_1) - center_x(&canvas_0);
+    assert!(
+        shift > 0.25 && shift < 0.75,
+        "Coverage shifted by {}",
+        shift
+    );
+}
+
+#[test]
+pub fn rasterize_glyph_at_absurd_size() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('a').unwrap();
+    // The bounds of the glyph don't fit in an `i32`.
+    match font.rasterize_glyph_subpixel(glyph_id, 1.0e20, 0, 0, RasterizationOptions::GrayscaleAa) {
+        Err(GlyphLoadingError::OutOfMemory) => {}
+        Err(error) => panic!("Expected out of memory but got {:?}", error),
+        Ok((canvas, _)) => panic!("Expected out of memory but got {:?}", canvas),
+    }
+
+    // The size of the pixels overflows a `usize`, so these fail before anything is allocated.
+    match Canvas::new(&Size2D::new(u32::MAX, u32::MAX), Format::Rgba32) {
+        Err(GlyphLoadingError::OutOfMemory) => {}
+        Err(error) => panic!("Expected out of memory but got {:?}", error),
+        Ok(canvas) => panic!("Expected out of memory but got {:?}", canvas),
+    }
+    match Canvas::with_stride(&Size2D::new(1, 2), usize::MAX, Format::A8) {
+        Err(GlyphLoadingError::OutOfMemory) => {}
+        Err(error) => panic!("Expected out of memory but got {:?}", error),
+        Ok(canvas) => panic!("Expected out of memory but got {:?}", canvas),
+    }
+}
+
+#[cfg(any(
+    not(any(target_os = "macos", target_os = "ios", target_family = "windows")),
+    feature = "loader-freetype-default"
+))]
+#[test]
+pub fn rasterize_glyph_with_full_hinting() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let glyph_id = font.glyph_for_char('L').unwrap();
+    let size = 32.0;
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::Bilevel,
+        )
+        .unwrap();
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8).unwrap();
+    font.rasterize_glyph(
+        &mut canvas,
+        glyph_id,
+        size,
+        &FontTransform::identity(),
+        &origin,
+        HintingOptions::Full(size),
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
+    )
+    .unwrap();
+    check_L_shape(&canvas);
+
+    // Make sure the top and bottom (non-blank) rows have some fully black pixels in them.
+    let mut top_row = &canvas.pixels[0..canvas.stride];
+    if top_row.iter().all(|&value| value == 0) {
+        top_row = &canvas.pixels[(1 * canvas.stride)..(2 * canvas.stride)];
+    }
+
+    assert!(top_row.iter().any(|&value| value == 0xff));
+    for y in (0..(canvas.size.height as usize)).rev() {
+        let bottom_row = &canvas.pixels[(y * canvas.stride)..((y + 1) * canvas.stride)];
+        if bottom_row.iter().all(|&value| value == 0) {
+            continue;
+        }
+        assert!(bottom_row.iter().any(|&value| value == 0xff));
+        break;
+    }
+}
+
+// FreeType's autohinter snaps the glyph to the pixel grid differently from the hints in the font,
+// and from no hinting at all. The other loaders don't have an autohinter.
+#[test]
+pub fn rasterize_glyph_with_autohint() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('a').unwrap();
+    let size = 13.0;
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap()
+        .inflate(2, 2);
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let rasterize = |hinting_options| {
+        let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8).unwrap();
+        font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &origin,
+            hinting_options,
+            RasterizationOptions::GrayscaleAa,
+            AlphaMode::Premultiplied,
+        )
+        .unwrap();
+        canvas.pixels
+    };
+
+    let unhinted_pixels = rasterize(HintingOptions::None);
+    let vertically_hinted_pixels = rasterize(HintingOptions::Vertical(size));
+    let fully_hinted_pixels = rasterize(HintingOptions::Full(size));
+    let autohinted_pixels = rasterize(HintingOptions::AutoHint(size));
+    assert!(autohinted_pixels.iter().any(|&value| value != 0));
+    if font.supports_hinting_options(HintingOptions::AutoHint(size), true) {
+        assert_ne!(autohinted_pixels, unhinted_pixels);
+        assert_ne!(autohinted_pixels, vertically_hinted_pixels);
+        assert_ne!(autohinted_pixels, fully_hinted_pixels);
+    } else {
+        // Not even the grid fitting size of the hinting options makes a difference.
+        assert_eq!(autohinted_pixels, unhinted_pixels);
+        assert_eq!(
+            rasterize(HintingOptions::AutoHint(size * 2.0)),
+            unhinted_pixels
+        );
+    }
+    assert_eq!(
+        HintingOptions::AutoHint(size).grid_fitting_size(),
+        Some(size)
+    );
+}
+
+#[cfg(target_family = "windows")]
+#[test]
+pub fn rasterize_glyph() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let glyph_id = font.glyph_for_char('{').unwrap();
+    let size = 32.0;
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let mut canvas = Canvas::new(&raster_rect.size.to_u32(), Format::A8).unwrap();
+    font.rasterize_glyph(
+        &mut canvas,
+        glyph_id,
+        size,
+        &FontTransform::identity(),
+        &origin,
+        HintingOptions::None,
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
+    )
+    .unwrap();
+    check_curly_shape(&canvas);
+}
+
+#[test]
+pub fn pack_canvas_channels() {
+    let size = Size2D::new(3, 2);
+    let channels: Vec<Canvas> = (0..4u8)
+        .map(|channel_index| {
+            let mut canvas = Canvas::with_stride(&size, 4, Format::A8).unwrap();
+            for y in 0..2 {
+                for x in 0..3 {
+                    canvas.pixels[y * 4 + x] = channel_index * 64 + (y * 3 + x) as u8;
+                }
+            }
+            canvas
+        })
+        .collect();
+
+    let packed =
+        Canvas::pack_channels(&channels[0], &channels[1], &channels[2], &channels[3]).unwrap();
+    assert_eq!(packed.format, Format::Rgba32);
+    assert_eq!(packed.size, size);
+    for (channel_index, channel) in channels.iter().enumerate() {
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(
+                    packed.pixels[y * packed.stride + x * 4 + channel_index],
+                    channel.pixels[y * channel.stride + x]
+                );
+            }
+        }
+    }
+
+    let small_canvas = Canvas::new(&Size2D::new(2, 2), Format::A8).unwrap();
+    assert!(
+        Canvas::pack_channels(&channels[0], &channels[1], &channels[2], &small_canvas).is_none()
+    );
+    let rgb_canvas = Canvas::new(&size, Format::Rgb24).unwrap();
+    assert!(Canvas::pack_channels(&rgb_canvas, &channels[1], &channels[2], &channels[3]).is_none());
+}
+
+#[test]
+pub fn composite_canvas_onto_canvas() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('g').unwrap();
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            32.0,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let size = raster_rect.size.to_u32();
+    let mut glyph_canvas = Canvas::new(&size, Format::A8).unwrap();
+    font.rasterize_glyph(
+        &mut glyph_canvas,
+        glyph_id,
+        32.0,
+        &FontTransform::identity(),
+        &origin,
+        HintingOptions::None,
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
+    )
+    .unwrap();
+
+    // A white glyph over opaque black shades from black to white with the glyph's coverage.
+    let background_size = Size2D::new(size.width + 4, size.height + 4);
+    let mut background = Canvas::new(&background_size, Format::Rgba32).unwrap();
+    for pixel in background.pixels.chunks_mut(4) {
+        pixel.copy_from_slice(&[0, 0, 0, 255]);
+    }
+    glyph_canvas.composite_onto(&mut background, 2, 2, CompositeMode::Over);
+    let mut saw_gray = false;
+    for y in 0..(size.height as usize) {
+        for x in 0..(size.width as usize) {
+            let coverage = glyph_canvas.pixels[y * glyph_canvas.stride + x];
+            let offset = (y + 2) * background.stride + (x + 2) * 4;
+            assert_eq!(
+                &background.pixels[offset..(offset + 4)],
+                &[coverage, coverage, coverage, 255]
+            );
+            saw_gray |= coverage > 0 && coverage < 255;
+        }
+    }
+    assert!(saw_gray);
+    assert_eq!(&background.pixels[0..4], &[0, 0, 0, 255]);
+
+    // Multiplying by white leaves the background alone, and adding saturates.
+    let mut gray = Canvas::new(&Size2D::new(2, 1), Format::Rgb24).unwrap();
+    gray.pixels.copy_from_slice(&[128, 64, 200, 128, 64, 200]);
+    let mut white = Canvas::new(&Size2D::new(2, 1), Format::A8).unwrap();
+    white.pixels.copy_from_slice(&[255, 100]);
+    white.composite_onto(&mut gray, 0, 0, CompositeMode::Multiply);
+    assert_eq!(gray.pixels, [128, 64, 200, 128, 64, 200]);
+    white.composite_onto(&mut gray, 0, 0, CompositeMode::Add);
+    assert_eq!(gray.pixels, [255, 255, 255, 228, 164, 255]);
+
+    // The parts of the source outside the destination are clipped.
+    let mut alpha = Canvas::new(&Size2D::new(2, 1), Format::A8).unwrap();
+    white.composite_onto(&mut alpha, -1, 0, CompositeMode::Over);
+    assert_eq!(alpha.pixels, [100, 0]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn canvas_to_image() {
+    use image::GenericImageView;
+
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('g').unwrap();
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            32.0,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let size = raster_rect.size.to_u32();
+
+    // Pad the rows, which the image shouldn't include.
+    let stride = size.width as usize + 3;
+    let mut canvas = Canvas::with_stride(&size, stride, Format::A8).unwrap();
+    font.rasterize_glyph(
+        &mut canvas,
+        glyph_id,
+        32.0,
+        &FontTransform::identity(),
+        &origin,
+        HintingOptions::None,
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
+    )
+    .unwrap();
+    let image = canvas.to_image();
+    assert_eq!(image.dimensions(), (size.width, size.height));
+    let gray_image = image.as_luma8().unwrap();
+    for y in 0..(size.height as usize) {
+        let width = size.width as usize;
+        assert_eq!(
+            &gray_image.as_raw()[(y * width)..((y + 1) * width)],
+            &canvas.pixels[(y * stride)..(y * stride + width)]
+        );
+    }
+
+    let rgba_canvas = Canvas::new(&size, Format::Rgba32).unwrap();
+    let rgba_image = rgba_canvas.to_image();
+    assert_eq!(
+        rgba_image.as_rgba8().unwrap().dimensions(),
+        (size.width, size.height)
+    );
+    let rgb_canvas = Canvas::new(&Size2D::new(0, 0), Format::Rgb24).unwrap();
+    assert_eq!(rgb_canvas.to_image().dimensions(), (0, 0));
+}
+
+#[cfg(feature = "png")]
+#[test]
+pub fn canvas_save_to_png() {
+    fn decode_png(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let (info, mut reader) = png::Decoder::new(bytes).read_info().unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        (info, pixels)
+    }
+
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let glyph_id = font.glyph_for_char('g').unwrap();
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            32.0,
+            &FontTransform::identity(),
+            &Point2D::zero(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+    let origin = Point2D::new(-raster_rect.origin.x, -raster_rect.origin.y).to_f32();
+    let size = raster_rect.size.to_u32();
+
+    // Pad the rows, which the image shouldn't include.
+    let stride = size.width as usize + 3;
+    let mut canvas = Canvas::with_stride(&size, stride, Format::A8).unwrap();
+    font.rasterize_glyph(
+        &mut canvas,
+        glyph_id,
+        32.0,
+        &FontTransform::identity(),
+        &origin,
+        HintingOptions::None,
+        RasterizationOptions::GrayscaleAa,
+        AlphaMode::Premultiplied,
+    )
+    .unwrap();
+    let path = env::temp_dir().join(format!("font-kit-canvas-{}.png", process::id()));
+    canvas.save_to_png(&path).unwrap();
+    let (info, pixels) = decode_png(&fs::read(&path).unwrap());
+    fs::remove_file(&path).unwrap();
+    assert_eq!((info.width, info.height), (size.width, size.height));
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    let width = size.width as usize;
+    for y in 0..(size.height as usize) {
+        assert_eq!(
+            &pixels[(y * width)..((y + 1) * width)],
+            &canvas.pixels[(y * stride)..(y * stride + width)]
+        );
+    }
+
+    // PNG images have straight alpha.
+    let mut rgba_canvas = Canvas::new(&Size2D::new(2, 1), Format::Rgba32).unwrap();
+    rgba_canvas.pixels = vec![64, 32, 0, 128, 10, 20, 30, 255];
+    let (info, pixels) = decode_png(&rgba_canvas.to_png_bytes().unwrap());
+    assert_eq!(info.color_type, png::ColorType::RGBA);
+    assert_eq!(pixels, [128, 64, 0, 128, 10, 20, 30, 255]);
+
+    let mut rgb_canvas = Canvas::new(&Size2D::new(1, 1), Format::Rgb24).unwrap();
+    rgb_canvas.pixels = vec![1, 2, 3];
+    let (info, pixels) = decode_png(&rgb_canvas.to_png_bytes().unwrap());
+    assert_eq!(info.color_type, png::ColorType::RGB);
+    assert_eq!(pixels, [1, 2, 3]);
+
+    // PNG images can't be empty, so empty canvases are an error rather than a panic, and no file
+    // is written for them.
+    for &(width, height) in &[(0, 0), (0, 3), (3, 0)] {
+        let empty_canvas = Canvas::new(&Size2D::new(width, height), Format::A8).unwrap();
+        let error = empty_canvas.to_png_bytes().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = empty_canvas.save_to_png(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+pub fn font_transform() {
+    let font = SystemSource::new()
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    let glyph_id = font.glyph_for_char('L').unwrap();
+    let size = 16.0;
+    let raster_rect = font
+        .raster_bounds(
+            glyph_id,
+            size,
+            &FontTransform::identity(),
+            &point2(8., 8.),
+            HintingOptions::None,
+            RasterizationOptions::Bilevel,
+        )
+        .unwrap();
+    let raster_rect2 = font
+        .raster_bounds(
+            glyph_id,
+            size,
+            &FontTransform::new(3., 0., 0., 3.),
+            &point2(8., 8.),
+            HintingOptions::None,
+            RasterizationOptions::Bilevel,
+        )
+        .unwrap();
+    assert!((raster_rect2.size.width - raster_rect.size.width * 3).abs() <= 3);
+    assert!((raster_rect2.size.height - raster_rect.size.height * 3).abs() <= 3);
+    assert!((raster_rect2.origin.x - ((raster_rect.origin.x - 8) * 3 + 8)).abs() <= 3);
+    assert!((raster_rect2.origin.y - ((raster_rect.origin.y - 8) * 3 + 8)).abs() <= 3);
+}
+
+#[test]
+fn load_font_from_mapped_file() {
+    let mapped_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let font_data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let buffered_font = Font::from_bytes(font_data.clone(), FaceIndex::FIRST).unwrap();
+    assert_eq!(mapped_font.glyph_count(), buffered_font.glyph_count());
+    assert_eq!(mapped_font.copy_font_data().unwrap(), font_data);
+
+    let handle = Handle::from_path(TEST_FONT_FILE_PATH.into(), FaceIndex::FIRST);
+    assert_eq!(
+        handle.load().unwrap().glyph_count(),
+        buffered_font.glyph_count()
+    );
+}
+
+// The loaders that map font files hand out handles to the file instead of copying it.
+#[cfg(not(target_family = "windows"))]
+#[test]
+fn handle_of_mapped_font() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    match font.handle().unwrap() {
+        Handle::Path { path, font_index } => {
+            assert_eq!(path, fs::canonicalize(TEST_FONT_FILE_PATH).unwrap());
+            assert_eq!(font_index, FaceIndex::FIRST);
+        }
+        Handle::Memory { .. } => panic!("expected a path handle"),
+    }
+
+    let font_data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let font = Font::from_bytes(font_data.clone(), FaceIndex::FIRST).unwrap();
+    match font.handle().unwrap() {
+        Handle::Memory { bytes, .. } => assert_eq!(bytes, font_data),
+        Handle::Path { .. } => panic!("expected a memory handle"),
+    }
+}
+
+// The loaders that map font files borrow the mapping instead of copying it.
+#[cfg(not(target_family = "windows"))]
+#[test]
+fn font_bytes() {
+    let font_data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let mapped_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    match mapped_font.font_bytes().unwrap() {
+        FontBytesRef::Slice(bytes) => assert_eq!(bytes, &font_data[..]),
+        FontBytesRef::Owned(_) => panic!("Expected the mapped file to be borrowed"),
+    }
+
+    let buffered_font = Font::from_bytes(font_data.clone(), FaceIndex::FIRST).unwrap();
+    match buffered_font.font_bytes().unwrap() {
+        FontBytesRef::Owned(ref bytes) => assert!(Arc::ptr_eq(bytes, &font_data)),
+        FontBytesRef::Slice(_) => panic!("Expected the data to be shared"),
+    }
+    assert_eq!(&*buffered_font.font_bytes().unwrap(), &font_data[..]);
+}
+
+#[test]
+fn approximate_memory_usage() {
+    let font_data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let buffered_font = Font::from_bytes(font_data.clone(), FaceIndex::FIRST).unwrap();
+    let buffered_usage = buffered_font.approximate_memory_usage();
+    assert!(buffered_usage >= font_data.len());
+
+    // Mapping the file costs no more than reading it into memory.
+    let mapped_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert!(mapped_font.approximate_memory_usage() <= buffered_usage);
+
+    // Caches built up by the font are accounted for.
+    buffered_font.glyph_is_color(0);
+    assert!(buffered_font.approximate_memory_usage() >= buffered_usage);
+}
+
+#[test]
+fn font_identity() {
+    let mapped_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let relative_path = format!("./resources/../{}", TEST_FONT_FILE_PATH);
+    let relative_font = Font::from_path(&relative_path, FaceIndex::FIRST).unwrap();
+    assert_eq!(mapped_font, relative_font);
+    assert_eq!(mapped_font, mapped_font.clone());
+
+    let font_data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let buffered_font = Font::from_bytes(font_data.clone(), FaceIndex::FIRST).unwrap();
+    let copied_font = Font::from_bytes(Arc::new((*font_data).clone()), FaceIndex::FIRST).unwrap();
+    assert_eq!(buffered_font, copied_font);
+
+    let other_font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    assert_ne!(mapped_font, other_font);
+    let collection_fonts = [
+        Font::from_path(TEST_FONT_COLLECTION_FILE_PATH, FaceIndex::FIRST).unwrap(),
+        Font::from_path(TEST_FONT_COLLECTION_FILE_PATH, FaceIndex(1)).unwrap(),
+    ];
+    assert_ne!(collection_fonts[0], collection_fonts[1]);
+
+    assert_eq!(mapped_font.identity(), relative_font.identity());
+    assert_eq!(buffered_font.identity(), copied_font.identity());
+    assert_ne!(mapped_font.identity(), other_font.identity());
+    assert_ne!(buffered_font.identity(), other_font.identity());
+    assert_ne!(
+        collection_fonts[0].identity(),
+        collection_fonts[1].identity()
+    );
+
+    let fonts: HashSet<Font> = vec![
+        mapped_font,
+        relative_font,
+        buffered_font,
+        copied_font,
+        other_font,
+    ]
+    .into_iter()
+    .chain(collection_fonts.iter().cloned())
+    .collect();
+    assert_eq!(fonts.len(), 5);
+}
+
+#[test]
+fn peek_info() {
+    for &(path, font_index) in &[
+        (TEST_FONT_FILE_PATH, FaceIndex::FIRST),
+        (FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST),
+        (TEST_FONT_COLLECTION_FILE_PATH, FaceIndex(1)),
+    ] {
+        let font_data = Arc::new(fs::read(path).unwrap());
+        let font_info = Font::peek_info(&font_data, font_index).unwrap();
+        let font = Font::from_bytes(font_data, font_index).unwrap();
+        assert_eq!(font_info.postscript_name, font.postscript_name());
+        assert_eq!(font_info.family_name, font.family_name());
+        // The FreeType loader only decodes Apple Unicode names, so it may fall back to the family
+        // name where `peek_info` finds the full name.
+        let full_name = font.full_name();
+        assert!(full_name == font_info.full_name || full_name == font_info.family_name);
+        assert!(font_info.full_name.starts_with(&font_info.family_name));
+        assert_eq!(font_info.properties, font.properties());
+    }
+
+    let font_data = fs::read(TEST_FONT_FILE_PATH).unwrap();
+    match Font::peek_info(&font_data, FaceIndex(1)) {
+        Err(FontLoadingError::NoSuchFontInCollection) => {}
+        result => panic!("Expected `NoSuchFontInCollection` but got {:?}", result),
+    }
+}
+
+#[test]
+fn load_fonts_from_opentype_collection() {
+    let mut file = File::open(TEST_FONT_COLLECTION_FILE_PATH).unwrap();
+    {
+        let font = Font::from_file(&mut file, FaceIndex::FIRST).unwrap();
+        assert_eq!(
+            font.postscript_name().unwrap(),
+            TEST_FONT_COLLECTION_POSTSCRIPT_NAME[0]
+        );
+    }
+    let font = Font::from_file(&mut file, FaceIndex(1)).unwrap();
+    assert_eq!(
+        font.postscript_name().unwrap(),
+        TEST_FONT_COLLECTION_POSTSCRIPT_NAME[1]
+    );
+}
+
+#[test]
+fn get_glyph_count() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert_eq!(font.glyph_count(), 3084);
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+#[test]
+fn core_text_font_is_send_and_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    assert_send::<crate::loaders::core_text::Font>();
+    assert_sync::<crate::loaders::core_text::Font>();
+}
+
+#[test]
+fn glyph_exists() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert!(font.glyph_exists(0));
+    assert!(font.glyph_exists(3083));
+    assert!(!font.glyph_exists(3084));
+    assert!(!font.glyph_exists(u32::max_value()));
+}
+
+#[test]
+fn get_default_glyph() {
+    let font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    assert_eq!(font.default_glyph(), 0);
+    assert!(font.glyph_exists(font.default_glyph()));
+}
+
+#[test]
+fn get_number_of_h_metrics() {
+    let font = Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST).unwrap();
+    let number_of_h_metrics = font.number_of_h_metrics().unwrap();
+    assert!(number_of_h_metrics >= 1);
+    assert!(number_of_h_metrics as u32 <= font.glyph_count());
+}
+
+// The initial off-curve point used to cause an assertion in the FreeType backend.
+#[test]
+fn get_glyph_outline_eb_garamond_exclam() {
+    let mut path_builder = Path::builder();
+    let mut file = File::open(FILE_PATH_EB_GARAMOND_TTF).unwrap();
+    let font = Font::from_file(&mut file, FaceIndex::FIRST).unwrap();
+    let glyph = font.glyph_for_char('!').expect("No glyph for char!");
+    font.outline(glyph, HintingOptions::None, &mut path_builder)
+        .unwrap();
+    let path = path_builder.build();
+
+    // The TrueType spec doesn't specify the rounding method for midpoints, as far as I can tell.
+    // So we are lenient and accept either values rounded down (what Core Text provides if the
+    // first point is off-curve, it seems) or precise floating-point values (what our FreeType
+    // loader provides).
+    let mut events = path.into_iter();
+    assert_eq!(
+        events.next(),
+        Some(PathEvent::MoveTo(Point2D::new(114.0, 598.0)))
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(114.0, 619.0),
+        Point2D::new(127.5, 634.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(141.0, 649.0),
+        Point2D::new(161.0, 649.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(181.0, 649.0),
+        Point2D::new(193.5, 634.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(206.0, 619.0),
+        Point2D::new(206.0, 598.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(206.0, 526.0),
+        Point2D::new(176.0, 244.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(172.0, 205.0),
+        Point2D::new(158.0, 205.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(144.0, 205.0),
+        Point2D::new(140.0, 244.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(114.0, 491.0),
+        Point2D::new(114.0, 598.0)
+    );
+    assert_close!(events.next());
+    let event = events.next();
+    assert!(
+        event == Some(PathEvent::MoveTo(Point2D::new(117.0, 88.0)))
+            || event == Some(PathEvent::MoveTo(Point2D::new(117.5, 88.5)))
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(135.0, 106.0),
+        Point2D::new(160.0, 106.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(185.0, 106.0),
+        Point2D::new(202.5, 88.5)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(220.0, 71.0),
+        Point2D::new(220.0, 46.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(220.0, 21.0),
+        Point2D::new(202.5, 3.5)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(185.0, -14.0),
+        Point2D::new(160.0, -14.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(135.0, -14.0),
+        Point2D::new(117.5, 3.5)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(100.0, 21.0),
+        Point2D::new(100.0, 46.0)
+    );
+    match events.next() {
+        Some(PathEvent::Quadratic(ref segment)) => {
+            assert_eq!(segment.ctrl, Point2D::new(100.0, 71.0));
+            assert!(
+                segment.to == Point2D::new(117.0, 88.0) || segment.to == Point2D::new(117.5, 88.5)
+            )
+        }
+        other => panic!("Expected quadratic got {:?}", other),
+    }
+    assert_close!(events.next());
+}
+
+// https://github.com/pcwalton/pathfinder/issues/84
+#[allow(non_snake_case)]
+#[test]
+fn get_glyph_outline_inconsolata_J() {
+    let mut path_builder = Path::builder();
+    let mut file = File::open(FILE_PATH_INCONSOLATA_TTF).unwrap();
+    let font = Font::from_file(&mut file, FaceIndex::FIRST).unwrap();
+    let glyph = font.glyph_for_char('J').expect("No glyph for char!");
+    font.outline(glyph, HintingOptions::None, &mut path_builder)
+        .unwrap();
+    let path = path_builder.build();
+
+    let mut events = path.into_iter();
+    assert_eq!(
+        events.next(),
+        Some(PathEvent::MoveTo(Point2D::new(198.0, -11.0)))
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(106.0, -11.0),
+        Point2D::new(49.0, 58.0)
+    );
+    assert_line_to!(events.next(), Point2D::new(89.0, 108.0));
+    assert_line_to!(events.next(), Point2D::new(96.0, 116.0));
+    assert_line_to!(events.next(), Point2D::new(101.0, 112.0));
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(102.0, 102.0),
+        Point2D::new(106.0, 95.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(110.0, 88.0),
+        Point2D::new(122.0, 78.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(157.0, 51.0),
+        Point2D::new(196.0, 51.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(247.0, 51.0),
+        Point2D::new(269.5, 86.5)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(292.0, 122.0),
+        Point2D::new(292.0, 208.0)
+    );
+    assert_line_to!(events.next(), Point2D::new(292.0, 564.0));
+    assert_line_to!(events.next(), Point2D::new(172.0, 564.0));
+    assert_line_to!(events.next(), Point2D::new(172.0, 623.0));
+    assert_line_to!(events.next(), Point2D::new(457.0, 623.0));
+    assert_line_to!(events.next(), Point2D::new(457.0, 564.0));
+    assert_line_to!(events.next(), Point2D::new(361.0, 564.0));
+    assert_line_to!(events.next(), Point2D::new(361.0, 209.0));
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(363.0, 133.0),
+        Point2D::new(341.0, 84.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(319.0, 35.0),
+        Point2D::new(281.5, 12.0)
+    );
+    assert_quadratic_to!(
+        events.next(),
+        Point2D::new(244.0, -11.0),
+        Point2D::new(198.0, -11.0)
+    );
+    assert_close!(events.next());
+}
+
+// Makes sure that a canvas has an "L" shape in it. This is used to test rasterization.
+#[allow(non_snake_case)]
+fn check_L_shape(canvas: &Canvas) {
+    // Find any empty rows at the start.
+    let mut y = 0;
+    while y < canvas.size.height {
+        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
+        if canvas.pixels[row_start..row_end].iter().any(|&p| p != 0) {
+            break;
+        }
+        y += 1;
+    }
+    assert!(y < canvas.size.height);
+
+    // Find the top part of the L.
+    let mut top_stripe_width = None;
+    while y < canvas.size.height {
+        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
+        if let Some(stripe_width) = stripe_width(&canvas.pixels[row_start..row_end]) {
+            if let Some(top_stripe_width) = top_stripe_width {
+                if stripe_width > top_stripe_width {
+                    break;
+                }
+                assert_eq!(stripe_width, top_stripe_width);
+            }
+            top_stripe_width = Some(stripe_width);
+        }
+        y += 1;
+    }
+    assert!(y < canvas.size.height);
+
+    // Find the bottom part of the L.
+    let mut bottom_stripe_width = None;
+    while y < canvas.size.height {
+        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
+        y += 1;
+        if let Some(stripe_width) = stripe_width(&canvas.pixels[row_start..row_end]) {
+            if let Some(bottom_stripe_width) = bottom_stripe_width {
+                assert!(bottom_stripe_width > top_stripe_width.unwrap());
+                assert_eq!(stripe_width, bottom_stripe_width);
+            }
+            bottom_stripe_width = Some(stripe_width);
+        }
+    }
+
+    // Find any empty rows at the end.
+    while y < canvas.size.height {
+        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
+        y += 1;
+        if canvas.pixels[row_start..row_end].iter().any(|&p| p != 0) {
+            break;
+        }
+    }
+
+    // Make sure we made it to the end.
+    assert_eq!(y, canvas.size.height);
+}
+
+// Makes sure that a canvas has an "{" shape in it. This is used to test rasterization.
+#[cfg(target_family = "windows")]
+fn check_curly_shape(canvas: &Canvas) {
+    let mut y = 0;
+    let height = canvas.size.height;
+    // check the upper row and the lower rows are symmetrical
+    while y < height / 2 {
+        let (upper_row_start, upper_row_end) =
+            (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
+        let (lower_row_start, lower_row_end) = (
+            canvas.stride * (height - y - 1) as usize,
+            canvas.stride * (height - y) as usize,
+        );
+        let upper_row = &canvas.pixels[upper_row_start..upper_row_end];
+        let lower_row = &canvas.pixels[lower_row_start..lower_row_end];
+        let top_row_width = stripe_width(upper_row).unwrap();
+        let lower_row_width = stripe_width(lower_row).unwrap();
+        let upper_row_pixel_start = stride_pixel_start(upper_row).unwrap();
+        let lower_row_pixel_start = stride_pixel_start(lower_row).unwrap();
+        if top_row_width == lower_row_width {
+            // check non-zero pixels start at the same index
+            assert_eq!(upper_row_pixel_start, lower_row_pixel_start);
+        } else {
+            //if not, assert that the difference is not greater than 1
+            assert_eq!((lower_row_width as i32 - top_row_width as i32).abs(), 1);
+            // and assert that the non-zero pixel index difference is not greater than 1
+            assert_eq!(
+                (upper_row_pixel_start as i32 - lower_row_pixel_start as i32).abs(),
+                1
+            );
+        }
+        y += 1;
+    }
+}
+
+// return the first non-zero pixel index
+#[cfg(target_family = "windows")]
+fn stride_pixel_start(pixels: &[u8]) -> Option<u32> {
+    let mut index = 0;
+    for x in pixels {
+        if *x != 0 {
+            return Some(index);
+        }
+        index += 1;
+    }
+    None
+}
+
+fn stripe_width(pixels: &[u8]) -> Option<u32> {
+    let mut x = 0;
+    // Find the initial empty part.
+    while x < pixels.len() && pixels[x] == 0 {
+        x += 1
+    }
+    if x == pixels.len() {
+        return None;
+    }
+    // Find the stripe width.
+    let mut stripe_width = 0;
+    while x < pixels.len() && pixels[x] != 0 {
+        x += 1;
+        stripe_width += 1;
+    }
+    // Find the last empty part.
+    while x < pixels.len() && pixels[x] == 0 {
+        x += 1;
+    }
+    assert_eq!(x, pixels.len());
+    Some(stripe_width)
+}
+
+// A source that only implements the required `Source` methods, so that the default
+// implementations of the others get exercised.
+struct MinimalSource(MemSource);
+
+impl Source for MinimalSource {
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.0.all_families()
+    }
+
+    // Unlike the built-in sources, this only matches family names exactly, so that the
+    // case-insensitive fallbacks in the default implementations get exercised.
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        if !self
+            .0
+            .all_families()?
+            .iter()
+            .any(|name| name == family_name)
+        {
+            return Err(SelectionError::NotFound);
+        }
+        self.0.select_family_by_name(family_name)
+    }
+}
+
+// A source that counts how many times each query that `CachingSource` remembers reaches it.
+struct CountingSource {
+    source: MinimalSource,
+    all_families: AtomicUsize,
+    select_family_by_name: AtomicUsize,
+    select_by_postscript_name: AtomicUsize,
+    select_best_match: AtomicUsize,
+}
+
+impl CountingSource {
+    fn new() -> CountingSource {
+        CountingSource {
+            source: MinimalSource(test_mem_source()),
+            all_families: AtomicUsize::new(0),
+            select_family_by_name: AtomicUsize::new(0),
+            select_by_postscript_name: AtomicUsize::new(0),
+            select_best_match: AtomicUsize::new(0),
+        }
+    }
+
+    fn counts(&self) -> [usize; 4] {
+        [
+            self.all_families.load(Ordering::SeqCst),
+            self.select_family_by_name.load(Ordering::SeqCst),
+            self.select_by_postscript_name.load(Ordering::SeqCst),
+            self.select_best_match.load(Ordering::SeqCst),
+        ]
+    }
+}
+
+impl Source for CountingSource {
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families.fetch_add(1, Ordering::SeqCst);
+        self.source.all_families()
+    }
+
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.select_family_by_name.fetch_add(1, Ordering::SeqCst);
+        self.source.select_family_by_name(family_name)
+    }
+
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name
+            .fetch_add(1, Ordering::SeqCst);
+        self.source.select_by_postscript_name(postscript_name)
+    }
+
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match.fetch_add(1, Ordering::SeqCst);
+        self.source.select_best_match(family_names, properties)
+    }
+}
+
+fn test_mem_source() -> MemSource {
+    let paths = [
+        TEST_FONT_FILE_PATH,
+        "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+        FILE_PATH_INCONSOLATA_TTF,
+    ];
+    MemSource::from_fonts(
+        paths
+            .iter()
+            .map(|&path| Handle::from_path(path.into(), FaceIndex::FIRST)),
+    )
+    .unwrap()
+}
+
+#[test]
+fn mem_source_from_paths() {
+    let paths = [
+        FilePath::new(FILE_PATH_INCONSOLATA_TTF).to_owned(),
+        FilePath::new(TEST_FONT_COLLECTION_FILE_PATH).to_owned(),
+    ];
+    let source = MemSource::from_paths(&paths).unwrap();
+    assert_eq!(
+        source.all_families().unwrap(),
+        ["EB Garamond", "Inconsolata"]
+    );
+    assert_eq!(source.all_fonts().unwrap().len(), 3);
+
+    let paths = [
+        FilePath::new(TEST_FONT_FILE_PATH).to_owned(),
+        FilePath::new("resources/tests/nonexistent.otf").to_owned(),
+    ];
+    assert_eq!(
+        MemSource::from_paths(&paths).err(),
+        Some(SelectionError::CannotAccessSource)
+    );
+}
+
+#[test]
+fn mem_source_from_bytes() {
+    let fonts = vec![
+        Arc::new(
+            include_bytes!("../resources/tests/eb-garamond/EBGaramond12-Regular.otf").to_vec(),
+        ),
+        Arc::new(include_bytes!("../resources/tests/inconsolata/Inconsolata-Regular.ttf").to_vec()),
+    ];
+    let source = MemSource::from_bytes(fonts.clone()).unwrap();
+    assert_eq!(
+        source.all_families().unwrap(),
+        ["EB Garamond", "Inconsolata"]
+    );
+
+    let handle = source
+        .select_best_match(
+            &[FamilyName::Title("Inconsolata".to_owned())],
+            &Properties::new(),
+        )
+        .unwrap();
+    match handle {
+        Handle::Memory { ref bytes, .. } => assert!(Arc::ptr_eq(bytes, &fonts[1])),
+        Handle::Path { .. } => panic!("expected a memory handle"),
+    }
+    assert_eq!(
+        handle.load().unwrap().postscript_name().unwrap(),
+        "Inconsolata-Regular"
+    );
+
+    let fonts = vec![Arc::new(b"not a font".to_vec())];
+    assert!(MemSource::from_bytes(fonts).is_err());
+}
+
+#[test]
+fn mock_font_and_mock_source() {
+    let metrics = Metrics {
+        units_per_em: 2048,
+        ..MockFont::builder().build().metrics()
+    };
+    let regular = MockFont::builder()
+        .with_family_name("Test")
+        .with_postscript_name("Test-Regular")
+        .with_metrics(metrics)
+        .with_glyph('A', 1)
+        .with_glyph('B', 2)
+        .with_advance(2, 1200.0)
+        .build();
+    assert_eq!(regular.family_name(), "Test");
+    assert_eq!(regular.full_name(), "Test");
+    assert_eq!(regular.metrics().units_per_em, 2048);
+    assert_eq!(regular.glyph_count(), 3);
+    assert_eq!(regular.glyph_for_char('B'), Some(2));
+    assert_eq!(regular.glyph_for_char('C'), None);
+    assert_eq!(regular.glyph_to_char(1), ['A']);
+    assert_eq!(regular.advance(1).unwrap(), Vector2D::new(1024.0, 0.0));
+    assert_eq!(regular.advance(2).unwrap(), Vector2D::new(1200.0, 0.0));
+    assert_eq!(regular.advance(3), Err(GlyphLoadingError::NoSuchGlyph));
+    assert!(MockFont::from_bytes(Arc::new(vec![]), FaceIndex::FIRST).is_err());
+
+    let bold = MockFont::builder()
+        .with_family_name("Test")
+        .with_postscript_name("Test-Bold")
+        .with_properties(*Properties::new().weight(Weight::BOLD))
+        .with_glyph('A', 1)
+        .build();
+    let other = MockFont::builder()
+        .with_family_name("Other")
+        .with_glyph('Z', 1)
+        .build();
+    let source = MockSource::new(vec![regular, bold, other]);
+    assert_eq!(source.all_families().unwrap(), ["Other", "Test"]);
+    assert_eq!(source.all_fonts().unwrap().len(), 3);
+    assert_eq!(source.select_family_by_name("test").unwrap().len(), 2);
+    assert_eq!(
+        source.select_family_by_name("Missing").unwrap_err(),
+        SelectionError::NotFound
+    );
+
+    let handle = source
+        .select_best_match(
+            &[FamilyName::Title("Test".to_owned())],
+            Properties::new().weight(Weight::BOLD),
+        )
+        .unwrap();
+    let font = source.font(&handle).unwrap();
+    assert_eq!(font.postscript_name().unwrap(), "Test-Bold");
+    assert!(Font::from_handle(&handle).is_err());
+
+    let handle = source.select_by_postscript_name("Test-Regular").unwrap();
+    assert_eq!(source.font(&handle).unwrap().glyph_for_char('B'), Some(2));
+    let handle = source
+        .select_fallback_for_char('Z', "en-US", &Properties::new())
+        .unwrap();
+    assert_eq!(source.font(&handle).unwrap().family_name(), "Other");
+    let characters = ['A', 'B', 'Z'].iter().cloned().collect();
+    let families = source
+        .select_families_by_unicode_coverage(&characters)
+        .unwrap();
+    assert_eq!(families[0].name(), Some("Test"));
+    assert_eq!(families[1].name(), Some("Other"));
+}
+
+#[test]
+fn validate_checksums_of_font_data() {
+    let mut font_data = fs::read(TEST_FONT_FILE_PATH).unwrap();
+    validate::validate_checksums(&font_data).unwrap();
+    validate::validate_checksums(&fs::read(FILE_PATH_INCONSOLATA_TTF).unwrap()).unwrap();
+    validate::validate_checksums(&fs::read(TEST_FONT_COLLECTION_FILE_PATH).unwrap()).unwrap();
+
+    // Find the `name` table in the table directory and change a byte of it, which breaks both its
+    // checksum and that of the whole font.
+    let table_count = BigEndian::read_u16(&font_data[4..6]) as usize;
+    let name_table_offset = (0..table_count)
+        .map(|table_index| &font_data[(12 + table_index * 16)..(28 + table_index * 16)])
+        .find(|record| BigEndian::read_u32(&record[0..4]) == sfnt::NAME_TABLE_TAG)
+        .map(|record| BigEndian::read_u32(&record[8..12]) as usize)
+        .unwrap();
+    font_data[name_table_offset + 1] ^= 0xff;
+    match validate::validate_checksums(&font_data) {
+        Err(FontLoadingError::ChecksumMismatch(table_tags)) => {
+            assert_eq!(table_tags, [sfnt::NAME_TABLE_TAG, sfnt::HEAD_TABLE_TAG])
+        }
+        result => panic!("expected a checksum mismatch, got {:?}", result),
+    }
+
+    // A font whose tables are intact but whose `checkSumAdjustment` is wrong.
+    let mut font_data = fs::read(FILE_PATH_INCONSOLATA_TTF).unwrap();
+    let head_table = Font::from_bytes(Arc::new(font_data.clone()), FaceIndex::FIRST)
+        .unwrap()
+        .load_font_table(sfnt::HEAD_TABLE_TAG)
+        .unwrap();
+    let head_table_offset = font_data
+        .windows(head_table.len())
+        .position(|window| window == &*head_table)
+        .unwrap();
+    font_data[head_table_offset + 8] ^= 0x01;
+    let error = validate::validate_checksums(&font_data).unwrap_err();
+    assert_eq!(error.to_string(), "checksum mismatch in tables: head");
+
+    assert!(validate::validate_checksums(b"not a font").is_err());
+
+    // A table record whose offset and length add up to more than fits in a `usize` on 32-bit
+    // targets is out of bounds rather than an overflow.
+    let mut font_data = fs::read(FILE_PATH_INCONSOLATA_TTF).unwrap();
+    font_data[20..28].copy_from_slice(&[0xff; 8]);
+    match validate::validate_checksums(&font_data) {
+        Err(FontLoadingError::Parse) => {}
+        result => panic!("expected a parse error, got {:?}", result),
+    }
+}
+
+fn check_select_by_postscript_name_prefix<S>(source: &S)
+where
+    S: Source,
+{
+    let mut postscript_names: Vec<_> = source
+        .select_by_postscript_name_prefix("EBGaramond12-")
+        .unwrap()
+        .iter()
+        .map(|handle| handle.load().unwrap().postscript_name().unwrap())
+        .collect();
+    postscript_names.sort();
+    assert_eq!(
+        postscript_names,
+        ["EBGaramond12-Italic", "EBGaramond12-Regular"]
+    );
+
+    match source.select_by_postscript_name_prefix("Garamond") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+}
+
+fn check_select_by_properties<S>(source: &S)
+where
+    S: Source,
+{
+    let postscript_names = |filter: &PropertiesFilter| -> Vec<String> {
+        let mut postscript_names: Vec<_> = source
+            .select_by_properties(filter)
+            .unwrap()
+            .iter()
+            .map(|handle| handle.load().unwrap().postscript_name().unwrap())
+            .collect();
+        postscript_names.sort();
+        postscript_names
+    };
+
+    assert_eq!(
+        postscript_names(PropertiesFilter::new().monospace(true)),
+        ["Inconsolata-Regular"]
+    );
+    assert_eq!(
+        postscript_names(PropertiesFilter::new().monospace(false)),
+        ["EBGaramond12-Italic", "EBGaramond12-Regular"]
+    );
+    assert_eq!(
+        postscript_names(PropertiesFilter::new().styles(&[Style::Italic])),
+        ["EBGaramond12-Italic"]
+    );
+    assert_eq!(
+        postscript_names(
+            PropertiesFilter::new()
+                .styles(&[Style::Normal])
+                .monospace(false)
+        ),
+        ["EBGaramond12-Regular"]
+    );
+
+    match source.select_by_properties(PropertiesFilter::new().weight(Weight::BOLD..=Weight::BLACK))
+    {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+}
+
+fn query_caching_source(source: &CachingSource<CountingSource>) {
+    assert_eq!(source.all_families().unwrap().len(), 2);
+    assert_eq!(
+        source
+            .select_family_by_name("EB Garamond")
+            .unwrap()
+            .fonts()
+            .len(),
+        2
+    );
+    assert_eq!(
+        source
+            .select_family_by_name("Nonexistent Family")
+            .unwrap_err(),
+        SelectionError::NotFound
+    );
+    source
+        .select_by_postscript_name(TEST_FONT_POSTSCRIPT_NAME)
+        .unwrap();
+    let family_names = [FamilyName::Title("EB Garamond".to_owned())];
+    source
+        .select_best_match(&family_names, &Properties::new())
+        .unwrap();
+    source
+        .select_best_match(&family_names, Properties::new().style(Style::Italic))
+        .unwrap();
+}
+
+#[test]
+fn caching_source_queries_once() {
+    let source = CachingSource::new(CountingSource::new());
+    query_caching_source(&source);
+    query_caching_source(&source);
+    // Two different family lookups and two different best matches were asked for.
+    assert_eq!(source.source().counts(), [1, 2, 1, 2]);
+
+    let source = Arc::new(source);
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let source = source.clone();
+            thread::spawn(move || query_caching_source(&source))
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(source.source().counts(), [1, 2, 1, 2]);
+}
+
+#[test]
+fn caching_source_invalidate() {
+    let source = CachingSource::new(CountingSource::new());
+    query_caching_source(&source);
+    assert_eq!(source.generation(), 0);
+
+    // Only the query that found nothing and the list of families are asked for again.
+    source.invalidate_not_found();
+    assert_eq!(source.generation(), 1);
+    query_caching_source(&source);
+    assert_eq!(source.source().counts(), [2, 3, 1, 2]);
+
+    source.invalidate();
+    assert_eq!(source.generation(), 2);
+    query_caching_source(&source);
+    assert_eq!(source.source().counts(), [3, 5, 2, 4]);
+}
+
+#[test]
+fn all_fonts() {
+    assert_eq!(
+        MinimalSource(test_mem_source()).all_fonts().unwrap().len(),
+        3
+    );
+
+    let directory = env::temp_dir().join(format!("font-kit-all-fonts-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for path in &[
+        TEST_FONT_FILE_PATH,
+        FILE_PATH_EB_GARAMOND_TTF,
+        FILE_PATH_INCONSOLATA_TTF,
+    ] {
+        fs::copy(
+            path,
+            directory.join(FilePath::new(path).file_name().unwrap()),
+        )
+        .unwrap();
+    }
+    let handles = FsSource::in_path(&directory).all_fonts();
+    fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(handles.unwrap().len(), 3);
+}
+
+#[test]
+fn android_source_fonts_xml() {
+    // Stand in for a few of the fonts that the configuration of Android 9 declares.
+    let directory = env::temp_dir().join(format!("font-kit-android-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for &(path, file_name) in &[
+        (TEST_FONT_FILE_PATH, "Roboto-Regular.ttf"),
+        (
+            "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+            "Roboto-Italic.ttf",
+        ),
+        (FILE_PATH_EB_GARAMOND_TTF, "Roboto-Medium.ttf"),
+        (FILE_PATH_INCONSOLATA_TTF, "DroidSansMono.ttf"),
+        (FILE_PATH_INCONSOLATA_TTF, "NotoColorEmoji.ttf"),
+        (
+            FILE_PATH_INCONSOLATA_TTF,
+            "NotoSansSymbols-Regular-Subsetted2.ttf",
+        ),
+    ] {
+        fs::copy(path, directory.join(file_name)).unwrap();
+    }
+    let source =
+        AndroidSource::from_config_files(&["resources/tests/android/fonts-9.xml"], &directory);
+    let family_file_names =
+        |family_name: &str| file_names(source.select_family_by_name(family_name).unwrap().fonts());
+
+    // Fonts whose files are missing are left out, along with families that have none.
+    let roboto_file_names = [
+        "Roboto-Regular.ttf",
+        "Roboto-Italic.ttf",
+        "Roboto-Medium.ttf",
+    ];
+    assert_eq!(family_file_names("sans-serif"), roboto_file_names);
+    assert_eq!(family_file_names("Helvetica"), roboto_file_names);
+    assert_eq!(
+        family_file_names("sans-serif-medium"),
+        ["Roboto-Medium.ttf"]
+    );
+    assert_eq!(family_file_names("monaco"), ["DroidSansMono.ttf"]);
+    assert!(source.select_family_by_name("serif").is_err());
+    assert!(source.select_family_by_name("times").is_err());
+    let family_names = source.all_families().unwrap();
+    assert_eq!(family_names[..2], ["sans-serif", "monospace"]);
+    assert!(family_names.contains(&"sans-serif-medium".to_owned()));
+    assert!(family_names.contains(&"Inconsolata".to_owned()));
+    assert_eq!(
+        source.generic_family_names(&FamilyName::SansSerif)[0],
+        "sans-serif"
+    );
+
+    // Matching uses the declared weights and styles rather than those of the files, so the
+    // closest font to bold is the medium one.
+    let best_match_file_name = |properties: &Properties| {
+        let handle = source
+            .select_best_match(&[FamilyName::SansSerif], properties)
+            .unwrap();
+        file_names(&[handle])[0].clone()
+    };
+    assert_eq!(
+        best_match_file_name(Properties::new().weight(Weight::BOLD)),
+        "Roboto-Medium.ttf"
+    );
+    assert_eq!(
+        best_match_file_name(Properties::new().style(Style::Italic)),
+        "Roboto-Italic.ttf"
+    );
+
+    // The fallback chain starts with the default family, and prefers families for the locale.
+    let fallback_file_name = |character: char, locale: &str| {
+        let handle = source
+            .select_fallback_for_char(character, locale, &Properties::new())
+            .unwrap();
+        file_names(&[handle])[0].clone()
+    };
+    assert!(fallback_file_name('A', "en-US").starts_with("Roboto-"));
+    assert_eq!(fallback_file_name('₽', "en-US"), "NotoColorEmoji.ttf");
+    assert_eq!(
+        fallback_file_name('₽', "und-Zsym"),
+        "NotoSansSymbols-Regular-Subsetted2.ttf"
+    );
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn android_source_legacy_fonts_xml() {
+    // Stand in for a few of the fonts that the configuration of Android 4.4 declares.
+    let directory = env::temp_dir().join(format!("font-kit-android-legacy-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for &(path, file_name) in &[
+        (TEST_FONT_FILE_PATH, "Roboto-Regular.ttf"),
+        (
+            "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+            "Roboto-Italic.ttf",
+        ),
+        (FILE_PATH_INCONSOLATA_TTF, "DroidSansMono.ttf"),
+        (FILE_PATH_INCONSOLATA_TTF, "AndroidEmoji.ttf"),
+        (FILE_PATH_INCONSOLATA_TTF, "MTLmr3m.ttf"),
+    ] {
+        fs::copy(path, directory.join(file_name)).unwrap();
+    }
+    let source = AndroidSource::from_config_files(
+        &[
+            "resources/tests/android/system_fonts-4.4.xml",
+            "resources/tests/android/fallback_fonts-4.4.xml",
+        ],
+        &directory,
+    );
+
+    // All but the first name of a family are aliases.
+    assert_eq!(
+        file_names(source.select_family_by_name("arial").unwrap().fonts()),
+        ["Roboto-Regular.ttf", "Roboto-Italic.ttf"]
+    );
+    assert_eq!(
+        file_names(source.select_family_by_name("monaco").unwrap().fonts()),
+        ["DroidSansMono.ttf"]
+    );
+
+    // Without declared properties, those of the files are used.
+    let italic_handle = source
+        .select_best_match(
+            &[FamilyName::Title("helvetica".to_owned())],
+            Properties::new().style(Style::Italic),
+        )
+        .unwrap();
+    assert_eq!(file_names(&[italic_handle]), ["Roboto-Italic.ttf"]);
+
+    let fallback_file_name = |character: char, locale: &str| {
+        let handle = source
+            .select_fallback_for_char(character, locale, &Properties::new())
+            .unwrap();
+        file_names(&[handle])[0].clone()
+    };
+    assert_eq!(fallback_file_name('₽', "en-US"), "AndroidEmoji.ttf");
+    assert_eq!(fallback_file_name('₽', "ja-JP"), "MTLmr3m.ttf");
+
+    // Without a configuration, the source has the same families as an `FsSource`.
+    let unconfigured_source =
+        AndroidSource::from_config_files(&["resources/tests/android/nonexistent.xml"], &directory);
+    assert_eq!(
+        unconfigured_source.all_families().unwrap(),
+        FsSource::in_path(&directory).all_families().unwrap()
+    );
+    assert!(unconfigured_source
+        .select_family_by_name("sans-serif")
+        .is_err());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn fs_source_generation() {
+    let directory = env::temp_dir().join(format!("font-kit-generation-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::copy(
+        TEST_FONT_FILE_PATH,
+        directory.join("EBGaramond12-Regular.otf"),
+    )
+    .unwrap();
+    let source = FsSource::in_path(&directory);
+    let caching_source = CachingSource::new(FsSource::in_path(&directory));
+    let generation = source.generation();
+    let caching_generation = caching_source.generation();
+    assert_eq!(source.generation(), generation);
+    assert_eq!(caching_source.all_families().unwrap().len(), 1);
+
+    fs::copy(
+        FILE_PATH_INCONSOLATA_TTF,
+        directory.join("Inconsolata-Regular.ttf"),
+    )
+    .unwrap();
+    let new_generation = source.generation();
+    let new_caching_generation = caching_source.generation();
+    let font_count = source.all_fonts().map(|handles| handles.len());
+    let family_count = caching_source.all_families().map(|families| families.len());
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert!(new_generation > generation);
+    assert!(new_caching_generation > caching_generation);
+    assert_eq!(font_count.unwrap(), 2);
+    assert_eq!(family_count.unwrap(), 2);
+}
+
+#[test]
+fn fs_source_options() {
+    // root/
+    //     EBGaramond12-Regular.otf
+    //     notes.txt
+    //     loop -> root
+    //     linked -> elsewhere/
+    //     nested/
+    //         EBGaramond12-Italic.otf
+    //         deeper/
+    //             Inconsolata-Regular.ttf
+    // elsewhere/
+    //     EBGaramond12-Regular.ttf
+    let directory = env::temp_dir().join(format!("font-kit-fs-options-{}", process::id()));
+    let (root, elsewhere) = (directory.join("root"), directory.join("elsewhere"));
+    let deeper = root.join("nested").join("deeper");
+    fs::create_dir_all(&deeper).unwrap();
+    fs::create_dir_all(&elsewhere).unwrap();
+    for &(source_path, ref destination_path) in &[
+        (TEST_FONT_FILE_PATH, root.join("EBGaramond12-Regular.otf")),
+        (
+            "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+            root.join("nested").join("EBGaramond12-Italic.otf"),
+        ),
+        (
+            FILE_PATH_INCONSOLATA_TTF,
+            deeper.join("Inconsolata-Regular.ttf"),
+        ),
+        (
+            FILE_PATH_EB_GARAMOND_TTF,
+            elsewhere.join("EBGaramond12-Regular.ttf"),
+        ),
+    ] {
+        fs::copy(source_path, destination_path).unwrap();
+    }
+    fs::write(root.join("notes.txt"), "not a font").unwrap();
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&root, root.join("loop")).unwrap();
+        std::os::unix::fs::symlink(&elsewhere, root.join("linked")).unwrap();
+    }
+
+    let font_count = |options: FsSourceOptions| {
+        FsSource::with_options(vec![root.clone()], options)
+            .all_fonts()
+            .unwrap()
+            .len()
+    };
+    let default_count = FsSource::from_paths(vec![root.clone()])
+        .all_fonts()
+        .unwrap()
+        .len();
+    let shallow_count = font_count(FsSourceOptions {
+        recursive: false,
+        ..FsSourceOptions::default()
+    });
+    let depth_1_count = font_count(FsSourceOptions {
+        max_depth: Some(1),
+        ..FsSourceOptions::default()
+    });
+    let following_count = font_count(FsSourceOptions {
+        follow_symlinks: true,
+        ..FsSourceOptions::default()
+    });
+    let file_count = FsSource::from_paths(vec![root.join("EBGaramond12-Regular.otf")])
+        .all_fonts()
+        .unwrap()
+        .len();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(default_count, 3);
+    assert_eq!(shallow_count, 1);
+    assert_eq!(depth_1_count, 2);
+    if cfg!(unix) {
+        // The loop is skipped, and the linked directory is searched once.
+        assert_eq!(following_count, 4);
+    }
+    assert_eq!(file_count, 1);
+}
+
+#[test]
+fn fs_source_cache() {
+    let directory = env::temp_dir().join(format!("font-kit-fs-cache-{}", process::id()));
+    let fonts_directory = directory.join("fonts");
+    let cache_path = directory.join("cache").join("fonts.cache");
+    fs::create_dir_all(&fonts_directory).unwrap();
+    let regular_path = fonts_directory.join("Regular.otf");
+    for &(source_path, ref destination_path) in &[
+        (TEST_FONT_FILE_PATH, regular_path.clone()),
+        (
+            "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+            fonts_directory.join("Italic.otf"),
+        ),
+        (
+            FILE_PATH_INCONSOLATA_TTF,
+            fonts_directory.join("Inconsolata.ttf"),
+        ),
+    ] {
+        fs::copy(source_path, destination_path).unwrap();
+    }
+    fs::write(fonts_directory.join("notes.txt"), "not a font").unwrap();
+
+    let open = || {
+        FsSource::with_options_and_cache(
+            vec![fonts_directory.clone()],
+            FsSourceOptions::default(),
+            &cache_path,
+        )
+    };
+    let postscript_names = |source: &FsSource| {
+        let mut postscript_names: Vec<_> = source
+            .all_fonts()
+            .unwrap()
+            .iter()
+            .map(|handle| handle.load().unwrap().postscript_name().unwrap())
+            .collect();
+        postscript_names.sort();
+        postscript_names
+    };
+
+    // Every file is read the first time, including the one that isn't a font.
+    let source = open();
+    assert_eq!(source.analyzed_file_count(), 4);
+    let expected_names = postscript_names(&source);
+    #[cfg(unix)]
+    let cache_inode = {
+        use std::os::unix::fs::MetadataExt;
+        || fs::metadata(&cache_path).unwrap().ino()
+    };
+    #[cfg(unix)]
+    let written_cache_inode = cache_inode();
+    let source = open();
+    assert_eq!(source.analyzed_file_count(), 0);
+    assert_eq!(postscript_names(&source), expected_names);
+    // A cache that is up to date isn't written again, and no temporary files are left behind.
+    #[cfg(unix)]
+    assert_eq!(cache_inode(), written_cache_inode);
+    assert_eq!(
+        fs::read_dir(cache_path.parent().unwrap()).unwrap().count(),
+        1
+    );
+    assert_eq!(
+        source.all_families().unwrap(),
+        ["EB Garamond", "Inconsolata"]
+    );
+
+    // Only the changed file is read again, both when opening a new source and when an existing
+    // one notices the change.
+    fs::copy(
+        "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+        &regular_path,
+    )
+    .unwrap();
+    let generation = source.generation();
+    assert!(generation > 0);
+    assert_eq!(source.analyzed_file_count(), 1);
+    let source = open();
+    assert_eq!(source.analyzed_file_count(), 0);
+    fs::copy(FILE_PATH_INCONSOLATA_TTF, &regular_path).unwrap();
+    let source = open();
+    assert_eq!(source.analyzed_file_count(), 1);
+    assert_eq!(
+        postscript_names(&source),
+        [
+            "EBGaramond12-Italic",
+            "Inconsolata-Regular",
+            "Inconsolata-Regular"
+        ]
+    );
+
+    // Corrupt caches are rebuilt.
+    let mut cache_data = fs::read(&cache_path).unwrap();
+    for &length in &[0, 12, cache_data.len() - 1] {
+        fs::write(&cache_path, &cache_data[..length]).unwrap();
+        assert_eq!(open().analyzed_file_count(), 4);
+        assert_eq!(open().analyzed_file_count(), 0);
+    }
+    cache_data[8] ^= 0xff;
+    fs::write(&cache_path, &cache_data).unwrap();
+    let source = open();
+    assert_eq!(source.analyzed_file_count(), 4);
+    assert_eq!(postscript_names(&source).len(), 3);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn fs_source_parallel_indexing() {
+    // A few hundred fonts spread over several directories.
+    let directory = env::temp_dir().join(format!("font-kit-fs-parallel-{}", process::id()));
+    let source_paths = [
+        FILE_PATH_INCONSOLATA_TTF,
+        TEST_FONT_FILE_PATH,
+        "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+    ];
+    for index in 0..300 {
+        let subdirectory = directory.join(format!("{}", index % 7));
+        fs::create_dir_all(&subdirectory).unwrap();
+        let source_path = source_paths[index % source_paths.len()];
+        let file_name = FilePath::new(source_path).file_name().unwrap();
+        fs::copy(
+            source_path,
+            subdirectory.join(format!("{}-{}", index, file_name.to_str().unwrap())),
+        )
+        .unwrap();
+    }
+    fs::write(directory.join("notes.txt"), "not a font").unwrap();
+
+    let index = |parallel| {
+        let source = FsSource::with_options(
+            vec![directory.clone()],
+            FsSourceOptions {
+                parallel,
+                ..FsSourceOptions::default()
+            },
+        );
+        let families = source.all_families().unwrap();
+        let fonts: Vec<_> = source
+            .all_fonts()
+            .unwrap()
+            .into_iter()
+            .map(|handle| match handle {
+                Handle::Path { path, font_index } => (path, font_index),
+                Handle::Memory { .. } => panic!("expected a path handle"),
+            })
+            .collect();
+        (families, fonts)
+    };
+    let (serial_families, serial_fonts) = index(false);
+    let (parallel_families, parallel_fonts) = index(true);
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(serial_fonts.len(), 300);
+    assert_eq!(serial_families, parallel_families);
+    assert_eq!(serial_fonts, parallel_fonts);
+}
+
+#[test]
+fn all_system_fonts_load() {
+    let source = SystemSource::new();
+    let handles = source.all_fonts().unwrap();
+    for handle in &handles {
+        if let Err(error) = handle.load() {
+            panic!("Failed to load {:?}: {:?}", handle, error);
+        }
+    }
+
+    let mut family_font_count = 0;
+    for family_name in source.all_families().unwrap() {
+        if let Ok(family_handle) = source.select_family_by_name(&family_name) {
+            family_font_count += family_handle.fonts().len();
+        }
+    }
+    assert!(handles.len() >= family_font_count);
+}
+
+#[test]
+fn mem_source_add_and_remove_fonts() {
+    let paths = [
+        "resources/tests/eb-garamond/EBGaramond12-Italic.otf",
+        FILE_PATH_INCONSOLATA_TTF,
+    ];
+    let handles = paths
+        .iter()
+        .map(|&path| Handle::from_path(path.into(), FaceIndex::FIRST));
+    let mut source = MemSource::from_fonts(handles).unwrap();
+    let generation = source.generation();
+
+    let family_names = [FamilyName::Title("EB Garamond".to_owned())];
+    let mut bold = Properties::new();
+    bold.weight(Weight::BOLD);
+    let font = source
+        .select_best_match(&family_names, &bold)
+        .unwrap()
+        .load()
+        .unwrap();
+    assert_eq!(font.properties().style, Style::Italic);
+
+    // Make a bold upright face by changing the weight class in the `OS/2` table.
+    let regular_font = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST).unwrap();
+    let mut os2_table = regular_font
+        .load_font_table(sfnt::OS2_TABLE_TAG)
+        .unwrap()
+        .to_vec();
+    BigEndian::write_u16(&mut os2_table[4..6], 700);
+    let font_data = font_data_with_tables(TEST_FONT_FILE_PATH, vec![(*b"OS/2", os2_table)]);
+    source
+        .add_font(Handle::from_memory(font_data, FaceIndex::FIRST))
+        .unwrap();
+    assert!(source.generation() > generation);
+    let font = source
+        .select_best_match(&family_names, &bold)
+        .unwrap()
+        .load()
+        .unwrap();
+    assert_eq!(font.properties().weight, Weight::BOLD);
+    assert_eq!(font.properties().style, Style::Normal);
+
+    // A font that fails to load leaves the source as it was.
+    let generation = source.generation();
+    let invalid_handle = Handle::from_memory(Arc::new(vec![0; 64]), FaceIndex::FIRST);
+    assert!(source.add_fonts(vec![invalid_handle].into_iter()).is_err());
+    assert_eq!(source.all_fonts().unwrap().len(), 3);
+    assert_eq!(source.generation(), generation);
+
+    assert!(source.remove_font_by_postscript_name("EBGaramond12-Italic"));
+    assert_eq!(
+        source.all_families().unwrap(),
+        ["EB Garamond", "Inconsolata"]
+    );
+    assert!(source.remove_font_by_postscript_name(TEST_FONT_POSTSCRIPT_NAME));
+    assert!(!source.remove_font_by_postscript_name(TEST_FONT_POSTSCRIPT_NAME));
+    assert_eq!(source.all_families().unwrap(), ["Inconsolata"]);
+    match source.select_family_by_name("EB Garamond") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no family but got {:?}", other.map(|_| ())),
+    }
+    assert!(source.generation() > generation);
+}
+
+#[test]
+fn select_families_by_unicode_coverage() {
+    // Inconsolata has the card suit and the check mark, and EB Garamond has the Greek letter.
+    let source = MinimalSource(test_mem_source());
+    let characters: BTreeSet<char> = ['A', '♠', '✓', 'α'].iter().cloned().collect();
+    let families = source
+        .select_families_by_unicode_coverage(&characters)
+        .unwrap();
+    let family_names: Vec<_> = families
+        .iter()
+        .map(|family| family.fonts()[0].load().unwrap().family_name())
+        .collect();
+    assert_eq!(family_names, ["Inconsolata", "EB Garamond"]);
+    assert_eq!(families[1].fonts().len(), 2);
+
+    let characters: BTreeSet<char> = ['\u{e000}'].iter().cloned().collect();
+    match source.select_families_by_unicode_coverage(&characters) {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("expected NotFound, got {:?}", other.map(|_| ())),
+    }
+
+    // The family that covers the most of these characters should cover at least the Latin ones.
+    let characters: BTreeSet<char> = "Aa中".chars().collect();
+    let families = SystemSource::new()
+        .select_families_by_unicode_coverage(&characters)
+        .unwrap();
+    assert!(families[0].fonts().iter().any(|handle| {
+        let font = handle.load().unwrap();
+        font.glyph_for_char('A').is_some() && font.glyph_for_char('a').is_some()
+    }));
+}
+
+#[test]
+fn select_fallback_for_char() {
+    // Of the test fonts, only Inconsolata has the place-of-interest sign.
+    let source = MinimalSource(test_mem_source());
+    let handle = source
+        .select_fallback_for_char('⌘', "en-US", &Properties::new())
+        .unwrap();
+    assert_eq!(
+        handle.load().unwrap().postscript_name().unwrap(),
+        "Inconsolata-Regular"
+    );
+
+    // Check an emoji, a CJK ideograph, and an Arabic letter against the installed fonts.
+    let source = SystemSource::new();
+    let system_fonts: Vec<Font> = source
+        .all_fonts()
+        .unwrap()
+        .iter()
+        .filter_map(|handle| handle.load().ok())
+        .collect();
+    for &(character, locale) in &[('😀', "en-US"), ('中', "zh-Hans-CN"), ('ب', "ar")] {
+        let result = source.select_fallback_for_char(character, locale, &Properties::new());
+        if system_fonts
+            .iter()
+            .any(|font| font.glyph_for_char(character).is_some())
+        {
+            let font = result.unwrap().load().unwrap();
+            assert!(font.glyph_for_char(character).is_some());
+        } else {
+            match result {
+                Err(SelectionError::NotFound) => {}
+                other => panic!("Expected no match for {:?} but got {:?}", character, other),
+            }
+        }
+    }
+}
+
+#[test]
+fn peek_handle_metadata() {
+    let mut font_data = vec![];
+    File::open(FILE_PATH_INCONSOLATA_TTF)
+        .unwrap()
+        .read_to_end(&mut font_data)
+        .unwrap();
+    let handles = [
+        Handle::from_path(TEST_FONT_FILE_PATH.into(), FaceIndex::FIRST),
+        Handle::from_path(
+            "resources/tests/eb-garamond/EBGaramond12-Italic.otf".into(),
+            FaceIndex::FIRST,
+        ),
+        Handle::from_path(FILE_PATH_EB_GARAMOND_TTF.into(), FaceIndex::FIRST),
+        Handle::from_path(TEST_FONT_COLLECTION_FILE_PATH.into(), FaceIndex(1)),
+        Handle::from_memory(Arc::new(font_data), FaceIndex::FIRST),
+    ];
+    for handle in &handles {
+        let font = handle.load().unwrap();
+        assert_eq!(handle.family_name().unwrap(), font.family_name());
+        assert_eq!(handle.properties().unwrap(), font.properties());
+    }
+
+    let handle = Handle::from_path(TEST_FONT_FILE_PATH.into(), FaceIndex(1));
+    match handle.properties() {
+        Err(FontLoadingError::NoSuchFontInCollection) => {}
+        other => panic!("Expected no such font but got {:?}", other),
+    }
+    // A table that claims to run almost 4 GiB past the end of the data isn't read.
+    let mut font_data = fs::read(TEST_FONT_FILE_PATH).unwrap();
+    let table_count = BigEndian::read_u16(&font_data[4..6]) as usize;
+    let record_offset = (0..table_count)
+        .map(|table_index| 12 + table_index * 16)
+        .find(|&record_offset| {
+            BigEndian::read_u32(&font_data[record_offset..]) == sfnt::OS2_TABLE_TAG
+        })
+        .unwrap();
+    BigEndian::write_u32(&mut font_data[(record_offset + 12)..], 0xffff_fff0);
+    let handle = Handle::from_memory(Arc::new(font_data), FaceIndex::FIRST);
+    match handle.properties() {
+        Err(FontLoadingError::Parse) => {}
+        other => panic!("Expected a parse error but got {:?}", other),
+    }
+}
+
+#[test]
+fn select_family_by_name_ignoring_case() {
+    let source = test_mem_source();
+    let family_name = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST)
+        .unwrap()
+        .family_name();
+    let expected = source.select_family_by_name(&family_name).unwrap();
+    let actual = source
+        .select_family_by_name(&family_name.to_uppercase())
+        .unwrap();
+    check_same_postscript_names(expected.fonts(), actual.fonts());
+
+    // `MinimalSource` relies on the case-insensitive fallback in the default `Source`
+    // implementation.
+    let source = MinimalSource(test_mem_source());
+    let properties = Properties::new();
+    let expected = source
+        .select_best_match(&[FamilyName::Title(family_name.clone())], &properties)
+        .unwrap();
+    let actual = source
+        .select_best_match(
+            &[FamilyName::Title(family_name.to_lowercase())],
+            &properties,
+        )
+        .unwrap();
+    check_same_postscript_names(&[expected], &[actual]);
+}
+
+#[test]
+fn family_names_round_trip() {
+    let source = test_mem_source();
+    for family_name in source.all_families().unwrap() {
+        let family_handle = source.select_family_by_name(&family_name).unwrap();
+        assert_eq!(family_handle.name(), Some(&*family_name));
+    }
+
+    // Lookups that ignore case report the family's own spelling.
+    let family_handle = source.select_family_by_name("eb garamond").unwrap();
+    assert_eq!(family_handle.name(), Some("EB Garamond"));
+    assert_eq!(family_handle.len(), 2);
+    let family = Family::<Font>::from_handle(&family_handle).unwrap();
+    assert_eq!(family.name(), Some("EB Garamond"));
+    assert_eq!(family.len(), 2);
+
+    let family_handle = FamilyHandle::from_font_handles(family_handle.fonts().iter().cloned());
+    assert_eq!(family_handle.name(), None);
+    assert_eq!(
+        Family::<Font>::from_handle(&family_handle).unwrap().name(),
+        None
+    );
+}
+
+#[test]
+fn system_family_name_round_trips() {
+    let family_handle = SystemSource::new()
+        .select_family_by_name(KNOWN_SYSTEM_FONT_NAME)
+        .unwrap();
+    assert_eq!(family_handle.name(), Some(KNOWN_SYSTEM_FONT_NAME));
+    let family = Family::<Font>::from_handle(&family_handle).unwrap();
+    assert_eq!(family.name(), Some(KNOWN_SYSTEM_FONT_NAME));
+    assert_eq!(family.len(), family_handle.len());
+
+    let family_handle = SystemSource::new()
+        .select_family_by_name(&KNOWN_SYSTEM_FONT_NAME.to_lowercase())
+        .unwrap();
+    assert_eq!(family_handle.name(), Some(KNOWN_SYSTEM_FONT_NAME));
+}
+
+#[test]
+fn fuzzy_family_names() {
+    for &(family_name, loose_family_name) in &[
+        ("Segoe UI", "segoe ui"),
+        ("Helvetica Neue", "Helvetica-Neue"),
+        ("Helvetica Neue", "helvetica_neue regular"),
+        ("Fira Sans", "FiraSans-Regular"),
+        ("Straße", "STRASSE"),
+    ] {
         assert_eq!(
-            font.postscript_name().unwrap(),
-            TEST_FONT_COLLECTION_POSTSCRIPT_NAME[0]
+            source::fuzzy_family_name(family_name),
+            source::fuzzy_family_name(loose_family_name)
         );
     }
-    let font = Font::from_file(&mut file, 1).unwrap();
+    assert_ne!(
+        source::fuzzy_family_name("Segoe UI"),
+        source::fuzzy_family_name("Segoe UI Light")
+    );
+    // A name that is nothing but a style name is kept.
+    assert_eq!(source::fuzzy_family_name("Book"), "book");
+}
+
+#[test]
+fn select_family_by_name_fuzzy() {
+    let family_name = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST)
+        .unwrap()
+        .family_name();
+    let mem_source = test_mem_source();
+    let minimal_source = MinimalSource(test_mem_source());
+    for source in &[&mem_source as &dyn Source, &minimal_source] {
+        let expected = source.select_family_by_name_fuzzy(&family_name).unwrap();
+        check_same_postscript_names(
+            expected.fonts(),
+            mem_source
+                .select_family_by_name(&family_name)
+                .unwrap()
+                .fonts(),
+        );
+        for loose_family_name in &["eb garamond", "EB-Garamond", " EBGaramond Regular "] {
+            let actual = source
+                .select_family_by_name_fuzzy(loose_family_name)
+                .unwrap();
+            check_same_postscript_names(expected.fonts(), actual.fonts());
+        }
+        for missing_family_name in &["EB Garamond 13", "Regular", ""] {
+            match source.select_family_by_name_fuzzy(missing_family_name) {
+                Err(SelectionError::NotFound) => {}
+                other => panic!("expected NotFound, got {:?}", other.map(|_| ())),
+            }
+        }
+    }
+
+    let source = SystemSource::new();
+    let expected = source
+        .select_family_by_name(KNOWN_SYSTEM_FONT_NAME)
+        .unwrap();
+    let loose_family_name = KNOWN_SYSTEM_FONT_NAME.to_lowercase().replace(' ', "-");
+    let actual = source
+        .select_family_by_name_fuzzy(&loose_family_name)
+        .unwrap();
+    check_same_postscript_names(expected.fonts(), actual.fonts());
+    match source.select_family_by_name_fuzzy("Genuinely Missing Family") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("expected NotFound, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn select_matches() {
+    let source = MinimalSource(test_mem_source());
+    let family_names = [
+        FamilyName::Title(
+            Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST)
+                .unwrap()
+                .family_name(),
+        ),
+        FamilyName::Title(
+            Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST)
+                .unwrap()
+                .family_name(),
+        ),
+    ];
+    let mut properties = Properties::new();
+    properties.style(Style::Italic);
+
+    // Families come in the order given, and fonts within each family in order of preference.
+    let handles = source.select_matches(&family_names, &properties).unwrap();
+    let postscript_names: Vec<_> = handles
+        .iter()
+        .map(|handle| handle.load().unwrap().postscript_name().unwrap())
+        .collect();
+    assert_eq!(
+        postscript_names,
+        [
+            "Inconsolata-Regular",
+            "EBGaramond12-Italic",
+            "EBGaramond12-Regular"
+        ]
+    );
+    let best_match = source
+        .select_best_match(&family_names[1..], &properties)
+        .unwrap();
+    check_same_postscript_names(&[best_match], &handles[1..2]);
+
+    let family_names = [FamilyName::SansSerif, FamilyName::Monospace];
+    let source = SystemSource::new();
+    let handles = source
+        .select_matches(&family_names, &Properties::new())
+        .unwrap();
+    let best_match = source
+        .select_best_match(&family_names, &Properties::new())
+        .unwrap();
+    check_same_postscript_names(&[best_match], &handles[0..1]);
+}
+
+#[test]
+fn select_matches_falls_back_to_later_families() {
+    let source = MinimalSource(test_mem_source());
+    let family_names = [
+        FamilyName::Title("EB Garamond".to_owned()),
+        FamilyName::Title("Inconsolata".to_owned()),
+    ];
+    let mut properties = Properties::new();
+    properties.weight(Weight::BOLD);
+
+    // Neither family has a bold face, so the closest fonts of the first family come first and the
+    // second family follows.
+    let postscript_names: Vec<_> = source
+        .select_matches(&family_names, &properties)
+        .unwrap()
+        .iter()
+        .map(|handle| handle.load().unwrap().postscript_name().unwrap())
+        .collect();
+    assert_eq!(
+        postscript_names,
+        [
+            "EBGaramond12-Regular",
+            "EBGaramond12-Italic",
+            "Inconsolata-Regular"
+        ]
+    );
+}
+
+// Fontconfig resolves generic families through its configuration, so this works whether or not the
+// families in the built-in lists are installed.
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+#[test]
+fn fontconfig_generic_family_names() {
+    let source = SystemSource::new();
+    let family_names = source.generic_family_names(&FamilyName::SansSerif);
+    assert!(!family_names
+        .iter()
+        .any(|family_name| family_name == "sans-serif"));
+
+    // The family that fontconfig picks comes first.
+    let font = source
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap()
+        .load()
+        .unwrap();
+    assert_eq!(font.family_name(), family_names[0]);
+}
+
+// The DejaVu fonts come with a fontconfig configuration that aliases the Bitstream Vera families,
+// which they are derived from, to them.
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+#[test]
+fn fontconfig_select_aliased_family() {
+    let source = SystemSource::new();
+    let family = source.select_family_by_name("Bitstream Vera Sans").unwrap();
+    let font = family.fonts()[0].load().unwrap();
+    assert!(font.family_name().starts_with("DejaVu"));
+
+    // Names that fontconfig knows nothing about don't fall back to a generic family.
+    match source.select_family_by_name("No Such Family") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+}
+
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+#[test]
+fn fontconfig_sort() {
+    use crate::sources::fontconfig::Spec;
+
+    let source = SystemSource::new();
+    let spec = Spec {
+        family_names: vec![FamilyName::SansSerif],
+        properties: *Properties::new().weight(Weight::BOLD),
+        characters: vec!['A'].into_iter().collect(),
+        trim: true,
+        ..Spec::default()
+    };
+    let handles = source.sort(&spec).unwrap();
+    let font = handles[0].load().unwrap();
     assert_eq!(
-        font.postscript_name().unwrap(),
-        TEST_FONT_COLLECTION_POSTSCRIPT_NAME[1]
+        font.family_name(),
+        source.generic_family_names(&FamilyName::SansSerif)[0]
     );
+    assert_eq!(font.properties().weight, Weight::BOLD);
+    assert!(font.glyph_for_char('A').is_some());
+
+    // Without trimming, every installed font is sorted.
+    let untrimmed_handles = source
+        .sort(&Spec {
+            trim: false,
+            ..spec
+        })
+        .unwrap();
+    assert!(untrimmed_handles.len() > handles.len());
+    assert_eq!(untrimmed_handles.len(), source.all_fonts().unwrap().len());
 }
 
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
 #[test]
-fn get_glyph_count() {
-    let font = Font::from_path(TEST_FONT_FILE_PATH, 0).unwrap();
-    assert_eq!(font.glyph_count(), 3084);
+fn fontconfig_with_config_file() {
+    use crate::sources::fontconfig::FontconfigSource;
+
+    let directory = env::temp_dir().join(format!("font-kit-fontconfig-{}", process::id()));
+    let font_directory = directory.join("fonts");
+    fs::create_dir_all(&font_directory).unwrap();
+    fs::copy(
+        FILE_PATH_INCONSOLATA_TTF,
+        font_directory.join("Inconsolata-Regular.ttf"),
+    )
+    .unwrap();
+    let config_path = directory.join("fonts.conf");
+    fs::write(
+        &config_path,
+        format!(
+            "<?xml version=\"1.0\"?>\n\
+             <!DOCTYPE fontconfig SYSTEM \"fonts.dtd\">\n\
+             <fontconfig>\n\
+             <dir>{}</dir>\n\
+             <cachedir>{}</cachedir>\n\
+             </fontconfig>\n",
+            font_directory.display(),
+            directory.join("cache").display()
+        ),
+    )
+    .unwrap();
+
+    let source = FontconfigSource::with_config_file(&config_path).unwrap();
+    assert_eq!(source.all_families().unwrap(), ["Inconsolata"]);
+    assert_eq!(source.all_fonts().unwrap().len(), 1);
+    match source.select_family_by_name(KNOWN_SYSTEM_FONT_NAME) {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+
+    // The system configuration is unaffected.
+    drop(source);
+    assert!(SystemSource::new()
+        .select_family_by_name(KNOWN_SYSTEM_FONT_NAME)
+        .is_ok());
+
+    match FontconfigSource::with_config_file(&directory.join("missing.conf")) {
+        Err(SelectionError::CannotAccessSource) => {}
+        other => panic!(
+            "Expected CannotAccessSource but got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    fs::remove_dir_all(&directory).unwrap();
 }
 
-// The initial off-curve point used to cause an assertion in the FreeType backend.
+// Fontconfig picks the font within the family, so DejaVu Sans, which keeps its condensed faces in
+// the same family, yields a condensed bold face for a condensed bold query.
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
 #[test]
-fn get_glyph_outline_eb_garamond_exclam() {
-    let mut path_builder = Path::builder();
-    let mut file = File::open(FILE_PATH_EB_GARAMOND_TTF).unwrap();
-    let font = Font::from_file(&mut file, 0).unwrap();
-    let glyph = font.glyph_for_char('!').expect("No glyph for char!");
-    font.outline(glyph, HintingOptions::None, &mut path_builder)
+fn fontconfig_select_best_match_by_style() {
+    let source = SystemSource::new();
+    let mut properties = Properties::new();
+    properties.weight(Weight::BOLD);
+    let font = source
+        .select_best_match(&[FamilyName::SansSerif], &properties)
+        .unwrap()
+        .load()
         .unwrap();
-    let path = path_builder.build();
+    assert!(font.properties().weight >= Weight::BOLD);
+    assert_eq!(font.properties().stretch, Stretch::NORMAL);
 
-    // The TrueType spec doesn't specify the rounding method for midpoints, as far as I can tell.
-    // So we are lenient and accept either values rounded down (what Core Text provides if the
-    // first point is off-curve, it seems) or precise floating-point values (what our FreeType
-    // loader provides).
-    let mut events = path.into_iter();
-    assert_eq!(
-        events.next(),
-        Some(PathEvent::MoveTo(Point2D::new(114.0, 598.0)))
+    properties
+        .stretch(Stretch::SEMI_CONDENSED)
+        .style(Style::Italic);
+    let handles = source
+        .select_matches(&[FamilyName::SansSerif], &properties)
+        .unwrap();
+    let font = handles[0].load().unwrap();
+    assert!(font.properties().weight >= Weight::BOLD);
+    assert!(font.properties().stretch < Stretch::NORMAL);
+    assert_ne!(font.properties().style, Style::Normal);
+    check_same_postscript_names(
+        &[source
+            .select_best_match(&[FamilyName::SansSerif], &properties)
+            .unwrap()],
+        &handles[0..1],
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(114.0, 619.0),
-        Point2D::new(127.5, 634.0)
+}
+
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "android"
+)))]
+#[test]
+fn fontconfig_app_fonts() {
+    let source = SystemSource::new();
+    let generation = source.generation();
+    match source.select_family_by_name("Inconsolata") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Inconsolata is installed on this system: {:?}", other),
+    }
+
+    source
+        .add_app_font_file(FilePath::new(FILE_PATH_INCONSOLATA_TTF))
+        .unwrap();
+    assert!(source.generation() > generation);
+    assert!(source
+        .all_families()
+        .unwrap()
+        .contains(&"Inconsolata".to_owned()));
+    let font = source
+        .select_best_match(
+            &[FamilyName::Title("Inconsolata".to_owned())],
+            &Properties::new(),
+        )
+        .unwrap()
+        .load()
+        .unwrap();
+    assert_eq!(font.family_name(), "Inconsolata");
+
+    let mut data = vec![];
+    File::open(TEST_FONT_FILE_PATH)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    source.add_app_font_bytes(Arc::new(data)).unwrap();
+    let family = source.select_family_by_name("EB Garamond 12").unwrap();
+    let temporary_path = match family.fonts()[0] {
+        Handle::Path { ref path, .. } => path.clone(),
+        ref handle => panic!("Expected a path handle but got {:?}", handle),
+    };
+    assert_eq!(
+        family.fonts()[0].load().unwrap().postscript_name(),
+        Some(TEST_FONT_POSTSCRIPT_NAME.to_owned())
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(141.0, 649.0),
-        Point2D::new(161.0, 649.0)
+
+    assert!(source
+        .add_app_font_file(FilePath::new("Cargo.toml"))
+        .is_err());
+
+    source.clear_app_fonts();
+    match source.select_family_by_name("Inconsolata") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+    assert!(!temporary_path.exists());
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn core_text_register_fonts() {
+    let source = SystemSource::new();
+    let family_name = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST)
+        .unwrap()
+        .family_name();
+    let data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    source.register_font_data(data.clone()).unwrap();
+    assert!(source.all_families().unwrap().contains(&family_name));
+    let family = source.select_family_by_name(&family_name).unwrap();
+    assert_eq!(
+        family.fonts()[0].load().unwrap().postscript_name(),
+        Some(TEST_FONT_POSTSCRIPT_NAME.to_owned())
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(181.0, 649.0),
-        Point2D::new(193.5, 634.0)
+
+    // Core Text refuses a second font with the same PostScript name.
+    match source.register_font_data(data.clone()) {
+        Err(FontLoadingError::Io(ref error)) if error.kind() == io::ErrorKind::AlreadyExists => {}
+        other => panic!("Expected AlreadyExists but got {:?}", other),
+    }
+
+    source.unregister_font_data(&data).unwrap();
+    match source.select_family_by_name(&family_name) {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+
+    let path = FilePath::new(FILE_PATH_INCONSOLATA_TTF);
+    source.register_font_url(path).unwrap();
+    let font = source
+        .select_best_match(
+            &[FamilyName::Title("Inconsolata".to_owned())],
+            &Properties::new(),
+        )
+        .unwrap()
+        .load()
+        .unwrap();
+    assert_eq!(font.family_name(), "Inconsolata");
+    assert!(source.register_font_url(path).is_err());
+    source.unregister_font_url(path).unwrap();
+}
+
+#[cfg(target_family = "windows")]
+#[test]
+fn directwrite_source_from_fonts() {
+    use crate::sources::directwrite::DirectWriteSource;
+
+    let family_name = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST)
+        .unwrap()
+        .family_name();
+    let data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let fonts = vec![
+        Handle::from_memory(data.clone(), FaceIndex::FIRST),
+        Handle::from_path(
+            FilePath::new(FILE_PATH_INCONSOLATA_TTF).to_owned(),
+            FaceIndex::FIRST,
+        ),
+    ];
+
+    let source = DirectWriteSource::from_fonts(fonts.clone().into_iter()).unwrap();
+    assert_eq!(source.all_fonts().unwrap().len(), 2);
+    let family = source.select_family_by_name(&family_name).unwrap();
+    match family.fonts()[0] {
+        Handle::Memory { ref bytes, .. } => assert!(Arc::ptr_eq(bytes, &data)),
+        ref handle => panic!("Expected a memory handle but got {:?}", handle),
+    }
+    assert_eq!(
+        family.fonts()[0].load().unwrap().postscript_name(),
+        Some(TEST_FONT_POSTSCRIPT_NAME.to_owned())
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(206.0, 619.0),
-        Point2D::new(206.0, 598.0)
+    let font = source
+        .select_best_match(
+            &[FamilyName::Title("Inconsolata".to_owned())],
+            &Properties::new(),
+        )
+        .unwrap()
+        .load()
+        .unwrap();
+    assert_eq!(font.family_name(), "Inconsolata");
+    match source.select_family_by_name(KNOWN_SYSTEM_FONT_NAME) {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+
+    let source = DirectWriteSource::system_and_custom(fonts.into_iter()).unwrap();
+    source.select_family_by_name(&family_name).unwrap();
+    source
+        .select_family_by_name(KNOWN_SYSTEM_FONT_NAME)
+        .unwrap();
+}
+
+#[cfg(target_family = "windows")]
+#[test]
+fn directwrite_source_refresh() {
+    use crate::sources::directwrite::DirectWriteSource;
+
+    let mut source = DirectWriteSource::new();
+    source.refresh().unwrap();
+    let generation = source.generation();
+    // No fonts are installed in the meantime, so there is nothing more to refresh.
+    assert!(!source.refresh().unwrap());
+    assert!(!source.refresh().unwrap());
+    assert_eq!(source.generation(), generation);
+    source
+        .select_family_by_name(KNOWN_SYSTEM_FONT_NAME)
+        .unwrap();
+
+    let fonts = vec![Handle::from_path(
+        FilePath::new(FILE_PATH_INCONSOLATA_TTF).to_owned(),
+        FaceIndex::FIRST,
+    )];
+    let mut source = DirectWriteSource::from_fonts(fonts.into_iter()).unwrap();
+    assert!(!source.refresh().unwrap());
+    assert_eq!(source.generation(), 0);
+}
+
+#[test]
+fn multi_source_priority() {
+    let font_data = Arc::new(fs::read(TEST_FONT_FILE_PATH).unwrap());
+    let bundled_source =
+        MemSource::from_fonts(iter::once(Handle::from_memory(font_data, FaceIndex::FIRST)))
+            .unwrap();
+    let mut source =
+        MultiSource::from_sources(vec![Box::new(bundled_source), Box::new(test_mem_source())]);
+    let family_name = Font::from_path(TEST_FONT_FILE_PATH, FaceIndex::FIRST)
+        .unwrap()
+        .family_name();
+    let is_bundled = |handle: &Handle| match *handle {
+        Handle::Memory { .. } => true,
+        Handle::Path { .. } => false,
+    };
+
+    let families = source.all_families().unwrap();
+    assert_eq!(families.len(), 2);
+    assert!(families.contains(&family_name));
+
+    let family = source.select_family_by_name(&family_name).unwrap();
+    assert_eq!(family.fonts().len(), 1);
+    assert!(is_bundled(&family.fonts()[0]));
+    let family_names = [FamilyName::Title(family_name.clone())];
+    let mut italic = Properties::new();
+    italic.style(Style::Italic);
+    let handle = source.select_best_match(&family_names, &italic).unwrap();
+    assert!(is_bundled(&handle));
+
+    // With the families merged, the italic face comes from the other source, but the bundled
+    // regular face still wins over the one that matches equally well.
+    source.set_merge_families(true);
+    assert_eq!(
+        source
+            .select_family_by_name(&family_name)
+            .unwrap()
+            .fonts()
+            .len(),
+        3
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(206.0, 526.0),
-        Point2D::new(176.0, 244.0)
+    let handle = source.select_best_match(&family_names, &italic).unwrap();
+    assert!(!is_bundled(&handle));
+    assert_eq!(handle.load().unwrap().properties().style, Style::Italic);
+    let handle = source
+        .select_best_match(&family_names, &Properties::new())
+        .unwrap();
+    assert!(is_bundled(&handle));
+}
+
+#[test]
+fn set_generic_families() {
+    let mut source = MultiSource::from_sources(vec![Box::new(test_mem_source())]);
+    let family_names = vec![
+        "No Such Family".to_owned(),
+        Font::from_path(FILE_PATH_INCONSOLATA_TTF, FaceIndex::FIRST)
+            .unwrap()
+            .family_name(),
+    ];
+    source.set_generic_families(FamilyName::SansSerif, family_names.clone());
+    assert_eq!(
+        source.generic_family_names(&FamilyName::SansSerif),
+        family_names
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(172.0, 205.0),
-        Point2D::new(158.0, 205.0)
+    let handle = source
+        .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+        .unwrap();
+    assert_eq!(
+        handle.load().unwrap().postscript_name().unwrap(),
+        "Inconsolata-Regular"
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(144.0, 205.0),
-        Point2D::new(140.0, 244.0)
+
+    // None of the test fonts are in the built-in lists.
+    source.set_generic_families(FamilyName::SansSerif, vec![]);
+    match source.select_best_match(&[FamilyName::SansSerif], &Properties::new()) {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("Expected no match but got {:?}", other),
+    }
+}
+
+#[test]
+fn named_weights_and_stretches() {
+    assert_eq!(Weight::BOLD, Weight(700.0));
+    assert_eq!(Weight::default(), Weight::NORMAL);
+    assert!(Weight::THIN < Weight::LIGHT && Weight::SEMIBOLD < Weight::BLACK);
+    assert_eq!(Stretch::CONDENSED, Stretch(0.75));
+    assert_eq!(Stretch::default(), Stretch::NORMAL);
+    assert!(Stretch::ULTRA_CONDENSED < Stretch::EXPANDED);
+
+    // None of the test fonts are bold or condensed, so the closest match in the family is the
+    // one with the requested style.
+    let source = MinimalSource(test_mem_source());
+    let family_names = [FamilyName::Title("EB Garamond".to_owned())];
+    let mut properties = Properties::new();
+    properties.weight(Weight::BOLD).stretch(Stretch::CONDENSED);
+    let handle = source
+        .select_best_match(&family_names, &properties)
+        .unwrap();
+    assert_eq!(
+        handle.load().unwrap().postscript_name().unwrap(),
+        "EBGaramond12-Regular"
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(114.0, 491.0),
-        Point2D::new(114.0, 598.0)
+    properties.style(Style::Italic);
+    let handle = source
+        .select_best_match(&family_names, &properties)
+        .unwrap();
+    assert_eq!(
+        handle.load().unwrap().postscript_name().unwrap(),
+        "EBGaramond12-Italic"
     );
-    assert_close!(events.next());
-    let event = events.next();
-    assert!(
-        event == Some(PathEvent::MoveTo(Point2D::new(117.0, 88.0)))
-            || event == Some(PathEvent::MoveTo(Point2D::new(117.5, 88.5)))
+}
+
+#[test]
+fn parse_properties() {
+    assert_eq!("bold".parse(), Ok(Weight(700.0)));
+    assert_eq!(" Semi-Bold ".parse(), Ok(Weight::SEMIBOLD));
+    assert_eq!("350".parse(), Ok(Weight(350.0)));
+    assert_eq!(
+        "heavyish".parse::<Weight>(),
+        Err(PropertyParseError::UnknownKeyword)
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(135.0, 106.0),
-        Point2D::new(160.0, 106.0)
+    assert_eq!(
+        "bolder".parse::<Weight>(),
+        Err(PropertyParseError::UnknownKeyword)
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(185.0, 106.0),
-        Point2D::new(202.5, 88.5)
+    assert_eq!("-5".parse::<Weight>(), Err(PropertyParseError::OutOfRange));
+
+    assert_eq!("expanded".parse(), Ok(Stretch(1.25)));
+    assert_eq!("semi-condensed".parse(), Ok(Stretch::SEMI_CONDENSED));
+    assert_eq!("87.5%".parse(), Ok(Stretch(0.875)));
+    assert_eq!("1.5".parse(), Ok(Stretch(1.5)));
+    assert_eq!(
+        "300%".parse::<Stretch>(),
+        Err(PropertyParseError::OutOfRange)
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(220.0, 71.0),
-        Point2D::new(220.0, 46.0)
+    assert_eq!(
+        "squished".parse::<Stretch>(),
+        Err(PropertyParseError::UnknownKeyword)
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(220.0, 21.0),
-        Point2D::new(202.5, 3.5)
+
+    assert_eq!("italic".parse(), Ok(Style::Italic));
+    assert_eq!(
+        "Oblique".parse(),
+        Ok(Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE))
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(185.0, -14.0),
-        Point2D::new(160.0, -14.0)
+    assert_eq!("oblique -10.5deg".parse(), Ok(Style::Oblique(-10.5)));
+    assert_eq!(
+        "oblique 100deg".parse::<Style>(),
+        Err(PropertyParseError::OutOfRange)
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(135.0, -14.0),
-        Point2D::new(117.5, 3.5)
+    assert_eq!(
+        "oblique 10".parse::<Style>(),
+        Err(PropertyParseError::UnknownKeyword)
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(100.0, 21.0),
-        Point2D::new(100.0, 46.0)
+    assert_eq!(
+        "slanted".parse::<Style>(),
+        Err(PropertyParseError::UnknownKeyword)
     );
-    match events.next() {
-        Some(PathEvent::Quadratic(ref segment)) => {
-            assert_eq!(segment.ctrl, Point2D::new(100.0, 71.0));
-            assert!(
-                segment.to == Point2D::new(117.0, 88.0) || segment.to == Point2D::new(117.5, 88.5)
-            )
-        }
-        other => panic!("Expected quadratic got {:?}", other),
+}
+
+#[test]
+fn convert_properties() {
+    assert_eq!(Weight::from(700), Weight::BOLD);
+    assert_eq!(Weight::from(350).to_usweight_class(), 350);
+    assert_eq!(Weight(449.6).to_usweight_class(), 450);
+    assert_eq!(Weight(0.0).to_usweight_class(), 1);
+
+    for width_class in 1..=9 {
+        assert_eq!(
+            Stretch::from_uswidth_class(width_class).to_uswidth_class(),
+            width_class
+        );
     }
-    assert_close!(events.next());
+    assert_eq!(Stretch::from_uswidth_class(3), Stretch::CONDENSED);
+    assert_eq!(Stretch::from_uswidth_class(0), Stretch::NORMAL);
+    assert_eq!(Stretch(1.7).to_uswidth_class(), 8);
+    assert_eq!(Stretch::from_percentage(62.5), Stretch::EXTRA_CONDENSED);
+    assert_eq!(Stretch::SEMI_EXPANDED.to_percentage(), 112.5);
+
+    assert!(Style::Normal < Style::Italic);
+}
+
+#[test]
+fn display_properties() {
+    assert_eq!(Weight::BOLD.to_string(), "bold");
+    assert_eq!(Weight::EXTRA_LIGHT.to_string(), "extra-light");
+    assert_eq!(Weight(350.0).to_string(), "350");
+    assert_eq!(Stretch::SEMI_CONDENSED.to_string(), "semi-condensed");
+    assert_eq!(Stretch(0.8).to_string(), "80%");
+    assert_eq!(Style::Oblique(14.0).to_string(), "oblique");
+    assert_eq!(Style::Oblique(8.5).to_string(), "oblique 8.5deg");
+
+    for &weight in &[Weight::SEMIBOLD, Weight(123.5)] {
+        assert_eq!(weight.to_string().parse(), Ok(weight));
+    }
+    for &stretch in &[Stretch::ULTRA_EXPANDED, Stretch(1.75)] {
+        assert_eq!(stretch.to_string().parse(), Ok(stretch));
+    }
+    for &style in &[Style::Italic, Style::Oblique(14.0), Style::Oblique(-20.0)] {
+        assert_eq!(style.to_string().parse(), Ok(style));
+    }
+}
+
+#[test]
+fn properties_from_style_names() {
+    use crate::properties::Style::{Italic, Normal};
+    let oblique = Style::Oblique(Style::OBLIQUE_DEFAULT_ANGLE);
+    let cases = [
+        ("Regular", Normal, Weight::NORMAL, Stretch::NORMAL),
+        ("Italic", Italic, Weight::NORMAL, Stretch::NORMAL),
+        ("Bold", Normal, Weight::BOLD, Stretch::NORMAL),
+        ("Bold Italic", Italic, Weight::BOLD, Stretch::NORMAL),
+        ("BoldItalic", Italic, Weight::BOLD, Stretch::NORMAL),
+        ("Thin", Normal, Weight::THIN, Stretch::NORMAL),
+        ("Hairline Italic", Italic, Weight::THIN, Stretch::NORMAL),
+        ("ExtraLight", Normal, Weight::EXTRA_LIGHT, Stretch::NORMAL),
+        (
+            "Ultra Light Italic",
+            Italic,
+            Weight::EXTRA_LIGHT,
+            Stretch::NORMAL,
+        ),
+        ("Light", Normal, Weight::LIGHT, Stretch::NORMAL),
+        ("Lt It", Italic, Weight::LIGHT, Stretch::NORMAL),
+        ("Book", Normal, Weight::NORMAL, Stretch::NORMAL),
+        ("Book Oblique", oblique, Weight::NORMAL, Stretch::NORMAL),
+        ("Roman", Normal, Weight::NORMAL, Stretch::NORMAL),
+        ("Medium", Normal, Weight::MEDIUM, Stretch::NORMAL),
+        ("Md Italic", Italic, Weight::MEDIUM, Stretch::NORMAL),
+        ("SemiBold", Normal, Weight::SEMIBOLD, Stretch::NORMAL),
+        ("SemiBold Italic", Italic, Weight::SEMIBOLD, Stretch::NORMAL),
+        ("Semi-Bold", Normal, Weight::SEMIBOLD, Stretch::NORMAL),
+        ("Demibold", Normal, Weight::SEMIBOLD, Stretch::NORMAL),
+        ("Demi Italic", Italic, Weight::SEMIBOLD, Stretch::NORMAL),
+        ("SmBd", Normal, Weight::SEMIBOLD, Stretch::NORMAL),
+        ("ExtraBold", Normal, Weight::EXTRA_BOLD, Stretch::NORMAL),
+        ("XBd", Normal, Weight::EXTRA_BOLD, Stretch::NORMAL),
+        ("Black", Normal, Weight::BLACK, Stretch::NORMAL),
+        ("Heavy Oblique", oblique, Weight::BLACK, Stretch::NORMAL),
+        ("Cond Black", Normal, Weight::BLACK, Stretch::CONDENSED),
+        ("Condensed", Normal, Weight::NORMAL, Stretch::CONDENSED),
+        ("Cn", Normal, Weight::NORMAL, Stretch::CONDENSED),
+        ("BdCn", Normal, Weight::BOLD, Stretch::CONDENSED),
+        (
+            "Narrow Bold Italic",
+            Italic,
+            Weight::BOLD,
+            Stretch::CONDENSED,
+        ),
+        (
+            "SemiCondensed Light",
+            Normal,
+            Weight::LIGHT,
+            Stretch::SEMI_CONDENSED,
+        ),
+        (
+            "ExtraCondensed",
+            Normal,
+            Weight::NORMAL,
+            Stretch::EXTRA_CONDENSED,
+        ),
+        (
+            "Ultra Condensed Medium",
+            Normal,
+            Weight::MEDIUM,
+            Stretch::ULTRA_CONDENSED,
+        ),
+        (
+            "SemiExpanded",
+            Normal,
+            Weight::NORMAL,
+            Stretch::SEMI_EXPANDED,
+        ),
+        ("Extended", Normal, Weight::NORMAL, Stretch::EXPANDED),
+        ("Bold Extended", Normal, Weight::BOLD, Stretch::EXPANDED),
+        (
+            "Extra Expanded Thin",
+            Normal,
+            Weight::THIN,
+            Stretch::EXTRA_EXPANDED,
+        ),
+        (
+            "UltraExpanded",
+            Normal,
+            Weight::NORMAL,
+            Stretch::ULTRA_EXPANDED,
+        ),
+        ("Kursiv", Italic, Weight::NORMAL, Stretch::NORMAL),
+        ("Halbfett Kursiv", Italic, Weight::NORMAL, Stretch::NORMAL),
+        ("Display Titling", Normal, Weight::NORMAL, Stretch::NORMAL),
+        ("", Normal, Weight::NORMAL, Stretch::NORMAL),
+    ];
+    for &(name, style, weight, stretch) in &cases {
+        assert_eq!(
+            Properties::from_style_name(name),
+            Properties {
+                style,
+                weight,
+                stretch
+            },
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn rank_matches_keeps_ties_in_order() {
+    let mut bold = Properties::new();
+    bold.weight(Weight::BOLD);
+    let mut italic = Properties::new();
+    italic.style(Style::Italic);
+    let candidates = [bold, Properties::new(), italic, Properties::new()];
+    assert_eq!(
+        matching::rank_matches(&candidates, &Properties::new()),
+        [1, 3, 0, 2]
+    );
+    assert_eq!(
+        matching::find_best_match(&candidates, &Properties::new()).unwrap(),
+        1
+    );
 }
 
-// https://github.com/pcwalton/pathfinder/issues/84
-#[allow(non_snake_case)]
 #[test]
-fn get_glyph_outline_inconsolata_J() {
-    let mut path_builder = Path::builder();
-    let mut file = File::open(FILE_PATH_INCONSOLATA_TTF).unwrap();
-    let font = Font::from_file(&mut file, 0).unwrap();
-    let glyph = font.glyph_for_char('J').expect("No glyph for char!");
-    font.outline(glyph, HintingOptions::None, &mut path_builder)
-        .unwrap();
-    let path = path_builder.build();
-
-    let mut events = path.into_iter();
+fn match_oblique_faces_by_angle() {
+    let with_style = |style| *Properties::new().style(style);
+    let candidates = [
+        with_style(Style::Oblique(10.0)),
+        with_style(Style::Oblique(20.0)),
+        with_style(Style::Italic),
+        with_style(Style::Oblique(-15.0)),
+        with_style(Style::Normal),
+    ];
+    let best_match = |style| matching::find_best_match(&candidates, &with_style(style)).unwrap();
+    // Steeper angles in the same direction come first, then shallower ones, then the other
+    // direction.
+    assert_eq!(best_match(Style::Oblique(14.0)), 1);
+    assert_eq!(best_match(Style::Oblique(8.0)), 0);
+    assert_eq!(best_match(Style::Oblique(25.0)), 1);
+    assert_eq!(best_match(Style::Oblique(-10.0)), 3);
+    assert_eq!(best_match(Style::Oblique(-30.0)), 3);
+    assert_eq!(best_match(Style::Italic), 2);
     assert_eq!(
-        events.next(),
-        Some(PathEvent::MoveTo(Point2D::new(198.0, -11.0)))
-    );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(106.0, -11.0),
-        Point2D::new(49.0, 58.0)
+        matching::rank_matches(&candidates, &with_style(Style::Oblique(12.0))),
+        [1, 0, 3, 2, 4]
     );
-    assert_line_to!(events.next(), Point2D::new(89.0, 108.0));
-    assert_line_to!(events.next(), Point2D::new(96.0, 116.0));
-    assert_line_to!(events.next(), Point2D::new(101.0, 112.0));
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(102.0, 102.0),
-        Point2D::new(106.0, 95.0)
+
+    // Without italic faces, `italic` matches like `oblique 14deg`, and `normal` like
+    // `oblique 0deg`.
+    let candidates = [
+        with_style(Style::Oblique(10.0)),
+        with_style(Style::Oblique(20.0)),
+        with_style(Style::Oblique(-5.0)),
+    ];
+    assert_eq!(
+        matching::find_best_match(&candidates, &with_style(Style::Italic)).unwrap(),
+        1
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(110.0, 88.0),
-        Point2D::new(122.0, 78.0)
+    assert_eq!(
+        matching::find_best_match(&candidates, &with_style(Style::Normal)).unwrap(),
+        0
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(157.0, 51.0),
-        Point2D::new(196.0, 51.0)
+}
+
+#[test]
+fn select_by_postscript_name_prefix() {
+    check_select_by_postscript_name_prefix(&MinimalSource(test_mem_source()));
+    check_select_by_postscript_name_prefix(&test_mem_source());
+}
+
+#[test]
+fn select_by_properties() {
+    check_select_by_properties(&MinimalSource(test_mem_source()));
+    check_select_by_properties(&test_mem_source());
+}
+
+#[test]
+fn select_monospace_system_fonts_by_properties() {
+    let handles = SystemSource::new()
+        .select_by_properties(PropertiesFilter::new().monospace(true))
+        .unwrap();
+    for handle in &handles {
+        let font = handle.load().unwrap();
+        assert!(font.is_monospace(), "{} isn't monospace", font.full_name());
+        assert_ne!(font.family_name(), KNOWN_SYSTEM_FONT_NAME);
+    }
+}
+
+#[test]
+fn select_by_postscript_name_matches_brute_force() {
+    // `MinimalSource` uses the default implementation, which peeks at `name` tables.
+    check_select_by_postscript_name_matches_brute_force(&MinimalSource(test_mem_source()), 3);
+    check_select_by_postscript_name_matches_brute_force(&SystemSource::new(), 8);
+}
+
+#[test]
+fn peek_postscript_name() {
+    let handle = Handle::from_path(TEST_FONT_COLLECTION_FILE_PATH.into(), FaceIndex(1));
+    assert_eq!(
+        handle.postscript_name().unwrap(),
+        handle.load().unwrap().postscript_name().unwrap()
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(247.0, 51.0),
-        Point2D::new(269.5, 86.5)
+}
+
+#[test]
+fn select_by_full_name_and_by_family_and_style() {
+    let source = SystemSource::new();
+    let full_name = format!(
+        "{} {}",
+        KNOWN_SYSTEM_FONT_NAME, KNOWN_SYSTEM_BOLD_ITALIC_STYLE_NAME
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(292.0, 122.0),
-        Point2D::new(292.0, 208.0)
+    let by_full_name = source
+        .select_by_full_name(&full_name.to_uppercase())
+        .unwrap();
+    let by_family_and_style = source
+        .select_by_family_and_style(
+            &KNOWN_SYSTEM_FONT_NAME.to_lowercase(),
+            KNOWN_SYSTEM_BOLD_ITALIC_STYLE_NAME,
+        )
+        .unwrap();
+    check_same_postscript_names(&[by_full_name.clone()], &[by_family_and_style]);
+
+    let font = by_full_name.load().unwrap();
+    assert_eq!(font.family_name(), KNOWN_SYSTEM_FONT_NAME);
+    assert_eq!(font.properties().weight, Weight::BOLD);
+    assert_ne!(font.properties().style, Style::Normal);
+}
+
+#[test]
+fn select_by_full_name_and_by_family_and_style_by_scanning() {
+    let source = MinimalSource(test_mem_source());
+    let italic_postscript_name = Some("EBGaramond12-Italic".to_owned());
+
+    let handle = source.select_by_full_name("eb garamond 12 italic").unwrap();
+    assert_eq!(
+        handle.load().unwrap().postscript_name(),
+        italic_postscript_name
     );
-    assert_line_to!(events.next(), Point2D::new(292.0, 564.0));
-    assert_line_to!(events.next(), Point2D::new(172.0, 564.0));
-    assert_line_to!(events.next(), Point2D::new(172.0, 623.0));
-    assert_line_to!(events.next(), Point2D::new(457.0, 623.0));
-    assert_line_to!(events.next(), Point2D::new(457.0, 564.0));
-    assert_line_to!(events.next(), Point2D::new(361.0, 564.0));
-    assert_line_to!(events.next(), Point2D::new(361.0, 209.0));
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(363.0, 133.0),
-        Point2D::new(341.0, 84.0)
+    // Both the typographic and the legacy family and style names work.
+    for &(family, style) in &[("EB Garamond", "12 Italic"), ("EB GARAMOND 12", "italic")] {
+        let handle = source.select_by_family_and_style(family, style).unwrap();
+        assert_eq!(
+            handle.load().unwrap().postscript_name(),
+            italic_postscript_name
+        );
+    }
+
+    // "Regular" may be left out of the full name.
+    let handle = source.select_by_full_name("Inconsolata").unwrap();
+    assert_eq!(
+        handle.load().unwrap().postscript_name(),
+        Some("Inconsolata-Regular".to_owned())
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(319.0, 35.0),
-        Point2D::new(281.5, 12.0)
+
+    match source.select_by_family_and_style("EB Garamond 12", "Bold Italic") {
+        Err(SelectionError::NotFound) => {}
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn pair_family_and_style_names_by_language() {
+    // The German family name has a style name only in German, and the English family name has a
+    // style name only on the Macintosh platform, so neither pair mixes names from the two records.
+    let name_table = name_table_with_records(&[
+        (3, 1, 0x409, 1, "Family"),
+        (3, 1, 0x407, 1, "Familie"),
+        (3, 1, 0x407, 2, "Fett"),
+        (1, 0, 0, 1, "Mac Family"),
+        (1, 0, 0, 2, "Bold"),
+    ]);
+    assert_eq!(
+        sfnt::families_and_styles(&name_table),
+        [("Familie".to_owned(), "Fett".to_owned())]
     );
-    assert_quadratic_to!(
-        events.next(),
-        Point2D::new(244.0, -11.0),
-        Point2D::new(198.0, -11.0)
+
+    // A typographic family name is paired with the legacy style name in the same language.
+    let name_table = name_table_with_records(&[
+        (3, 1, 0x409, 1, "Family Bold"),
+        (3, 1, 0x409, 2, "Regular"),
+        (3, 1, 0x409, 16, "Family"),
+        (3, 1, 0x407, 17, "Fett"),
+    ]);
+    assert_eq!(
+        sfnt::families_and_styles(&name_table),
+        [
+            ("Family".to_owned(), "Regular".to_owned()),
+            ("Family Bold".to_owned(), "Regular".to_owned()),
+        ]
     );
-    assert_close!(events.next());
 }
 
-// Makes sure that a canvas has an "L" shape in it. This is used to test rasterization.
-#[allow(non_snake_case)]
-fn check_L_shape(canvas: &Canvas) {
-    // Find any empty rows at the start.
-    let mut y = 0;
-    while y < canvas.size.height {
-        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
-        if canvas.pixels[row_start..row_end].iter().any(|&p| p != 0) {
-            break;
-        }
-        y += 1;
-    }
-    assert!(y < canvas.size.height);
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_handles() {
+    let path_handle = Handle::from_path(TEST_FONT_COLLECTION_FILE_PATH.into(), FaceIndex(1));
+    let mut bytes = vec![];
+    File::open(TEST_FONT_FILE_PATH)
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    let memory_handle = Handle::from_memory(Arc::new(bytes), FaceIndex::FIRST);
 
-    // Find the top part of the L.
-    let mut top_stripe_width = None;
-    while y < canvas.size.height {
-        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
-        if let Some(stripe_width) = stripe_width(&canvas.pixels[row_start..row_end]) {
-            if let Some(top_stripe_width) = top_stripe_width {
-                if stripe_width > top_stripe_width {
-                    break;
-                }
-                assert_eq!(stripe_width, top_stripe_width);
-            }
-            top_stripe_width = Some(stripe_width);
-        }
-        y += 1;
+    for (handle, postscript_name) in &[
+        (path_handle, TEST_FONT_COLLECTION_POSTSCRIPT_NAME[1]),
+        (memory_handle, TEST_FONT_POSTSCRIPT_NAME),
+    ] {
+        let json = serde_json::to_string(handle).unwrap();
+        check_serialized_handle(
+            handle,
+            &serde_json::from_str(&json).unwrap(),
+            postscript_name,
+        );
+        let binary = bincode::serialize(handle).unwrap();
+        check_serialized_handle(
+            handle,
+            &bincode::deserialize(&binary).unwrap(),
+            postscript_name,
+        );
     }
-    assert!(y < canvas.size.height);
+}
 
-    // Find the bottom part of the L.
-    let mut bottom_stripe_width = None;
-    while y < canvas.size.height {
-        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
-        y += 1;
-        if let Some(stripe_width) = stripe_width(&canvas.pixels[row_start..row_end]) {
-            if let Some(bottom_stripe_width) = bottom_stripe_width {
-                assert!(bottom_stripe_width > top_stripe_width.unwrap());
-                assert_eq!(stripe_width, bottom_stripe_width);
-            }
-            bottom_stripe_width = Some(stripe_width);
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_properties() {
+    let mut properties = Properties::new();
+    properties
+        .style(Style::Italic)
+        .weight(Weight::BOLD)
+        .stretch(Stretch::CONDENSED);
+    let family_names = vec![
+        FamilyName::Title("EB Garamond".to_owned()),
+        FamilyName::Serif,
+    ];
+
+    let json = serde_json::to_string(&(&properties, &family_names)).unwrap();
+    let (new_properties, new_family_names): (Properties, Vec<FamilyName>) =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(new_properties, properties);
+    assert_eq!(new_family_names, family_names);
+
+    let binary = bincode::serialize(&(&properties, &family_names)).unwrap();
+    let (new_properties, new_family_names): (Properties, Vec<FamilyName>) =
+        bincode::deserialize(&binary).unwrap();
+    assert_eq!(new_properties, properties);
+    assert_eq!(new_family_names, family_names);
+}
+
+// Makes sure that a deserialized handle matches the original and loads the expected font.
+#[cfg(feature = "serde")]
+fn check_serialized_handle(original: &Handle, deserialized: &Handle, postscript_name: &str) {
+    match (original, deserialized) {
+        (
+            &Handle::Path {
+                ref path,
+                font_index,
+            },
+            &Handle::Path {
+                path: ref new_path,
+                font_index: new_font_index,
+            },
+        ) => {
+            assert_eq!(new_path, path);
+            assert_eq!(new_font_index, font_index);
+        }
+        (
+            &Handle::Memory {
+                ref bytes,
+                font_index,
+            },
+            &Handle::Memory {
+                bytes: ref new_bytes,
+                font_index: new_font_index,
+            },
+        ) => {
+            assert_eq!(new_bytes, bytes);
+            assert_eq!(new_font_index, font_index);
         }
+        _ => panic!("Handle variant changed: {:?}", deserialized),
     }
+    let font = Font::from_handle(deserialized).unwrap();
+    assert_eq!(font.postscript_name().unwrap(), postscript_name);
+}
 
-    // Find any empty rows at the end.
-    while y < canvas.size.height {
-        let (row_start, row_end) = (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
-        y += 1;
-        if canvas.pixels[row_start..row_end].iter().any(|&p| p != 0) {
-            break;
-        }
+// Checks that `select_by_postscript_name` finds the first few fonts of the source just like a
+// search that loads every font does.
+fn check_select_by_postscript_name_matches_brute_force<S>(source: &S, font_count: usize)
+where
+    S: Source,
+{
+    let loaded_fonts: Vec<(Handle, String)> = source
+        .all_fonts()
+        .unwrap()
+        .into_iter()
+        .filter_map(|handle| {
+            let postscript_name = handle.load().ok()?.postscript_name()?;
+            Some((handle, postscript_name))
+        })
+        .collect();
+    assert!(!loaded_fonts.is_empty());
+    for &(ref handle, ref postscript_name) in loaded_fonts.iter().take(font_count) {
+        let selected_handle = source.select_by_postscript_name(postscript_name).unwrap();
+        check_same_postscript_names(slice::from_ref(handle), slice::from_ref(&selected_handle));
     }
+}
 
-    // Make sure we made it to the end.
-    assert_eq!(y, canvas.size.height);
+fn check_same_postscript_names(expected: &[Handle], actual: &[Handle]) {
+    let postscript_names = |handles: &[Handle]| -> Vec<_> {
+        handles
+            .iter()
+            .map(|handle| handle.load().unwrap().postscript_name())
+            .collect()
+    };
+    assert_eq!(postscript_names(actual), postscript_names(expected));
 }
 
-// Makes sure that a canvas has an "{" shape in it. This is used to test rasterization.
-#[cfg(target_family = "windows")]
-fn check_curly_shape(canvas: &Canvas) {
-    let mut y = 0;
-    let height = canvas.size.height;
-    // check the upper row and the lower rows are symmetrical
-    while y < height / 2 {
-        let (upper_row_start, upper_row_end) =
-            (canvas.stride * y as usize, canvas.stride * (y + 1) as usize);
-        let (lower_row_start, lower_row_end) = (
-            canvas.stride * (height - y - 1) as usize,
-            canvas.stride * (height - y) as usize,
-        );
-        let upper_row = &canvas.pixels[upper_row_start..upper_row_end];
-        let lower_row = &canvas.pixels[lower_row_start..lower_row_end];
-        let top_row_width = stripe_width(upper_row).unwrap();
-        let lower_row_width = stripe_width(lower_row).unwrap();
-        let upper_row_pixel_start = stride_pixel_start(upper_row).unwrap();
-        let lower_row_pixel_start = stride_pixel_start(lower_row).unwrap();
-        if top_row_width == lower_row_width {
-            // check non-zero pixels start at the same index
-            assert_eq!(upper_row_pixel_start, lower_row_pixel_start);
-        } else {
-            //if not, assert that the difference is not greater than 1
-            assert_eq!((lower_row_width as i32 - top_row_width as i32).abs(), 1);
-            // and assert that the non-zero pixel index difference is not greater than 1
-            assert_eq!(
-                (upper_row_pixel_start as i32 - lower_row_pixel_start as i32).abs(),
-                1
-            );
-        }
-        y += 1;
+// Builds a version 0 `COLR` table that defines a single color glyph from the given layers, each
+// of which is a glyph ID and a palette index.
+fn colr_v0_table(base_glyph_id: u32, layers: &[(u32, u16)]) -> Vec<u8> {
+    let mut colr_table = vec![];
+    colr_table.write_u16::<BigEndian>(0).unwrap();
+    colr_table.write_u16::<BigEndian>(1).unwrap();
+    colr_table.write_u32::<BigEndian>(14).unwrap();
+    colr_table.write_u32::<BigEndian>(20).unwrap();
+    colr_table
+        .write_u16::<BigEndian>(layers.len() as u16)
+        .unwrap();
+    colr_table
+        .write_u16::<BigEndian>(base_glyph_id as u16)
+        .unwrap();
+    colr_table.write_u16::<BigEndian>(0).unwrap();
+    colr_table
+        .write_u16::<BigEndian>(layers.len() as u16)
+        .unwrap();
+    for &(glyph_id, palette_index) in layers {
+        colr_table.write_u16::<BigEndian>(glyph_id as u16).unwrap();
+        colr_table.write_u16::<BigEndian>(palette_index).unwrap();
     }
+    colr_table
 }
 
-// return the first non-zero pixel index
-#[cfg(target_family = "windows")]
-fn stride_pixel_start(pixels: &[u8]) -> Option<u32> {
-    let mut index = 0;
-    for x in pixels {
-        if *x != 0 {
-            return Some(index);
+// Builds `EBLC` and `EBDT` tables with bitmaps of a single glyph in two strikes: a 1-bit, 3×2
+// image in format 2 at 12 pixels per em, and an 8-bit, 2×1 image in format 5 at 16 pixels per em.
+fn eblc_and_ebdt_tables(glyph_id: u32) -> (Vec<u8>, Vec<u8>) {
+    let mut eblc_table = vec![];
+    eblc_table.write_u32::<BigEndian>(0x0002_0000).unwrap();
+    eblc_table.write_u32::<BigEndian>(2).unwrap();
+    for &(subtable_array_offset, ppem, bit_depth) in &[(104, 12, 1), (128, 16, 8)] {
+        eblc_table
+            .write_u32::<BigEndian>(subtable_array_offset)
+            .unwrap();
+        eblc_table.write_u32::<BigEndian>(24).unwrap();
+        eblc_table.write_u32::<BigEndian>(1).unwrap();
+        eblc_table.extend_from_slice(&[0; 28]);
+        eblc_table.write_u16::<BigEndian>(glyph_id as u16).unwrap();
+        eblc_table.write_u16::<BigEndian>(glyph_id as u16).unwrap();
+        eblc_table.extend_from_slice(&[ppem, ppem, bit_depth, 1]);
+    }
+
+    // Index format 1 with image format 2, and index format 2 with image format 5.
+    for &(index_format, image_format, image_data_offset) in &[(1, 2, 4), (2, 5, 10)] {
+        eblc_table.write_u16::<BigEndian>(glyph_id as u16).unwrap();
+        eblc_table.write_u16::<BigEndian>(glyph_id as u16).unwrap();
+        eblc_table.write_u32::<BigEndian>(8).unwrap();
+        eblc_table.write_u16::<BigEndian>(index_format).unwrap();
+        eblc_table.write_u16::<BigEndian>(image_format).unwrap();
+        eblc_table
+            .write_u32::<BigEndian>(image_data_offset)
+            .unwrap();
+        if index_format == 1 {
+            eblc_table.write_u32::<BigEndian>(0).unwrap();
+            eblc_table.write_u32::<BigEndian>(6).unwrap();
+        } else {
+            eblc_table.write_u32::<BigEndian>(2).unwrap();
+            eblc_table.extend_from_slice(&[1, 2, 0, 5, 3, 0, 0, 0]);
         }
-        index += 1;
     }
-    None
+
+    // The rows of the 1-bit image, 101 and 010, are packed without padding.
+    let mut ebdt_table = vec![];
+    ebdt_table.write_u32::<BigEndian>(0x0002_0000).unwrap();
+    ebdt_table.extend_from_slice(&[2, 3, 1, 9, 4, 0b1010_1000]);
+    ebdt_table.extend_from_slice(&[0x40, 0xff]);
+    (eblc_table, ebdt_table)
 }
 
-fn stripe_width(pixels: &[u8]) -> Option<u32> {
-    let mut x = 0;
-    // Find the initial empty part.
-    while x < pixels.len() && pixels[x] == 0 {
-        x += 1
-    }
-    if x == pixels.len() {
-        return None;
+// Returns the data of the font at `path` with the given tables added to it, replacing any existing
+// tables with the same tags. This is used to test support for tables that none of our test fonts
+// have.
+fn font_data_with_tables(path: &str, new_tables: Vec<([u8; 4], Vec<u8>)>) -> Arc<Vec<u8>> {
+    let mut font_data = vec![];
+    File::open(path)
+        .unwrap()
+        .read_to_end(&mut font_data)
+        .unwrap();
+
+    let table_count = BigEndian::read_u16(&font_data[4..6]) as usize;
+    let mut tables: Vec<([u8; 4], Vec<u8>)> = (0..table_count)
+        .map(|table_index| {
+            let record = &font_data[(12 + table_index * 16)..(28 + table_index * 16)];
+            let offset = BigEndian::read_u32(&record[8..12]) as usize;
+            let length = BigEndian::read_u32(&record[12..16]) as usize;
+            let mut tag = [0; 4];
+            tag.copy_from_slice(&record[0..4]);
+            (tag, font_data[offset..(offset + length)].to_vec())
+        })
+        .filter(|&(tag, _)| new_tables.iter().all(|&(new_tag, _)| new_tag != tag))
+        .collect();
+    tables.extend(new_tables);
+    tables.sort_by_key(|&(tag, _)| tag);
+
+    let entry_selector = (tables.len() as f32).log2() as u16;
+    let search_range = (1 << entry_selector) * 16;
+    let mut new_font_data = font_data[0..4].to_vec();
+    new_font_data
+        .write_u16::<BigEndian>(tables.len() as u16)
+        .unwrap();
+    new_font_data.write_u16::<BigEndian>(search_range).unwrap();
+    new_font_data
+        .write_u16::<BigEndian>(entry_selector)
+        .unwrap();
+    new_font_data
+        .write_u16::<BigEndian>(tables.len() as u16 * 16 - search_range)
+        .unwrap();
+
+    let mut offset = 12 + tables.len() * 16;
+    for &(ref tag, ref table) in &tables {
+        let checksum = table.chunks(4).fold(0u32, |checksum, chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            checksum.wrapping_add(BigEndian::read_u32(&word))
+        });
+        new_font_data.extend_from_slice(tag);
+        new_font_data.write_u32::<BigEndian>(checksum).unwrap();
+        new_font_data.write_u32::<BigEndian>(offset as u32).unwrap();
+        new_font_data
+            .write_u32::<BigEndian>(table.len() as u32)
+            .unwrap();
+        offset += (table.len() + 3) & !3;
     }
-    // Find the stripe width.
-    let mut stripe_width = 0;
-    while x < pixels.len() && pixels[x] != 0 {
-        x += 1;
-        stripe_width += 1;
+    for &(_, ref table) in &tables {
+        new_font_data.extend_from_slice(table);
+        new_font_data.resize((new_font_data.len() + 3) & !3, 0);
     }
-    // Find the last empty part.
-    while x < pixels.len() && pixels[x] == 0 {
-        x += 1;
+    Arc::new(new_font_data)
+}
+
+// Returns the names of the files of the given font handles.
+fn file_names(handles: &[Handle]) -> Vec<String> {
+    handles
+        .iter()
+        .map(|handle| match *handle {
+            Handle::Path { ref path, .. } => path.file_name().unwrap().to_str().unwrap().to_owned(),
+            Handle::Memory { .. } => panic!("expected a handle to a font file"),
+        })
+        .collect()
+}
+
+// Builds a `name` table from (platform ID, encoding ID, language ID, name ID, name) records. Mac
+// names must be ASCII.
+fn name_table_with_records(records: &[(u16, u16, u16, u16, &str)]) -> Vec<u8> {
+    let mut table = vec![];
+    table.write_u16::<BigEndian>(0).unwrap();
+    table.write_u16::<BigEndian>(records.len() as u16).unwrap();
+    table
+        .write_u16::<BigEndian>(6 + records.len() as u16 * 12)
+        .unwrap();
+    let mut storage = vec![];
+    for &(platform_id, encoding_id, language_id, name_id, name) in records {
+        let string: Vec<u8> = if platform_id == 1 {
+            name.bytes().collect()
+        } else {
+            name.encode_utf16()
+                .flat_map(|unit| unit.to_be_bytes().to_vec())
+                .collect()
+        };
+        for &value in &[
+            platform_id,
+            encoding_id,
+            language_id,
+            name_id,
+            string.len() as u16,
+            storage.len() as u16,
+        ] {
+            table.write_u16::<BigEndian>(value).unwrap();
+        }
+        storage.extend(string);
     }
-    assert_eq!(x, pixels.len());
-    Some(stripe_width)
+    table.extend(storage);
+    table
 }
@@ -0,0 +1,574 @@
+// font-kit/src/testing.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fonts and sources with configurable contents, for tests that mustn't depend on the fonts
+//! installed on the system.
+//!
+//! This module is only available with the `testing` feature, and in font-kit's own tests. A
+//! `MockFont` implements `Loader` without any font data behind it, and a `MockSource` implements
+//! `Source` over a list of mock fonts, so code that is generic over either trait can be tested the
+//! same way on every machine.
+
+use euclid::default::{Point2D, Rect, Size2D, Vector2D};
+use lyon_path::builder::PathBuilder;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use crate::canvas::{AlphaMode, Canvas, RasterizationOptions};
+use crate::error::{FontLoadingError, GlyphLoadingError, SelectionError};
+use crate::family_handle::FamilyHandle;
+use crate::file_type::FileType;
+use crate::font::FaceIndex;
+use crate::handle::Handle;
+use crate::hinting::HintingOptions;
+use crate::loader::{FallbackResult, FontTransform, Loader};
+use crate::matching;
+use crate::metrics::Metrics;
+use crate::properties::{Properties, PropertiesFilter};
+use crate::source::{self, Source};
+use crate::utils;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+
+// The metrics of a mock font that isn't given any, which resemble those of a typical Latin font.
+const DEFAULT_METRICS: Metrics = Metrics {
+    units_per_em: 1000,
+    ascent: 800.0,
+    descent: -200.0,
+    line_gap: 0.0,
+    underline_position: -100.0,
+    underline_thickness: 50.0,
+    cap_height: 700.0,
+    x_height: 500.0,
+};
+
+/// A font whose names, properties, metrics, and glyphs are configured by hand rather than read
+/// from font data.
+///
+/// Mock fonts are created with a `MockFontBuilder`, as in
+/// `MockFont::builder().with_family_name("Test").with_glyph('A', 1).build()`.
+///
+/// Glyph 0 is the missing glyph, as in OpenType fonts. Every glyph is drawn as a rectangle that
+/// is as wide as its advance and as tall as the cap height; rasterizing a glyph leaves the canvas
+/// untouched. Mock fonts can't be loaded from font data, so the `from_*` and `analyze_*`
+/// constructors of `Loader` fail with `FontLoadingError::UnknownFormat`.
+#[derive(Clone, Debug)]
+pub struct MockFont {
+    family_name: String,
+    full_name: Option<String>,
+    postscript_name: Option<String>,
+    properties: Properties,
+    metrics: Metrics,
+    glyphs: BTreeMap<char, u32>,
+    advances: BTreeMap<u32, f32>,
+    glyph_count: Option<u32>,
+    tables: BTreeMap<u32, Arc<Vec<u8>>>,
+}
+
+impl MockFont {
+    /// Returns a builder for a mock font, which starts out as a regular font in the family
+    /// "Mock" that has no glyphs besides the missing glyph.
+    #[inline]
+    pub fn builder() -> MockFontBuilder {
+        MockFontBuilder::new()
+    }
+
+    /// Returns the advance that glyphs without one of their own have, which is half an em.
+    #[inline]
+    fn default_advance(&self) -> f32 {
+        self.metrics.units_per_em as f32 * 0.5
+    }
+
+    fn check_glyph(&self, glyph_id: u32) -> Result<(), GlyphLoadingError> {
+        if glyph_id < <Self as Loader>::glyph_count(self) {
+            Ok(())
+        } else {
+            Err(GlyphLoadingError::NoSuchGlyph)
+        }
+    }
+}
+
+/// Configures the contents of a `MockFont`.
+#[derive(Clone, Debug)]
+pub struct MockFontBuilder {
+    font: MockFont,
+}
+
+impl Default for MockFontBuilder {
+    #[inline]
+    fn default() -> MockFontBuilder {
+        MockFontBuilder::new()
+    }
+}
+
+impl MockFontBuilder {
+    /// Creates a builder for a regular font in the family "Mock" that has no glyphs besides the
+    /// missing glyph.
+    pub fn new() -> MockFontBuilder {
+        MockFontBuilder {
+            font: MockFont {
+                family_name: "Mock".to_owned(),
+                full_name: None,
+                postscript_name: None,
+                properties: Properties::new(),
+                metrics: DEFAULT_METRICS,
+                glyphs: BTreeMap::new(),
+                advances: BTreeMap::new(),
+                glyph_count: None,
+                tables: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Sets the family name.
+    #[inline]
+    pub fn with_family_name(mut self, family_name: &str) -> MockFontBuilder {
+        self.font.family_name = family_name.to_owned();
+        self
+    }
+
+    /// Sets the full name. By default, the full name is the same as the family name.
+    #[inline]
+    pub fn with_full_name(mut self, full_name: &str) -> MockFontBuilder {
+        self.font.full_name = Some(full_name.to_owned());
+        self
+    }
+
+    /// Sets the PostScript name. By default, the font has none.
+    #[inline]
+    pub fn with_postscript_name(mut self, postscript_name: &str) -> MockFontBuilder {
+        self.font.postscript_name = Some(postscript_name.to_owned());
+        self
+    }
+
+    /// Sets the weight, stretch, and style.
+    #[inline]
+    pub fn with_properties(mut self, properties: Properties) -> MockFontBuilder {
+        self.font.properties = properties;
+        self
+    }
+
+    /// Sets the font-wide metrics, in font units.
+    ///
+    /// By default, the font has 1,000 units per em, an ascent of 800, and a descent of -200.
+    #[inline]
+    pub fn with_metrics(mut self, metrics: Metrics) -> MockFontBuilder {
+        self.font.metrics = metrics;
+        self
+    }
+
+    /// Maps a character to a glyph, replacing any glyph that the character mapped to before.
+    ///
+    /// Unless the glyph count is set with `with_glyph_count`, the font has glyphs up to the
+    /// highest ID mapped to or given an advance.
+    #[inline]
+    pub fn with_glyph(mut self, character: char, glyph_id: u32) -> MockFontBuilder {
+        self.font.glyphs.insert(character, glyph_id);
+        self
+    }
+
+    /// Sets the horizontal advance of a glyph, in font units. By default, every glyph is half an
+    /// em wide.
+    #[inline]
+    pub fn with_advance(mut self, glyph_id: u32, advance: f32) -> MockFontBuilder {
+        self.font.advances.insert(glyph_id, advance);
+        self
+    }
+
+    /// Sets the number of glyphs in the font.
+    #[inline]
+    pub fn with_glyph_count(mut self, glyph_count: u32) -> MockFontBuilder {
+        self.font.glyph_count = Some(glyph_count);
+        self
+    }
+
+    /// Adds an OpenType table, which `load_font_table` returns and which the default methods of
+    /// `Loader` that read tables, such as `panose` or `ligature_for`, use.
+    #[inline]
+    pub fn with_table(mut self, table_tag: u32, table_data: Vec<u8>) -> MockFontBuilder {
+        self.font.tables.insert(table_tag, Arc::new(table_data));
+        self
+    }
+
+    /// Creates the mock font.
+    #[inline]
+    pub fn build(self) -> MockFont {
+        self.font
+    }
+}
+
+impl Loader for MockFont {
+    type NativeFont = MockFont;
+
+    #[inline]
+    fn from_bytes(_: Arc<Vec<u8>>, _: FaceIndex) -> Result<MockFont, FontLoadingError> {
+        Err(FontLoadingError::UnknownFormat)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    fn from_file(_: &mut File, _: FaceIndex) -> Result<MockFont, FontLoadingError> {
+        Err(FontLoadingError::UnknownFormat)
+    }
+
+    #[inline]
+    unsafe fn from_native_font(native_font: MockFont) -> MockFont {
+        native_font
+    }
+
+    #[inline]
+    fn analyze_bytes(_: Arc<Vec<u8>>) -> Result<FileType, FontLoadingError> {
+        Err(FontLoadingError::UnknownFormat)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    fn analyze_file(_: &mut File) -> Result<FileType, FontLoadingError> {
+        Err(FontLoadingError::UnknownFormat)
+    }
+
+    #[inline]
+    fn native_font(&self) -> MockFont {
+        self.clone()
+    }
+
+    #[inline]
+    fn postscript_name(&self) -> Option<String> {
+        self.postscript_name.clone()
+    }
+
+    #[inline]
+    fn full_name(&self) -> String {
+        self.full_name
+            .clone()
+            .unwrap_or_else(|| self.family_name.clone())
+    }
+
+    #[inline]
+    fn family_name(&self) -> String {
+        self.family_name.clone()
+    }
+
+    #[inline]
+    fn properties(&self) -> Properties {
+        self.properties
+    }
+
+    fn glyph_count(&self) -> u32 {
+        if let Some(glyph_count) = self.glyph_count {
+            return glyph_count;
+        }
+        let last_mapped_glyph_id = self.glyphs.values().cloned().max().unwrap_or(0);
+        let last_advanced_glyph_id = self.advances.keys().cloned().max().unwrap_or(0);
+        last_mapped_glyph_id.max(last_advanced_glyph_id) + 1
+    }
+
+    #[inline]
+    fn glyph_for_char(&self, character: char) -> Option<u32> {
+        self.glyphs.get(&character).cloned()
+    }
+
+    fn glyph_to_char(&self, glyph_id: u32) -> Vec<char> {
+        self.glyphs
+            .iter()
+            .filter(|&(_, &mapped_glyph_id)| mapped_glyph_id == glyph_id)
+            .map(|(&character, _)| character)
+            .collect()
+    }
+
+    fn outline<B>(
+        &self,
+        glyph_id: u32,
+        _: HintingOptions,
+        path_builder: &mut B,
+    ) -> Result<(), GlyphLoadingError>
+    where
+        B: PathBuilder,
+    {
+        let bounds = self.typographic_bounds(glyph_id)?;
+        if bounds.is_empty() {
+            return Ok(());
+        }
+        let (min, max) = (bounds.min(), bounds.max());
+        path_builder.move_to(min);
+        path_builder.line_to(Point2D::new(min.x, max.y));
+        path_builder.line_to(max);
+        path_builder.line_to(Point2D::new(max.x, min.y));
+        path_builder.close();
+        Ok(())
+    }
+
+    fn typographic_bounds(&self, glyph_id: u32) -> Result<Rect<f32>, GlyphLoadingError> {
+        let advance = self.advance(glyph_id)?;
+        Ok(Rect::new(
+            Point2D::zero(),
+            Size2D::new(advance.x, self.metrics.cap_height),
+        ))
+    }
+
+    fn advance(&self, glyph_id: u32) -> Result<Vector2D<f32>, GlyphLoadingError> {
+        self.check_glyph(glyph_id)?;
+        let advance = match self.advances.get(&glyph_id) {
+            Some(&advance) => advance,
+            None => self.default_advance(),
+        };
+        Ok(Vector2D::new(advance, 0.0))
+    }
+
+    fn origin(&self, glyph_id: u32) -> Result<Point2D<f32>, GlyphLoadingError> {
+        self.check_glyph(glyph_id)?;
+        Ok(Point2D::zero())
+    }
+
+    #[inline]
+    fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    #[inline]
+    fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
+        None
+    }
+
+    #[inline]
+    fn supports_hinting_options(&self, hinting_options: HintingOptions, _: bool) -> bool {
+        hinting_options == HintingOptions::None
+    }
+
+    fn rasterize_glyph(
+        &self,
+        _: &mut Canvas,
+        glyph_id: u32,
+        _: f32,
+        _: &FontTransform,
+        _: &Point2D<f32>,
+        _: HintingOptions,
+        _: RasterizationOptions,
+        _: AlphaMode,
+    ) -> Result<(), GlyphLoadingError> {
+        self.check_glyph(glyph_id)
+    }
+
+    #[inline]
+    fn get_fallbacks(&self, _: &str, _: &str) -> FallbackResult<MockFont> {
+        FallbackResult {
+            fonts: vec![],
+            valid_len: 0,
+        }
+    }
+
+    #[inline]
+    fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
+        self.tables
+            .get(&table_tag)
+            .map(|table_data| table_data.to_vec().into_boxed_slice())
+    }
+
+    #[inline]
+    fn table_tags(&self) -> Vec<u32> {
+        self.tables.keys().cloned().collect()
+    }
+}
+
+/// A source that contains a fixed list of mock fonts.
+///
+/// The handles that the source returns can't be loaded with `Font::from_handle`, since there's no
+/// font data behind them; pass them to `MockSource::font` to get the mock font back. Families are
+/// formed by the family names of the fonts, which are compared case-insensitively, and list their
+/// fonts in the order that they were added.
+#[derive(Clone, Debug)]
+pub struct MockSource {
+    fonts: Vec<MockFont>,
+    // The bytes that all handles of this source point to, which tell them apart from the handles
+    // of other sources.
+    handle_bytes: Arc<Vec<u8>>,
+}
+
+impl Default for MockSource {
+    #[inline]
+    fn default() -> MockSource {
+        MockSource::new(vec![])
+    }
+}
+
+impl MockSource {
+    /// Creates a source that contains the given fonts.
+    pub fn new(fonts: Vec<MockFont>) -> MockSource {
+        MockSource {
+            fonts,
+            handle_bytes: Arc::new(b"mock".to_vec()),
+        }
+    }
+
+    /// Adds a font to the source.
+    #[inline]
+    pub fn add_font(&mut self, font: MockFont) {
+        self.fonts.push(font)
+    }
+
+    /// Returns the mock font that a handle returned by this source refers to, or `None` if the
+    /// handle came from elsewhere.
+    pub fn font(&self, handle: &Handle) -> Option<&MockFont> {
+        match *handle {
+            Handle::Memory {
+                ref bytes,
+                font_index,
+            } if Arc::ptr_eq(bytes, &self.handle_bytes) => self.fonts.get(font_index.0 as usize),
+            _ => None,
+        }
+    }
+
+    fn handle(&self, font_index: usize) -> Handle {
+        Handle::from_memory(self.handle_bytes.clone(), FaceIndex(font_index as u32))
+    }
+
+    // Returns the handles of all the fonts that satisfy the given predicate, in order.
+    fn handles_where<P>(&self, mut predicate: P) -> Vec<Handle>
+    where
+        P: FnMut(&MockFont) -> bool,
+    {
+        (0..self.fonts.len())
+            .filter(|&font_index| predicate(&self.fonts[font_index]))
+            .map(|font_index| self.handle(font_index))
+            .collect()
+    }
+}
+
+impl Source for MockSource {
+    #[inline]
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        Ok(self.handles_where(|_| true))
+    }
+
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        let mut families: Vec<String> = vec![];
+        for font in &self.fonts {
+            if !families.contains(&font.family_name) {
+                families.push(font.family_name.clone());
+            }
+        }
+        families.sort();
+        Ok(families)
+    }
+
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        let folded_family_name = utils::fold_case(family_name);
+        let font = self
+            .fonts
+            .iter()
+            .find(|font| utils::fold_case(&font.family_name) == folded_family_name)
+            .ok_or(SelectionError::NotFound)?;
+        let mut family_handle = FamilyHandle::from_font_handles(
+            self.handles_where(|other_font| other_font.family_name == font.family_name)
+                .into_iter(),
+        );
+        family_handle.set_name(font.family_name.clone());
+        Ok(family_handle)
+    }
+
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.handles_where(|font| {
+            font.postscript_name.as_ref().map(|name| &name[..]) == Some(postscript_name)
+        })
+        .into_iter()
+        .next()
+        .ok_or(SelectionError::NotFound)
+    }
+
+    fn select_by_full_name(&self, full_name: &str) -> Result<Handle, SelectionError> {
+        let folded_full_name = utils::fold_case(full_name);
+        self.handles_where(|font| utils::fold_case(&font.full_name()) == folded_full_name)
+            .into_iter()
+            .next()
+            .ok_or(SelectionError::NotFound)
+    }
+
+    fn select_by_postscript_name_prefix(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let handles = self.handles_where(|font| match font.postscript_name {
+            Some(ref postscript_name) => postscript_name.starts_with(prefix),
+            None => false,
+        });
+        if handles.is_empty() {
+            Err(SelectionError::NotFound)
+        } else {
+            Ok(handles)
+        }
+    }
+
+    fn select_by_properties(
+        &self,
+        filter: &PropertiesFilter,
+    ) -> Result<Vec<Handle>, SelectionError> {
+        let handles =
+            self.handles_where(|font| filter.matches(&font.properties, font.is_monospace()));
+        if handles.is_empty() {
+            Err(SelectionError::NotFound)
+        } else {
+            Ok(handles)
+        }
+    }
+
+    fn select_fallback_for_char(
+        &self,
+        character: char,
+        _: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        let mut handles = self.handles_where(|font| font.glyph_for_char(character).is_some());
+        let candidates: Vec<_> = handles
+            .iter()
+            .filter_map(|handle| self.font(handle))
+            .map(|font| font.properties)
+            .collect();
+        let index = matching::find_best_match(&candidates, properties)?;
+        Ok(handles.swap_remove(index))
+    }
+
+    fn select_families_by_unicode_coverage(
+        &self,
+        characters: &BTreeSet<char>,
+    ) -> Result<Vec<FamilyHandle>, SelectionError> {
+        let mut families = vec![];
+        for family_name in self.all_families()? {
+            let family_handle = self.select_family_by_name(&family_name)?;
+            let covered_count = characters
+                .iter()
+                .filter(|&&character| {
+                    family_handle
+                        .fonts()
+                        .iter()
+                        .filter_map(|handle| self.font(handle))
+                        .any(|font| font.glyph_for_char(character).is_some())
+                })
+                .count();
+            families.push((covered_count, family_handle));
+        }
+        source::sort_families_by_coverage(families)
+    }
+
+    fn select_descriptions_in_family(
+        &self,
+        family: &FamilyHandle,
+    ) -> Result<Vec<Properties>, SelectionError> {
+        family
+            .fonts()
+            .iter()
+            .map(|handle| {
+                self.font(handle)
+                    .map(|font| font.properties)
+                    .ok_or(SelectionError::NotFound)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,372 @@
+// font-kit/src/unicode_ranges.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The Unicode blocks that a font claims to cover, according to its `OS/2` table.
+
+use std::char;
+use std::ops::RangeInclusive;
+
+// The Unicode blocks that each bit of `ulUnicodeRange1` through `ulUnicodeRange4` stands for, as
+// bit, first code point, and last code point. Bit 57 (non-BMP characters) is left out, since it
+// doesn't name any particular block.
+static UNICODE_RANGE_BLOCKS: &[(u32, u32, u32)] = &[
+    (0, 0x0000, 0x007f),
+    (1, 0x0080, 0x00ff),
+    (2, 0x0100, 0x017f),
+    (3, 0x0180, 0x024f),
+    (4, 0x0250, 0x02af),
+    (4, 0x1d00, 0x1d7f),
+    (4, 0x1d80, 0x1dbf),
+    (5, 0x02b0, 0x02ff),
+    (5, 0xa700, 0xa71f),
+    (6, 0x0300, 0x036f),
+    (6, 0x1dc0, 0x1dff),
+    (7, 0x0370, 0x03ff),
+    (8, 0x2c80, 0x2cff),
+    (9, 0x0400, 0x04ff),
+    (9, 0x0500, 0x052f),
+    (9, 0x2de0, 0x2dff),
+    (9, 0xa640, 0xa69f),
+    (10, 0x0530, 0x058f),
+    (11, 0x0590, 0x05ff),
+    (12, 0xa500, 0xa63f),
+    (13, 0x0600, 0x06ff),
+    (13, 0x0750, 0x077f),
+    (14, 0x07c0, 0x07ff),
+    (15, 0x0900, 0x097f),
+    (16, 0x0980, 0x09ff),
+    (17, 0x0a00, 0x0a7f),
+    (18, 0x0a80, 0x0aff),
+    (19, 0x0b00, 0x0b7f),
+    (20, 0x0b80, 0x0bff),
+    (21, 0x0c00, 0x0c7f),
+    (22, 0x0c80, 0x0cff),
+    (23, 0x0d00, 0x0d7f),
+    (24, 0x0e00, 0x0e7f),
+    (25, 0x0e80, 0x0eff),
+    (26, 0x10a0, 0x10ff),
+    (26, 0x2d00, 0x2d2f),
+    (27, 0x1b00, 0x1b7f),
+    (28, 0x1100, 0x11ff),
+    (29, 0x1e00, 0x1eff),
+    (29, 0x2c60, 0x2c7f),
+    (29, 0xa720, 0xa7ff),
+    (30, 0x1f00, 0x1fff),
+    (31, 0x2000, 0x206f),
+    (31, 0x2e00, 0x2e7f),
+    (32, 0x2070, 0x209f),
+    (33, 0x20a0, 0x20cf),
+    (34, 0x20d0, 0x20ff),
+    (35, 0x2100, 0x214f),
+    (36, 0x2150, 0x218f),
+    (37, 0x2190, 0x21ff),
+    (37, 0x27f0, 0x27ff),
+    (37, 0x2900, 0x297f),
+    (37, 0x2b00, 0x2bff),
+    (38, 0x2200, 0x22ff),
+    (38, 0x27c0, 0x27ef),
+    (38, 0x2980, 0x29ff),
+    (38, 0x2a00, 0x2aff),
+    (39, 0x2300, 0x23ff),
+    (40, 0x2400, 0x243f),
+    (41, 0x2440, 0x245f),
+    (42, 0x2460, 0x24ff),
+    (43, 0x2500, 0x257f),
+    (44, 0x2580, 0x259f),
+    (45, 0x25a0, 0x25ff),
+    (46, 0x2600, 0x26ff),
+    (47, 0x2700, 0x27bf),
+    (48, 0x3000, 0x303f),
+    (49, 0x3040, 0x309f),
+    (50, 0x30a0, 0x30ff),
+    (50, 0x31f0, 0x31ff),
+    (51, 0x3100, 0x312f),
+    (51, 0x31a0, 0x31bf),
+    (52, 0x3130, 0x318f),
+    (53, 0xa840, 0xa87f),
+    (54, 0x3200, 0x32ff),
+    (55, 0x3300, 0x33ff),
+    (56, 0xac00, 0xd7af),
+    (58, 0x10900, 0x1091f),
+    (59, 0x2e80, 0x2eff),
+    (59, 0x2f00, 0x2fdf),
+    (59, 0x2ff0, 0x2fff),
+    (59, 0x3190, 0x319f),
+    (59, 0x3400, 0x4dbf),
+    (59, 0x4e00, 0x9fff),
+    (59, 0x20000, 0x2a6df),
+    (60, 0xe000, 0xf8ff),
+    (61, 0x31c0, 0x31ef),
+    (61, 0xf900, 0xfaff),
+    (61, 0x2f800, 0x2fa1f),
+    (62, 0xfb00, 0xfb4f),
+    (63, 0xfb50, 0xfdff),
+    (64, 0xfe20, 0xfe2f),
+    (65, 0xfe10, 0xfe1f),
+    (65, 0xfe30, 0xfe4f),
+    (66, 0xfe50, 0xfe6f),
+    (67, 0xfe70, 0xfeff),
+    (68, 0xff00, 0xffef),
+    (69, 0xfff0, 0xffff),
+    (70, 0x0f00, 0x0fff),
+    (71, 0x0700, 0x074f),
+    (72, 0x0780, 0x07bf),
+    (73, 0x0d80, 0x0dff),
+    (74, 0x1000, 0x109f),
+    (75, 0x1200, 0x137f),
+    (75, 0x1380, 0x139f),
+    (75, 0x2d80, 0x2ddf),
+    (76, 0x13a0, 0x13ff),
+    (77, 0x1400, 0x167f),
+    (78, 0x1680, 0x169f),
+    (79, 0x16a0, 0x16ff),
+    (80, 0x1780, 0x17ff),
+    (80, 0x19e0, 0x19ff),
+    (81, 0x1800, 0x18af),
+    (82, 0x2800, 0x28ff),
+    (83, 0xa000, 0xa48f),
+    (83, 0xa490, 0xa4cf),
+    (84, 0x1700, 0x177f),
+    (85, 0x10300, 0x1032f),
+    (86, 0x10330, 0x1034f),
+    (87, 0x10400, 0x1044f),
+    (88, 0x1d000, 0x1d24f),
+    (89, 0x1d400, 0x1d7ff),
+    (90, 0xf0000, 0xffffd),
+    (90, 0x100000, 0x10fffd),
+    (91, 0xfe00, 0xfe0f),
+    (91, 0xe0100, 0xe01ef),
+    (92, 0xe0000, 0xe007f),
+    (93, 0x1900, 0x194f),
+    (94, 0x1950, 0x197f),
+    (95, 0x1980, 0x19df),
+    (96, 0x1a00, 0x1a1f),
+    (97, 0x2c00, 0x2c5f),
+    (98, 0x2d30, 0x2d7f),
+    (99, 0x4dc0, 0x4dff),
+    (100, 0xa800, 0xa82f),
+    (101, 0x10000, 0x1013f),
+    (102, 0x10140, 0x1018f),
+    (103, 0x10380, 0x1039f),
+    (104, 0x103a0, 0x103df),
+    (105, 0x10450, 0x1047f),
+    (106, 0x10480, 0x104af),
+    (107, 0x10800, 0x1083f),
+    (108, 0x10a00, 0x10a5f),
+    (109, 0x1d300, 0x1d35f),
+    (110, 0x12000, 0x1247f),
+    (111, 0x1d360, 0x1d37f),
+    (112, 0x1b80, 0x1bbf),
+    (113, 0x1c00, 0x1c4f),
+    (114, 0x1c50, 0x1c7f),
+    (115, 0xa880, 0xa8df),
+    (116, 0xa900, 0xa92f),
+    (117, 0xa930, 0xa95f),
+    (118, 0xaa00, 0xaa5f),
+    (119, 0x10190, 0x101cf),
+    (120, 0x101d0, 0x101ff),
+    (121, 0x10280, 0x1029f),
+    (121, 0x102a0, 0x102df),
+    (121, 0x10920, 0x1093f),
+    (122, 0x1f000, 0x1f09f),
+];
+
+/// The Unicode blocks that a font claims to cover, as the 128-bit `ulUnicodeRange1` through
+/// `ulUnicodeRange4` fields of the OpenType `OS/2` table.
+///
+/// These bits are set by the font's author, so they're a quick but coarse check: a font may set a
+/// bit while covering only part of the block, and fonts without an `OS/2` table set none. Use
+/// `glyph_for_char` to check individual characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct UnicodeRanges(pub [u32; 4]);
+
+impl UnicodeRanges {
+    /// Returns true if and only if the given bit, from 0 to 127, is set.
+    #[inline]
+    pub fn has_bit(&self, bit: u32) -> bool {
+        bit < 128 && self.0[(bit / 32) as usize] & (1 << (bit % 32)) != 0
+    }
+
+    /// Returns true if the font covers Basic Latin (U+0000–U+007F).
+    #[inline]
+    pub fn supports_basic_latin(&self) -> bool {
+        self.has_bit(0)
+    }
+
+    /// Returns true if the font covers Latin-1 Supplement (U+0080–U+00FF).
+    #[inline]
+    pub fn supports_latin_1_supplement(&self) -> bool {
+        self.has_bit(1)
+    }
+
+    /// Returns true if the font covers Latin Extended-A (U+0100–U+017F).
+    #[inline]
+    pub fn supports_latin_extended_a(&self) -> bool {
+        self.has_bit(2)
+    }
+
+    /// Returns true if the font covers Latin Extended-B (U+0180–U+024F).
+    #[inline]
+    pub fn supports_latin_extended_b(&self) -> bool {
+        self.has_bit(3)
+    }
+
+    /// Returns true if the font covers Greek and Coptic (U+0370–U+03FF).
+    #[inline]
+    pub fn supports_greek(&self) -> bool {
+        self.has_bit(7)
+    }
+
+    /// Returns true if the font covers Cyrillic (U+0400–U+04FF) and its supplements.
+    #[inline]
+    pub fn supports_cyrillic(&self) -> bool {
+        self.has_bit(9)
+    }
+
+    /// Returns true if the font covers Armenian (U+0530–U+058F).
+    #[inline]
+    pub fn supports_armenian(&self) -> bool {
+        self.has_bit(10)
+    }
+
+    /// Returns true if the font covers Hebrew (U+0590–U+05FF).
+    #[inline]
+    pub fn supports_hebrew(&self) -> bool {
+        self.has_bit(11)
+    }
+
+    /// Returns true if the font covers Arabic (U+0600–U+06FF) and Arabic Supplement.
+    #[inline]
+    pub fn supports_arabic(&self) -> bool {
+        self.has_bit(13)
+    }
+
+    /// Returns true if the font covers Devanagari (U+0900–U+097F).
+    #[inline]
+    pub fn supports_devanagari(&self) -> bool {
+        self.has_bit(15)
+    }
+
+    /// Returns true if the font covers Thai (U+0E00–U+0E7F).
+    #[inline]
+    pub fn supports_thai(&self) -> bool {
+        self.has_bit(24)
+    }
+
+    /// Returns true if the font covers Georgian (U+10A0–U+10FF) and Georgian Supplement.
+    #[inline]
+    pub fn supports_georgian(&self) -> bool {
+        self.has_bit(26)
+    }
+
+    /// Returns true if the font covers Hangul Jamo (U+1100–U+11FF).
+    #[inline]
+    pub fn supports_hangul_jamo(&self) -> bool {
+        self.has_bit(28)
+    }
+
+    /// Returns true if the font covers General Punctuation (U+2000–U+206F) and Supplemental
+    /// Punctuation.
+    #[inline]
+    pub fn supports_general_punctuation(&self) -> bool {
+        self.has_bit(31)
+    }
+
+    /// Returns true if the font covers Currency Symbols (U+20A0–U+20CF).
+    #[inline]
+    pub fn supports_currency_symbols(&self) -> bool {
+        self.has_bit(33)
+    }
+
+    /// Returns true if the font covers CJK Symbols and Punctuation (U+3000–U+303F).
+    #[inline]
+    pub fn supports_cjk_symbols_and_punctuation(&self) -> bool {
+        self.has_bit(48)
+    }
+
+    /// Returns true if the font covers Hiragana (U+3040–U+309F).
+    #[inline]
+    pub fn supports_hiragana(&self) -> bool {
+        self.has_bit(49)
+    }
+
+    /// Returns true if the font covers Katakana (U+30A0–U+30FF) and Katakana Phonetic Extensions.
+    #[inline]
+    pub fn supports_katakana(&self) -> bool {
+        self.has_bit(50)
+    }
+
+    /// Returns true if the font covers Hangul Syllables (U+AC00–U+D7AF).
+    #[inline]
+    pub fn supports_hangul_syllables(&self) -> bool {
+        self.has_bit(56)
+    }
+
+    /// Returns true if the font covers at least one character outside the Basic Multilingual
+    /// Plane.
+    #[inline]
+    pub fn supports_non_bmp(&self) -> bool {
+        self.has_bit(57)
+    }
+
+    /// Returns true if the font covers CJK Unified Ideographs (U+4E00–U+9FFF) and the related
+    /// radical and extension blocks.
+    #[inline]
+    pub fn supports_cjk_unified_ideographs(&self) -> bool {
+        self.has_bit(59)
+    }
+
+    /// Returns true if the font has glyphs in the Private Use Area of the Basic Multilingual Plane
+    /// (U+E000–U+F8FF).
+    #[inline]
+    pub fn supports_private_use_area(&self) -> bool {
+        self.has_bit(60)
+    }
+
+    /// Returns true if the font covers Halfwidth and Fullwidth Forms (U+FF00–U+FFEF).
+    #[inline]
+    pub fn supports_halfwidth_and_fullwidth_forms(&self) -> bool {
+        self.has_bit(68)
+    }
+
+    /// Returns the ranges of characters in the blocks that the set bits stand for, in order,
+    /// with adjacent blocks merged.
+    ///
+    /// The non-BMP bit is ignored, since it doesn't say which characters outside the Basic
+    /// Multilingual Plane are covered. Surrogate code points, which aren't characters, are left
+    /// out.
+    pub fn to_set(&self) -> Vec<RangeInclusive<char>> {
+        let mut blocks: Vec<(u32, u32)> = UNICODE_RANGE_BLOCKS
+            .iter()
+            .filter(|&&(bit, _, _)| self.has_bit(bit))
+            .map(|&(_, first, last)| (first, last))
+            .collect();
+        blocks.sort();
+
+        let mut merged_blocks: Vec<(u32, u32)> = vec![];
+        for (first, last) in blocks {
+            match merged_blocks.last_mut() {
+                Some(&mut (_, ref mut merged_last)) if first <= *merged_last + 1 => {
+                    *merged_last = (*merged_last).max(last)
+                }
+                _ => merged_blocks.push((first, last)),
+            }
+        }
+
+        merged_blocks
+            .into_iter()
+            .filter_map(|(first, last)| {
+                let first = char::from_u32(first)?;
+                let last = char::from_u32(last)?;
+                Some(first..=last)
+            })
+            .collect()
+    }
+}
@@ -42,6 +42,22 @@ pub(crate) fn div_round_up(a: usize, b: usize) -> usize {
     (a + b - 1) / b
 }
 
+// Case-folds a family name so that it can be compared case-insensitively.
+//
+// This is lowercasing plus the full case foldings that lowercasing misses for letters that turn up
+// in font names, so that "Straße" and "STRASSE" compare equal.
+pub(crate) fn fold_case(name: &str) -> String {
+    let mut folded_name = String::with_capacity(name.len());
+    for character in name.chars().flat_map(char::to_lowercase) {
+        match character {
+            'ß' => folded_name.push_str("ss"),
+            'ς' => folded_name.push('σ'),
+            _ => folded_name.push(character),
+        }
+    }
+    folded_name
+}
+
 pub(crate) fn slurp_file(file: &mut File) -> Result<Vec<u8>, IOError> {
     let mut data = match file.metadata() {
         Ok(metadata) => Vec::with_capacity(metadata.len() as usize),
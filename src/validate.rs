@@ -0,0 +1,125 @@
+// font-kit/src/validate.rs
+//
+// Copyright © 2019 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integrity checks of raw TrueType and OpenType font data.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::error::FontLoadingError;
+use crate::sfnt::{read_u16, read_u32, HEAD_TABLE_TAG};
+use crate::utils::SFNT_VERSIONS;
+
+const TTC_TAG: [u8; 4] = *b"ttcf";
+
+// The value that the checksum of a whole font comes to when its `checkSumAdjustment` is correct.
+const WHOLE_FONT_CHECKSUM: u32 = 0xb1b0_afba;
+
+// The offset of the `checkSumAdjustment` field in the `head` table.
+const HEAD_CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
+
+/// Checks that the checksums stored in raw TrueType/OpenType font data (the contents of a
+/// `.ttf`/`.otf`/`.ttc`/etc. file) match the data, as they won't if the data was corrupted or
+/// was modified without updating them.
+///
+/// The checksum of every table in the table directory is recomputed, as is the checksum of the
+/// whole font, which the `checkSumAdjustment` field of the `head` table makes come out to a fixed
+/// value. The whole-font checksum isn't checked for collections, since the specification doesn't
+/// define it for them.
+///
+/// If any checksum is wrong, returns `FontLoadingError::ChecksumMismatch` with the tags of the
+/// tables whose checksums are wrong; the `head` table is listed if the whole-font checksum is.
+/// Data that isn't a TrueType/OpenType font or collection results in
+/// `FontLoadingError::UnknownFormat`, and a table directory that points past the end of the data
+/// in `FontLoadingError::Parse`.
+pub fn validate_checksums(data: &[u8]) -> Result<(), FontLoadingError> {
+    let mut mismatched_table_tags = vec![];
+    if data.get(0..4) == Some(&TTC_TAG[..]) {
+        let font_count = read_u32(data, 8).ok_or(FontLoadingError::Parse)?;
+        for font_index in 0..(font_count as usize) {
+            let directory_offset =
+                read_u32(data, 12 + font_index * 4).ok_or(FontLoadingError::Parse)?;
+            check_table_checksums(data, directory_offset as usize, &mut mismatched_table_tags)?;
+        }
+    } else {
+        let has_head_table = check_table_checksums(data, 0, &mut mismatched_table_tags)?;
+        if has_head_table
+            && checksum(data) != WHOLE_FONT_CHECKSUM
+            && !mismatched_table_tags.contains(&HEAD_TABLE_TAG)
+        {
+            mismatched_table_tags.push(HEAD_TABLE_TAG);
+        }
+    }
+
+    if mismatched_table_tags.is_empty() {
+        Ok(())
+    } else {
+        Err(FontLoadingError::ChecksumMismatch(mismatched_table_tags))
+    }
+}
+
+// Checks the checksums of the tables in the table directory at the given offset, adding the tags
+// of the tables whose checksums are wrong to `mismatched_table_tags` unless they're already there.
+// Returns true if the font has a `head` table.
+fn check_table_checksums(
+    data: &[u8],
+    directory_offset: usize,
+    mismatched_table_tags: &mut Vec<u32>,
+) -> Result<bool, FontLoadingError> {
+    let version = get_range(data, directory_offset, 4)?;
+    if !SFNT_VERSIONS
+        .iter()
+        .any(|sfnt_version| version == sfnt_version)
+    {
+        return Err(FontLoadingError::UnknownFormat);
+    }
+
+    let table_count = read_u16(data, directory_offset + 4).ok_or(FontLoadingError::Parse)?;
+    let mut has_head_table = false;
+    for table_index in 0..(table_count as usize) {
+        let record_offset = directory_offset + 12 + table_index * 16;
+        let record = get_range(data, record_offset, 16)?;
+        let table_tag = BigEndian::read_u32(&record[0..4]);
+        let stored_checksum = BigEndian::read_u32(&record[4..8]);
+        let table_offset = BigEndian::read_u32(&record[8..12]) as usize;
+        let table_length = BigEndian::read_u32(&record[12..16]) as usize;
+        let table = get_range(data, table_offset, table_length)?;
+
+        // The checksum of the `head` table is computed as though `checkSumAdjustment` were 0.
+        let mut table_checksum = checksum(table);
+        if table_tag == HEAD_TABLE_TAG {
+            has_head_table = true;
+            let checksum_adjustment =
+                read_u32(table, HEAD_CHECKSUM_ADJUSTMENT_OFFSET).ok_or(FontLoadingError::Parse)?;
+            table_checksum = table_checksum.wrapping_sub(checksum_adjustment);
+        }
+        if table_checksum != stored_checksum && !mismatched_table_tags.contains(&table_tag) {
+            mismatched_table_tags.push(table_tag);
+        }
+    }
+    Ok(has_head_table)
+}
+
+// Returns `length` bytes of the data starting at `offset`, treating a range whose end overflows
+// like any other range past the end of the data.
+fn get_range(data: &[u8], offset: usize, length: usize) -> Result<&[u8], FontLoadingError> {
+    offset
+        .checked_add(length)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(FontLoadingError::Parse)
+}
+
+// Adds up the data as big-endian 32-bit words, padding it with zeroes to a multiple of 4 bytes.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(BigEndian::read_u32(&word))
+    })
+}
@@ -12,6 +12,7 @@ extern crate font_kit;
 
 use font_kit::error::SelectionError;
 use font_kit::family_name::FamilyName;
+use font_kit::font::FaceIndex;
 use font_kit::handle::Handle;
 use font_kit::properties::Properties;
 use font_kit::source::SystemSource;
@@ -26,9 +27,9 @@ macro_rules! match_handle {
             } => {
                 assert_eq!(path, path);
                 assert_eq!(
-                    font_index, $index,
+                    font_index.0, $index,
                     "expecting font index {} not {}",
-                    font_index, $index
+                    font_index.0, $index
                 );
             }
             _ => unreachable!(),
@@ -41,7 +42,7 @@ fn check_filename(handle: &Handle, filename: &str) {
     match *handle {
         Handle::Path { ref path, font_index } => {
             assert_eq!(path.file_name(), Some(OsStr::new(filename)));
-            assert_eq!(font_index, 0);
+            assert_eq!(font_index, FaceIndex::FIRST);
         }
         _ => panic!("Expected path handle!"),
     }
@@ -220,7 +221,7 @@ mod test {
         let mut filenames: Vec<String> = family.fonts().iter().map(|handle| {
             match *handle {
                 Handle::Path { ref path, font_index } => {
-                    assert_eq!(font_index, 0);
+                    assert_eq!(font_index, FaceIndex::FIRST);
                     path.file_name()
                         .expect("Where's the filename?")
                         .to_string_lossy()